@@ -109,7 +109,9 @@
         const resp = await fetch(url, options);
         const body = await resp.json();
         if (!resp.ok || body.code !== 200) {
-            throw new Error(body.msg || `request failed: ${url}`);
+            const err = new Error(body.msg || `request failed: ${url}`);
+            err.code = body.code;
+            throw err;
         }
         return body;
     }
@@ -374,11 +376,19 @@
                 content: el.content.value,
                 auto_sync: el.syncOnSave.checked,
             };
-            const resp = await request("/journal", {
+            const post = () => request("/journal", {
                 method: "POST",
                 headers: {"Content-Type": "application/json"},
                 body: JSON.stringify(payload),
             });
+            let resp;
+            try {
+                resp = await post();
+            } catch (err) {
+                if (err.code !== 409 || !confirm(`${payload.date} 已有日记，是否覆盖？`)) throw err;
+                payload.overwrite = true;
+                resp = await post();
+            }
             let msg = `保存成功: #${resp.data.id}`;
             if (el.syncOnSave.checked) {
                 const syncMsg = await syncJournalInternal();
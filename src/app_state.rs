@@ -1,9 +1,23 @@
 use crate::config::app_config::AppConfig;
+use crate::util::blocking::BlockingPool;
+use crate::util::crypto::ContentCipher;
+use crate::util::outbound::OutboundClient;
+use crate::util::render_cache::RenderCache;
 use sqlx::Pool;
 use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: Pool<sqlx::Sqlite>,
+    /// 只读连接池，列表、搜索、统计等不写入的查询用它
+    pub read_db: Pool<sqlx::Sqlite>,
     pub config: Arc<AppConfig>,
+    pub render_cache: Arc<RenderCache>,
+    pub blocking: Arc<BlockingPool>,
+    /// 访问配置里写明的服务（telegram、matrix、备份上传）
+    pub http: reqwest::Client,
+    /// 访问用户提供的地址（通知 webhook、导入时下载图片），有 `[outbound]` 的地址限制
+    pub outbound: OutboundClient,
+    /// 日记正文写库前 `seal`、读出后 `open`，未开启 `[encryption]` 时原样进出
+    pub cipher: Arc<ContentCipher>,
 }
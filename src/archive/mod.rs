@@ -0,0 +1,267 @@
+use crate::app_state::AppState;
+use crate::http::journal::{self, UpsertEntry};
+use crate::job::{JobDef, JobFuture};
+use crate::util::date_util;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use zip::write::SimpleFileOptions;
+
+/// 归档包内的日记文件
+const ARCHIVE_ENTRY_NAME: &str = "journals.json";
+const SCHEDULE: &str = "@every 6h";
+
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveRecord {
+    pub id: i64,
+    pub file_name: String,
+    /// 归档了此日期之前的日记（不含）
+    pub before_date: String,
+    pub count: i64,
+    /// 归档后是否已从数据库删除
+    pub removed: bool,
+    pub create_time: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ArchivedJournal {
+    date: String,
+    content: String,
+    metadata: Option<String>,
+    create_time: i64,
+    update_time: i64,
+}
+
+/// 定时把超过 `retention.archive_after_years` 年的日记打包归档
+pub fn job(state: &AppState) -> Option<JobDef> {
+    let cfg = &state.config.retention;
+    if !cfg.enabled {
+        return None;
+    }
+    if cfg.archive_after_years == 0 {
+        warn!("retention skipped: archive_after_years must be at least 1");
+        return None;
+    }
+    info!(
+        "retention job enabled, archive_after_years={}, remove_from_db={}",
+        cfg.archive_after_years, cfg.remove_from_db
+    );
+    Some(JobDef {
+        name: "archive".to_string(),
+        kind: "archive",
+        schedule: SCHEDULE.to_string(),
+        handler: run_job,
+        max_retries: 1,
+        catch_up: true,
+    })
+}
+
+fn run_job(state: AppState, _: Option<String>) -> JobFuture {
+    Box::pin(async move {
+        if let Some(record) = run(&state).await? {
+            info!(
+                "retention archived {} journals before {} -> {}",
+                record.count, record.before_date, record.file_name
+            );
+        }
+        Ok(())
+    })
+}
+
+/// 归档截止日期：今天往前 `archive_after_years` 年的同一天
+pub fn cutoff_date(state: &AppState) -> String {
+    let today = state.config.today();
+    let years = state.config.retention.archive_after_years as i64;
+    let (y, rest) = today.split_at(4);
+    let year = y.parse::<i64>().unwrap_or(1970) - years;
+    // 2 月 29 日往前推到非闰年时取 2 月 28 日
+    let date = format!("{:04}{}", year, rest);
+    if date_util::parse_date(&date).is_some() {
+        date
+    } else {
+        format!("{:04}-02-28", year)
+    }
+}
+
+/// 归档截止日期之前、上次归档之后的日记，没有需要归档的返回 None
+pub async fn run(state: &AppState) -> Result<Option<ArchiveRecord>, String> {
+    let before = cutoff_date(state);
+    let since = sqlx::query_scalar::<_, Option<String>>(
+        "select max(before_date) from journal_archive where removed = 0",
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| e.to_string())?
+    .unwrap_or_default();
+    if !since.is_empty() && since >= before {
+        return Ok(None);
+    }
+
+    let rows = sqlx::query_as::<_, ArchivedJournal>(
+        "select date, content, metadata, create_time, update_time from journal where date < ? and date >= ? order by date asc",
+    )
+    .bind(&before)
+    .bind(&since)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| e.to_string())?;
+    if rows.is_empty() {
+        return Ok(None);
+    }
+
+    let dir = PathBuf::from(state.config.get_archive_path());
+    let file_name = format!(
+        "daylog-archive-before-{}-{}.zip",
+        before,
+        date_util::now_secs()
+    );
+    let path = dir.join(&file_name);
+    let count = rows.len() as i64;
+    let json = serde_json::to_vec(&rows).map_err(|e| e.to_string())?;
+    state
+        .blocking
+        .run("retention archive", move || write_archive(&path, &json))
+        .await
+        .map_err(|_| "archive task join failed".to_string())??;
+
+    let remove = state.config.retention.remove_from_db;
+    let mut tx = state.db.begin().await.map_err(|e| e.to_string())?;
+    let id = sqlx::query(
+        "insert into journal_archive (file_name, before_date, count, removed, create_time) values (?, ?, ?, ?, ?)",
+    )
+    .bind(&file_name)
+    .bind(&before)
+    .bind(count)
+    .bind(remove)
+    .bind(date_util::now_secs())
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?
+    .last_insert_rowid();
+    if remove {
+        sqlx::query(
+            "delete from journal_review where journal_id in (select id from journal where date < ? and date >= ?)",
+        )
+        .bind(&before)
+        .bind(&since)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        sqlx::query(
+            "delete from journal_tag where journal_id in (select id from journal where date < ? and date >= ?)",
+        )
+        .bind(&before)
+        .bind(&since)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        sqlx::query("delete from journal where date < ? and date >= ?")
+            .bind(&before)
+            .bind(&since)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+    if remove {
+        state.render_cache.clear();
+    }
+
+    let record = sqlx::query_as::<_, ArchiveRecord>(
+        "select id, file_name, before_date, count, removed, create_time from journal_archive where id = ?",
+    )
+    .bind(id)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(Some(record))
+}
+
+/// 已经归档并从数据库删除的日期上界，仓库导入时跳过这之前的日记
+pub async fn removed_before(state: &AppState) -> Option<String> {
+    sqlx::query_scalar::<_, Option<String>>(
+        "select max(before_date) from journal_archive where removed = 1",
+    )
+    .fetch_one(&state.db)
+    .await
+    .ok()
+    .flatten()
+}
+
+pub async fn list(state: &AppState) -> Result<Vec<ArchiveRecord>, sqlx::Error> {
+    sqlx::query_as::<_, ArchiveRecord>(
+        "select id, file_name, before_date, count, removed, create_time from journal_archive order by id desc",
+    )
+    .fetch_all(&state.db)
+    .await
+}
+
+/// 把归档包中的日记写回数据库，同一天已有日记时以归档内容覆盖，返回恢复的篇数
+pub async fn restore(state: &AppState, id: i64) -> Result<usize, String> {
+    let record = sqlx::query_as::<_, ArchiveRecord>(
+        "select id, file_name, before_date, count, removed, create_time from journal_archive where id = ?",
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "archive not found".to_string())?;
+
+    let path = PathBuf::from(state.config.get_archive_path()).join(&record.file_name);
+    let rows = state
+        .blocking
+        .run("retention restore", move || read_archive(&path))
+        .await
+        .map_err(|_| "restore task join failed".to_string())??;
+    let entries = rows
+        .into_iter()
+        .map(|v| UpsertEntry {
+            date: v.date,
+            content: v.content,
+            metadata: v.metadata,
+            metadata_patch: None,
+        })
+        .collect::<Vec<_>>();
+    let report = journal::upsert_by_date_batch(state, &entries, "archive restore").await;
+    if !report.failed.is_empty() {
+        return Err(format!(
+            "{} of {} journals failed to restore",
+            report.failed.len(),
+            entries.len()
+        ));
+    }
+    // 日记重新回到库中，标记为未删除后下次归档从它的截止日期之后开始，避免重复打包
+    sqlx::query("update journal_archive set removed = 0 where id = ?")
+        .bind(id)
+        .execute(&state.db)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(report.upserted)
+}
+
+pub(crate) fn write_archive(path: &Path, json: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let file = fs::File::create(path).map_err(|e| e.to_string())?;
+    let mut zip = zip::ZipWriter::new(file);
+    zip.start_file(ARCHIVE_ENTRY_NAME, SimpleFileOptions::default())
+        .map_err(|e| e.to_string())?;
+    zip.write_all(json).map_err(|e| e.to_string())?;
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn read_archive(path: &Path) -> Result<Vec<ArchivedJournal>, String> {
+    let file = fs::File::open(path).map_err(|e| format!("open archive failed: {}", e))?;
+    let mut zip = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+    let mut entry = zip.by_name(ARCHIVE_ENTRY_NAME).map_err(|e| e.to_string())?;
+    let mut json = Vec::new();
+    entry.read_to_end(&mut json).map_err(|e| e.to_string())?;
+    serde_json::from_slice(&json).map_err(|e| e.to_string())
+}
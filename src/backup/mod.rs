@@ -0,0 +1,353 @@
+use crate::app_state::AppState;
+use crate::archive::{self, ArchivedJournal};
+use crate::config::app_config::BackupConfig;
+use crate::job::{JobDef, JobFuture};
+use crate::util::{date_util, file_util};
+use hmac::{Hmac, Mac};
+use reqwest::Url;
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::FromRow;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+const UPLOAD_TIMEOUT_SECS: u64 = 600;
+
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupRecord {
+    pub id: i64,
+    pub file_name: String,
+    pub target: String,
+    /// 本地路径或上传后的地址
+    pub location: String,
+    pub size: i64,
+    pub encrypted: bool,
+    pub create_time: i64,
+}
+
+/// 按 `backup.interval_hours` 定时备份
+pub fn job(state: &AppState) -> Option<JobDef> {
+    let cfg = &state.config.backup;
+    if !cfg.enabled || cfg.interval_hours == 0 {
+        return None;
+    }
+    if let Err(e) = validate(cfg) {
+        warn!("backup skipped: {}", e);
+        return None;
+    }
+    info!(
+        "backup job enabled, interval_hours={}, target={}, encrypted={}",
+        cfg.interval_hours,
+        cfg.target,
+        !cfg.encrypt_command.trim().is_empty()
+    );
+    Some(JobDef {
+        name: "backup".to_string(),
+        kind: "backup",
+        schedule: format!("@every {}h", cfg.interval_hours),
+        handler: run_job,
+        max_retries: 3,
+        catch_up: true,
+    })
+}
+
+fn run_job(state: AppState, _: Option<String>) -> JobFuture {
+    Box::pin(async move {
+        let record = run(&state).await?;
+        info!(
+            "backup finished {} -> {}",
+            record.file_name, record.location
+        );
+        Ok(())
+    })
+}
+
+/// 异地备份不允许上传明文
+pub fn validate(cfg: &BackupConfig) -> Result<(), String> {
+    let encrypted = !cfg.encrypt_command.trim().is_empty();
+    match cfg.target.trim() {
+        "local" => Ok(()),
+        "webdav" | "s3" if !encrypted => Err(format!(
+            "backup.encrypt_command is required for target {}",
+            cfg.target.trim()
+        )),
+        "webdav" if cfg.webdav_url.trim().is_empty() => {
+            Err("backup.webdav_url is required for webdav".to_string())
+        }
+        "s3" if [
+            &cfg.s3_endpoint,
+            &cfg.s3_bucket,
+            &cfg.s3_access_key,
+            &cfg.s3_secret_key,
+        ]
+        .iter()
+        .any(|v| v.trim().is_empty()) =>
+        {
+            Err("backup.s3_endpoint, s3_bucket, s3_access_key and s3_secret_key are required for s3".to_string())
+        }
+        "webdav" | "s3" => Ok(()),
+        other => Err(format!("unsupported backup target: {}", other)),
+    }
+}
+
+/// 打包全部日记，按配置加密并上传，成功后记录到 `backup_run`
+pub async fn run(state: &AppState) -> Result<BackupRecord, String> {
+    let cfg = &state.config.backup;
+    validate(cfg)?;
+    let target = cfg.target.trim().to_string();
+
+    let rows = sqlx::query_as::<_, ArchivedJournal>(
+        "select date, content, metadata, create_time, update_time from journal order by date asc",
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| e.to_string())?;
+    let json = serde_json::to_vec(&rows).map_err(|e| e.to_string())?;
+
+    let dir = PathBuf::from(state.config.get_backup_path());
+    let plain_name = format!("daylog-backup-{}.zip", date_util::now_secs());
+    let plain_path = dir.join(&plain_name);
+    let path = plain_path.clone();
+    state
+        .blocking
+        .run("backup", move || archive::write_archive(&path, &json))
+        .await
+        .map_err(|_| "backup task join failed".to_string())??;
+
+    let command = cfg.encrypt_command.trim();
+    let (file_name, file_path) = if command.is_empty() {
+        (plain_name, plain_path)
+    } else {
+        let name = format!("{}{}", plain_name, cfg.encrypt_suffix.trim());
+        let path = dir.join(&name);
+        let result = encrypt(cfg, &plain_path, &path).await;
+        // 明文包只在加密期间存在
+        let _ = tokio::fs::remove_file(&plain_path).await;
+        if let Err(e) = result {
+            let _ = tokio::fs::remove_file(&path).await;
+            return Err(e);
+        }
+        (name, path)
+    };
+
+    let body = tokio::fs::read(&file_path)
+        .await
+        .map_err(|e| format!("read backup failed: {}", e))?;
+    let size = body.len() as i64;
+    let location = match target.as_str() {
+        "webdav" => upload_webdav(&state.http, cfg, &file_name, body).await,
+        "s3" => upload_s3(&state.http, cfg, &file_name, body).await,
+        _ => Ok(file_path.to_string_lossy().to_string()),
+    };
+    let location = match location {
+        Ok(v) => v,
+        Err(e) => {
+            if !cfg.keep_local {
+                let _ = tokio::fs::remove_file(&file_path).await;
+            }
+            return Err(e);
+        }
+    };
+    if target != "local" && !cfg.keep_local {
+        let _ = tokio::fs::remove_file(&file_path).await;
+    }
+
+    let id = sqlx::query(
+        "insert into backup_run (file_name, target, location, size, encrypted, create_time) values (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&file_name)
+    .bind(&target)
+    .bind(&location)
+    .bind(size)
+    .bind(!command.is_empty())
+    .bind(date_util::now_secs())
+    .execute(&state.db)
+    .await
+    .map_err(|e| e.to_string())?
+    .last_insert_rowid();
+    sqlx::query_as::<_, BackupRecord>(
+        "select id, file_name, target, location, size, encrypted, create_time from backup_run where id = ?",
+    )
+    .bind(id)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+pub async fn list(state: &AppState) -> Result<Vec<BackupRecord>, sqlx::Error> {
+    sqlx::query_as::<_, BackupRecord>(
+        "select id, file_name, target, location, size, encrypted, create_time from backup_run order by id desc",
+    )
+    .fetch_all(&state.db)
+    .await
+}
+
+async fn encrypt(cfg: &BackupConfig, input: &Path, output: &Path) -> Result<(), String> {
+    let input = input.to_string_lossy();
+    let output = output.to_string_lossy();
+    let args = cfg
+        .encrypt_command
+        .split_whitespace()
+        .map(|v| v.replace("{input}", &input).replace("{output}", &output))
+        .collect::<Vec<_>>();
+    let (program, args) = args
+        .split_first()
+        .ok_or_else(|| "backup.encrypt_command is empty".to_string())?;
+    let run = Command::new(program).args(args).kill_on_drop(true).output();
+    let timeout = Duration::from_secs(cfg.encrypt_timeout_secs.max(1));
+    match tokio::time::timeout(timeout, run).await {
+        Ok(Ok(out)) if out.status.success() => {}
+        Ok(Ok(out)) => {
+            return Err(format!(
+                "encrypt command exit with {}: {}",
+                out.status,
+                String::from_utf8_lossy(&out.stderr).trim()
+            ));
+        }
+        Ok(Err(e)) => return Err(format!("run encrypt command failed: {}", e)),
+        Err(_) => return Err(format!("encrypt timeout after {}s", timeout.as_secs())),
+    }
+    match tokio::fs::metadata(output.as_ref()).await {
+        Ok(v) if v.len() > 0 => Ok(()),
+        _ => Err("encrypt command did not write {output}".to_string()),
+    }
+}
+
+async fn upload_webdav(
+    client: &reqwest::Client,
+    cfg: &BackupConfig,
+    file_name: &str,
+    body: Vec<u8>,
+) -> Result<String, String> {
+    let url = format!(
+        "{}/{}",
+        cfg.webdav_url.trim().trim_end_matches('/'),
+        file_name
+    );
+    let mut req = client
+        .put(&url)
+        .timeout(Duration::from_secs(UPLOAD_TIMEOUT_SECS))
+        .body(body);
+    if !cfg.webdav_username.trim().is_empty() {
+        req = req.basic_auth(cfg.webdav_username.trim(), Some(cfg.webdav_password.trim()));
+    }
+    let resp = req.send().await.map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("webdav upload failed: {}", resp.status()));
+    }
+    Ok(url)
+}
+
+/// 用 AWS Signature V4 签名直接 PUT，兼容 MinIO、R2 等 S3 接口
+async fn upload_s3(
+    client: &reqwest::Client,
+    cfg: &BackupConfig,
+    file_name: &str,
+    body: Vec<u8>,
+) -> Result<String, String> {
+    let endpoint = Url::parse(cfg.s3_endpoint.trim()).map_err(|e| e.to_string())?;
+    let host = match (endpoint.host_str(), endpoint.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => return Err("backup.s3_endpoint has no host".to_string()),
+    };
+    let key = format!(
+        "{}{}",
+        cfg.s3_prefix.trim().trim_start_matches('/'),
+        file_name
+    );
+    let path = format!(
+        "{}/{}/{}",
+        endpoint.path().trim_end_matches('/'),
+        uri_encode(cfg.s3_bucket.trim()),
+        uri_encode(&key)
+    );
+    let url = format!("{}://{}{}", endpoint.scheme(), host, path);
+
+    let now = date_util::now_secs();
+    let (y, m, d) = date_util::civil_from_days(now.div_euclid(86_400));
+    let secs = now.rem_euclid(86_400);
+    let date = format!("{:04}{:02}{:02}", y, m, d);
+    let amz_date = format!(
+        "{}T{:02}{:02}{:02}Z",
+        date,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    );
+    let payload_hash = file_util::file_hash(&body);
+    let region = cfg.s3_region.trim();
+    let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+    let canonical = format!(
+        "PUT\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+        path, host, payload_hash, amz_date, signed_headers, payload_hash
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        file_util::file_hash(&canonical)
+    );
+    let mut signing_key = hmac_sha256(
+        format!("AWS4{}", cfg.s3_secret_key.trim()).as_bytes(),
+        date.as_bytes(),
+    );
+    for part in [region, "s3", "aws4_request"] {
+        signing_key = hmac_sha256(&signing_key, part.as_bytes());
+    }
+    let signature = hmac_sha256(&signing_key, string_to_sign.as_bytes())
+        .iter()
+        .map(|v| format!("{:02x}", v))
+        .collect::<String>();
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        cfg.s3_access_key.trim(),
+        scope,
+        signed_headers,
+        signature
+    );
+
+    let resp = client
+        .put(&url)
+        .timeout(Duration::from_secs(UPLOAD_TIMEOUT_SECS))
+        .header("x-amz-content-sha256", &payload_hash)
+        .header("x-amz-date", &amz_date)
+        .header("authorization", authorization)
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        return Err(format!(
+            "s3 upload failed: {} {}",
+            status,
+            text.chars().take(200).collect::<String>()
+        ));
+    }
+    Ok(url)
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// S3 签名要求的路径编码，保留 `/` 和非保留字符
+fn uri_encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~' | b'/') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
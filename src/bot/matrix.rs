@@ -0,0 +1,266 @@
+use crate::app_state::AppState;
+use crate::bot::{self, Capture, CaptureSource, Incoming};
+use crate::config::app_config::MatrixConfig;
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tracing::{info, warn};
+
+const SYNC_TIMEOUT_MS: u64 = 30_000;
+
+static TXN_SEQ: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Deserialize)]
+struct JoinResp {
+    room_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SyncResp {
+    next_batch: String,
+    #[serde(default)]
+    rooms: SyncRooms,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SyncRooms {
+    #[serde(default)]
+    join: HashMap<String, JoinedRoom>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JoinedRoom {
+    #[serde(default)]
+    timeline: Timeline,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Timeline {
+    #[serde(default)]
+    events: Vec<RoomEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RoomEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    sender: String,
+    #[serde(default)]
+    content: Value,
+}
+
+pub struct MatrixSource {
+    cfg: MatrixConfig,
+    client: reqwest::Client,
+    room_id: Option<String>,
+    since: Option<String>,
+}
+
+/// 配置启用时在后台加入房间并开始 /sync 长轮询
+pub fn spawn(state: AppState) {
+    let cfg = state.config.matrix.clone();
+    if !cfg.enabled {
+        return;
+    }
+    if cfg.access_token.trim().is_empty() || cfg.room_id.trim().is_empty() {
+        warn!("matrix bot skipped: matrix.access_token and matrix.room_id are required");
+        return;
+    }
+    let source = MatrixSource {
+        cfg,
+        client: state.http.clone(),
+        room_id: None,
+        since: None,
+    };
+    bot::spawn(state, source);
+}
+
+impl CaptureSource for MatrixSource {
+    fn name(&self) -> &'static str {
+        "matrix"
+    }
+
+    async fn poll(&mut self) -> Result<Vec<Incoming>, String> {
+        let room_id = match self.room_id.clone() {
+            Some(v) => v,
+            None => {
+                let v = self.join_room().await?;
+                info!("matrix joined room {}", v);
+                self.room_id = Some(v.clone());
+                v
+            }
+        };
+
+        let first_sync = self.since.is_none();
+        let resp = self.sync().await?;
+        self.since = Some(resp.next_batch);
+        // 首次 sync 只记录位置，不回放历史消息
+        if first_sync {
+            return Ok(Vec::new());
+        }
+
+        let mut rooms = resp.rooms.join;
+        let Some(room) = rooms.remove(&room_id) else {
+            return Ok(Vec::new());
+        };
+        let mut out = Vec::new();
+        for event in room.timeline.events {
+            if event.kind != "m.room.message" || event.sender == self.cfg.user_id.trim() {
+                continue;
+            }
+            let capture = match self.to_capture(&event.content).await {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!("matrix message parse failed: {}", e);
+                    Capture::Unsupported
+                }
+            };
+            out.push(Incoming {
+                reply_to: room_id.clone(),
+                capture,
+            });
+        }
+        Ok(out)
+    }
+
+    async fn reply(&self, reply_to: &str, text: &str) -> Result<(), String> {
+        let txn_id = format!(
+            "daylog-{}-{}",
+            crate::util::date_util::now_secs(),
+            TXN_SEQ.fetch_add(1, Ordering::Relaxed)
+        );
+        let url = self.api_url(&[
+            "v3",
+            "rooms",
+            reply_to,
+            "send",
+            "m.room.message",
+            txn_id.as_str(),
+        ])?;
+        self.client
+            .put(url)
+            .bearer_auth(self.cfg.access_token.trim())
+            .json(&json!({ "msgtype": "m.text", "body": text }))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.without_url().to_string())?;
+        Ok(())
+    }
+}
+
+impl MatrixSource {
+    async fn to_capture(&self, content: &Value) -> Result<Capture, String> {
+        let msgtype = content.get("msgtype").and_then(Value::as_str).unwrap_or("");
+        let body = content
+            .get("body")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string();
+        match msgtype {
+            "m.text" => {
+                let trimmed = body.trim();
+                if trimmed == "!today" || trimmed == "/today" {
+                    return Ok(Capture::Today);
+                }
+                Ok(Capture::Text(body))
+            }
+            "m.image" => {
+                let mxc = content
+                    .get("url")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "encrypted or missing image url".to_string())?;
+                let mime = content
+                    .get("info")
+                    .and_then(|v| v.get("mimetype"))
+                    .and_then(Value::as_str)
+                    .unwrap_or("image/jpeg")
+                    .to_string();
+                let bytes = self.download_media(mxc).await?;
+                Ok(Capture::Image {
+                    name: format!("matrix_{}", body),
+                    mime,
+                    bytes,
+                    caption: None,
+                })
+            }
+            _ => Ok(Capture::Unsupported),
+        }
+    }
+
+    async fn join_room(&self) -> Result<String, String> {
+        let url = self.api_url(&["v3", "join", self.cfg.room_id.trim()])?;
+        let resp = self
+            .client
+            .post(url)
+            .bearer_auth(self.cfg.access_token.trim())
+            .json(&json!({}))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.without_url().to_string())?
+            .json::<JoinResp>()
+            .await
+            .map_err(|e| e.without_url().to_string())?;
+        Ok(resp.room_id)
+    }
+
+    async fn sync(&self) -> Result<SyncResp, String> {
+        let mut url = self.api_url(&["v3", "sync"])?;
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("timeout", &SYNC_TIMEOUT_MS.to_string());
+            if let Some(since) = self.since.as_deref() {
+                query.append_pair("since", since);
+            }
+        }
+        self.client
+            .get(url)
+            .bearer_auth(self.cfg.access_token.trim())
+            .timeout(Duration::from_millis(SYNC_TIMEOUT_MS + 10_000))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.without_url().to_string())?
+            .json::<SyncResp>()
+            .await
+            .map_err(|e| e.without_url().to_string())
+    }
+
+    async fn download_media(&self, mxc: &str) -> Result<Vec<u8>, String> {
+        let rest = mxc
+            .strip_prefix("mxc://")
+            .ok_or_else(|| format!("invalid mxc url: {}", mxc))?;
+        let (server, media_id) = rest
+            .split_once('/')
+            .ok_or_else(|| format!("invalid mxc url: {}", mxc))?;
+        let url = self.api_url(&["v1", "media", "download", server, media_id])?;
+        let bytes = self
+            .client
+            .get(url)
+            .bearer_auth(self.cfg.access_token.trim())
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.without_url().to_string())?
+            .bytes()
+            .await
+            .map_err(|e| e.without_url().to_string())?;
+        Ok(bytes.to_vec())
+    }
+
+    /// 拼接 `/_matrix/client/...`，每段都会做 url 编码（房间 id 含有 `!` `:`）
+    fn api_url(&self, segments: &[&str]) -> Result<Url, String> {
+        let mut url = Url::parse(self.cfg.homeserver.trim())
+            .map_err(|e| format!("invalid matrix.homeserver: {}", e))?;
+        url.path_segments_mut()
+            .map_err(|_| "invalid matrix.homeserver".to_string())?
+            .pop_if_empty()
+            .extend(["_matrix", "client"])
+            .extend(segments);
+        Ok(url)
+    }
+}
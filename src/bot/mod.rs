@@ -0,0 +1,125 @@
+pub mod matrix;
+pub mod telegram;
+
+use crate::app_state::AppState;
+use crate::http::{file, journal};
+use std::future::Future;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// 单条消息最大回复长度，兼容 Telegram(4096) 等平台的限制
+const MAX_REPLY_LEN: usize = 4000;
+
+/// 聊天平台收到的一条记录
+pub enum Capture {
+    Text(String),
+    Image {
+        name: String,
+        mime: String,
+        bytes: Vec<u8>,
+        caption: Option<String>,
+    },
+    Today,
+    Unsupported,
+}
+
+pub struct Incoming {
+    /// 回复目标，例如 Telegram chat id / Matrix room id
+    pub reply_to: String,
+    pub capture: Capture,
+}
+
+/// 快速记录来源（Telegram/Matrix 等），追加日记的逻辑由 `run` 统一处理
+pub trait CaptureSource: Send + 'static {
+    fn name(&self) -> &'static str;
+
+    fn poll(&mut self) -> impl Future<Output = Result<Vec<Incoming>, String>> + Send;
+
+    fn reply(&self, reply_to: &str, text: &str) -> impl Future<Output = Result<(), String>> + Send;
+}
+
+pub fn spawn<S: CaptureSource>(state: AppState, source: S) {
+    tokio::spawn(run(state, source));
+}
+
+async fn run<S: CaptureSource>(state: AppState, mut source: S) {
+    info!("{} bot started", source.name());
+    loop {
+        let items = match source.poll().await {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("{} poll failed: {}", source.name(), e);
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+        };
+        for item in items {
+            let reply = match handle_capture(&state, item.capture).await {
+                Ok(v) => v,
+                Err(e) => {
+                    error!("{} message handle failed: {}", source.name(), e);
+                    format!("failed: {}", e)
+                }
+            };
+            if let Err(e) = source.reply(&item.reply_to, &reply).await {
+                warn!("{} reply failed: {}", source.name(), e);
+            }
+        }
+    }
+}
+
+async fn handle_capture(state: &AppState, capture: Capture) -> Result<String, String> {
+    let today = state.config.today();
+    match capture {
+        Capture::Today => {
+            let journal = journal::find_journal_by_date(state, &today)
+                .await
+                .map_err(|_| "db query failed".to_string())?;
+            Ok(match journal {
+                Some(j) => truncate_text(&format!("{}\n\n{}", j.date, j.content), MAX_REPLY_LEN),
+                None => format!("{}: no journal yet", today),
+            })
+        }
+        Capture::Text(text) => {
+            let text = text.trim();
+            if text.is_empty() {
+                return Ok("empty message ignored".to_string());
+            }
+            journal::append_to_date(state, &today, text)
+                .await
+                .map_err(|_| "db update failed".to_string())?;
+            Ok(format!("appended to {}", today))
+        }
+        Capture::Image {
+            name,
+            mime,
+            bytes,
+            caption,
+        } => {
+            let name = file::sanitize_file_name(&name);
+            let uri = file::store_file(state, &name, &mime, bytes.into())
+                .await
+                .map_err(|(_, msg)| msg.to_string())?;
+            let mut text = format!("![{}]({})", name, uri);
+            if let Some(caption) = caption.as_deref().map(str::trim)
+                && !caption.is_empty()
+            {
+                text = format!("{}\n\n{}", caption, text);
+            }
+            journal::append_to_date(state, &today, &text)
+                .await
+                .map_err(|_| "db update failed".to_string())?;
+            Ok(format!("photo appended to {}", today))
+        }
+        Capture::Unsupported => Ok("unsupported message, try /today".to_string()),
+    }
+}
+
+fn truncate_text(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut out = text.chars().take(max_chars).collect::<String>();
+    out.push_str("\n...");
+    out
+}
@@ -0,0 +1,208 @@
+use crate::app_state::AppState;
+use crate::bot::{self, Capture, CaptureSource, Incoming};
+use crate::config::app_config::TelegramConfig;
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::warn;
+
+const API_BASE: &str = "https://api.telegram.org";
+
+#[derive(Debug, Deserialize)]
+struct TgResp<T> {
+    ok: bool,
+    description: Option<String>,
+    result: Option<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    chat: Chat,
+    text: Option<String>,
+    caption: Option<String>,
+    photo: Option<Vec<PhotoSize>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct PhotoSize {
+    file_id: String,
+    file_unique_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TgFile {
+    file_path: Option<String>,
+}
+
+pub struct TelegramSource {
+    cfg: TelegramConfig,
+    client: reqwest::Client,
+    offset: i64,
+}
+
+/// 配置启用时在后台启动 Telegram 长轮询
+pub fn spawn(state: AppState) {
+    let cfg = state.config.telegram.clone();
+    if !cfg.enabled {
+        return;
+    }
+    if cfg.token.trim().is_empty() {
+        warn!("telegram bot skipped: telegram.token is empty");
+        return;
+    }
+    let source = TelegramSource {
+        cfg,
+        client: state.http.clone(),
+        offset: 0,
+    };
+    bot::spawn(state, source);
+}
+
+impl CaptureSource for TelegramSource {
+    fn name(&self) -> &'static str {
+        "telegram"
+    }
+
+    async fn poll(&mut self) -> Result<Vec<Incoming>, String> {
+        let updates = self.get_updates().await?;
+        let mut out = Vec::new();
+        for update in updates {
+            self.offset = self.offset.max(update.update_id + 1);
+            let Some(message) = update.message else {
+                continue;
+            };
+            let chat_id = message.chat.id;
+            if !self.cfg.allowed_chat_ids.contains(&chat_id) {
+                warn!("telegram message ignored: chat {} not allowed", chat_id);
+                continue;
+            }
+            let capture = match self.to_capture(message).await {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!("telegram message parse failed: {}", e);
+                    Capture::Unsupported
+                }
+            };
+            out.push(Incoming {
+                reply_to: chat_id.to_string(),
+                capture,
+            });
+        }
+        Ok(out)
+    }
+
+    async fn reply(&self, reply_to: &str, text: &str) -> Result<(), String> {
+        let url = format!("{}/bot{}/sendMessage", API_BASE, self.cfg.token.trim());
+        let resp = self
+            .client
+            .post(url)
+            .json(&serde_json::json!({ "chat_id": reply_to, "text": text }))
+            .send()
+            .await
+            .map_err(|e| e.without_url().to_string())?
+            .json::<TgResp<serde_json::Value>>()
+            .await
+            .map_err(|e| e.without_url().to_string())?;
+        unwrap_resp(resp).map(|_| ())
+    }
+}
+
+impl TelegramSource {
+    async fn to_capture(&self, message: Message) -> Result<Capture, String> {
+        if let Some(text) = message.text {
+            let trimmed = text.trim();
+            if trimmed == "/today" || trimmed.starts_with("/today@") {
+                return Ok(Capture::Today);
+            }
+            if trimmed.starts_with('/') {
+                return Ok(Capture::Unsupported);
+            }
+            return Ok(Capture::Text(text));
+        }
+
+        let Some(photo) = message.photo.as_ref().and_then(|v| v.last()) else {
+            return Ok(Capture::Unsupported);
+        };
+        let bytes = self.download_file(&photo.file_id).await?;
+        Ok(Capture::Image {
+            name: format!("telegram_{}.jpg", photo.file_unique_id),
+            mime: "image/jpeg".to_string(),
+            bytes,
+            caption: message.caption,
+        })
+    }
+
+    async fn get_updates(&self) -> Result<Vec<Update>, String> {
+        let url = format!("{}/bot{}/getUpdates", API_BASE, self.cfg.token.trim());
+        let resp = self
+            .client
+            .get(url)
+            .query(&[
+                ("offset", self.offset.to_string()),
+                ("timeout", self.cfg.poll_timeout.to_string()),
+            ])
+            .timeout(Duration::from_secs(self.cfg.poll_timeout + 10))
+            .send()
+            .await
+            .map_err(|e| e.without_url().to_string())?
+            .json::<TgResp<Vec<Update>>>()
+            .await
+            .map_err(|e| e.without_url().to_string())?;
+        unwrap_resp(resp)
+    }
+
+    async fn download_file(&self, file_id: &str) -> Result<Vec<u8>, String> {
+        let url = format!("{}/bot{}/getFile", API_BASE, self.cfg.token.trim());
+        let resp = self
+            .client
+            .get(url)
+            .query(&[("file_id", file_id)])
+            .send()
+            .await
+            .map_err(|e| e.without_url().to_string())?
+            .json::<TgResp<TgFile>>()
+            .await
+            .map_err(|e| e.without_url().to_string())?;
+        let file_path = unwrap_resp(resp)?
+            .file_path
+            .ok_or_else(|| "telegram file_path missing".to_string())?;
+
+        let url = format!(
+            "{}/file/bot{}/{}",
+            API_BASE,
+            self.cfg.token.trim(),
+            file_path
+        );
+        let bytes = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.without_url().to_string())?
+            .bytes()
+            .await
+            .map_err(|e| e.without_url().to_string())?;
+        Ok(bytes.to_vec())
+    }
+}
+
+fn unwrap_resp<T>(resp: TgResp<T>) -> Result<T, String> {
+    if !resp.ok {
+        return Err(resp
+            .description
+            .unwrap_or_else(|| "telegram api error".to_string()));
+    }
+    resp.result
+        .ok_or_else(|| "telegram api returned empty result".to_string())
+}
@@ -1,7 +1,9 @@
 use crate::util;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tracing::error;
 
 fn default_base_path() -> String {
@@ -31,12 +33,33 @@ fn default_static_path() -> String {
 fn default_upload_file_limit() -> usize {
     1024 * 1024 * 100
 }
+fn default_json_body_limit() -> usize {
+    1024 * 1024 * 2
+}
 fn default_auto_switch_port_time() -> i16 {
     100
 }
+fn default_blocking_workers() -> usize {
+    2
+}
+fn default_locale() -> String {
+    "en".to_string()
+}
+fn default_highlight_tag() -> String {
+    String::new()
+}
+fn default_render_cache_size() -> usize {
+    512
+}
+fn default_utc_offset_minutes() -> i32 {
+    0
+}
 fn default_sync_enabled() -> bool {
     false
 }
+fn default_sync_backend() -> String {
+    "git".to_string()
+}
 fn default_sync_repo_url() -> String {
     "".to_string()
 }
@@ -85,11 +108,316 @@ fn default_sync_repo_local_path() -> String {
 fn default_sync_import_patterns() -> Vec<String> {
     Vec::new()
 }
+fn default_sync_interval_minutes() -> u64 {
+    0
+}
+fn default_sync_on_startup() -> bool {
+    false
+}
+fn default_sync_mode() -> String {
+    "push".to_string()
+}
+fn default_sync_conflict_strategy() -> String {
+    "newest_wins".to_string()
+}
+fn default_sync_squash_window() -> u64 {
+    0
+}
+fn default_sync_commit_trailers() -> Vec<String> {
+    Vec::new()
+}
+fn default_sync_index_file() -> bool {
+    false
+}
+fn default_sync_readme_file() -> bool {
+    false
+}
+fn default_sync_sparse_paths() -> Vec<String> {
+    Vec::new()
+}
+fn default_sync_outputs() -> Vec<SyncOutput> {
+    Vec::new()
+}
+fn default_notify_enabled() -> bool {
+    false
+}
+fn default_notify_provider() -> String {
+    "ntfy".to_string()
+}
+fn default_notify_webhook_url() -> String {
+    "".to_string()
+}
+fn default_notify_ntfy_server() -> String {
+    "https://ntfy.sh".to_string()
+}
+fn default_notify_ntfy_topic() -> String {
+    "".to_string()
+}
+fn default_notify_telegram_chat_id() -> i64 {
+    0
+}
+fn default_notify_events() -> Vec<String> {
+    vec![
+        "sync_failed".to_string(),
+        "missed_journal".to_string(),
+        "reminder".to_string(),
+        "weekly_summary".to_string(),
+        "trash_purged".to_string(),
+    ]
+}
+fn default_matrix_enabled() -> bool {
+    false
+}
+fn default_matrix_homeserver() -> String {
+    "https://matrix.org".to_string()
+}
+fn default_matrix_access_token() -> String {
+    "".to_string()
+}
+fn default_matrix_user_id() -> String {
+    "".to_string()
+}
+fn default_matrix_room_id() -> String {
+    "".to_string()
+}
+fn default_reminder_enabled() -> bool {
+    false
+}
+fn default_reminder_times() -> Vec<String> {
+    vec!["21:30".to_string()]
+}
+fn default_digest_enabled() -> bool {
+    true
+}
+fn default_export_pdf_command() -> String {
+    "".to_string()
+}
+fn default_db_wal_autocheckpoint() -> u32 {
+    1000
+}
+fn default_db_cache_size() -> i64 {
+    -2000
+}
+fn default_db_mmap_size() -> u64 {
+    0
+}
+fn default_db_read_pool_size() -> u32 {
+    4
+}
+fn default_retention_enabled() -> bool {
+    false
+}
+fn default_retention_archive_after_years() -> u32 {
+    5
+}
+fn default_retention_remove_from_db() -> bool {
+    false
+}
+fn default_retention_trash_days() -> u32 {
+    0
+}
+fn default_backup_enabled() -> bool {
+    false
+}
+fn default_backup_interval_hours() -> u64 {
+    24
+}
+fn default_backup_encrypt_command() -> String {
+    "".to_string()
+}
+fn default_backup_encrypt_suffix() -> String {
+    ".age".to_string()
+}
+fn default_backup_encrypt_timeout_secs() -> u64 {
+    300
+}
+fn default_backup_target() -> String {
+    "local".to_string()
+}
+fn default_backup_keep_local() -> bool {
+    false
+}
+fn default_backup_webdav_url() -> String {
+    "".to_string()
+}
+fn default_backup_webdav_username() -> String {
+    "".to_string()
+}
+fn default_backup_webdav_password() -> String {
+    "".to_string()
+}
+fn default_backup_s3_endpoint() -> String {
+    "".to_string()
+}
+fn default_backup_s3_region() -> String {
+    "us-east-1".to_string()
+}
+fn default_backup_s3_bucket() -> String {
+    "".to_string()
+}
+fn default_backup_s3_prefix() -> String {
+    "daylog/".to_string()
+}
+fn default_backup_s3_access_key() -> String {
+    "".to_string()
+}
+fn default_backup_s3_secret_key() -> String {
+    "".to_string()
+}
+fn default_quick_token() -> String {
+    "".to_string()
+}
+fn default_auth_token() -> String {
+    "".to_string()
+}
+fn default_auth_tokens() -> Vec<ScopedToken> {
+    Vec::new()
+}
+fn default_auth_username() -> String {
+    "".to_string()
+}
+fn default_auth_password() -> String {
+    "".to_string()
+}
+fn default_auth_session_days() -> i64 {
+    30
+}
+fn default_auth_max_failures() -> u32 {
+    5
+}
+fn default_auth_lockout_secs() -> u64 {
+    60
+}
+fn default_auth_max_lockout_secs() -> u64 {
+    3600
+}
+fn default_auth_trust_forwarded_for() -> bool {
+    false
+}
+fn default_auth_public_files() -> bool {
+    true
+}
+fn default_hooks_token() -> String {
+    "".to_string()
+}
+fn default_public_stats() -> bool {
+    false
+}
+fn default_public_per_minute() -> u32 {
+    30
+}
+fn default_upload_scan_command() -> String {
+    "".to_string()
+}
+fn default_upload_scan_timeout_secs() -> u64 {
+    60
+}
+fn default_upload_thumb_small_px() -> u32 {
+    320
+}
+fn default_upload_thumb_medium_px() -> u32 {
+    1280
+}
+fn default_upload_optimize_min_bytes() -> u64 {
+    0
+}
+fn default_upload_optimize_quality() -> u8 {
+    82
+}
+fn default_upload_optimize_keep_original() -> bool {
+    false
+}
+fn default_media_gif_max_px() -> u32 {
+    0
+}
+fn default_media_video_max_px() -> u32 {
+    0
+}
+fn default_media_video_max_kbps() -> u32 {
+    0
+}
+fn default_media_over_limit() -> String {
+    "reject".to_string()
+}
+fn default_media_probe_command() -> String {
+    "ffprobe -v error -print_format json -show_format -show_streams {input}".to_string()
+}
+fn default_media_transcode_command() -> String {
+    "".to_string()
+}
+fn default_media_timeout_secs() -> u64 {
+    600
+}
+fn default_media_keep_original() -> bool {
+    false
+}
+fn default_rate_limit_per_minute() -> u32 {
+    300
+}
+fn default_rate_limit_upload_per_minute() -> u32 {
+    30
+}
+fn default_rate_limit_sync_per_minute() -> u32 {
+    6
+}
+fn default_search_tokenizer() -> String {
+    "unicode61".to_string()
+}
+fn default_render_raw_html() -> bool {
+    false
+}
+fn default_render_allowed_tags() -> Vec<String> {
+    Vec::new()
+}
+fn default_render_allowed_url_schemes() -> Vec<String> {
+    ["http", "https", "mailto", "tel"]
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+fn default_security_enabled() -> bool {
+    true
+}
+fn default_security_content_security_policy() -> String {
+    "default-src 'self'; img-src 'self' data: blob: https:; media-src 'self' blob: https:; style-src 'self' 'unsafe-inline'; script-src 'self'; object-src 'none'; base-uri 'self'; form-action 'self'".to_string()
+}
+fn default_security_frame_ancestors() -> String {
+    "'none'".to_string()
+}
+fn default_security_referrer_policy() -> String {
+    "same-origin".to_string()
+}
+fn default_http_timeout_secs() -> u64 {
+    30
+}
+fn default_http_connect_timeout_secs() -> u64 {
+    10
+}
+fn default_outbound_block_private() -> bool {
+    true
+}
+fn default_telegram_enabled() -> bool {
+    false
+}
+fn default_telegram_token() -> String {
+    "".to_string()
+}
+fn default_telegram_allowed_chat_ids() -> Vec<i64> {
+    Vec::new()
+}
+fn default_telegram_poll_timeout() -> u64 {
+    30
+}
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct SyncConfig {
     #[serde(default = "default_sync_enabled")]
     pub enabled: bool,
+    /// `git` 推送到 git 仓库；`webdav` 上传到 WebDAV 目录（例如 Nextcloud）；`dir` 复制到本地目录（网盘同步目录、NAS 挂载点）。
+    /// 后两种没有提交历史，提交信息、`squash_window`、`sparse_paths` 不生效，双向同步按文件修改时间判断冲突
+    #[serde(default = "default_sync_backend")]
+    pub backend: String,
+    /// git 仓库地址、WebDAV 目录地址或本地目录的绝对路径；`username` `password` 也用于 WebDAV 认证
     #[serde(default = "default_sync_repo_url")]
     pub repo_url: String,
     #[serde(default = "default_sync_branch")]
@@ -114,6 +442,8 @@ pub struct SyncConfig {
     pub author_email: String,
     #[serde(default = "default_sync_commit_message")]
     pub commit_message: String,
+    /// `markdown` `json` `html`；json 和 html 输出时路径模板中的 `.md` 换成 `.json` `.html`，
+    /// 双向同步只读回 markdown 文件
     #[serde(default = "default_sync_output_format")]
     pub output_format: String,
     #[serde(default = "default_sync_output_path")]
@@ -122,12 +452,54 @@ pub struct SyncConfig {
     pub repo_local_path: String,
     #[serde(default = "default_sync_import_patterns")]
     pub import_patterns: Vec<String>,
+    /// 定时同步间隔（分钟），0 为关闭
+    #[serde(default = "default_sync_interval_minutes")]
+    pub interval_minutes: u64,
+    /// 启动导入完成后把数据库中的日记同步回仓库
+    #[serde(default = "default_sync_on_startup")]
+    pub sync_on_startup: bool,
+    /// `push` 只把数据库写入仓库，启动时整体导入仓库；
+    /// `two_way` 每次同步前先拉取仓库中改过的日记，两边都改过的按 `conflict_strategy` 处理
+    #[serde(default = "default_sync_mode")]
+    pub mode: String,
+    /// `remote_wins` `local_wins` `newest_wins`（比较提交时间和日记更新时间）`keep_both`（两边内容合并到同一篇）
+    #[serde(default = "default_sync_conflict_strategy")]
+    pub conflict_strategy: String,
+    /// 定时同步改写上一次定时提交的时间窗口（分钟），从被改写的第一次提交算起，0 为关闭
+    #[serde(default = "default_sync_squash_window")]
+    pub squash_window: u64,
+    /// 追加到每条提交信息末尾的 trailer，支持提交信息的占位符，例如 `Synced-By: day-log v{version}`
+    #[serde(default = "default_sync_commit_trailers")]
+    pub commit_trailers: Vec<String>,
+    /// 每次同步在输出根目录写入 index.json（日期 -> 路径、更新时间、字数）
+    #[serde(default = "default_sync_index_file")]
+    pub index_file: bool,
+    /// 每次同步在输出根目录生成 README.md 目录（年 -> 月 -> 每天的链接和标题），方便在 GitHub 上浏览
+    #[serde(default = "default_sync_readme_file")]
+    pub readme_file: bool,
+    /// 一次同步写出多个目标，例如按天的 markdown 加一个汇总的 README.md；
+    /// 配置后取代 `output_path` `output_format`
+    #[serde(default = "default_sync_outputs")]
+    pub outputs: Vec<SyncOutput>,
+    /// 日记放在大仓库的子目录时只检出这些目录，同步也只写入它们下面的文件；
+    /// libgit2 不支持 partial clone，对象仍会完整下载，省下的是工作区
+    #[serde(default = "default_sync_sparse_paths")]
+    pub sparse_paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SyncOutput {
+    /// 同 `output_path`，含日期占位符时每篇日记一个文件，否则全部写入一个文件
+    pub path_template: String,
+    #[serde(default = "default_sync_output_format")]
+    pub format: String,
 }
 
 impl Default for SyncConfig {
     fn default() -> Self {
         Self {
             enabled: default_sync_enabled(),
+            backend: default_sync_backend(),
             repo_url: default_sync_repo_url(),
             branch: default_sync_branch(),
             username: default_sync_username(),
@@ -144,9 +516,687 @@ impl Default for SyncConfig {
             output_path: default_sync_output_path(),
             repo_local_path: default_sync_repo_local_path(),
             import_patterns: default_sync_import_patterns(),
+            interval_minutes: default_sync_interval_minutes(),
+            sync_on_startup: default_sync_on_startup(),
+            mode: default_sync_mode(),
+            conflict_strategy: default_sync_conflict_strategy(),
+            squash_window: default_sync_squash_window(),
+            commit_trailers: default_sync_commit_trailers(),
+            index_file: default_sync_index_file(),
+            readme_file: default_sync_readme_file(),
+            outputs: default_sync_outputs(),
+            sparse_paths: default_sync_sparse_paths(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TelegramConfig {
+    #[serde(default = "default_telegram_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_telegram_token")]
+    pub token: String,
+    #[serde(default = "default_telegram_allowed_chat_ids")]
+    pub allowed_chat_ids: Vec<i64>,
+    #[serde(default = "default_telegram_poll_timeout")]
+    pub poll_timeout: u64,
+}
+
+impl Default for TelegramConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_telegram_enabled(),
+            token: default_telegram_token(),
+            allowed_chat_ids: default_telegram_allowed_chat_ids(),
+            poll_timeout: default_telegram_poll_timeout(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotifyConfig {
+    #[serde(default = "default_notify_enabled")]
+    pub enabled: bool,
+    /// slack/discord/ntfy/telegram/webhook
+    #[serde(default = "default_notify_provider")]
+    pub provider: String,
+    #[serde(default = "default_notify_webhook_url")]
+    pub webhook_url: String,
+    #[serde(default = "default_notify_ntfy_server")]
+    pub ntfy_server: String,
+    #[serde(default = "default_notify_ntfy_topic")]
+    pub ntfy_topic: String,
+    /// telegram 通知复用 `telegram.token`，为 0 时发给 `telegram.allowed_chat_ids` 的第一个
+    #[serde(default = "default_notify_telegram_chat_id")]
+    pub telegram_chat_id: i64,
+    #[serde(default = "default_notify_events")]
+    pub events: Vec<String>,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_notify_enabled(),
+            provider: default_notify_provider(),
+            webhook_url: default_notify_webhook_url(),
+            ntfy_server: default_notify_ntfy_server(),
+            ntfy_topic: default_notify_ntfy_topic(),
+            telegram_chat_id: default_notify_telegram_chat_id(),
+            events: default_notify_events(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MatrixConfig {
+    #[serde(default = "default_matrix_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_matrix_homeserver")]
+    pub homeserver: String,
+    #[serde(default = "default_matrix_access_token")]
+    pub access_token: String,
+    /// bot 自己的 user id，用于忽略自己发出的消息
+    #[serde(default = "default_matrix_user_id")]
+    pub user_id: String,
+    /// 房间 id 或别名，例如 !abc:matrix.org / #daylog:matrix.org
+    #[serde(default = "default_matrix_room_id")]
+    pub room_id: String,
+}
+
+impl Default for MatrixConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_matrix_enabled(),
+            homeserver: default_matrix_homeserver(),
+            access_token: default_matrix_access_token(),
+            user_id: default_matrix_user_id(),
+            room_id: default_matrix_room_id(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReminderConfig {
+    #[serde(default = "default_reminder_enabled")]
+    pub enabled: bool,
+    /// 本地时间 HH:mm，到点时当天还没有日记就通过 notify 提醒
+    #[serde(default = "default_reminder_times")]
+    pub times: Vec<String>,
+}
+
+impl Default for ReminderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_reminder_enabled(),
+            times: default_reminder_times(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DigestConfig {
+    /// 每周一自动生成上周周报，并以 weekly_summary 事件推送
+    #[serde(default = "default_digest_enabled")]
+    pub enabled: bool,
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_digest_enabled(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExportConfig {
+    /// html 转 pdf 的外部命令，`{input}` `{output}` 会被替换为文件路径，
+    /// 例如 `wkhtmltopdf --enable-local-file-access {input} {output}`
+    #[serde(default = "default_export_pdf_command")]
+    pub pdf_command: String,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            pdf_command: default_export_pdf_command(),
+        }
+    }
+}
+
+/// 后台任务的执行时间，key 为任务名（`GET /admin/jobs` 中的 name），
+/// value 为 5 段 cron 表达式（本地时间）或 `@every 6h` 这样的间隔，例如 `backup = "0 3 * * *"`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JobsConfig {
+    #[serde(default)]
+    pub schedules: HashMap<String, String>,
+}
+
+/// 每个连接建立时设置的 sqlite pragma，默认值和 sqlite 相同；
+/// 树莓派这类 SD 卡存储上调大 `wal_autocheckpoint` 可以减少随机写
+#[derive(Debug, Clone, Deserialize)]
+pub struct DbConfig {
+    /// wal 文件超过这么多页后自动写回数据库，0 为关闭自动 checkpoint
+    #[serde(default = "default_db_wal_autocheckpoint")]
+    pub wal_autocheckpoint: u32,
+    /// 正数为页数，负数为 KiB，例如 -8192 是 8 MiB
+    #[serde(default = "default_db_cache_size")]
+    pub cache_size: i64,
+    /// 内存映射读取的字节数，0 为不使用
+    #[serde(default = "default_db_mmap_size")]
+    pub mmap_size: u64,
+    /// 列表、搜索、统计使用的只读连接数，0 为和写入共用一个连接池
+    #[serde(default = "default_db_read_pool_size")]
+    pub read_pool_size: u32,
+}
+
+impl Default for DbConfig {
+    fn default() -> Self {
+        Self {
+            wal_autocheckpoint: default_db_wal_autocheckpoint(),
+            cache_size: default_db_cache_size(),
+            mmap_size: default_db_mmap_size(),
+            read_pool_size: default_db_read_pool_size(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetentionConfig {
+    /// 定时把旧日记打包到 `{base_path}/archive/`
+    #[serde(default = "default_retention_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_retention_archive_after_years")]
+    pub archive_after_years: u32,
+    /// 归档后从数据库删除，可通过 `POST /archive/{id}/restore` 恢复
+    #[serde(default = "default_retention_remove_from_db")]
+    pub remove_from_db: bool,
+    /// 删除的日记先放进回收站，这么多天后连同不再被引用的附件一起彻底删除；0 为直接删除
+    #[serde(default = "default_retention_trash_days")]
+    pub trash_days: u32,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_retention_enabled(),
+            archive_after_years: default_retention_archive_after_years(),
+            remove_from_db: default_retention_remove_from_db(),
+            trash_days: default_retention_trash_days(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BackupConfig {
+    /// 定时把全部日记打包、加密后存到 `target`
+    #[serde(default = "default_backup_enabled")]
+    pub enabled: bool,
+    /// 两次备份的间隔，0 为只通过 `POST /backup/run` 手动备份
+    #[serde(default = "default_backup_interval_hours")]
+    pub interval_hours: u64,
+    /// 加密命令，`{input}` 替换为明文备份包，`{output}` 替换为加密后的文件，
+    /// 例如 `age -R /etc/daylog/backup.pub -o {output} {input}`；
+    /// 服务器上只放公钥，解密用的私钥另外保管
+    #[serde(default = "default_backup_encrypt_command")]
+    pub encrypt_command: String,
+    /// 加密后文件的后缀，gpg 可改为 `.gpg`
+    #[serde(default = "default_backup_encrypt_suffix")]
+    pub encrypt_suffix: String,
+    #[serde(default = "default_backup_encrypt_timeout_secs")]
+    pub encrypt_timeout_secs: u64,
+    /// `local` 只保存在 `{base_path}/backup/`，`webdav` `s3` 上传到异地，异地备份必须配置 `encrypt_command`
+    #[serde(default = "default_backup_target")]
+    pub target: String,
+    /// 上传成功后仍保留本地的加密文件
+    #[serde(default = "default_backup_keep_local")]
+    pub keep_local: bool,
+    /// 备份目录地址，文件名追加在后面
+    #[serde(default = "default_backup_webdav_url")]
+    pub webdav_url: String,
+    #[serde(default = "default_backup_webdav_username")]
+    pub webdav_username: String,
+    #[serde(default = "default_backup_webdav_password")]
+    pub webdav_password: String,
+    /// 例如 `https://s3.us-east-1.amazonaws.com`，按 path-style 访问 `{endpoint}/{bucket}/{key}`
+    #[serde(default = "default_backup_s3_endpoint")]
+    pub s3_endpoint: String,
+    #[serde(default = "default_backup_s3_region")]
+    pub s3_region: String,
+    #[serde(default = "default_backup_s3_bucket")]
+    pub s3_bucket: String,
+    #[serde(default = "default_backup_s3_prefix")]
+    pub s3_prefix: String,
+    #[serde(default = "default_backup_s3_access_key")]
+    pub s3_access_key: String,
+    #[serde(default = "default_backup_s3_secret_key")]
+    pub s3_secret_key: String,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_backup_enabled(),
+            interval_hours: default_backup_interval_hours(),
+            encrypt_command: default_backup_encrypt_command(),
+            encrypt_suffix: default_backup_encrypt_suffix(),
+            encrypt_timeout_secs: default_backup_encrypt_timeout_secs(),
+            target: default_backup_target(),
+            keep_local: default_backup_keep_local(),
+            webdav_url: default_backup_webdav_url(),
+            webdav_username: default_backup_webdav_username(),
+            webdav_password: default_backup_webdav_password(),
+            s3_endpoint: default_backup_s3_endpoint(),
+            s3_region: default_backup_s3_region(),
+            s3_bucket: default_backup_s3_bucket(),
+            s3_prefix: default_backup_s3_prefix(),
+            s3_access_key: default_backup_s3_access_key(),
+            s3_secret_key: default_backup_s3_secret_key(),
         }
     }
 }
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuickConfig {
+    /// 为空时关闭 `POST /quick`
+    #[serde(default = "default_quick_token")]
+    pub token: String,
+}
+
+impl Default for QuickConfig {
+    fn default() -> Self {
+        Self {
+            token: default_quick_token(),
+        }
+    }
+}
+
+/// 不需要鉴权的 `/public` 接口，只返回汇总数字，不经过鉴权中间件，按 `per_minute` 单独限流
+#[derive(Debug, Clone, Deserialize)]
+pub struct PublicConfig {
+    /// 开启 `GET /public/stats`：连续记录天数和总篇数
+    #[serde(default = "default_public_stats")]
+    pub stats: bool,
+    /// 每个 ip 每分钟的请求数，0 为不限制
+    #[serde(default = "default_public_per_minute")]
+    pub per_minute: u32,
+}
+
+impl Default for PublicConfig {
+    fn default() -> Self {
+        Self {
+            stats: default_public_stats(),
+            per_minute: default_public_per_minute(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HooksConfig {
+    /// 为空时关闭 `POST /hooks/ingest`
+    #[serde(default = "default_hooks_token")]
+    pub token: String,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            token: default_hooks_token(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthConfig {
+    /// api 访问令牌，首次启动时可通过 `POST /setup` 写入
+    #[serde(default = "default_auth_token")]
+    pub token: String,
+    /// 限定权限的令牌，例如只给快捷指令 `upload-only`，`token` 相当于拥有 `admin`
+    #[serde(default = "default_auth_tokens")]
+    pub tokens: Vec<ScopedToken>,
+    /// 配置后可通过 `POST /auth/login` 登录，换取带 `admin` 权限的会话令牌
+    #[serde(default = "default_auth_username")]
+    pub username: String,
+    #[serde(default = "default_auth_password")]
+    pub password: String,
+    /// 登录会话的有效天数
+    #[serde(default = "default_auth_session_days")]
+    pub session_days: i64,
+    /// 同一 ip 或令牌连续失败这么多次后开始锁定，0 为不锁定
+    #[serde(default = "default_auth_max_failures")]
+    pub max_failures: u32,
+    /// 第一次锁定的秒数，之后每多失败一次翻倍
+    #[serde(default = "default_auth_lockout_secs")]
+    pub lockout_secs: u64,
+    #[serde(default = "default_auth_max_lockout_secs")]
+    pub max_lockout_secs: u64,
+    /// 部署在反向代理之后时按 `X-Forwarded-For` 的第一个地址区分客户端，限流也按这个地址计数
+    #[serde(default = "default_auth_trust_forwarded_for")]
+    pub trust_forwarded_for: bool,
+    /// `/files/` 下上传的文件不需要令牌即可读取，分享页中的图片依赖这一点；
+    /// 为 false 时需要 `read` 权限，浏览器登录后带 cookie 访问。`/files/by-id/{id}` 始终需要 `read`
+    #[serde(default = "default_auth_public_files")]
+    pub public_files: bool,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            token: default_auth_token(),
+            tokens: default_auth_tokens(),
+            username: default_auth_username(),
+            password: default_auth_password(),
+            session_days: default_auth_session_days(),
+            max_failures: default_auth_max_failures(),
+            lockout_secs: default_auth_lockout_secs(),
+            max_lockout_secs: default_auth_max_lockout_secs(),
+            trust_forwarded_for: default_auth_trust_forwarded_for(),
+            public_files: default_auth_public_files(),
+        }
+    }
+}
+
+impl AuthConfig {
+    /// 配置了任意令牌或登录密码后才开启接口鉴权
+    pub fn enabled(&self) -> bool {
+        !self.token.trim().is_empty()
+            || self.tokens.iter().any(|v| !v.token.trim().is_empty())
+            || self.login_enabled()
+    }
+
+    pub fn login_enabled(&self) -> bool {
+        !self.username.trim().is_empty() && !self.password.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScopedToken {
+    /// 日志中显示的名字，不参与鉴权
+    #[serde(default)]
+    pub name: String,
+    pub token: String,
+    pub scopes: Vec<TokenScope>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenScope {
+    /// 读取日记、设置和导出
+    Read,
+    /// 包含 `read` 和 `upload-only`，可以修改和删除日记
+    Write,
+    /// 全部权限，包括修改设置、归档恢复和同步诊断
+    Admin,
+    /// 只能上传文件和 `POST /quick` 追加
+    UploadOnly,
+}
+
+impl TokenScope {
+    pub fn grants(self, required: TokenScope) -> bool {
+        match self {
+            TokenScope::Admin => true,
+            TokenScope::Write => required != TokenScope::Admin,
+            TokenScope::Read | TokenScope::UploadOnly => self == required,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UploadConfig {
+    /// 文件保存后、写入 `file_blob` 前执行的扫描命令，`{path}` 替换为文件路径，
+    /// 例如 `clamscan --no-summary {path}`；退出码非 0 或超时的文件移入 `{base_path}/quarantine/` 并拒绝上传
+    #[serde(default = "default_upload_scan_command")]
+    pub scan_command: String,
+    #[serde(default = "default_upload_scan_timeout_secs")]
+    pub scan_timeout_secs: u64,
+    /// 上传图片时生成的缩略图最长边像素，`GET /files/picture/{name}?size=thumb` 和 `?size=medium` 返回；
+    /// 0 为不生成这一档，原图不比这一档大时也不生成
+    #[serde(default = "default_upload_thumb_small_px")]
+    pub thumb_small_px: u32,
+    #[serde(default = "default_upload_thumb_medium_px")]
+    pub thumb_medium_px: u32,
+    /// 大于这个字节数的 jpeg/png 图片上传后重新压缩，压缩后的文件替换原文件，没有变小时保留原文件；0 为不压缩
+    #[serde(default = "default_upload_optimize_min_bytes")]
+    pub optimize_min_bytes: u64,
+    /// 重新压缩 jpeg 的质量（1-100），png 为无损压缩不受影响
+    #[serde(default = "default_upload_optimize_quality")]
+    pub optimize_quality: u8,
+    /// 压缩前的原文件移到 `{base_path}/original/` 保留，不参与同步和引用
+    #[serde(default = "default_upload_optimize_keep_original")]
+    pub optimize_keep_original: bool,
+}
+
+impl Default for UploadConfig {
+    fn default() -> Self {
+        Self {
+            scan_command: default_upload_scan_command(),
+            scan_timeout_secs: default_upload_scan_timeout_secs(),
+            thumb_small_px: default_upload_thumb_small_px(),
+            thumb_medium_px: default_upload_thumb_medium_px(),
+            optimize_min_bytes: default_upload_optimize_min_bytes(),
+            optimize_quality: default_upload_optimize_quality(),
+            optimize_keep_original: default_upload_optimize_keep_original(),
+        }
+    }
+}
+
+/// 上传的动图（gif）和视频的尺寸、码率上限，超出时拒绝上传或交给 `transcode_command` 缩小。
+/// 各项上限为 0 时不限制
+#[derive(Debug, Clone, Deserialize)]
+pub struct MediaConfig {
+    /// gif 最长边像素
+    #[serde(default = "default_media_gif_max_px")]
+    pub gif_max_px: u32,
+    /// 视频最长边像素
+    #[serde(default = "default_media_video_max_px")]
+    pub video_max_px: u32,
+    /// 视频总码率（kbps），文件没有记录码率时按大小和时长估算
+    #[serde(default = "default_media_video_max_kbps")]
+    pub video_max_kbps: u32,
+    /// 超出上限时的处理：`reject` 拒绝上传，`transcode` 用 `transcode_command` 转码，没有配置或转码失败时仍然拒绝
+    #[serde(default = "default_media_over_limit")]
+    pub over_limit: String,
+    /// 读取视频尺寸和码率的命令，`{input}` 替换为文件路径，需要输出 ffprobe 的 json 格式；
+    /// gif 的尺寸直接读取文件头，不经过这个命令
+    #[serde(default = "default_media_probe_command")]
+    pub probe_command: String,
+    /// 转码命令，`{input}` `{output}` 替换为原文件和输出文件路径（扩展名和原文件相同），
+    /// `{max_px}` `{max_kbps}` 替换为要缩小到的最长边和码率（上限为 0 或原文件没超出时取原文件的值），例如
+    /// `ffmpeg -y -v error -i {input} -vf scale=w={max_px}:h={max_px}:force_original_aspect_ratio=decrease:force_divisible_by=2 -b:v {max_kbps}k {output}`
+    #[serde(default = "default_media_transcode_command")]
+    pub transcode_command: String,
+    /// 检查和转码各自的超时
+    #[serde(default = "default_media_timeout_secs")]
+    pub timeout_secs: u64,
+    /// 转码前的原文件移到 `{base_path}/original/` 保留，不参与同步和引用
+    #[serde(default = "default_media_keep_original")]
+    pub keep_original: bool,
+}
+
+impl Default for MediaConfig {
+    fn default() -> Self {
+        Self {
+            gif_max_px: default_media_gif_max_px(),
+            video_max_px: default_media_video_max_px(),
+            video_max_kbps: default_media_video_max_kbps(),
+            over_limit: default_media_over_limit(),
+            probe_command: default_media_probe_command(),
+            transcode_command: default_media_transcode_command(),
+            timeout_secs: default_media_timeout_secs(),
+            keep_original: default_media_keep_original(),
+        }
+    }
+}
+
+/// 按客户端 ip 限制每分钟的请求数，超出时返回 429；前端页面和 `/files` 下的文件不计数。
+/// 各项为 0 时不限制
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimitConfig {
+    #[serde(default = "default_rate_limit_per_minute")]
+    pub per_minute: u32,
+    /// `POST /upload` 单独计数
+    #[serde(default = "default_rate_limit_upload_per_minute")]
+    pub upload_per_minute: u32,
+    /// `POST /sync/journal` 单独计数，每次都会拉取和推送 git 仓库
+    #[serde(default = "default_rate_limit_sync_per_minute")]
+    pub sync_per_minute: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_minute: default_rate_limit_per_minute(),
+            upload_per_minute: default_rate_limit_upload_per_minute(),
+            sync_per_minute: default_rate_limit_sync_per_minute(),
+        }
+    }
+}
+
+/// 全文搜索，见 `search`
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchConfig {
+    /// 分词方式：`unicode61` 按空格和标点切分，适合英文；`trigram` 按三个字符切分，
+    /// 能搜任意子串但少于三个字的词搜不到；`cjk` 把中日韩文字逐字切开、查询时按短语匹配，适合中文。
+    /// 修改后下次启动时自动重建索引
+    #[serde(default = "default_search_tokenizer")]
+    pub tokenizer: String,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            tokenizer: default_search_tokenizer(),
+        }
+    }
+}
+
+/// 日记正文加密保存，见 `util::crypto`
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct EncryptionConfig {
+    /// 开启后新写入的正文都会加密，已有的明文用 `POST /admin/encryption/migrate` 转换
+    #[serde(default)]
+    pub enabled: bool,
+    /// 用 pbkdf2 派生密钥，盐保存在数据库里；和 `key_file` 二选一
+    #[serde(default)]
+    pub passphrase: String,
+    /// 32 字节的密钥文件，原始字节、hex 或 base64 都可以，相对路径相对配置文件所在目录
+    #[serde(default)]
+    pub key_file: String,
+    /// 同步到 git 时正文保持加密；默认写出明文
+    #[serde(default)]
+    pub sync_encrypted: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RenderConfig {
+    /// 为 true 时日记中的原始 html 按白名单过滤后保留，否则作为文本显示
+    #[serde(default = "default_render_raw_html")]
+    pub raw_html: bool,
+    /// 允许的 html 标签，为空时使用内置的常用标签；script、style、iframe 等无论如何都会去掉
+    #[serde(default = "default_render_allowed_tags")]
+    pub allowed_tags: Vec<String>,
+    /// 链接和图片地址允许的协议，相对地址不受限制
+    #[serde(default = "default_render_allowed_url_schemes")]
+    pub allowed_url_schemes: Vec<String>,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            raw_html: default_render_raw_html(),
+            allowed_tags: default_render_allowed_tags(),
+            allowed_url_schemes: default_render_allowed_url_schemes(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SecurityConfig {
+    /// 给前端页面、分享页和接口响应加上安全相关的响应头，处理函数自己设置的同名头不覆盖
+    #[serde(default = "default_security_enabled")]
+    pub enabled: bool,
+    /// 为空时不发送 `Content-Security-Policy`；前端引用了外部脚本或字体时需要在这里放开
+    #[serde(default = "default_security_content_security_policy")]
+    pub content_security_policy: String,
+    /// 追加到 csp 的 `frame-ancestors`，例如 `'self'`，为空时不限制嵌入
+    #[serde(default = "default_security_frame_ancestors")]
+    pub frame_ancestors: String,
+    #[serde(default = "default_security_referrer_policy")]
+    pub referrer_policy: String,
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_security_enabled(),
+            content_security_policy: default_security_content_security_policy(),
+            frame_ancestors: default_security_frame_ancestors(),
+            referrer_policy: default_security_referrer_policy(),
+        }
+    }
+}
+
+/// 所有对外 http 请求共用的设置，见 `util::http_client`
+#[derive(Debug, Clone, Deserialize)]
+pub struct HttpConfig {
+    /// 例如 `http://127.0.0.1:7890`；为空时读取 `HTTPS_PROXY` 等环境变量，
+    /// 但访问用户提供地址的请求（见 `[outbound]`）不使用环境变量里的代理
+    #[serde(default)]
+    pub proxy: String,
+    /// 单个请求的总超时，长轮询和备份上传自己设置
+    #[serde(default = "default_http_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_http_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// 为空时使用 `day-log/<版本>`
+    #[serde(default)]
+    pub user_agent: String,
+    /// 额外信任的 CA 证书（pem，可以包含多个），自建的 webdav、ntfy 使用私有证书时配置
+    #[serde(default)]
+    pub ca_cert_file: String,
+    /// 不校验服务端证书，只用于排查问题
+    #[serde(default)]
+    pub accept_invalid_certs: bool,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            proxy: String::new(),
+            timeout_secs: default_http_timeout_secs(),
+            connect_timeout_secs: default_http_connect_timeout_secs(),
+            user_agent: String::new(),
+            ca_cert_file: String::new(),
+            accept_invalid_certs: false,
+        }
+    }
+}
+
+/// 导入时下载图片、通知 webhook 等访问外部地址时的限制，见 `util::outbound`
+#[derive(Debug, Clone, Deserialize)]
+pub struct OutboundConfig {
+    /// 拒绝连接回环、内网、链路本地等非公网地址
+    #[serde(default = "default_outbound_block_private")]
+    pub block_private: bool,
+    /// 不受 `block_private` 限制的主机，例如局域网里的 ntfy；
+    /// 支持 `host`、`*.example.com`、IP 和 `192.168.1.0/24`
+    #[serde(default)]
+    pub allow_hosts: Vec<String>,
+    /// 始终拒绝的主机，写法同 `allow_hosts`，优先于 `allow_hosts`
+    #[serde(default)]
+    pub deny_hosts: Vec<String>,
+}
+
+impl Default for OutboundConfig {
+    fn default() -> Self {
+        Self {
+            block_private: default_outbound_block_private(),
+            allow_hosts: Vec::new(),
+            deny_hosts: Vec::new(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct AppConfig {
     #[serde(default = "default_base_path")]
@@ -165,21 +1215,130 @@ pub struct AppConfig {
     pub index_path: String,
     #[serde(default = "default_static_path")]
     pub static_path: String,
+    /// 上传文件、导入和恢复备份接口的请求体上限
     #[serde(default = "default_upload_file_limit")]
     pub upload_file_limit: usize,
+    /// 其余接口的请求体上限
+    #[serde(default = "default_json_body_limit")]
+    pub json_body_limit: usize,
     #[serde(default = "default_auto_switch_port_time")]
     pub auto_switch_port_time: i16,
+    /// 服务端计算“今天”时使用的 utc 偏移（分钟），例如东八区为 480
+    #[serde(default = "default_utc_offset_minutes")]
+    pub utc_offset_minutes: i32,
+    /// 接口错误消息的语言，请求的 `Accept-Language` 中没有支持的语言时使用；可选 `en`、`zh-CN`
+    #[serde(default = "default_locale")]
+    pub locale: String,
+    /// 带这个标签的日记和 `pinned` 的日记一样算作精选，列在同步的 README、`index.json` 和周报中；为空时只看 `pinned`
+    #[serde(default = "default_highlight_tag")]
+    pub highlight_tag: String,
+    /// 渲染后 html 的缓存篇数，0 为不缓存
+    #[serde(default = "default_render_cache_size")]
+    pub render_cache_size: usize,
+    /// git 同步、导入解析、文件哈希等重任务同时运行的上限
+    #[serde(default = "default_blocking_workers")]
+    pub blocking_workers: usize,
+    #[serde(default)]
+    pub db: DbConfig,
+    #[serde(default)]
+    pub jobs: JobsConfig,
     #[serde(default)]
     pub sync: SyncConfig,
+    #[serde(default)]
+    pub telegram: TelegramConfig,
+    #[serde(default)]
+    pub matrix: MatrixConfig,
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    #[serde(default)]
+    pub reminder: ReminderConfig,
+    #[serde(default)]
+    pub digest: DigestConfig,
+    #[serde(default)]
+    pub export: ExportConfig,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    #[serde(default)]
+    pub backup: BackupConfig,
+    #[serde(default)]
+    pub quick: QuickConfig,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub security: SecurityConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub public: PublicConfig,
+    #[serde(default)]
+    pub upload: UploadConfig,
+    #[serde(default)]
+    pub media: MediaConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub outbound: OutboundConfig,
+    /// 服务端渲染 markdown（分享页、`render=html`）时的 html 过滤规则
+    #[serde(default)]
+    pub render: RenderConfig,
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+    #[serde(default)]
+    pub search: SearchConfig,
+    /// 读取的配置文件路径，`POST /setup` 写回这里
+    #[serde(skip)]
+    pub config_path: String,
 }
 
 impl AppConfig {
     pub fn load_from_file(path: &str) -> Result<AppConfig, Box<dyn std::error::Error>> {
-        let contents = fs::read_to_string(path)?;
-        let config = toml::from_str::<AppConfig>(&contents)?;
+        // 配置文件不存在时全部使用默认值，首次启动后可通过 `POST /setup` 生成
+        let contents = match fs::read_to_string(path) {
+            Ok(v) => v,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut config = toml::from_str::<AppConfig>(&contents)?;
+        config.config_path = path.to_string();
+        config.resolve_paths()?;
         Ok(config)
     }
 
+    /// 把所有路径配置展开成绝对路径，之后不再受启动时工作目录影响：
+    /// `~` 展开为 HOME；`base_path`、前端文件和 ssh 密钥的相对路径相对配置文件所在目录；
+    /// 数据库、图片等数据目录和同步仓库的相对路径相对 `base_path`
+    fn resolve_paths(&mut self) -> Result<(), String> {
+        let config_dir = Path::new(&self.config_path)
+            .parent()
+            .filter(|v| !v.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let config_dir = std::path::absolute(config_dir).map_err(|e| e.to_string())?;
+        self.base_path = resolve_path(&self.base_path, &config_dir)?;
+        let base = PathBuf::from(&self.base_path);
+        for path in [
+            &mut self.db_path,
+            &mut self.picture_path,
+            &mut self.media_path,
+            &mut self.file_path,
+            &mut self.sync.repo_local_path,
+        ] {
+            *path = resolve_path(path, &base)?;
+        }
+        for path in [
+            &mut self.index_path,
+            &mut self.static_path,
+            &mut self.sync.ssh_private_key_path,
+            &mut self.sync.ssh_public_key_path,
+            &mut self.http.ca_cert_file,
+            &mut self.encryption.key_file,
+        ] {
+            *path = resolve_path(path, &config_dir)?;
+        }
+        Ok(())
+    }
+
     pub async fn init(&self) {
         self.init_db().await;
         self.init_picture_dir().await;
@@ -187,36 +1346,27 @@ impl AppConfig {
         self.init_file_dir().await;
     }
     async fn init_db(&self) {
-        let mut path = self.base_path.clone() + "/" + self.db_path.as_str();
-        path = path.replace("//", "/");
-
-        if let Err(e) = util::file_util::ensure_file_path(&path).await {
+        if let Err(e) = util::file_util::ensure_file_path(&self.db_path).await {
             error!("{}", e)
         }
     }
     async fn init_picture_dir(&self) {
-        let mut path = self.base_path.clone() + "/" + self.picture_path.as_str() + "/";
-        path = path.replace("//", "/");
-        if let Err(e) = util::file_util::ensure_path(&path).await {
+        if let Err(e) = util::file_util::ensure_path(&self.get_picture_path()).await {
             error!("{}", e)
         }
     }
     async fn init_media_dir(&self) {
-        let mut path = self.base_path.clone() + "/" + self.media_path.as_str() + "/";
-        path = path.replace("//", "/");
-        if let Err(e) = util::file_util::ensure_path(&path).await {
+        if let Err(e) = util::file_util::ensure_path(&self.get_media_path()).await {
             error!("{}", e)
         }
     }
     async fn init_file_dir(&self) {
-        let mut path = self.base_path.clone() + "/" + self.file_path.as_str() + "/";
-        path = path.replace("//", "/");
-        if let Err(e) = util::file_util::ensure_path(&path).await {
+        if let Err(e) = util::file_util::ensure_path(&self.get_file_path()).await {
             error!("{}", e)
         }
     }
     pub fn get_db_path(&self) -> String {
-        (self.base_path.clone() + "/" + self.db_path.as_str()).replace("//", "/")
+        self.db_path.clone()
     }
 
     pub fn get_index_path(&self) -> String {
@@ -228,25 +1378,88 @@ impl AppConfig {
     }
 
     pub fn get_media_path(&self) -> String {
-        let path = self.base_path.clone() + "/" + self.media_path.as_str() + "/";
-        path.replace("//", "/")
+        format!("{}/", self.media_path.trim_end_matches('/'))
     }
 
     pub fn get_picture_path(&self) -> String {
-        let path = self.base_path.clone() + "/" + self.picture_path.as_str() + "/";
-        path.replace("//", "/")
+        format!("{}/", self.picture_path.trim_end_matches('/'))
     }
 
     pub fn get_file_path(&self) -> String {
-        let path = self.base_path.clone() + "/" + self.file_path.as_str() + "/";
-        path.replace("//", "/")
+        format!("{}/", self.file_path.trim_end_matches('/'))
+    }
+
+    pub fn get_tmp_path(&self) -> String {
+        (self.base_path.clone() + "/tmp/").replace("//", "/")
+    }
+
+    pub fn get_thumb_path(&self) -> String {
+        (self.base_path.clone() + "/thumb/").replace("//", "/")
+    }
+
+    pub fn get_original_path(&self) -> String {
+        (self.base_path.clone() + "/original/").replace("//", "/")
+    }
+
+    pub fn get_quarantine_path(&self) -> String {
+        (self.base_path.clone() + "/quarantine/").replace("//", "/")
+    }
+
+    pub fn get_archive_path(&self) -> String {
+        (self.base_path.clone() + "/archive/").replace("//", "/")
+    }
+
+    pub fn get_backup_path(&self) -> String {
+        (self.base_path.clone() + "/backup/").replace("//", "/")
     }
 
     pub fn get_sync_repo_path(&self) -> String {
-        let p = Path::new(&self.sync.repo_local_path);
-        if p.is_absolute() {
-            return p.to_string_lossy().to_string();
+        self.sync.repo_local_path.clone()
+    }
+
+    /// 写入 `file_blob.file_path` 的路径，在 `base_path` 下时保存相对路径
+    pub fn to_stored_path(&self, path: &Path) -> String {
+        path.strip_prefix(&self.base_path)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// 读取 `file_blob.file_path`：相对路径接到 `base_path` 下，旧数据的绝对路径原样使用
+    pub fn resolve_stored_path(&self, stored: &str) -> PathBuf {
+        let path = Path::new(stored);
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            Path::new(&self.base_path).join(path)
         }
-        (self.base_path.clone() + "/" + self.sync.repo_local_path.as_str()).replace("//", "/")
     }
+
+    pub fn today(&self) -> String {
+        util::date_util::today(self.utc_offset_minutes)
+    }
+}
+
+/// 展开 `~` 并把相对路径接到 `base` 下，空字符串表示未配置，原样返回
+fn resolve_path(input: &str, base: &Path) -> Result<String, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(String::new());
+    }
+    if input.starts_with('~') && input != "~" && !input.starts_with("~/") {
+        return Err(format!("unsupported ~ path form '{}', use ~/xxx", input));
+    }
+    let path = if input.starts_with('~') {
+        let home =
+            env::var("HOME").map_err(|_| format!("HOME env is required to expand '{}'", input))?;
+        Path::new(&home).join(input.trim_start_matches('~').trim_start_matches('/'))
+    } else {
+        PathBuf::from(input)
+    };
+    let path = if path.is_absolute() {
+        path
+    } else {
+        base.join(path)
+    };
+    Ok(path.to_string_lossy().to_string())
 }
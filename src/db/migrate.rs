@@ -0,0 +1,592 @@
+use crate::error::DayLogError;
+use crate::util::date_util;
+use sqlx::{Pool, Sqlite, SqliteConnection};
+use std::path::Path;
+use tracing::{info, warn};
+
+/// 一个版本内的步骤在同一个事务中执行
+struct Migration {
+    version: i64,
+    name: &'static str,
+    steps: &'static [Step],
+}
+
+enum Step {
+    Sql(&'static str),
+    /// 引入版本号之前，老库启动时会按需补列，这些列在已有的库中可能已经存在，存在时跳过
+    AddColumn {
+        table: &'static str,
+        column: &'static str,
+        definition: &'static str,
+    },
+    /// 要先检查数据或用到配置、写不成一条 sql 的步骤
+    Data(DataStep),
+}
+
+enum DataStep {
+    /// 批量导入依赖 `on conflict(date)`，一天一篇由接口保证，这里补上唯一索引；
+    /// 已有重复日期时迁移失败，列出日期，处理后再启动
+    JournalDateUnique,
+    /// 旧数据的 `file_blob.file_path` 是绝对路径，在 `base_path` 下的改成相对路径，挪动 `base_path` 后仍能找到；
+    /// 不在 `base_path` 下的保留绝对路径，可以用 `POST /admin/files/repair-paths` 修复
+    RelativeFilePaths,
+    /// 更早的数据按配置的相对 `base_path` 拼出路径，例如 `.daylog/file/x.png`，是相对启动时工作目录的；
+    /// `base_path` 展开成绝对路径后再接到它下面就找不到了，这里按工作目录找到文件后改成相对 `base_path` 的路径，
+    /// 不在 `base_path` 下的改成绝对路径，都找不到的留给 `POST /admin/files/repair-paths`
+    LegacyRelativeFilePaths,
+}
+
+/// 只能在末尾追加新版本，已发布的版本不要修改；新增的列直接写 `alter table`
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "baseline",
+        steps: BASELINE,
+    },
+    Migration {
+        version: 2,
+        name: "journal_counts",
+        steps: &[
+            // 写入时按正文计算，旧数据为 null，由 `journal_counts` 任务补上
+            Step::AddColumn {
+                table: "journal",
+                column: "word_count",
+                definition: "integer",
+            },
+            Step::AddColumn {
+                table: "journal",
+                column: "reading_minutes",
+                definition: "integer",
+            },
+        ],
+    },
+    Migration {
+        version: 3,
+        name: "file_blob_thumbnails",
+        steps: &[
+            // 图片的缩略图，旧数据和不是图片的文件为 null
+            Step::AddColumn {
+                table: "file_blob",
+                column: "thumb_small_path",
+                definition: "text",
+            },
+            Step::AddColumn {
+                table: "file_blob",
+                column: "thumb_medium_path",
+                definition: "text",
+            },
+        ],
+    },
+    Migration {
+        version: 4,
+        name: "search_dirty",
+        steps: &[
+            // 所有写 journal 的地方（接口、导入、同步、回收站恢复）都经过触发器，不用逐个通知索引；
+            // 不用 `insert or ignore`，外层语句是 upsert 时触发器里的冲突处理会被覆盖
+            Step::Sql(
+                r#"
+                create table if not exists search_dirty (
+                    journal_id integer primary key
+                )
+                "#,
+            ),
+            Step::Sql(
+                "create trigger if not exists journal_search_insert after insert on journal begin insert into search_dirty (journal_id) select new.id where not exists (select 1 from search_dirty where journal_id = new.id); end",
+            ),
+            Step::Sql(
+                "create trigger if not exists journal_search_update after update of content, metadata on journal begin insert into search_dirty (journal_id) select new.id where not exists (select 1 from search_dirty where journal_id = new.id); end",
+            ),
+            Step::Sql(
+                "create trigger if not exists journal_search_delete after delete on journal begin insert into search_dirty (journal_id) select old.id where not exists (select 1 from search_dirty where journal_id = old.id); end",
+            ),
+        ],
+    },
+    Migration {
+        version: 5,
+        name: "file_blob_optimize",
+        steps: &[
+            // 上传时重新压缩的图片，`oid` 是压缩后的哈希，`source_oid` 是上传内容的哈希，用于去重；
+            // `original_path` 是保留的原文件
+            Step::Sql("alter table file_blob add column source_oid text"),
+            Step::Sql("alter table file_blob add column original_path text"),
+            Step::Sql(
+                "create index if not exists idx_file_blob_source_oid on file_blob (source_oid)",
+            ),
+        ],
+    },
+    Migration {
+        version: 6,
+        name: "file_blob_access",
+        steps: &[
+            // `/files/` 和 `GET /files/by-id/{id}` 完整返回内容时累加，304 和 `Range` 请求不计
+            Step::Sql("alter table file_blob add column access_count integer not null default 0"),
+            Step::Sql("alter table file_blob add column last_access_time integer"),
+        ],
+    },
+    Migration {
+        version: 7,
+        name: "journal_date_unique",
+        steps: &[Step::Data(DataStep::JournalDateUnique)],
+    },
+    Migration {
+        version: 8,
+        name: "file_blob_relative_path",
+        steps: &[Step::Data(DataStep::RelativeFilePaths)],
+    },
+    Migration {
+        version: 9,
+        name: "file_blob_legacy_relative_path",
+        steps: &[Step::Data(DataStep::LegacyRelativeFilePaths)],
+    },
+    Migration {
+        version: 10,
+        name: "journal_trash_tags",
+        steps: &[
+            // 删除时的标签 id（json 数组），恢复时重新挂上，旧数据为 null
+            Step::Sql("alter table journal_trash add column tag_ids text"),
+        ],
+    },
+];
+
+/// 引入版本号之前 `db::init` 每次启动执行的建表语句，都带 `if not exists`，老库执行一遍也不会出错
+const BASELINE: &[Step] = &[
+    Step::Sql(
+        r#"
+            create table if not exists journal (
+                id integer primary key autoincrement,
+                content text not null,
+                date text not null,
+                create_time integer not null,
+                update_time integer not null,
+                metadata text
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists resource (
+                id integer primary key autoincrement,
+                kind text not null,
+                uri text not null,
+                file_path text not null,
+                create_time integer not null,
+                update_time integer not null
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists blob (
+                id integer primary key autoincrement,
+                kind text not null,
+                algo text not null,
+                oid text not null,
+                mime text not null,
+                size integer not null,
+                original_name text not null,
+                uri text not null,
+                daylog_uri text not null,
+                file_path text not null,
+                create_time integer not null,
+                update_time integer not null,
+                unique (kind, algo, oid)
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists file_blob (
+                id integer primary key autoincrement,
+                kind text not null,
+                algo text not null,
+                oid text not null,
+                mime text not null,
+                size integer not null,
+                original_name text not null,
+                uri text not null,
+                file_path text not null,
+                create_time integer not null,
+                update_time integer not null,
+                unique (kind, algo, oid)
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists app_setting (
+                key text primary key,
+                value text not null,
+                update_time integer not null
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists digest (
+                id integer primary key autoincrement,
+                kind text not null,
+                period_start text not null,
+                period_end text not null,
+                content text not null,
+                create_time integer not null,
+                update_time integer not null,
+                unique (kind, period_start)
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists journal_review (
+                journal_id integer primary key,
+                review_count integer not null,
+                last_review_time integer not null
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists journal_history (
+                id integer primary key autoincrement,
+                journal_id integer not null,
+                action text not null,
+                content text not null,
+                metadata text,
+                detail text,
+                create_time integer not null
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists journal_draft (
+                date text primary key,
+                content text not null,
+                base_update_time integer,
+                update_time integer not null
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists sync_pending_delete (
+                rel_path text primary key,
+                create_time integer not null
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists share_link (
+                token text primary key,
+                scope text not null,
+                period text not null,
+                password_hash text,
+                expire_time integer,
+                create_time integer not null
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists journal_archive (
+                id integer primary key autoincrement,
+                file_name text not null,
+                before_date text not null,
+                count integer not null,
+                removed integer not null,
+                create_time integer not null
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists tag (
+                id integer primary key autoincrement,
+                name text not null unique collate nocase,
+                color text,
+                create_time integer not null
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists journal_tag (
+                journal_id integer not null,
+                tag_id integer not null,
+                create_time integer not null,
+                primary key (journal_id, tag_id)
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists auth_failure (
+                id integer primary key autoincrement,
+                ip text not null,
+                token_hint text,
+                method text not null,
+                path text not null,
+                reason text not null,
+                create_time integer not null
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists auth_lockout (
+                key text primary key,
+                failures integer not null,
+                locked_until integer not null,
+                update_time integer not null
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists auth_session (
+                id integer primary key autoincrement,
+                token_hash text not null unique,
+                label text not null,
+                scopes text not null,
+                last_seen_ip text,
+                last_seen_time integer,
+                expire_time integer,
+                create_time integer not null
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists backup_run (
+                id integer primary key autoincrement,
+                file_name text not null,
+                target text not null,
+                location text not null,
+                size integer not null,
+                encrypted integer not null,
+                create_time integer not null
+            )
+            "#,
+    ),
+    Step::Sql(
+        r#"
+            create table if not exists journal_trash (
+                id integer primary key autoincrement,
+                journal_id integer not null,
+                date text not null,
+                content text not null,
+                metadata text,
+                create_time integer not null,
+                update_time integer not null,
+                delete_time integer not null
+            )
+            "#,
+    ),
+    // 定时任务按 name 唯一，一次性任务（导入等）每次一行
+    Step::Sql(
+        r#"
+            create table if not exists job (
+                id integer primary key autoincrement,
+                name text not null,
+                kind text not null,
+                schedule text,
+                payload text,
+                status text not null,
+                next_run_time integer,
+                last_run_time integer,
+                last_finish_time integer,
+                last_error text,
+                attempts integer not null default 0,
+                max_retries integer not null default 0,
+                run_count integer not null default 0,
+                create_time integer not null,
+                update_time integer not null
+            )
+            "#,
+    ),
+    Step::AddColumn {
+        table: "journal",
+        column: "metadata",
+        definition: "text",
+    },
+    // 写入时的 utc 偏移（分钟），旧数据为 null，统计时按当前配置处理
+    Step::AddColumn {
+        table: "journal",
+        column: "create_utc_offset",
+        definition: "integer",
+    },
+    Step::AddColumn {
+        table: "journal",
+        column: "update_utc_offset",
+        definition: "integer",
+    },
+    Step::Sql("create index if not exists idx_job_next_run_time on job (next_run_time)"),
+    // 最近编辑/最近创建列表按时间倒序
+    Step::Sql("create index if not exists idx_journal_update_time on journal (update_time)"),
+    Step::Sql("create index if not exists idx_journal_create_time on journal (create_time)"),
+    // 按标签筛选日记
+    Step::Sql("create index if not exists idx_journal_tag_tag_id on journal_tag (tag_id)"),
+];
+
+/// 依次执行 `schema_version` 中还没有记录的版本，`base_path` 是展开后的绝对路径
+pub async fn run(pool: &Pool<Sqlite>, base_path: &str) -> Result<(), DayLogError> {
+    sqlx::query(
+        r#"
+        create table if not exists schema_version (
+            version integer primary key,
+            name text not null,
+            apply_time integer not null
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    let current = current_version(pool).await?;
+    let latest = MIGRATIONS.last().map(|v| v.version).unwrap_or(0);
+    if current > latest {
+        warn!(
+            "schema version v{} is newer than v{} supported by this build, migrations skipped",
+            current, latest
+        );
+        return Ok(());
+    }
+    for migration in MIGRATIONS.iter().filter(|v| v.version > current) {
+        let mut tx = pool.begin().await?;
+        for step in migration.steps {
+            match step {
+                Step::Sql(sql) => {
+                    sqlx::query(sql).execute(&mut *tx).await?;
+                }
+                Step::AddColumn {
+                    table,
+                    column,
+                    definition,
+                } => add_column(&mut tx, table, column, definition).await?,
+                Step::Data(DataStep::JournalDateUnique) => journal_date_unique(&mut tx).await?,
+                Step::Data(DataStep::RelativeFilePaths) => {
+                    relative_file_paths(&mut tx, base_path).await?
+                }
+                Step::Data(DataStep::LegacyRelativeFilePaths) => {
+                    legacy_relative_file_paths(&mut tx, base_path).await?
+                }
+            }
+        }
+        sqlx::query("insert into schema_version (version, name, apply_time) values (?, ?, ?)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(date_util::now_secs())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        info!("数据库迁移到 v{} {}", migration.version, migration.name);
+    }
+    Ok(())
+}
+
+/// 已执行到的版本，新库为 0
+pub async fn current_version(pool: &Pool<Sqlite>) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar::<_, i64>("select coalesce(max(version), 0) from schema_version")
+        .fetch_one(pool)
+        .await
+}
+
+async fn add_column(
+    conn: &mut SqliteConnection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), sqlx::Error> {
+    let columns = sqlx::query_scalar::<_, String>(&format!(
+        "select name from pragma_table_info('{}')",
+        table
+    ))
+    .fetch_all(&mut *conn)
+    .await?;
+    if columns.iter().any(|v| v == column) {
+        return Ok(());
+    }
+    sqlx::query(&format!(
+        "alter table {} add column {} {}",
+        table, column, definition
+    ))
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+async fn journal_date_unique(conn: &mut SqliteConnection) -> Result<(), DayLogError> {
+    let duplicated = sqlx::query_scalar::<_, String>(
+        "select date from journal group by date having count(1) > 1 order by date limit 20",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    if !duplicated.is_empty() {
+        return Err(DayLogError::Validation(format!(
+            "journal.date has duplicated rows, merge or delete them before upgrading: {}",
+            duplicated.join(", ")
+        )));
+    }
+    sqlx::query("create unique index if not exists idx_journal_date on journal (date)")
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+async fn relative_file_paths(
+    conn: &mut SqliteConnection,
+    base_path: &str,
+) -> Result<(), DayLogError> {
+    let prefix = format!("{}/", base_path.trim_end_matches('/'));
+    let updated = sqlx::query(
+        "update file_blob set file_path = substr(file_path, length(?1) + 1) where substr(file_path, 1, length(?1)) = ?1",
+    )
+    .bind(&prefix)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    if updated > 0 {
+        info!(
+            "file_blob.file_path 改为相对 base_path 的路径 rows={}",
+            updated
+        );
+    }
+    Ok(())
+}
+
+async fn legacy_relative_file_paths(
+    conn: &mut SqliteConnection,
+    base_path: &str,
+) -> Result<(), DayLogError> {
+    let rows = sqlx::query_as::<_, (i64, String)>(
+        "select id, file_path from file_blob where substr(file_path, 1, 1) != '/' order by id asc",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let cwd = std::env::current_dir().map_err(|e| DayLogError::io("current dir", e))?;
+    let base = Path::new(base_path);
+    let mut updated = 0;
+    for (id, stored) in rows {
+        if base.join(&stored).is_file() {
+            continue;
+        }
+        let old = cwd.join(&stored);
+        let file_path = match old.strip_prefix(base) {
+            Ok(rel) if base.join(rel).is_file() => rel.to_string_lossy().to_string(),
+            _ if old.is_file() => old.to_string_lossy().to_string(),
+            _ => continue,
+        };
+        sqlx::query("update file_blob set file_path = ? where id = ?")
+            .bind(file_path)
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+        updated += 1;
+    }
+    if updated > 0 {
+        info!(
+            "file_blob.file_path 中相对工作目录的旧路径已改写 rows={}",
+            updated
+        );
+    }
+    Ok(())
+}
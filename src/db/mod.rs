@@ -1,2 +1,3 @@
+pub mod migrate;
 pub mod pool;
-pub use pool::init;
+pub use pool::{init, init_read};
@@ -1,94 +1,43 @@
 use crate::config::app_config::AppConfig;
+use crate::db::migrate;
+use crate::error::DayLogError;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
 use sqlx::{Pool, SqlitePool};
+use std::str::FromStr;
 
-pub async fn init(config: &AppConfig) -> Result<Pool<sqlx::Sqlite>, sqlx::Error> {
-    let path = config.get_db_path();
-    let url = format!("sqlite://{}", path);
-    let pool = SqlitePool::connect(&url).await?;
+/// 连接数据库并执行迁移，迁移失败（例如日记日期重复）时不启动
+pub async fn init(config: &AppConfig) -> Result<Pool<sqlx::Sqlite>, DayLogError> {
+    // wal 模式下读连接不会被写入阻塞
+    let options = connect_options(config)?.journal_mode(SqliteJournalMode::Wal);
+    let pool = SqlitePool::connect_with(options).await?;
 
-    sqlx::query(
-        r#"
-        create table if not exists journal (
-            id integer primary key autoincrement,
-            content text not null,
-            date text not null,
-            create_time integer not null,
-            update_time integer not null
-        )
-        "#,
-    )
-    .execute(&pool)
-    .await?;
-
-    sqlx::query(
-        r#"
-        create table if not exists resource (
-            id integer primary key autoincrement,
-            kind text not null,
-            uri text not null,
-            file_path text not null,
-            create_time integer not null,
-            update_time integer not null
-        )
-        "#,
-    )
-    .execute(&pool)
-    .await?;
+    migrate::run(&pool, &config.base_path).await?;
 
-    sqlx::query(
-        r#"
-        create table if not exists blob (
-            id integer primary key autoincrement,
-            kind text not null,
-            algo text not null,
-            oid text not null,
-            mime text not null,
-            size integer not null,
-            original_name text not null,
-            uri text not null,
-            daylog_uri text not null,
-            file_path text not null,
-            create_time integer not null,
-            update_time integer not null,
-            unique (kind, algo, oid)
-        )
-        "#,
-    )
-    .execute(&pool)
-    .await?;
+    Ok(pool)
+}
 
-    sqlx::query(
-        r#"
-        create table if not exists file_blob (
-            id integer primary key autoincrement,
-            kind text not null,
-            algo text not null,
-            oid text not null,
-            mime text not null,
-            size integer not null,
-            original_name text not null,
-            uri text not null,
-            file_path text not null,
-            create_time integer not null,
-            update_time integer not null,
-            unique (kind, algo, oid)
-        )
-        "#,
-    )
-    .execute(&pool)
-    .await?;
+/// 列表、搜索、统计这类只读查询使用的连接池（`mode=ro`），长查询不占用写连接；
+/// `db.read_pool_size` 为 0 时直接使用写连接池
+pub async fn init_read(
+    config: &AppConfig,
+    writer: &Pool<sqlx::Sqlite>,
+) -> Result<Pool<sqlx::Sqlite>, sqlx::Error> {
+    if config.db.read_pool_size == 0 {
+        return Ok(writer.clone());
+    }
+    SqlitePoolOptions::new()
+        .max_connections(config.db.read_pool_size)
+        .connect_with(connect_options(config)?.read_only(true))
+        .await
+}
 
-    sqlx::query(
-        r#"
-        create table if not exists app_setting (
-            key text primary key,
-            value text not null,
-            update_time integer not null
+fn connect_options(config: &AppConfig) -> Result<SqliteConnectOptions, sqlx::Error> {
+    let url = format!("sqlite://{}", config.get_db_path());
+    Ok(SqliteConnectOptions::from_str(&url)?
+        .pragma(
+            "wal_autocheckpoint",
+            config.db.wal_autocheckpoint.to_string(),
         )
-        "#,
-    )
-    .execute(&pool)
-    .await?;
-
-    Ok(pool)
+        .pragma("cache_size", config.db.cache_size.to_string())
+        .pragma("mmap_size", config.db.mmap_size.to_string()))
 }
@@ -0,0 +1,208 @@
+use crate::app_state::AppState;
+use crate::http::tag;
+use crate::job::{JobDef, JobFuture};
+use crate::notify::{self, NotifyEvent};
+use crate::util::date_util;
+use serde::Serialize;
+use sqlx::FromRow;
+use std::collections::HashSet;
+use tracing::info;
+
+pub const KIND_WEEKLY: &str = "weekly";
+
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Digest {
+    pub id: i64,
+    pub kind: String,
+    pub period_start: String,
+    pub period_end: String,
+    pub content: String,
+    pub create_time: i64,
+    pub update_time: i64,
+}
+
+#[derive(Debug, FromRow)]
+struct DigestJournalRow {
+    id: i64,
+    date: String,
+    content: String,
+}
+
+/// 周一每小时检查一次，上周的周报还没生成时生成并通过 notify 推送
+pub fn job(state: &AppState) -> Option<JobDef> {
+    if !state.config.digest.enabled {
+        return None;
+    }
+    Some(JobDef {
+        name: "weekly_digest".to_string(),
+        kind: "weekly_digest",
+        schedule: "0 * * * 1".to_string(),
+        handler: run_job,
+        max_retries: 2,
+        catch_up: true,
+    })
+}
+
+fn run_job(state: AppState, _: Option<String>) -> JobFuture {
+    Box::pin(async move {
+        let days = date_util::local_days(date_util::now_secs(), state.config.utc_offset_minutes);
+        // 执行时间可以配置，按本周一往前推一周
+        let week_start = days - date_util::weekday_from_days(days) - 7;
+        if exists(&state, KIND_WEEKLY, &date_util::date_from_days(week_start))
+            .await
+            .map_err(|e| e.to_string())?
+        {
+            return Ok(());
+        }
+        let digest = generate_weekly(&state, week_start)
+            .await
+            .map_err(|e| e.to_string())?;
+        info!(
+            "weekly digest generated {} ~ {}",
+            digest.period_start, digest.period_end
+        );
+        notify::spawn_send(
+            &state,
+            NotifyEvent::WeeklySummary {
+                title: format!(
+                    "DayLog weekly digest {} ~ {}",
+                    digest.period_start, digest.period_end
+                ),
+                body: digest.content,
+            },
+        );
+        Ok(())
+    })
+}
+
+async fn exists(state: &AppState, kind: &str, period_start: &str) -> Result<bool, sqlx::Error> {
+    let id = sqlx::query_scalar::<_, i64>(
+        "select id from digest where kind = ? and period_start = ? limit 1",
+    )
+    .bind(kind)
+    .bind(period_start)
+    .fetch_optional(&state.db)
+    .await?;
+    Ok(id.is_some())
+}
+
+/// 生成 `week_start`（unix 天数，周一）开始的一周周报，已存在时覆盖
+pub async fn generate_weekly(state: &AppState, week_start: i64) -> Result<Digest, sqlx::Error> {
+    let period_start = date_util::date_from_days(week_start);
+    let period_end = date_util::date_from_days(week_start + 6);
+    let mut rows = sqlx::query_as::<_, DigestJournalRow>(
+        "select id, date, content from journal where date >= ? and date <= ? order by date asc",
+    )
+    .bind(&period_start)
+    .bind(&period_end)
+    .fetch_all(&state.db)
+    .await?;
+    for row in rows.iter_mut() {
+        row.content = state.cipher.open(std::mem::take(&mut row.content));
+    }
+    let streak = streak_until(state, week_start + 6).await?;
+    let highlights = tag::highlight_ids(state).await?;
+    let content = render_weekly(&period_start, &period_end, &rows, &highlights, streak);
+
+    let ts = date_util::now_secs();
+    sqlx::query(
+        r#"
+        insert into digest (kind, period_start, period_end, content, create_time, update_time)
+        values (?, ?, ?, ?, ?, ?)
+        on conflict(kind, period_start) do update set
+            period_end = excluded.period_end,
+            content = excluded.content,
+            update_time = excluded.update_time
+        "#,
+    )
+    .bind(KIND_WEEKLY)
+    .bind(&period_start)
+    .bind(&period_end)
+    .bind(&content)
+    .bind(ts)
+    .bind(ts)
+    .execute(&state.db)
+    .await?;
+
+    sqlx::query_as::<_, Digest>(
+        "select id, kind, period_start, period_end, content, create_time, update_time from digest where kind = ? and period_start = ?",
+    )
+    .bind(KIND_WEEKLY)
+    .bind(&period_start)
+    .fetch_one(&state.db)
+    .await
+}
+
+/// 截止到 `end_days` 当天（含）的连续记录天数
+pub async fn streak_until(state: &AppState, end_days: i64) -> Result<i64, sqlx::Error> {
+    let dates = sqlx::query_scalar::<_, String>(
+        "select date from journal where date <= ? order by date desc",
+    )
+    .bind(date_util::date_from_days(end_days))
+    .fetch_all(&state.db)
+    .await?;
+    let mut expect = end_days;
+    let mut streak = 0;
+    for date in dates {
+        match date_util::parse_date(&date) {
+            Some(d) if d == expect => {
+                streak += 1;
+                expect -= 1;
+            }
+            _ => break,
+        }
+    }
+    Ok(streak)
+}
+
+fn render_weekly(
+    period_start: &str,
+    period_end: &str,
+    rows: &[DigestJournalRow],
+    highlights: &HashSet<i64>,
+    streak: i64,
+) -> String {
+    let chars = rows
+        .iter()
+        .map(|r| r.content.chars().filter(|c| !c.is_whitespace()).count())
+        .sum::<usize>();
+    let mut out = format!("# 周报 {} ~ {}\n\n", period_start, period_end);
+    out.push_str(&format!("- 记录天数: {}/7\n", rows.len()));
+    out.push_str(&format!("- 字数: {}\n", chars));
+    out.push_str(&format!("- 连续记录: {} 天\n\n", streak));
+
+    let pinned = rows
+        .iter()
+        .filter(|r| highlights.contains(&r.id))
+        .collect::<Vec<_>>();
+    if !pinned.is_empty() {
+        out.push_str("## 精选\n\n");
+        for r in pinned {
+            out.push_str(&format!("- {}: {}\n", r.date, first_line(&r.content)));
+        }
+        out.push('\n');
+    }
+
+    out.push_str("## 每天\n\n");
+    if rows.is_empty() {
+        out.push_str("- 这周没有日记\n");
+    }
+    for r in rows {
+        out.push_str(&format!("- {}: {}\n", r.date, first_line(&r.content)));
+    }
+    out
+}
+
+fn first_line(content: &str) -> String {
+    let line = content
+        .lines()
+        .map(|v| v.trim().trim_start_matches('#').trim())
+        .find(|v| !v.is_empty())
+        .unwrap_or("");
+    let mut out = line.chars().take(80).collect::<String>();
+    if line.chars().count() > 80 {
+        out.push_str("...");
+    }
+    out
+}
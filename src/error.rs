@@ -0,0 +1,58 @@
+use std::fmt::Display;
+use std::io;
+use thiserror::Error;
+
+/// 各模块共用的错误，按来源分类；接口按变体返回不同的 `code`，见 `http::resp`
+#[derive(Debug, Error)]
+pub enum DayLogError {
+    /// 本地 git 仓库操作失败
+    #[error("{}", .0.message())]
+    Git(#[from] git2::Error),
+    /// 连接同步远端失败：clone、fetch、push、WebDAV 请求或认证
+    #[error("{0}")]
+    Remote(String),
+    /// 读写本地文件或目录失败
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Db(#[from] sqlx::Error),
+    /// 请求无法解析或功能未开启，例如上传的不是 zip、同步未开启
+    #[error("{0}")]
+    BadRequest(String),
+    /// 能解析但取值不合法，例如 `sync.mode`、路径模板、导入规则，消息中写明是哪一项
+    #[error("{0}")]
+    Validation(String),
+    /// 后台任务异常退出等不该发生的错误
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl DayLogError {
+    /// 在 io 错误前加上路径等上下文，保留错误类型
+    pub fn io(context: impl Display, e: io::Error) -> Self {
+        DayLogError::Io(io::Error::new(e.kind(), format!("{}: {}", context, e)))
+    }
+}
+
+/// 图片读不出来按内容不合法处理
+impl From<image::ImageError> for DayLogError {
+    fn from(e: image::ImageError) -> Self {
+        match e {
+            image::ImageError::IoError(e) => DayLogError::Io(e),
+            e => DayLogError::Validation(e.to_string()),
+        }
+    }
+}
+
+impl From<tokio::task::JoinError> for DayLogError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        DayLogError::Internal(format!("task failed: {}", e))
+    }
+}
+
+/// 后台任务、通知等只记录错误文字的地方直接用 `?`
+impl From<DayLogError> for String {
+    fn from(e: DayLogError) -> Self {
+        e.to_string()
+    }
+}
@@ -0,0 +1,47 @@
+use crate::app_state::AppState;
+use crate::archive::{self, ArchiveRecord};
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use axum::extract::{Path, State};
+use serde::Serialize;
+use tracing::{info, warn};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreResp {
+    pub restored: usize,
+}
+
+pub async fn list_archives(State(state): State<AppState>) -> ApiResult<Vec<ArchiveRecord>> {
+    let items = archive::list(&state).await.map_err(|_| {
+        ApiResponse::<Vec<ArchiveRecord>>::err(ApiCode::DbListFailed, "db query failed")
+    })?;
+    Ok(ApiResponse::ok(items))
+}
+
+/// 立即按保留策略归档一次，没有需要归档的日记时 data 为 null
+pub async fn run_archive(State(state): State<AppState>) -> ApiResult<Option<ArchiveRecord>> {
+    if state.config.retention.archive_after_years == 0 {
+        return Err(ApiResponse::<Option<ArchiveRecord>>::err(
+            ApiCode::BadRequest,
+            "retention.archive_after_years must be at least 1",
+        ));
+    }
+    info!("手动归档 before={}", archive::cutoff_date(&state));
+    let record = archive::run(&state).await.map_err(|msg| {
+        warn!("归档失败: {}", msg);
+        ApiResponse::<Option<ArchiveRecord>>::err(ApiCode::FileWriteFailed, &msg)
+    })?;
+    Ok(ApiResponse::ok(record))
+}
+
+pub async fn restore_archive(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> ApiResult<RestoreResp> {
+    info!("恢复归档 id={}", id);
+    let restored = archive::restore(&state, id).await.map_err(|msg| {
+        warn!("恢复归档失败: {}", msg);
+        ApiResponse::<RestoreResp>::err(ApiCode::BadRequest, &msg)
+    })?;
+    Ok(ApiResponse::ok(RestoreResp { restored }))
+}
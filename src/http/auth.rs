@@ -0,0 +1,903 @@
+use crate::app_state::AppState;
+use crate::config::app_config::{AuthConfig, TokenScope};
+use crate::http::quick;
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::util::{date_util, token};
+use axum::Json;
+use axum::extract::{ConnectInfo, Extension, Path, Query, Request, State};
+use axum::http::{HeaderMap, HeaderValue, Method, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{AppendHeaders, IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::net::SocketAddr;
+use tracing::{info, warn};
+
+/// 超过这么久没有再失败，失败次数重新计算
+const FAILURE_RESET_SECS: i64 = 24 * 3600;
+/// 审计记录保留天数
+const FAILURE_KEEP_SECS: i64 = 30 * 24 * 3600;
+/// 会话最近使用时间的更新间隔，避免每个请求都写库
+const SESSION_TOUCH_SECS: i64 = 60;
+/// 浏览器中保存会话令牌的 cookie
+pub const SESSION_COOKIE: &str = "daylog_session";
+/// 双重提交的 csrf 令牌，前端从这个 cookie 读出后放到 `CSRF_HEADER`
+pub const CSRF_COOKIE: &str = "daylog_csrf";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+#[derive(Debug, Deserialize)]
+pub struct FailuresQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailuresResp {
+    pub failures: Vec<AuthFailure>,
+    /// 仍在锁定中的 ip 和令牌
+    pub lockouts: Vec<AuthLockout>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthFailure {
+    pub id: i64,
+    pub ip: String,
+    /// 令牌哈希的前几位，只用于区分不同的令牌，不保存原文
+    pub token_hint: Option<String>,
+    pub method: String,
+    pub path: String,
+    pub reason: String,
+    pub create_time: i64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthLockout {
+    /// `ip:<地址>` 或 `token:<哈希前缀>`
+    pub key: String,
+    pub failures: i64,
+    pub locked_until: i64,
+}
+
+/// 通过鉴权的令牌，放在请求的 extensions 中
+#[derive(Debug, Clone)]
+pub struct AuthIdentity {
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    /// 通过 `auth_session` 登录时的会话 id，配置文件中的令牌为 None
+    pub session_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSessionReq {
+    /// 设备名，例如 `iPhone 快捷指令`
+    pub label: String,
+    /// 默认 `write`
+    pub scopes: Option<Vec<TokenScope>>,
+    /// 有效天数，不传为永久
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginReq {
+    pub username: String,
+    pub password: String,
+    /// 设备名，不传时取 User-Agent
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginResp {
+    pub token: String,
+    /// 同 `daylog_csrf` cookie，浏览器写请求放到 `X-CSRF-Token` 请求头
+    pub csrf_token: String,
+    pub session: AuthSession,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSessionResp {
+    /// 只在创建时返回一次，库中只保存哈希
+    pub token: String,
+    pub session: AuthSession,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthSession {
+    pub id: i64,
+    pub label: String,
+    pub scopes: Vec<TokenScope>,
+    pub last_seen_ip: Option<String>,
+    pub last_seen_time: Option<i64>,
+    pub expire_time: Option<i64>,
+    pub create_time: i64,
+    /// 是否是发起本次请求的会话
+    pub current: bool,
+}
+
+#[derive(Debug, FromRow)]
+struct SessionRow {
+    id: i64,
+    label: String,
+    scopes: String,
+    last_seen_ip: Option<String>,
+    last_seen_time: Option<i64>,
+    expire_time: Option<i64>,
+    create_time: i64,
+}
+
+impl SessionRow {
+    fn into_session(self, current: Option<i64>) -> AuthSession {
+        AuthSession {
+            current: current == Some(self.id),
+            scopes: serde_json::from_str(&self.scopes).unwrap_or_default(),
+            id: self.id,
+            label: self.label,
+            last_seen_ip: self.last_seen_ip,
+            last_seen_time: self.last_seen_time,
+            expire_time: self.expire_time,
+            create_time: self.create_time,
+        }
+    }
+}
+
+impl AuthIdentity {
+    pub fn allows(&self, required: TokenScope) -> bool {
+        self.scopes.iter().any(|v| v.grants(required))
+    }
+}
+
+/// 按路由分组检查令牌权限；没有配置任何令牌时不做检查。
+/// 连续失败的 ip 和令牌会被临时锁定，锁定时间按失败次数指数增长，没带令牌的请求直接 401、不计入失败；
+/// 只靠 cookie 鉴权的写请求还要通过 csrf 校验，带令牌请求头的 api 调用不受影响
+pub async fn require_auth(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let auth = &state.config.auth;
+    if !auth.enabled() {
+        return next.run(request).await;
+    }
+    let Some(required) = required_scope(request.method(), request.uri().path(), auth.public_files)
+    else {
+        return next.run(request).await;
+    };
+    // 请求头中的令牌优先，浏览器只带 cookie 时才按 cookie 会话处理
+    let header_token = quick::header_token(request.headers());
+    let via_cookie = header_token.is_none();
+    let provided = header_token
+        .or_else(|| cookie_value(request.headers(), SESSION_COOKIE))
+        .unwrap_or_default();
+    let provided = provided.trim();
+    let ip = client_ip(
+        request.headers(),
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|v| v.0),
+        auth.trust_forwarded_for,
+    );
+    let keys = lockout_keys(&ip, provided);
+
+    let lockouts = load_lockouts(&state, &keys).await.unwrap_or_default();
+    if let Some(resp) = locked_response(&lockouts, &ip) {
+        return resp;
+    }
+
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    // 没带令牌（例如退出登录后前端仍在轮询）不算猜令牌，不计入锁定也不写审计
+    if provided.is_empty() {
+        return reject(
+            StatusCode::UNAUTHORIZED,
+            ApiCode::Unauthorized,
+            "missing token",
+        );
+    }
+    let Some(identity) = authenticate(&state, provided, Some(&ip)).await else {
+        warn!(
+            "auth rejected: {} {} invalid token, ip={}",
+            method, path, ip
+        );
+        let failure = FailureInput {
+            ip: &ip,
+            token: provided,
+            method: &method,
+            path: &path,
+            reason: "invalid token",
+        };
+        if let Err(e) = record_failure(&state, &failure, &keys).await {
+            warn!("record auth failure failed: {}", e);
+        }
+        return reject(
+            StatusCode::UNAUTHORIZED,
+            ApiCode::Unauthorized,
+            "invalid token",
+        );
+    };
+    if !identity.allows(required) {
+        warn!(
+            "auth rejected: token '{}' lacks {:?} for {} {}",
+            identity.name, required, method, path
+        );
+        // 权限不足不是猜令牌，只记审计不计入锁定
+        let failure = FailureInput {
+            ip: &ip,
+            token: provided,
+            method: &method,
+            path: &path,
+            reason: "insufficient scope",
+        };
+        if let Err(e) = record_failure(&state, &failure, &[]).await {
+            warn!("record auth failure failed: {}", e);
+        }
+        return reject(
+            StatusCode::FORBIDDEN,
+            ApiCode::Forbidden,
+            "insufficient scope",
+        );
+    }
+    if via_cookie && !is_safe_method(request.method()) && !csrf_matches(request.headers()) {
+        warn!(
+            "auth rejected: {} {} csrf token mismatch, ip={}",
+            method, path, ip
+        );
+        return reject(
+            StatusCode::FORBIDDEN,
+            ApiCode::Forbidden,
+            "csrf token mismatch",
+        );
+    }
+    if !lockouts.is_empty()
+        && let Err(e) = clear_lockouts(&state, &keys).await
+    {
+        warn!("clear auth lockout failed: {}", e);
+    }
+    request.extensions_mut().insert(identity);
+    next.run(request).await
+}
+
+/// 最近的鉴权失败记录和正在生效的锁定
+pub async fn list_failures(
+    State(state): State<AppState>,
+    Query(query): Query<FailuresQuery>,
+) -> ApiResult<FailuresResp> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let failures = sqlx::query_as::<_, AuthFailure>(
+        "select id, ip, token_hint, method, path, reason, create_time from auth_failure order by id desc limit ?",
+    )
+    .bind(limit)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| ApiResponse::<FailuresResp>::err(ApiCode::DbListFailed, "db query failed"))?;
+    let lockouts = sqlx::query_as::<_, AuthLockout>(
+        "select key, failures, locked_until from auth_lockout where locked_until > ? order by locked_until desc",
+    )
+    .bind(date_util::now_secs())
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| ApiResponse::<FailuresResp>::err(ApiCode::DbListFailed, "db query failed"))?;
+    Ok(ApiResponse::ok(FailuresResp { failures, lockouts }))
+}
+
+/// 先匹配配置文件中的令牌，再查 `auth_session`；`ip` 不为空时顺便记录会话的最近使用
+pub async fn authenticate(
+    state: &AppState,
+    provided: &str,
+    ip: Option<&str>,
+) -> Option<AuthIdentity> {
+    if let Some(identity) = identify(&state.config.auth, provided) {
+        return Some(identity);
+    }
+    if provided.is_empty() {
+        return None;
+    }
+    let now = date_util::now_secs();
+    let row = sqlx::query_as::<_, SessionRow>(
+        "select id, label, scopes, last_seen_ip, last_seen_time, expire_time, create_time from auth_session where token_hash = ? and (expire_time is null or expire_time > ?)",
+    )
+    .bind(session_hash(provided))
+    .bind(now)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| warn!("load auth session failed: {}", e))
+    .ok()??;
+    if let Some(ip) = ip
+        && (row.last_seen_ip.as_deref() != Some(ip)
+            || row.last_seen_time.unwrap_or(0) + SESSION_TOUCH_SECS < now)
+        && let Err(e) =
+            sqlx::query("update auth_session set last_seen_ip = ?, last_seen_time = ? where id = ?")
+                .bind(ip)
+                .bind(now)
+                .bind(row.id)
+                .execute(&state.db)
+                .await
+    {
+        warn!("touch auth session failed: {}", e);
+    }
+    Some(AuthIdentity {
+        name: row.label.clone(),
+        session_id: Some(row.id),
+        scopes: serde_json::from_str(&row.scopes).unwrap_or_default(),
+    })
+}
+
+/// 签发一个会话令牌，丢失设备时通过 `DELETE /auth/sessions/{id}` 撤销
+pub async fn create_session(
+    State(state): State<AppState>,
+    Json(req): Json<CreateSessionReq>,
+) -> ApiResult<CreateSessionResp> {
+    let label = req.label.trim().to_string();
+    if label.is_empty() {
+        return Err(ApiResponse::<CreateSessionResp>::err(
+            ApiCode::Validation,
+            "label is required",
+        ));
+    }
+    let scopes = req.scopes.unwrap_or_else(|| vec![TokenScope::Write]);
+    if scopes.is_empty() {
+        return Err(ApiResponse::<CreateSessionResp>::err(
+            ApiCode::Validation,
+            "scopes must not be empty",
+        ));
+    }
+    let expires_in_days = match req.expires_in_days {
+        Some(days) if days <= 0 => {
+            return Err(ApiResponse::<CreateSessionResp>::err(
+                ApiCode::Validation,
+                "expiresInDays must be positive",
+            ));
+        }
+        v => v,
+    };
+    let (session_token, session) = insert_session(&state, &label, &scopes, expires_in_days)
+        .await
+        .map_err(|_| {
+            ApiResponse::<CreateSessionResp>::err(ApiCode::DbInsertFailed, "db insert failed")
+        })?;
+    info!(
+        "创建会话 id={}, label={}, scopes={:?}",
+        session.id, label, scopes
+    );
+
+    Ok(ApiResponse::ok(CreateSessionResp {
+        token: session_token,
+        session,
+    }))
+}
+
+/// 未过期的会话，配置文件中的令牌不在这里列出
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    identity: Option<Extension<AuthIdentity>>,
+) -> ApiResult<Vec<AuthSession>> {
+    let current = identity.and_then(|v| v.session_id);
+    let rows = sqlx::query_as::<_, SessionRow>(
+        "select id, label, scopes, last_seen_ip, last_seen_time, expire_time, create_time from auth_session where expire_time is null or expire_time > ? order by coalesce(last_seen_time, create_time) desc",
+    )
+    .bind(date_util::now_secs())
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| ApiResponse::<Vec<AuthSession>>::err(ApiCode::DbListFailed, "db query failed"))?;
+    Ok(ApiResponse::ok(
+        rows.into_iter().map(|v| v.into_session(current)).collect(),
+    ))
+}
+
+pub async fn revoke_session(State(state): State<AppState>, Path(id): Path<i64>) -> ApiResult<()> {
+    let result = sqlx::query("delete from auth_session where id = ?")
+        .bind(id)
+        .execute(&state.db)
+        .await
+        .map_err(|_| ApiResponse::<()>::err(ApiCode::DbDeleteFailed, "db delete failed"))?;
+    if result.rows_affected() == 0 {
+        return Err(ApiResponse::<()>::err(ApiCode::NotFound, "not found"));
+    }
+    info!("撤销会话 id={}", id);
+    Ok(ApiResponse::ok(()))
+}
+
+/// 用 `auth.username` `auth.password` 登录，签发一个 `admin` 会话；
+/// 令牌同时在响应体和 HttpOnly cookie 中返回，浏览器之后的写请求需要带上 `CSRF_HEADER`
+pub async fn login(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(req): Json<LoginReq>,
+) -> Response {
+    let auth = &state.config.auth;
+    if !auth.login_enabled() {
+        return ApiResponse::<()>::err(ApiCode::NotFound, "password login disabled")
+            .into_response();
+    }
+    let ip = client_ip(&headers, Some(peer), auth.trust_forwarded_for);
+    let username = req.username.trim();
+    let keys = lockout_keys(&ip, username);
+    let lockouts = load_lockouts(&state, &keys).await.unwrap_or_default();
+    if let Some(resp) = locked_response(&lockouts, &ip) {
+        return resp;
+    }
+
+    let ok = quick::token_eq(username, auth.username.trim())
+        && quick::token_eq(&req.password, &auth.password);
+    if !ok {
+        warn!("login rejected: user={}, ip={}", username, ip);
+        let failure = FailureInput {
+            ip: &ip,
+            token: username,
+            method: "POST",
+            path: "/auth/login",
+            reason: "invalid username or password",
+        };
+        if let Err(e) = record_failure(&state, &failure, &keys).await {
+            warn!("record auth failure failed: {}", e);
+        }
+        return reject(
+            StatusCode::UNAUTHORIZED,
+            ApiCode::Unauthorized,
+            "invalid username or password",
+        );
+    }
+    if !lockouts.is_empty()
+        && let Err(e) = clear_lockouts(&state, &keys).await
+    {
+        warn!("clear auth lockout failed: {}", e);
+    }
+
+    let label = req
+        .label
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .or_else(|| {
+            headers
+                .get(header::USER_AGENT)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.chars().take(80).collect())
+        })
+        .unwrap_or_else(|| "browser".to_string());
+    let days = auth.session_days.max(1);
+    let created = insert_session(&state, &label, &[TokenScope::Admin], Some(days)).await;
+    let (session_token, session) = match created {
+        Ok(v) => v,
+        Err(_) => {
+            return ApiResponse::<()>::err(ApiCode::DbInsertFailed, "db insert failed")
+                .into_response();
+        }
+    };
+    info!("登录成功 session={}, ip={}", session.id, ip);
+
+    let csrf_token = token::random_token(24);
+    let max_age = days * 86_400;
+    (
+        AppendHeaders([
+            (
+                header::SET_COOKIE,
+                session_cookie(SESSION_COOKIE, &session_token, max_age, true),
+            ),
+            (
+                header::SET_COOKIE,
+                session_cookie(CSRF_COOKIE, &csrf_token, max_age, false),
+            ),
+        ]),
+        ApiResponse::ok(LoginResp {
+            token: session_token,
+            csrf_token,
+            session,
+        }),
+    )
+        .into_response()
+}
+
+/// 撤销当前会话并清掉 cookie，配置文件中的令牌不受影响
+pub async fn logout(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    let provided = quick::header_token(&headers)
+        .or_else(|| cookie_value(&headers, SESSION_COOKIE))
+        .unwrap_or_default();
+    let provided = provided.trim();
+    let identity = if provided.is_empty() {
+        None
+    } else {
+        let attempt = match TokenAttempt::begin(&state, &headers, peer, provided).await {
+            Ok(v) => v,
+            Err(resp) => return resp,
+        };
+        let identity = authenticate(&state, provided, Some(attempt.ip())).await;
+        match &identity {
+            Some(_) => attempt.succeeded(&state).await,
+            None => {
+                warn!("logout with invalid token, ip={}", attempt.ip());
+                attempt
+                    .failed(&state, provided, "POST", "/auth/logout")
+                    .await;
+            }
+        }
+        identity
+    };
+    if let Some(id) = identity.and_then(|v| v.session_id) {
+        if let Err(e) = sqlx::query("delete from auth_session where id = ?")
+            .bind(id)
+            .execute(&state.db)
+            .await
+        {
+            warn!("删除会话失败: {}", e);
+            return ApiResponse::<()>::err(ApiCode::DbDeleteFailed, "db delete failed")
+                .into_response();
+        }
+        info!("退出登录 session={}", id);
+    }
+    (
+        AppendHeaders([
+            (
+                header::SET_COOKIE,
+                session_cookie(SESSION_COOKIE, "", 0, true),
+            ),
+            (
+                header::SET_COOKIE,
+                session_cookie(CSRF_COOKIE, "", 0, false),
+            ),
+        ]),
+        ApiResponse::ok(()),
+    )
+        .into_response()
+}
+
+async fn insert_session(
+    state: &AppState,
+    label: &str,
+    scopes: &[TokenScope],
+    expires_in_days: Option<i64>,
+) -> Result<(String, AuthSession), sqlx::Error> {
+    let now = date_util::now_secs();
+    let expire_time = expires_in_days.map(|days| now + days * 86_400);
+    let session_token = token::random_token(32);
+    let id = sqlx::query(
+        "insert into auth_session (token_hash, label, scopes, expire_time, create_time) values (?, ?, ?, ?, ?)",
+    )
+    .bind(session_hash(&session_token))
+    .bind(label)
+    .bind(serde_json::to_string(scopes).unwrap_or_else(|_| "[]".to_string()))
+    .bind(expire_time)
+    .bind(now)
+    .execute(&state.db)
+    .await?
+    .last_insert_rowid();
+    Ok((
+        session_token,
+        AuthSession {
+            id,
+            label: label.to_string(),
+            scopes: scopes.to_vec(),
+            last_seen_ip: None,
+            last_seen_time: None,
+            expire_time,
+            create_time: now,
+            current: false,
+        },
+    ))
+}
+
+/// 会话令牌是 32 字节随机数，不需要加盐
+fn session_hash(session_token: &str) -> String {
+    token::hash_secret("auth-session", session_token)
+}
+
+/// `auth.token` 拥有全部权限，其余按 `auth.tokens` 配置的 scopes
+fn identify(auth: &AuthConfig, provided: &str) -> Option<AuthIdentity> {
+    if provided.is_empty() {
+        return None;
+    }
+    let admin = auth.token.trim();
+    if !admin.is_empty() && quick::token_eq(provided, admin) {
+        return Some(AuthIdentity {
+            name: "admin".to_string(),
+            scopes: vec![TokenScope::Admin],
+            session_id: None,
+        });
+    }
+    auth.tokens
+        .iter()
+        .find(|v| !v.token.trim().is_empty() && quick::token_eq(provided, v.token.trim()))
+        .map(|v| AuthIdentity {
+            name: v.name.clone(),
+            scopes: v.scopes.clone(),
+            session_id: None,
+        })
+}
+
+/// 不需要鉴权的路由返回 None：前端页面、分享、徽章、接口文档和错误码列表是公开的，
+/// 上传的文件按 `auth.public_files`，`/files/by-id/` 的 id 是连续的、可以逐个遍历，始终需要 `read`；
+/// `/quick` `/hooks/ingest` 和登录登出自己校验，
+/// 没有配置令牌时 `require_auth` 不做检查，`/setup` 才对所有人开放，配置了令牌后需要 `admin`
+fn required_scope(method: &Method, path: &str, public_files: bool) -> Option<TokenScope> {
+    let path = path.trim_end_matches('/');
+    let public = path.is_empty()
+        || path.starts_with("/static/")
+        || (public_files
+            && path.starts_with("/files/")
+            && !path.starts_with("/files/by-id/")
+            && !path.ends_with("/references"))
+        || path.starts_with("/badge/")
+        || path == "/api-docs"
+        || path.starts_with("/api-docs/")
+        || path == "/errors"
+        || path == "/quick"
+        || path == "/hooks/ingest"
+        || path == "/auth/login"
+        || path == "/auth/logout"
+        || (path.starts_with("/share/") && path != "/share/month" && method != Method::DELETE);
+    if public {
+        return None;
+    }
+    let admin = (path == "/settings" && method != Method::GET)
+        || path.starts_with("/auth/")
+        || path == "/setup"
+        || path.starts_with("/setup/")
+        || path == "/sync/diagnose"
+        || path == "/backup"
+        || path.starts_with("/backup/")
+        || path == "/trash/purge"
+        || path.starts_with("/admin/")
+        || path.starts_with("/status/")
+        || (path.starts_with("/archive/") && method == Method::POST);
+    if admin {
+        return Some(TokenScope::Admin);
+    }
+    if path == "/upload" && method == Method::POST {
+        return Some(TokenScope::UploadOnly);
+    }
+    if method == Method::GET || method == Method::HEAD {
+        return Some(TokenScope::Read);
+    }
+    Some(TokenScope::Write)
+}
+
+/// 不经过 `require_auth`、自己校验令牌的接口（`/quick`、`/hooks/ingest`、`/auth/logout`）用，
+/// 和中间件一样先查锁定，失败计入锁定，成功后清除
+pub struct TokenAttempt {
+    ip: String,
+    keys: Vec<String>,
+    locked: bool,
+}
+
+impl TokenAttempt {
+    /// 已被锁定时返回 423 响应
+    pub async fn begin(
+        state: &AppState,
+        headers: &HeaderMap,
+        peer: SocketAddr,
+        provided: &str,
+    ) -> Result<Self, Response> {
+        let ip = client_ip(headers, Some(peer), state.config.auth.trust_forwarded_for);
+        let keys = lockout_keys(&ip, provided);
+        let lockouts = load_lockouts(state, &keys).await.unwrap_or_default();
+        if let Some(resp) = locked_response(&lockouts, &ip) {
+            return Err(resp);
+        }
+        Ok(Self {
+            ip,
+            keys,
+            locked: !lockouts.is_empty(),
+        })
+    }
+
+    pub fn ip(&self) -> &str {
+        &self.ip
+    }
+
+    /// 没带令牌时不计入锁定
+    pub async fn failed(&self, state: &AppState, provided: &str, method: &str, path: &str) {
+        if provided.is_empty() {
+            return;
+        }
+        let failure = FailureInput {
+            ip: &self.ip,
+            token: provided,
+            method,
+            path,
+            reason: "invalid token",
+        };
+        if let Err(e) = record_failure(state, &failure, &self.keys).await {
+            warn!("record auth failure failed: {}", e);
+        }
+    }
+
+    pub async fn succeeded(&self, state: &AppState) {
+        if self.locked
+            && let Err(e) = clear_lockouts(state, &self.keys).await
+        {
+            warn!("clear auth lockout failed: {}", e);
+        }
+    }
+}
+
+struct FailureInput<'a> {
+    ip: &'a str,
+    token: &'a str,
+    method: &'a str,
+    path: &'a str,
+    reason: &'a str,
+}
+
+fn is_safe_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// 跨站请求能带上 cookie 但读不到它的值，请求头和 cookie 中的 csrf 令牌一致才放行
+fn csrf_matches(headers: &HeaderMap) -> bool {
+    let Some(cookie) = cookie_value(headers, CSRF_COOKIE).filter(|v| !v.is_empty()) else {
+        return false;
+    };
+    headers
+        .get(CSRF_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| quick::token_eq(v.trim(), &cookie))
+}
+
+pub fn cookie_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v.trim().to_string())
+}
+
+pub fn client_ip(
+    headers: &HeaderMap,
+    peer: Option<SocketAddr>,
+    trust_forwarded_for: bool,
+) -> String {
+    if trust_forwarded_for
+        && let Some(v) = headers
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(',').next())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    {
+        return v.to_string();
+    }
+    peer.map(|v| v.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn token_hint(provided: &str) -> Option<String> {
+    if provided.is_empty() {
+        return None;
+    }
+    Some(token::hash_secret("auth-failure", provided)[..12].to_string())
+}
+
+fn lockout_keys(ip: &str, provided: &str) -> Vec<String> {
+    let mut keys = vec![format!("ip:{}", ip)];
+    if let Some(hint) = token_hint(provided) {
+        keys.push(format!("token:{}", hint));
+    }
+    keys
+}
+
+async fn load_lockouts(state: &AppState, keys: &[String]) -> Result<Vec<AuthLockout>, sqlx::Error> {
+    let keys = serde_json::to_string(keys).unwrap_or_else(|_| "[]".to_string());
+    sqlx::query_as::<_, AuthLockout>(
+        "select key, failures, locked_until from auth_lockout where key in (select value from json_each(?))",
+    )
+    .bind(keys)
+    .fetch_all(&state.db)
+    .await
+}
+
+async fn clear_lockouts(state: &AppState, keys: &[String]) -> Result<(), sqlx::Error> {
+    let keys = serde_json::to_string(keys).unwrap_or_else(|_| "[]".to_string());
+    sqlx::query("delete from auth_lockout where key in (select value from json_each(?))")
+        .bind(keys)
+        .execute(&state.db)
+        .await?;
+    Ok(())
+}
+
+/// 写审计记录，并给 `keys` 的失败次数加一，达到 `auth.max_failures` 后按
+/// `lockout_secs * 2^(超出次数)` 锁定，不超过 `max_lockout_secs`
+async fn record_failure(
+    state: &AppState,
+    failure: &FailureInput<'_>,
+    keys: &[String],
+) -> Result<(), sqlx::Error> {
+    let cfg = &state.config.auth;
+    let now = date_util::now_secs();
+    let mut tx = state.db.begin().await?;
+    sqlx::query(
+        "insert into auth_failure (ip, token_hint, method, path, reason, create_time) values (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(failure.ip)
+    .bind(token_hint(failure.token))
+    .bind(failure.method)
+    .bind(failure.path)
+    .bind(failure.reason)
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("delete from auth_failure where create_time < ?")
+        .bind(now - FAILURE_KEEP_SECS)
+        .execute(&mut *tx)
+        .await?;
+
+    for key in keys {
+        let failures = sqlx::query_scalar::<_, i64>(
+            "select failures from auth_lockout where key = ? and update_time >= ?",
+        )
+        .bind(key)
+        .bind(now - FAILURE_RESET_SECS)
+        .fetch_optional(&mut *tx)
+        .await?
+        .unwrap_or(0)
+            + 1;
+        let max_failures = cfg.max_failures as i64;
+        let locked_until = if max_failures > 0 && failures >= max_failures {
+            let exp = (failures - max_failures).min(30) as u32;
+            let secs = cfg
+                .lockout_secs
+                .saturating_mul(1u64 << exp)
+                .min(cfg.max_lockout_secs);
+            now + secs as i64
+        } else {
+            0
+        };
+        sqlx::query(
+            "insert or replace into auth_lockout (key, failures, locked_until, update_time) values (?, ?, ?, ?)",
+        )
+        .bind(key)
+        .bind(failures)
+        .bind(locked_until)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+/// 仍在锁定中时返回 423，带上 `Retry-After`
+fn locked_response(lockouts: &[AuthLockout], ip: &str) -> Option<Response> {
+    let now = date_util::now_secs();
+    let until = lockouts.iter().map(|v| v.locked_until).max()?;
+    if until <= now {
+        return None;
+    }
+    warn!("auth locked: ip={} until={}", ip, until);
+    let mut resp = reject(
+        StatusCode::LOCKED,
+        ApiCode::Locked,
+        "too many failed attempts, try again later",
+    );
+    if let Ok(v) = HeaderValue::from_str(&(until - now).to_string()) {
+        resp.headers_mut().insert(header::RETRY_AFTER, v);
+    }
+    Some(resp)
+}
+
+fn session_cookie(name: &str, value: &str, max_age: i64, http_only: bool) -> String {
+    format!(
+        "{}={}; Path=/; Max-Age={}; SameSite=Lax{}",
+        name,
+        value,
+        max_age,
+        if http_only { "; HttpOnly" } else { "" }
+    )
+}
+
+fn reject(status: StatusCode, code: ApiCode, msg: &str) -> Response {
+    let (_, body) = ApiResponse::<()>::err(code, msg);
+    (status, body).into_response()
+}
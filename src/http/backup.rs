@@ -0,0 +1,22 @@
+use crate::app_state::AppState;
+use crate::backup::{self, BackupRecord};
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use axum::extract::State;
+use tracing::{info, warn};
+
+pub async fn list_backups(State(state): State<AppState>) -> ApiResult<Vec<BackupRecord>> {
+    let items = backup::list(&state).await.map_err(|_| {
+        ApiResponse::<Vec<BackupRecord>>::err(ApiCode::DbListFailed, "db query failed")
+    })?;
+    Ok(ApiResponse::ok(items))
+}
+
+/// 立即备份一次，不要求开启定时备份
+pub async fn run_backup(State(state): State<AppState>) -> ApiResult<BackupRecord> {
+    info!("手动备份 target={}", state.config.backup.target);
+    let record = backup::run(&state).await.map_err(|msg| {
+        warn!("备份失败: {}", msg);
+        ApiResponse::<BackupRecord>::err(ApiCode::FileWriteFailed, &msg)
+    })?;
+    Ok(ApiResponse::ok(record))
+}
@@ -0,0 +1,97 @@
+use crate::app_state::AppState;
+use crate::digest;
+use crate::util::date_util;
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// shields.io 会自己缓存，这里再允许中间代理缓存几分钟
+const BADGE_MAX_AGE: &str = "public, max-age=300";
+
+#[derive(Debug, Deserialize)]
+pub struct BadgeQuery {
+    /// 默认 `journal`
+    pub label: Option<String>,
+    /// `streak` 只显示连续天数，`total` 只显示总篇数，默认两者都显示
+    pub show: Option<String>,
+}
+
+/// shields.io endpoint 格式：https://shields.io/badges/endpoint-badge
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BadgeResp {
+    pub schema_version: u8,
+    pub label: String,
+    pub message: String,
+    pub color: &'static str,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub is_error: bool,
+}
+
+/// 连续记录天数和总篇数的徽章，可以放进同步仓库的 README
+pub async fn streak_badge(
+    State(state): State<AppState>,
+    Query(query): Query<BadgeQuery>,
+) -> Response {
+    let label = query
+        .label
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .unwrap_or("journal")
+        .to_string();
+    let badge = match load_counts(&state).await {
+        Ok((streak, total)) => {
+            let streak_text = format!("{} day streak", streak);
+            let total_text = format!("{} entries", total);
+            let message = match query.show.as_deref() {
+                Some("streak") => streak_text,
+                Some("total") => total_text,
+                _ => format!("{} | {}", streak_text, total_text),
+            };
+            BadgeResp {
+                schema_version: 1,
+                label,
+                message,
+                color: if streak > 0 {
+                    "brightgreen"
+                } else {
+                    "lightgrey"
+                },
+                is_error: false,
+            }
+        }
+        Err(e) => {
+            warn!("读取徽章数据失败: {}", e);
+            BadgeResp {
+                schema_version: 1,
+                label,
+                message: "unavailable".to_string(),
+                color: "red",
+                is_error: true,
+            }
+        }
+    };
+    let mut resp = (StatusCode::OK, Json(badge)).into_response();
+    resp.headers_mut().insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(BADGE_MAX_AGE),
+    );
+    resp
+}
+
+/// 连续记录天数和总篇数；今天还没写时从昨天往前算，不会一早就显示断了
+pub async fn load_counts(state: &AppState) -> Result<(i64, i64), sqlx::Error> {
+    let today = date_util::parse_date(&state.config.today()).unwrap_or_default();
+    let mut streak = digest::streak_until(state, today).await?;
+    if streak == 0 {
+        streak = digest::streak_until(state, today - 1).await?;
+    }
+    let total = sqlx::query_scalar::<_, i64>("select count(*) from journal")
+        .fetch_one(&state.read_db)
+        .await?;
+    Ok((streak, total))
+}
@@ -0,0 +1,397 @@
+use crate::app_state::AppState;
+use crate::http::export::{self, StreamZipWriter, write_zip_entry};
+use crate::http::resp::{ApiCode, ApiResponse};
+use crate::util::{date_util, markdown};
+use async_zip::Compression;
+use axum::extract::{Query, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use base64::Engine;
+use serde::Deserialize;
+use sqlx::FromRow;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+#[derive(Debug, Deserialize)]
+pub struct BookQuery {
+    pub year: i32,
+    pub format: Option<String>,
+}
+
+#[derive(Debug, FromRow)]
+struct BookJournalRow {
+    date: String,
+    content: String,
+}
+
+#[derive(Debug, FromRow)]
+struct BlobRow {
+    mime: String,
+    file_path: String,
+}
+
+struct BookImage {
+    /// epub 内的文件名
+    name: String,
+    mime: String,
+    file_path: String,
+    /// pdf 内联用的 data uri，epub 写入时才从 `file_path` 读取
+    data_uri: Option<String>,
+}
+
+struct Chapter {
+    month: String,
+    body: String,
+}
+
+pub async fn export_book(
+    State(state): State<AppState>,
+    Query(query): Query<BookQuery>,
+) -> Response {
+    let format = query
+        .format
+        .as_deref()
+        .unwrap_or("epub")
+        .trim()
+        .to_ascii_lowercase();
+    if format != "epub" && format != "pdf" {
+        return ApiResponse::<()>::err(ApiCode::Validation, "format must be epub or pdf")
+            .into_response();
+    }
+    info!("导出年度日记 year={}, format={}", query.year, format);
+
+    let mut rows = match sqlx::query_as::<_, BookJournalRow>(
+        "select date, content from journal where date like ? order by date asc",
+    )
+    .bind(format!("{:04}-%", query.year))
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(v) => v,
+        Err(_) => {
+            return ApiResponse::<()>::err(ApiCode::DbListFailed, "db query failed")
+                .into_response();
+        }
+    };
+    for row in rows.iter_mut() {
+        row.content = state.cipher.open(std::mem::take(&mut row.content));
+    }
+    if rows.is_empty() {
+        return ApiResponse::<()>::err(ApiCode::NotFound, "no journals in this year")
+            .into_response();
+    }
+
+    let title = format!("DayLog {}", query.year);
+    let inline = format == "pdf";
+    let mut images: HashMap<String, BookImage> = HashMap::new();
+    let mut chapters: Vec<Chapter> = Vec::new();
+    for row in &rows {
+        let uris = image_uris(&row.content);
+        for uri in uris {
+            if images.contains_key(&uri) {
+                continue;
+            }
+            if let Some(image) = load_image(&state, &uri, images.len(), inline).await {
+                images.insert(uri, image);
+            }
+        }
+        let html = markdown::to_html_with_images(&row.content, |src| {
+            let image = images.get(src)?;
+            match image.data_uri.as_ref() {
+                Some(v) => Some(v.clone()),
+                None => Some(format!("images/{}", image.name)),
+            }
+        });
+        let month = row.date.get(0..7).unwrap_or(&row.date).to_string();
+        let section = format!(
+            "<section><h2>{}</h2>\n{}</section>\n",
+            markdown::escape_html(&row.date),
+            html
+        );
+        match chapters.last_mut() {
+            Some(ch) if ch.month == month => ch.body.push_str(&section),
+            _ => chapters.push(Chapter {
+                month,
+                body: section,
+            }),
+        }
+    }
+
+    let disposition = format!("attachment; filename=\"daylog-{}.{}\"", query.year, format);
+    if format == "epub" {
+        let year = query.year;
+        let images = images.into_values().collect::<Vec<_>>();
+        let body = export::stream_zip(move |zip| write_epub(zip, title, year, chapters, images));
+        return (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "application/epub+zip".to_string()),
+                (header::CONTENT_DISPOSITION, disposition),
+            ],
+            body,
+        )
+            .into_response();
+    }
+
+    match build_pdf(&state, &title, &chapters).await {
+        Ok(bytes) => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "application/pdf".to_string()),
+                (header::CONTENT_DISPOSITION, disposition),
+            ],
+            bytes,
+        )
+            .into_response(),
+        Err(msg) => {
+            warn!("导出年度日记失败: {}", msg);
+            ApiResponse::<()>::err(ApiCode::BadRequest, &msg).into_response()
+        }
+    }
+}
+
+/// 提取 markdown 中引用的本地图片地址
+fn image_uris(content: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut rest = content;
+    while let Some(pos) = rest.find("](") {
+        rest = &rest[pos + 2..];
+        let Some(end) = rest.find(')') else {
+            break;
+        };
+        let uri = rest[..end].split_whitespace().next().unwrap_or("");
+        if uri.starts_with("/files/picture/") {
+            out.push(uri.to_string());
+        }
+        rest = &rest[end..];
+    }
+    out
+}
+
+async fn load_image(state: &AppState, uri: &str, idx: usize, inline: bool) -> Option<BookImage> {
+    let row =
+        sqlx::query_as::<_, BlobRow>("select mime, file_path from file_blob where uri = ? limit 1")
+            .bind(uri)
+            .fetch_optional(&state.db)
+            .await
+            .ok()??;
+    let file_path = state
+        .config
+        .resolve_stored_path(&row.file_path)
+        .to_string_lossy()
+        .to_string();
+    let data_uri = if inline {
+        let bytes = tokio::fs::read(&file_path).await.ok()?;
+        Some(format!(
+            "data:{};base64,{}",
+            row.mime,
+            base64::engine::general_purpose::STANDARD.encode(&bytes)
+        ))
+    } else {
+        if !tokio::fs::try_exists(&file_path).await.unwrap_or(false) {
+            return None;
+        }
+        None
+    };
+    let ext = uri.rsplit_once('.').map(|(_, e)| e).unwrap_or("img");
+    Some(BookImage {
+        name: format!("img{}.{}", idx, ext),
+        mime: row.mime,
+        file_path,
+        data_uri,
+    })
+}
+
+fn xhtml_page(title: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n<head><meta charset=\"utf-8\"/><title>{}</title></head>\n<body>\n{}</body>\n</html>\n",
+        markdown::escape_html(title),
+        body
+    )
+}
+
+/// 边生成边写出 epub，图片在写入时才读取，避免整本书驻留内存
+async fn write_epub(
+    mut zip: StreamZipWriter,
+    title: String,
+    year: i32,
+    chapters: Vec<Chapter>,
+    images: Vec<BookImage>,
+) -> Result<(), String> {
+    let title = title.as_str();
+    let stored = Compression::Stored;
+    let deflated = Compression::Deflate;
+
+    // mimetype 必须是第一个且不压缩
+    write_zip_entry(&mut zip, "mimetype", stored, b"application/epub+zip").await?;
+    write_zip_entry(
+        &mut zip,
+        "META-INF/container.xml",
+        deflated,
+        br#"<?xml version="1.0" encoding="utf-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#,
+    )
+    .await?;
+
+    let mut manifest = String::new();
+    let mut spine = String::new();
+    let mut nav = String::new();
+    for (idx, ch) in chapters.iter().enumerate() {
+        let file = format!("chapter-{}.xhtml", idx + 1);
+        let body = format!("<h1>{}</h1>\n{}", markdown::escape_html(&ch.month), ch.body);
+        write_zip_entry(
+            &mut zip,
+            &format!("OEBPS/{}", file),
+            deflated,
+            xhtml_page(&ch.month, &body).as_bytes(),
+        )
+        .await?;
+        manifest.push_str(&format!(
+            "    <item id=\"ch{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+            idx + 1,
+            file
+        ));
+        spine.push_str(&format!("    <itemref idref=\"ch{}\"/>\n", idx + 1));
+        nav.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            file,
+            markdown::escape_html(&ch.month)
+        ));
+    }
+    for (idx, image) in images.iter().enumerate() {
+        let bytes = tokio::fs::read(&image.file_path)
+            .await
+            .map_err(|e| format!("读取图片 {} 失败: {}", image.file_path, e))?;
+        write_zip_entry(
+            &mut zip,
+            &format!("OEBPS/images/{}", image.name),
+            deflated,
+            &bytes,
+        )
+        .await?;
+        manifest.push_str(&format!(
+            "    <item id=\"img{}\" href=\"images/{}\" media-type=\"{}\"/>\n",
+            idx,
+            image.name,
+            markdown::escape_html(&image.mime)
+        ));
+    }
+
+    let nav_body = format!(
+        "<nav epub:type=\"toc\" id=\"toc\"><h1>{}</h1><ol>\n{}</ol></nav>\n",
+        markdown::escape_html(title),
+        nav
+    );
+    write_zip_entry(
+        &mut zip,
+        "OEBPS/nav.xhtml",
+        deflated,
+        xhtml_page(title, &nav_body).as_bytes(),
+    )
+    .await?;
+
+    let (y, m, d) = date_util::civil_from_days(date_util::now_secs().div_euclid(86_400));
+    let opf = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="bookid">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="bookid">urn:daylog:{year}</dc:identifier>
+    <dc:title>{title}</dc:title>
+    <dc:language>zh</dc:language>
+    <meta property="dcterms:modified">{y:04}-{m:02}-{d:02}T00:00:00Z</meta>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+{manifest}  </manifest>
+  <spine>
+{spine}  </spine>
+</package>
+"#,
+        year = year,
+        title = markdown::escape_html(title),
+        manifest = manifest,
+        spine = spine,
+    );
+    write_zip_entry(&mut zip, "OEBPS/content.opf", deflated, opf.as_bytes()).await?;
+
+    zip.close().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+async fn build_pdf(state: &AppState, title: &str, chapters: &[Chapter]) -> Result<Vec<u8>, String> {
+    let command = state.config.export.pdf_command.trim();
+    if command.is_empty() {
+        return Err("pdf export requires export.pdf_command".to_string());
+    }
+
+    let mut body = format!("<h1>{}</h1>\n", markdown::escape_html(title));
+    for ch in chapters {
+        body.push_str(&format!(
+            "<h1 style=\"page-break-before: always\">{}</h1>\n{}",
+            markdown::escape_html(&ch.month),
+            ch.body
+        ));
+    }
+    let html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title><style>img{{max-width:100%}}</style></head><body>\n{}</body></html>\n",
+        markdown::escape_html(title),
+        body
+    );
+
+    let tmp_dir = PathBuf::from(state.config.get_tmp_path());
+    tokio::fs::create_dir_all(&tmp_dir)
+        .await
+        .map_err(|e| e.to_string())?;
+    let stamp = format!("book_{}_{}", date_util::now_secs(), std::process::id());
+    let input = tmp_dir.join(format!("{}.html", stamp));
+    let output = tmp_dir.join(format!("{}.pdf", stamp));
+    tokio::fs::write(&input, html)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let result = run_pdf_command(command, &input, &output).await;
+    let bytes = match result {
+        Ok(()) => tokio::fs::read(&output).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+    let _ = tokio::fs::remove_file(&input).await;
+    let _ = tokio::fs::remove_file(&output).await;
+    bytes
+}
+
+async fn run_pdf_command(
+    command: &str,
+    input: &std::path::Path,
+    output: &std::path::Path,
+) -> Result<(), String> {
+    let args = command
+        .split_whitespace()
+        .map(|v| {
+            v.replace("{input}", &input.to_string_lossy())
+                .replace("{output}", &output.to_string_lossy())
+        })
+        .collect::<Vec<_>>();
+    let (program, args) = args
+        .split_first()
+        .ok_or_else(|| "export.pdf_command is empty".to_string())?;
+    let out = Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| format!("run pdf command failed: {}", e))?;
+    if !out.status.success() {
+        return Err(format!(
+            "pdf command exit with {}: {}",
+            out.status,
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use crate::app_state::AppState;
+use crate::http::resp::{ApiResponse, ApiResult};
+use crate::sync::git;
+use axum::extract::State;
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapabilitiesResp {
+    pub version: &'static str,
+    /// `none` 未配置访问令牌，`token` 需要 `auth.token` 或 `auth.tokens` 中的令牌
+    pub auth_mode: &'static str,
+    /// 可以用 `POST /auth/login` 登录
+    pub password_login: bool,
+    pub sync: SyncCapability,
+    pub importers: Vec<&'static str>,
+    pub import_strategies: Vec<&'static str>,
+    pub export_formats: Vec<&'static str>,
+    pub bots: Vec<&'static str>,
+    /// 已配置的通知渠道，未开启时为 None
+    pub notify: Option<String>,
+    pub quick_append: bool,
+    pub ingest_hook: bool,
+    pub reminder: bool,
+    pub weekly_digest: bool,
+    pub retention: bool,
+    /// 删除的日记先进回收站
+    pub trash: bool,
+    /// 定时备份的目标，未开启时为 None
+    pub backup: Option<String>,
+    /// 仍然可用但计划移除的接口和字段，客户端应尽快迁移到 `replacement`
+    pub deprecations: Vec<Deprecation>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncCapability {
+    pub enabled: bool,
+    /// `git` / `webdav` / `dir`
+    pub backend: Option<String>,
+    /// `password` / `ssh`，`sync.auth_method` 无效时为 None
+    pub auth_mode: Option<&'static str>,
+    pub scheduled: bool,
+    pub formats: Vec<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Deprecation {
+    pub target: &'static str,
+    pub replacement: &'static str,
+}
+
+/// 当前实例开启的可选功能，第三方客户端据此决定展示哪些入口，不用逐个请求接口再根据错误码判断
+pub async fn list_capabilities(State(state): State<AppState>) -> ApiResult<CapabilitiesResp> {
+    let cfg = &state.config;
+    let sync_enabled = cfg.sync.enabled && !cfg.sync.repo_url.trim().is_empty();
+
+    let mut export_formats = vec!["json", "epub"];
+    if !cfg.export.pdf_command.trim().is_empty() {
+        export_formats.push("pdf");
+    }
+    let mut bots = Vec::new();
+    if cfg.telegram.enabled {
+        bots.push("telegram");
+    }
+    if cfg.matrix.enabled {
+        bots.push("matrix");
+    }
+
+    Ok(ApiResponse::ok(CapabilitiesResp {
+        version: env!("CARGO_PKG_VERSION"),
+        auth_mode: if cfg.auth.enabled() { "token" } else { "none" },
+        password_login: cfg.auth.login_enabled(),
+        sync: SyncCapability {
+            enabled: sync_enabled,
+            backend: sync_enabled.then(|| cfg.sync.backend.trim().to_string()),
+            auth_mode: (sync_enabled && cfg.sync.backend.trim() == "git")
+                .then(|| git::auth_mode_name(&cfg.sync))
+                .flatten(),
+            scheduled: sync_enabled && cfg.sync.interval_minutes > 0,
+            formats: vec!["markdown", "json", "html"],
+        },
+        importers: vec!["zip", "wordpress"],
+        import_strategies: vec!["overwrite", "skip", "append"],
+        export_formats,
+        bots,
+        notify: cfg
+            .notify
+            .enabled
+            .then(|| cfg.notify.provider.trim().to_ascii_lowercase()),
+        quick_append: !cfg.quick.token.trim().is_empty(),
+        ingest_hook: !cfg.hooks.token.trim().is_empty(),
+        reminder: cfg.reminder.enabled,
+        weekly_digest: cfg.digest.enabled,
+        retention: cfg.retention.enabled,
+        trash: cfg.retention.trash_days > 0,
+        backup: cfg
+            .backup
+            .enabled
+            .then(|| cfg.backup.target.trim().to_string()),
+        deprecations: vec![
+            Deprecation {
+                target: "POST /journal/import/zip multipart field `patterns`",
+                replacement: "`options.patterns`",
+            },
+            Deprecation {
+                target: "POST /sync/journal response `filePath` `format`",
+                replacement: "`outputs`",
+            },
+        ],
+    }))
+}
@@ -0,0 +1,134 @@
+use crate::http::resp::ApiResponse;
+use crate::util::file_util;
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// 返回带 `ETag`/`Last-Modified` 的成功响应，客户端缓存仍然有效时返回 304
+///
+/// `ETag` 取响应体的哈希，同一秒内的多次修改也能区分；`last_modified` 为秒级时间戳
+pub fn ok_json<T: Serialize>(headers: &HeaderMap, data: T, last_modified: Option<i64>) -> Response {
+    let (_, body) = ApiResponse::ok(data);
+    let bytes = match serde_json::to_vec(&body.0) {
+        Ok(v) => v,
+        Err(_) => return (StatusCode::OK, body).into_response(),
+    };
+    let etag = format!("\"{}\"", &file_util::file_hash(&bytes)[..32]);
+    let last_modified = last_modified
+        .filter(|v| *v > 0)
+        .map(|v| httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_secs(v as u64)));
+
+    let mut resp = if not_modified(headers, &etag, last_modified.as_deref()) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "application/json")],
+            bytes,
+        )
+            .into_response()
+    };
+    let out = resp.headers_mut();
+    if let Ok(v) = HeaderValue::from_str(&etag) {
+        out.insert(header::ETAG, v);
+    }
+    if let Some(v) = last_modified.and_then(|v| HeaderValue::from_str(&v).ok()) {
+        out.insert(header::LAST_MODIFIED, v);
+    }
+    // 允许缓存，但每次都要带条件头重新验证
+    out.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    resp
+}
+
+/// 按 `If-None-Match`、`If-Modified-Since` 判断客户端缓存是否仍然有效
+pub fn not_modified(headers: &HeaderMap, etag: &str, last_modified: Option<&str>) -> bool {
+    match headers.get(header::IF_NONE_MATCH) {
+        // 有 If-None-Match 时忽略 If-Modified-Since
+        Some(v) => v.to_str().map(|v| etag_matches(v, etag)).unwrap_or(false),
+        None => match (headers.get(header::IF_MODIFIED_SINCE), last_modified) {
+            (Some(since), Some(modified)) => since
+                .to_str()
+                .ok()
+                .and_then(|v| httpdate::parse_http_date(v).ok())
+                .zip(httpdate::parse_http_date(modified).ok())
+                .map(|(since, modified)| modified <= since)
+                .unwrap_or(false),
+            _ => false,
+        },
+    }
+}
+
+/// `Range` 请求的结果
+#[derive(Debug, PartialEq, Eq)]
+pub enum ByteRange {
+    /// 没有 Range、`If-Range` 不匹配或者是多段范围，返回完整内容
+    Full,
+    /// 闭区间 `[start, end]`
+    Partial(u64, u64),
+    /// 起点超出文件大小，返回 416
+    Unsatisfiable,
+}
+
+/// 解析单段的 `bytes=start-end`、`bytes=start-`、`bytes=-suffix`；
+/// `If-Range` 与当前 `etag`/`last_modified` 不一致时说明文件已变，返回完整内容
+pub fn byte_range(
+    headers: &HeaderMap,
+    size: u64,
+    etag: &str,
+    last_modified: Option<&str>,
+) -> ByteRange {
+    let Some(raw) = headers.get(header::RANGE).and_then(|v| v.to_str().ok()) else {
+        return ByteRange::Full;
+    };
+    if let Some(if_range) = headers.get(header::IF_RANGE).and_then(|v| v.to_str().ok()) {
+        let if_range = if_range.trim();
+        let fresh = if if_range.starts_with('"') {
+            if_range == etag
+        } else {
+            last_modified == Some(if_range)
+        };
+        if !fresh {
+            return ByteRange::Full;
+        }
+    }
+    let Some(spec) = raw.trim().strip_prefix("bytes=") else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+    let (start, end) = (start.trim(), end.trim());
+    if start.is_empty() {
+        return match end.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if size == 0 => ByteRange::Unsatisfiable,
+            Ok(n) => ByteRange::Partial(size.saturating_sub(n), size - 1),
+            Err(_) => ByteRange::Full,
+        };
+    }
+    let Ok(start) = start.parse::<u64>() else {
+        return ByteRange::Full;
+    };
+    let end = match end {
+        "" => u64::MAX,
+        v => match v.parse::<u64>() {
+            Ok(v) if v >= start => v,
+            _ => return ByteRange::Full,
+        },
+    };
+    if start >= size {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial(start, end.min(size - 1))
+}
+
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|v| v == "*" || v.strip_prefix("W/").unwrap_or(v) == etag)
+}
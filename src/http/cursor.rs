@@ -0,0 +1,71 @@
+use crate::util::date_util;
+use axum::http::{HeaderName, HeaderValue};
+use axum::response::Response;
+
+/// 下一页的 `after`，没有更多数据时不返回
+pub const NEXT_CURSOR_HEADER: HeaderName = HeaderName::from_static("x-next-cursor");
+
+/// 列表的排序键，日记的 id 和日期都唯一，所以游标可以是其中任意一个
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Id,
+    Date,
+}
+
+/// 分页游标 `after=<date|id>`：从这条日记之后开始取，翻页期间插入或删除其他日记不会重复或跳过
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cursor {
+    Id(i64),
+    Date(String),
+}
+
+impl Cursor {
+    pub fn parse(raw: &str) -> Option<Cursor> {
+        let raw = raw.trim();
+        if let Ok(id) = raw.parse::<i64>() {
+            return Some(Cursor::Id(id));
+        }
+        (raw.len() == 10 && date_util::parse_date(raw).is_some())
+            .then(|| Cursor::Date(raw.to_string()))
+    }
+
+    /// 列表最后一条对应的游标
+    pub fn of(key: SortKey, id: i64, date: &str) -> Cursor {
+        match key {
+            SortKey::Id => Cursor::Id(id),
+            SortKey::Date => Cursor::Date(date.to_string()),
+        }
+    }
+
+    /// 按 `key` 排序时“在游标之后”的 sql 条件和参数，`desc` 为倒序；
+    /// 游标和排序键不是同一种时按游标查出对应的值，游标指向的日记已删除时结果为空
+    pub fn condition(&self, key: SortKey, desc: bool) -> (String, String) {
+        let op = if desc { "<" } else { ">" };
+        match (key, self) {
+            (SortKey::Id, Cursor::Id(id)) => (format!("id {} ?", op), id.to_string()),
+            (SortKey::Date, Cursor::Date(date)) => (format!("date {} ?", op), date.clone()),
+            (SortKey::Id, Cursor::Date(date)) => (
+                format!("id {} (select id from journal where date = ?)", op),
+                date.clone(),
+            ),
+            (SortKey::Date, Cursor::Id(id)) => (
+                format!("date {} (select date from journal where id = ?)", op),
+                id.to_string(),
+            ),
+        }
+    }
+
+    pub fn token(&self) -> String {
+        match self {
+            Cursor::Id(id) => id.to_string(),
+            Cursor::Date(date) => date.clone(),
+        }
+    }
+}
+
+/// 取满一页时在响应头里带上下一页的游标
+pub fn set_next(resp: &mut Response, next: Option<Cursor>) {
+    if let Some(v) = next.and_then(|v| HeaderValue::from_str(&v.token()).ok()) {
+        resp.headers_mut().insert(NEXT_CURSOR_HEADER, v);
+    }
+}
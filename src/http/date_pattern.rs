@@ -0,0 +1,651 @@
+use crate::http::settings::DatePlaceholders;
+use crate::util::date_util;
+use regex::Regex;
+
+/// 从路径中解析出的日期片段，`{ww}` `{ddd}` `{dddd}` 只能在日期确定后校验
+#[derive(Default)]
+struct Captured {
+    /// 规则含 `{ww}` 时 `{yyyy}` `{yy}` 表示 ISO 周所属年份，记在 `week_year`
+    week_based: bool,
+    yyyy: Option<String>,
+    yy: Option<String>,
+    mm: Option<String>,
+    dd: Option<String>,
+    week_year: Option<String>,
+    week: Option<String>,
+    checks: Vec<(String, String)>,
+    fields: PathFields,
+}
+
+/// 路径中日期之外的占位符，导入时记入日记 metadata，同步时写回文件名
+pub const TITLE_PLACEHOLDER: &str = "{title}";
+pub const SLUG_PLACEHOLDER: &str = "{slug}";
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PathFields {
+    pub title: Option<String>,
+    pub slug: Option<String>,
+}
+
+/// 导入路径匹配出的日期和其他占位符
+#[derive(Debug)]
+pub struct PathMatch {
+    pub date: String,
+    pub fields: PathFields,
+}
+
+/// 导入规则至少要能确定年月日，并且 `{}` `[]` 成对出现
+pub fn validate_import_pattern(
+    pattern: &str,
+    placeholders: &DatePlaceholders,
+) -> Result<(), String> {
+    let has_year = pattern.contains(&placeholders.yyyy) || pattern.contains(&placeholders.yy);
+    let has_month = pattern.contains(&placeholders.mm) || pattern.contains(&placeholders.m);
+    let has_day = pattern.contains(&placeholders.dd) || pattern.contains(&placeholders.d);
+    let has_ymd = has_year && has_month && has_day;
+    let has_date = pattern.contains(&placeholders.date);
+    if !has_ymd && !has_date {
+        return Err(format!(
+            "invalid import pattern '{}' , required placeholders: {}|{}+{}|{}+{}|{} or {}",
+            pattern,
+            placeholders.yyyy,
+            placeholders.yy,
+            placeholders.mm,
+            placeholders.m,
+            placeholders.dd,
+            placeholders.d,
+            placeholders.date
+        ));
+    }
+    compile_import_pattern(pattern, placeholders).map(|_| ())
+}
+
+/// 模板中是否含有随日期变化的占位符
+pub fn contains_date_placeholder(template: &str, placeholders: &DatePlaceholders) -> bool {
+    [
+        placeholders.yyyy.as_str(),
+        placeholders.yy.as_str(),
+        placeholders.mm.as_str(),
+        placeholders.m.as_str(),
+        placeholders.dd.as_str(),
+        placeholders.d.as_str(),
+        placeholders.date.as_str(),
+        placeholders.ww.as_str(),
+        placeholders.ddd.as_str(),
+        placeholders.dddd.as_str(),
+    ]
+    .iter()
+    .any(|k| template.contains(k))
+}
+
+/// 用 yyyy-MM-dd 日期替换模板中的日期占位符
+///
+/// 模板含 `{ww}` 时 `{yyyy}` `{yy}` 取 ISO 周所属年份，例如 2024-12-30 属于 2025 年第 1 周，
+/// 这样 `{yyyy}/W{ww}/` 下不会出现同一周被拆到两个年份目录
+pub fn render_date_template(
+    template: &str,
+    date: &str,
+    placeholders: &DatePlaceholders,
+) -> Result<String, String> {
+    let parts: Vec<&str> = date.split('-').collect();
+    if parts.len() != 3
+        || parts[0].len() != 4
+        || parts[1].len() != 2
+        || parts[2].len() != 2
+        || !parts.iter().all(|v| v.chars().all(|c| c.is_ascii_digit()))
+    {
+        return Err(format!("invalid journal date: {}", date));
+    }
+    let (yyyy, mm, dd) = (parts[0], parts[1], parts[2]);
+    let m = mm
+        .parse::<u32>()
+        .map_err(|_| format!("invalid month: {}", mm))?;
+    let d = dd
+        .parse::<u32>()
+        .map_err(|_| format!("invalid day: {}", dd))?;
+    let days =
+        date_util::parse_date(date).ok_or_else(|| format!("invalid journal date: {}", date))?;
+    let (week_year, week) = date_util::iso_week(days);
+    let yyyy = if template.contains(&placeholders.ww) {
+        format!("{:04}", week_year)
+    } else {
+        yyyy.to_string()
+    };
+    let weekday = date_util::weekday_from_days(days);
+    let locale = placeholders.locale.as_str();
+    Ok(template
+        .replace(&placeholders.yyyy, &yyyy)
+        .replace(&placeholders.yy, &yyyy[2..])
+        .replace(&placeholders.mm, mm)
+        .replace(&placeholders.m, &m.to_string())
+        .replace(
+            &placeholders.dddd,
+            date_util::weekday_name(weekday, locale, true),
+        )
+        .replace(
+            &placeholders.ddd,
+            date_util::weekday_name(weekday, locale, false),
+        )
+        .replace(&placeholders.dd, dd)
+        .replace(&placeholders.d, &d.to_string())
+        .replace(&placeholders.date, date)
+        .replace(&placeholders.ww, &format!("{:02}", week)))
+}
+
+/// 渲染同步输出路径：先替换 `{title}` `{slug}`，再替换日期占位符
+///
+/// `[...]` 中的 `{title}` `{slug}` 没有值时整段省略，例如 `{date}[-{slug}].md`；
+/// 没有 slug 时由标题生成
+pub fn render_path_template(
+    template: &str,
+    date: &str,
+    fields: &PathFields,
+    placeholders: &DatePlaceholders,
+) -> Result<String, String> {
+    let title = fields
+        .title
+        .as_deref()
+        .map(|v| v.trim().replace(['/', '\\'], "-"))
+        .unwrap_or_default();
+    let slug = fields
+        .slug
+        .as_deref()
+        .map(slugify)
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| slugify(&title));
+    let fill = |segment: &str| {
+        segment
+            .replace(TITLE_PLACEHOLDER, &title)
+            .replace(SLUG_PLACEHOLDER, &slug)
+    };
+    let has_value = |segment: &str| {
+        !(segment.contains(TITLE_PLACEHOLDER) && title.is_empty()
+            || segment.contains(SLUG_PLACEHOLDER) && slug.is_empty())
+    };
+
+    // 每层方括号一个缓冲区，闭合时决定整段是否保留
+    let mut stack = vec![String::new()];
+    for c in template.chars() {
+        match c {
+            '[' => stack.push(String::new()),
+            ']' if stack.len() > 1 => {
+                let segment = stack.pop().unwrap_or_default();
+                if has_value(&segment)
+                    && let Some(v) = stack.last_mut()
+                {
+                    v.push_str(&segment);
+                }
+            }
+            _ => {
+                if let Some(v) = stack.last_mut() {
+                    v.push(c);
+                }
+            }
+        }
+    }
+    if stack.len() != 1 {
+        return Err(format!("unclosed '[' in template '{}'", template));
+    }
+    let path = fill(&stack.concat());
+    render_date_template(&path, date, placeholders)
+}
+
+/// 路径模板中的 `{...}` 都必须是日期占位符或 `{title}` `{slug}`，大括号成对出现
+pub fn check_path_placeholders(
+    template: &str,
+    placeholders: &DatePlaceholders,
+) -> Result<(), String> {
+    let known = [
+        placeholders.yyyy.as_str(),
+        placeholders.yy.as_str(),
+        placeholders.mm.as_str(),
+        placeholders.m.as_str(),
+        placeholders.dd.as_str(),
+        placeholders.d.as_str(),
+        placeholders.date.as_str(),
+        placeholders.ww.as_str(),
+        placeholders.ddd.as_str(),
+        placeholders.dddd.as_str(),
+        TITLE_PLACEHOLDER,
+        SLUG_PLACEHOLDER,
+    ];
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        if rest[start..].starts_with('}') {
+            return Err(format!("unmatched '}}' at '{}'", &rest[start..]));
+        }
+        let end = rest[start..]
+            .find('}')
+            .map(|v| start + v + 1)
+            .ok_or_else(|| format!("unclosed '{{' at '{}'", &rest[start..]))?;
+        let token = &rest[start..end];
+        if !known.contains(&token) {
+            return Err(format!(
+                "unknown placeholder {}, supported: {}",
+                token,
+                known.join(" ")
+            ));
+        }
+        rest = &rest[end..];
+    }
+    Ok(())
+}
+
+/// 文件名用的 slug：小写，字母数字（含中文等）之外的字符合并为 `-`
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// 编译后的导入规则
+///
+/// `*` 匹配同一层目录名中的任意字符，`[...]` 中的内容可有可无，例如 `[daily/]{yyyy}/{MM}/{dd}-*.md`；
+/// 规则从路径末尾开始对齐，前面多出的目录不影响匹配
+pub struct ImportPattern {
+    source: String,
+    regex: Regex,
+    /// 按捕获组顺序排列的占位符名
+    keys: Vec<String>,
+    week_based: bool,
+}
+
+pub fn compile_import_patterns(
+    patterns: &[String],
+    placeholders: &DatePlaceholders,
+) -> Result<Vec<ImportPattern>, String> {
+    patterns
+        .iter()
+        .map(|p| compile_import_pattern(p, placeholders))
+        .collect()
+}
+
+fn compile_import_pattern(
+    pattern: &str,
+    placeholders: &DatePlaceholders,
+) -> Result<ImportPattern, String> {
+    let invalid = |reason: &str| format!("invalid import pattern '{}': {}", pattern, reason);
+    let mut expr = String::from("(?:^|/)");
+    let mut keys = Vec::new();
+    let mut depth = 0usize;
+    let mut rest = pattern;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match c {
+            '{' => {
+                let end = rest.find('}').ok_or_else(|| invalid("unclosed '{'"))?;
+                let key = &rest[..end];
+                rest = &rest[end + 1..];
+                let group = placeholder_regex(key, placeholders)
+                    .ok_or_else(|| invalid(&format!("unsupported placeholder {{{}}}", key)))?;
+                expr.push('(');
+                expr.push_str(group);
+                expr.push(')');
+                keys.push(key.to_string());
+            }
+            '*' => expr.push_str("[^/]*"),
+            '[' => {
+                depth += 1;
+                expr.push_str("(?:");
+            }
+            ']' => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| invalid("unmatched ']'"))?;
+                expr.push_str(")?");
+            }
+            _ => expr.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    if depth != 0 {
+        return Err(invalid("unclosed '['"));
+    }
+    expr.push('$');
+    Ok(ImportPattern {
+        source: pattern.to_string(),
+        regex: Regex::new(&expr).map_err(|e| invalid(&e.to_string()))?,
+        keys,
+        week_based: pattern.contains(&placeholders.ww),
+    })
+}
+
+/// 各占位符可以匹配的内容，数字类限定位数，相邻的 `{MM}{dd}` 也能切开
+fn placeholder_regex(key: &str, placeholders: &DatePlaceholders) -> Option<&'static str> {
+    let is = |token: &str| placeholder_key(token).is_ok_and(|v| v == key);
+    if is(&placeholders.yyyy) {
+        Some(r"\d{4}")
+    } else if is(&placeholders.yy) {
+        Some(r"\d{2}")
+    } else if is(&placeholders.mm)
+        || is(&placeholders.m)
+        || is(&placeholders.dd)
+        || is(&placeholders.d)
+        || is(&placeholders.ww)
+    {
+        Some(r"\d{1,2}")
+    } else if is(&placeholders.date) {
+        Some(r"\d{4}[-_.]?\d{1,2}[-_.]?\d{1,2}")
+    } else if is(&placeholders.ddd)
+        || is(&placeholders.dddd)
+        || is(TITLE_PLACEHOLDER)
+        || is(SLUG_PLACEHOLDER)
+    {
+        Some(r"[^/]+?")
+    } else {
+        None
+    }
+}
+
+/// 依次尝试导入规则，返回第一个匹配出的日期和标题、slug
+pub fn match_import_path(
+    path: &str,
+    patterns: &[ImportPattern],
+    placeholders: &DatePlaceholders,
+) -> Result<PathMatch, String> {
+    let mut reasons = Vec::new();
+    for pattern in patterns {
+        match match_path_with_pattern(path, pattern, placeholders) {
+            Ok(date) => return Ok(date),
+            Err(reason) => reasons.push(format!("[{}] {}", pattern.source, reason)),
+        }
+    }
+    Err(format!("path not match patterns: {}", reasons.join(" | ")))
+}
+
+fn match_path_with_pattern(
+    path: &str,
+    pattern: &ImportPattern,
+    placeholders: &DatePlaceholders,
+) -> Result<PathMatch, String> {
+    let caps = pattern
+        .regex
+        .captures(path)
+        .ok_or_else(|| "path does not match".to_string())?;
+
+    let mut captured = Captured {
+        week_based: pattern.week_based,
+        ..Default::default()
+    };
+    for (idx, key) in pattern.keys.iter().enumerate() {
+        // 没有出现的可选段
+        let Some(val) = caps.get(idx + 1) else {
+            continue;
+        };
+        assign_placeholder(key, val.as_str(), placeholders, &mut captured)
+            .map_err(|e| format!("placeholder {{{}}} parse failed: {}", key, e))?;
+    }
+    let mm = captured
+        .mm
+        .ok_or_else(|| "missing month from path".to_string())?;
+    let dd = captured
+        .dd
+        .ok_or_else(|| "missing day from path".to_string())?;
+    let yyyy = match (captured.yyyy, captured.yy) {
+        (Some(yyyy), Some(yy)) if !yyyy.ends_with(&yy) => {
+            return Err("yy conflicts with yyyy".to_string());
+        }
+        (Some(yyyy), _) => yyyy,
+        // 只有两位年份时按 2000 年以后处理
+        (None, Some(yy)) => format!("20{}", yy),
+        (None, None) => match (&captured.week_year, &captured.week) {
+            (Some(week_year), Some(week)) => year_of_week(week_year, week, &mm, &dd)
+                .ok_or_else(|| format!("{}-{} is not in week {} of {}", mm, dd, week, week_year))?,
+            _ => return Err("missing yyyy from path".to_string()),
+        },
+    };
+
+    if !valid_date_parts(&yyyy, &mm, &dd) {
+        return Err(format!("invalid date parts: {}-{}-{}", yyyy, mm, dd));
+    }
+    let date = format!("{}-{}-{}", yyyy, mm, dd);
+
+    if captured.week_based {
+        let (week_year, week) = date_util::parse_date(&date)
+            .map(date_util::iso_week)
+            .ok_or_else(|| format!("invalid date: {}", date))?;
+        let week_year = format!("{:04}", week_year);
+        let week = format!("{:02}", week);
+        if captured.week_year.as_ref().is_some_and(|v| *v != week_year)
+            || captured.week.as_ref().is_some_and(|v| *v != week)
+        {
+            return Err(format!(
+                "{} belongs to week {} of {}, path says week {} of {}",
+                date,
+                week,
+                week_year,
+                captured.week.as_deref().unwrap_or("?"),
+                captured.week_year.as_deref().unwrap_or("?")
+            ));
+        }
+    }
+
+    for (token, value) in &captured.checks {
+        let expected = render_date_template(token, &date, placeholders)?;
+        if !expected.eq_ignore_ascii_case(value) {
+            return Err(format!(
+                "placeholder {} expects '{}' for {}, got '{}'",
+                token, expected, date, value
+            ));
+        }
+    }
+    Ok(PathMatch {
+        date,
+        fields: captured.fields,
+    })
+}
+
+fn assign_placeholder(
+    key: &str,
+    val: &str,
+    placeholders: &DatePlaceholders,
+    captured: &mut Captured,
+) -> Result<(), String> {
+    if key == placeholder_key(TITLE_PLACEHOLDER)? {
+        return merge_text(&mut captured.fields.title, val);
+    }
+    if key == placeholder_key(SLUG_PLACEHOLDER)? {
+        return merge_text(&mut captured.fields.slug, val);
+    }
+    // 星期名称在日期确定后统一比对
+    for token in [&placeholders.ddd, &placeholders.dddd] {
+        if key == placeholder_key(token)? {
+            captured.checks.push((token.clone(), val.to_string()));
+            return Ok(());
+        }
+    }
+
+    let date_key = placeholder_key(&placeholders.date)?;
+    // `{date}` 允许 yyyy-MM-dd 等带分隔符的写法，由 parse_date_value 校验
+    if key != date_key && val.chars().any(|c| !c.is_ascii_digit()) {
+        return Err(format!("value '{}' contains non-digit", val));
+    }
+
+    let yyyy_key = placeholder_key(&placeholders.yyyy)?;
+    let yy_key = placeholder_key(&placeholders.yy)?;
+    let mm_key = placeholder_key(&placeholders.mm)?;
+    let m_key = placeholder_key(&placeholders.m)?;
+    let dd_key = placeholder_key(&placeholders.dd)?;
+    let d_key = placeholder_key(&placeholders.d)?;
+    let ww_key = placeholder_key(&placeholders.ww)?;
+
+    match key {
+        _ if key == yyyy_key => {
+            if val.len() != 4 {
+                return Err("yyyy must be 4 digits".to_string());
+            }
+            let slot = if captured.week_based {
+                &mut captured.week_year
+            } else {
+                &mut captured.yyyy
+            };
+            if merge_or_check(slot, val.to_string()) {
+                Ok(())
+            } else {
+                Err("year conflict with another placeholder".to_string())
+            }
+        }
+        _ if key == yy_key => {
+            if val.len() != 2 {
+                return Err("yy must be 2 digits".to_string());
+            }
+            let merged = if captured.week_based {
+                merge_or_check(&mut captured.week_year, format!("20{}", val))
+            } else {
+                merge_or_check(&mut captured.yy, val.to_string())
+            };
+            if merged {
+                Ok(())
+            } else {
+                Err("year conflict with another placeholder".to_string())
+            }
+        }
+        _ if key == mm_key || key == m_key => {
+            let Some(m) = normalize_month_or_day(val, 1, 12) else {
+                return Err("month out of range (1..12)".to_string());
+            };
+            if merge_or_check(&mut captured.mm, m) {
+                Ok(())
+            } else {
+                Err("month conflict with another placeholder".to_string())
+            }
+        }
+        _ if key == dd_key || key == d_key => {
+            let Some(d) = normalize_month_or_day(val, 1, 31) else {
+                return Err("day out of range (1..31)".to_string());
+            };
+            if merge_or_check(&mut captured.dd, d) {
+                Ok(())
+            } else {
+                Err("day conflict with another placeholder".to_string())
+            }
+        }
+        _ if key == date_key => {
+            let Some((py, pm, pd)) = parse_date_value(val) else {
+                return Err("unsupported date format".to_string());
+            };
+            if !merge_or_check(&mut captured.yyyy, py)
+                || !merge_or_check(&mut captured.mm, pm)
+                || !merge_or_check(&mut captured.dd, pd)
+            {
+                return Err("date conflicts with yyyy/MM/dd placeholders".to_string());
+            }
+            Ok(())
+        }
+        _ if key == ww_key => {
+            let Some(w) = normalize_month_or_day(val, 1, 53) else {
+                return Err("week out of range (1..53)".to_string());
+            };
+            if merge_or_check(&mut captured.week, w) {
+                Ok(())
+            } else {
+                Err("week conflict with another placeholder".to_string())
+            }
+        }
+        _ => Err(format!("unsupported placeholder: {}", key)),
+    }
+}
+
+/// 周所属年份与日历年份最多差一年，取让 `MM-dd` 落在该周的那一年
+fn year_of_week(week_year: &str, week: &str, mm: &str, dd: &str) -> Option<String> {
+    let week_year = week_year.parse::<i64>().ok()?;
+    let week = week.parse::<i64>().ok()?;
+    (week_year - 1..=week_year + 1)
+        .map(|y| format!("{:04}", y))
+        .find(|y| {
+            date_util::parse_date(&format!("{}-{}-{}", y, mm, dd))
+                .map(date_util::iso_week)
+                .is_some_and(|v| v == (week_year, week))
+        })
+}
+
+fn placeholder_key(token: &str) -> Result<&str, String> {
+    if !(token.starts_with('{') && token.ends_with('}') && token.len() >= 3) {
+        return Err(format!("invalid placeholder token '{}'", token));
+    }
+    Ok(&token[1..token.len() - 1])
+}
+
+fn merge_or_check(slot: &mut Option<String>, value: String) -> bool {
+    if let Some(existing) = slot.as_ref() {
+        existing == &value
+    } else {
+        *slot = Some(value);
+        true
+    }
+}
+
+fn merge_text(slot: &mut Option<String>, value: &str) -> Result<(), String> {
+    let value = value.trim();
+    if value.is_empty() || merge_or_check(slot, value.to_string()) {
+        Ok(())
+    } else {
+        Err(format!("conflicting value '{}'", value))
+    }
+}
+
+fn parse_date_value(v: &str) -> Option<(String, String, String)> {
+    let s = v.trim();
+    if s.len() == 8 && s.chars().all(|c| c.is_ascii_digit()) {
+        let y = &s[0..4];
+        let m = &s[4..6];
+        let d = &s[6..8];
+        if valid_date_parts(y, m, d) {
+            return Some((y.to_string(), m.to_string(), d.to_string()));
+        }
+        return None;
+    }
+
+    for sep in ['-', '_', '.'] {
+        let parts: Vec<&str> = s.split(sep).collect();
+        if parts.len() != 3 {
+            continue;
+        }
+        let y = parts[0];
+        let m = parts[1];
+        let d = parts[2];
+        if y.len() != 4 || !y.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        let (Some(mm), Some(dd)) = (
+            normalize_month_or_day(m, 1, 12),
+            normalize_month_or_day(d, 1, 31),
+        ) else {
+            continue;
+        };
+        if valid_date_parts(y, &mm, &dd) {
+            return Some((y.to_string(), mm, dd));
+        }
+    }
+
+    None
+}
+
+fn normalize_month_or_day(v: &str, min: u32, max: u32) -> Option<String> {
+    if v.is_empty() || v.len() > 2 || v.chars().any(|c| !c.is_ascii_digit()) {
+        return None;
+    }
+    let n = v.parse::<u32>().ok()?;
+    if n < min || n > max {
+        return None;
+    }
+    Some(format!("{:02}", n))
+}
+
+fn valid_date_parts(yyyy: &str, mm: &str, dd: &str) -> bool {
+    let year = yyyy.parse::<i32>().ok();
+    let month = mm.parse::<u32>().ok();
+    let day = dd.parse::<u32>().ok();
+
+    if year.is_none() || month.is_none() || day.is_none() {
+        return false;
+    }
+    let month = month.unwrap();
+    let day = day.unwrap();
+    (1..=12).contains(&month) && (1..=31).contains(&day)
+}
@@ -0,0 +1,280 @@
+use crate::app_state::AppState;
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::util::{date_util, file_util};
+use axum::body::Body;
+use axum::extract::{Multipart, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use sqlx::pool::PoolConnection;
+use sqlx::{Connection, Sqlite};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+use tracing::{info, warn};
+
+/// 恢复时必须有的表和列，其余表有就恢复，没有就保留当前数据
+const REQUIRED_JOURNAL_COLUMNS: &[&str] = &["id", "content", "date", "create_time", "update_time"];
+
+/// 不从备份恢复的表：迁移版本跟着当前程序走，搜索索引恢复后按日记重建
+const LOCAL_TABLES: &[&str] = &["schema_version", "search_dirty"];
+
+fn is_local_table(name: &str) -> bool {
+    LOCAL_TABLES.contains(&name) || name.starts_with("journal_fts")
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreResp {
+    /// 用备份内容替换的表
+    pub restored: Vec<String>,
+    /// 备份中没有、保留当前数据的表
+    pub skipped: Vec<String>,
+    /// 恢复前当前数据库的快照文件名，位于 `{base_path}/backup/`
+    pub previous: String,
+}
+
+/// 用 `vacuum into` 生成一致的数据库快照并下载，不需要停服
+pub async fn download_db_backup(State(state): State<AppState>) -> Response {
+    let path = match snapshot(&state, &state.config.get_tmp_path(), "snapshot").await {
+        Ok(v) => v,
+        Err(msg) => {
+            warn!("数据库快照失败: {}", msg);
+            return ApiResponse::<()>::err(ApiCode::DbQueryFailed, "db snapshot failed")
+                .into_response();
+        }
+    };
+    let file = match tokio::fs::File::open(&path).await {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("打开数据库快照失败: {}", e);
+            let _ = tokio::fs::remove_file(&path).await;
+            return ApiResponse::<()>::err(ApiCode::FileMissing, "read file failed")
+                .into_response();
+        }
+    };
+    let size = file.metadata().await.map(|v| v.len()).unwrap_or(0);
+    // 已经打开的文件删除后仍可读完，下载中断也不会留下临时文件
+    let _ = tokio::fs::remove_file(&path).await;
+    info!("下载数据库快照 size={}", size);
+    let disposition = format!(
+        "attachment; filename=\"daylog-{}.sqlite\"",
+        state.config.today()
+    );
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/vnd.sqlite3".to_string()),
+            (header::CONTENT_LENGTH, size.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response()
+}
+
+/// 上传 `GET /admin/backup` 下载的数据库，检查完整性和表结构后在一个事务中替换全部数据；
+/// 替换前先把当前数据库快照到 `{base_path}/backup/`
+pub async fn restore_db_backup(
+    State(state): State<AppState>,
+    mut multipart: Multipart,
+) -> ApiResult<RestoreResp> {
+    let tmp_dir = state.config.get_tmp_path();
+    file_util::ensure_path(&tmp_dir).await.map_err(|_| {
+        ApiResponse::<RestoreResp>::err(ApiCode::FileWriteFailed, "save file failed")
+    })?;
+    let upload = PathBuf::from(&tmp_dir).join(format!("restore_{}.sqlite", date_util::now_secs()));
+    let received = receive_upload(&mut multipart, &upload).await;
+    let result = match received {
+        Ok(true) => restore(&state, &upload).await,
+        Ok(false) => Err((ApiCode::FileMissing, "database file required".to_string())),
+        Err(e) => Err(e),
+    };
+    let _ = tokio::fs::remove_file(&upload).await;
+    let resp = result.map_err(|(code, msg)| {
+        warn!("恢复数据库失败: {}", msg);
+        ApiResponse::<RestoreResp>::err(code, &msg)
+    })?;
+    state.render_cache.clear();
+    info!(
+        "恢复数据库完成 restored={:?}, skipped={:?}",
+        resp.restored, resp.skipped
+    );
+    Ok(ApiResponse::ok(resp))
+}
+
+async fn receive_upload(multipart: &mut Multipart, path: &Path) -> Result<bool, (ApiCode, String)> {
+    let bad_request = |_| (ApiCode::BadRequest, "invalid multipart data".to_string());
+    while let Some(mut field) = multipart.next_field().await.map_err(bad_request)? {
+        if field.name() != Some("file") && field.file_name().is_none() {
+            continue;
+        }
+        let write_failed = |_| (ApiCode::FileWriteFailed, "save file failed".to_string());
+        let mut file = tokio::fs::File::create(path).await.map_err(write_failed)?;
+        while let Some(chunk) = field.chunk().await.map_err(bad_request)? {
+            file.write_all(&chunk).await.map_err(write_failed)?;
+        }
+        file.flush().await.map_err(write_failed)?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+async fn restore(state: &AppState, upload: &Path) -> Result<RestoreResp, (ApiCode, String)> {
+    let mut conn = state.db.acquire().await.map_err(|e| {
+        warn!("获取数据库连接失败: {}", e);
+        (ApiCode::DbQueryFailed, "db query failed".to_string())
+    })?;
+    sqlx::query("attach database ? as restore_src")
+        .bind(upload.to_string_lossy().to_string())
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| {
+            warn!("挂载备份数据库失败: {}", e);
+            (ApiCode::BadRequest, "not a sqlite database".to_string())
+        })?;
+    let result = async {
+        validate_backup(&mut conn).await?;
+        let previous = snapshot(state, &state.config.get_backup_path(), "before-restore")
+            .await
+            .map_err(|msg| {
+                warn!("恢复前快照失败: {}", msg);
+                (ApiCode::FileWriteFailed, "db snapshot failed".to_string())
+            })?;
+        let (restored, skipped) = copy_tables(&mut conn).await?;
+        Ok(RestoreResp {
+            restored,
+            skipped,
+            previous: previous
+                .file_name()
+                .map(|v| v.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        })
+    }
+    .await;
+    if let Err(e) = sqlx::query("detach database restore_src")
+        .execute(&mut *conn)
+        .await
+    {
+        warn!("detach restore database failed: {}", e);
+    }
+    result
+}
+
+async fn validate_backup(conn: &mut PoolConnection<Sqlite>) -> Result<(), (ApiCode, String)> {
+    let invalid = |e: sqlx::Error| {
+        warn!("读取备份数据库失败: {}", e);
+        (ApiCode::BadRequest, "invalid backup".to_string())
+    };
+    let check = sqlx::query_scalar::<_, String>("pragma restore_src.integrity_check")
+        .fetch_all(&mut **conn)
+        .await
+        .map_err(invalid)?;
+    if check != ["ok"] {
+        return Err((
+            ApiCode::BadRequest,
+            format!("backup integrity check failed: {}", check.join("; ")),
+        ));
+    }
+    let columns = table_columns(conn, "restore_src", "journal")
+        .await
+        .map_err(invalid)?;
+    if let Some(missing) = REQUIRED_JOURNAL_COLUMNS
+        .iter()
+        .find(|v| !columns.iter().any(|c| c == *v))
+    {
+        return Err((
+            ApiCode::BadRequest,
+            format!(
+                "backup is not a daylog database: journal.{} missing",
+                missing
+            ),
+        ));
+    }
+    Ok(())
+}
+
+async fn copy_tables(
+    conn: &mut PoolConnection<Sqlite>,
+) -> Result<(Vec<String>, Vec<String>), (ApiCode, String)> {
+    let db_err = |e: sqlx::Error| {
+        warn!("写入恢复数据失败: {}", e);
+        (ApiCode::DbUpdateFailed, "db update failed".to_string())
+    };
+    let tables = sqlx::query_scalar::<_, String>(
+        "select name from main.sqlite_master where type = 'table' and name not like 'sqlite_%' order by name",
+    )
+    .fetch_all(&mut **conn)
+    .await
+    .map_err(db_err)?;
+    let mut plan = Vec::new();
+    let mut skipped = Vec::new();
+    for table in tables.into_iter().filter(|v| !is_local_table(v)) {
+        let live = table_columns(conn, "main", &table).await.map_err(db_err)?;
+        let backup = table_columns(conn, "restore_src", &table)
+            .await
+            .map_err(db_err)?;
+        // 备份早于某些列或表加入时，缺少的列用默认值，缺少的表保留当前数据
+        let common = live
+            .into_iter()
+            .filter(|v| backup.contains(v))
+            .map(|v| format!("\"{}\"", v.replace('"', "\"\"")))
+            .collect::<Vec<_>>();
+        if common.is_empty() {
+            skipped.push(table);
+        } else {
+            plan.push((table, common.join(", ")));
+        }
+    }
+
+    let mut tx = conn.begin().await.map_err(db_err)?;
+    for (table, columns) in &plan {
+        let name = format!("\"{}\"", table.replace('"', "\"\""));
+        sqlx::query(&format!("delete from main.{}", name))
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        sqlx::query(&format!(
+            "insert into main.{0} ({1}) select {1} from restore_src.{0}",
+            name, columns
+        ))
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+    }
+    // 恢复的日记全部重新写入搜索索引
+    sqlx::query("insert or ignore into search_dirty (journal_id) select id from journal")
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+    tx.commit().await.map_err(db_err)?;
+    Ok((plan.into_iter().map(|(v, _)| v).collect(), skipped))
+}
+
+async fn table_columns(
+    conn: &mut PoolConnection<Sqlite>,
+    schema: &str,
+    table: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>("select name from pragma_table_info(?, ?)")
+        .bind(table)
+        .bind(schema)
+        .fetch_all(&mut **conn)
+        .await
+}
+
+/// `vacuum into` 写出一致的快照，写入期间其他连接照常读写
+async fn snapshot(state: &AppState, dir: &str, label: &str) -> Result<PathBuf, String> {
+    file_util::ensure_path(dir)
+        .await
+        .map_err(|e| e.to_string())?;
+    let path =
+        PathBuf::from(dir).join(format!("daylog-{}-{}.sqlite", label, date_util::now_secs()));
+    let _ = tokio::fs::remove_file(&path).await;
+    sqlx::query("vacuum into ?")
+        .bind(path.to_string_lossy().to_string())
+        .execute(&state.db)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(path)
+}
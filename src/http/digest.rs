@@ -0,0 +1,72 @@
+use crate::app_state::AppState;
+use crate::digest::{self, Digest};
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::util::date_util;
+use axum::extract::{Path, Query, State};
+use serde::Deserialize;
+use tracing::info;
+
+#[derive(Debug, Deserialize)]
+pub struct DigestListQuery {
+    pub kind: Option<String>,
+    pub size: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GenerateWeeklyQuery {
+    /// 这一周中的任意一天，默认上周
+    pub date: Option<String>,
+}
+
+pub async fn list_digests(
+    State(state): State<AppState>,
+    Query(query): Query<DigestListQuery>,
+) -> ApiResult<Vec<Digest>> {
+    let kind = query
+        .kind
+        .unwrap_or_else(|| digest::KIND_WEEKLY.to_string());
+    let size = query.size.unwrap_or(20).clamp(1, 100);
+    let items = sqlx::query_as::<_, Digest>(
+        "select id, kind, period_start, period_end, content, create_time, update_time from digest where kind = ? order by period_start desc limit ?",
+    )
+    .bind(kind)
+    .bind(size)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| ApiResponse::<Vec<Digest>>::err(ApiCode::DbListFailed, "db query failed"))?;
+    Ok(ApiResponse::ok(items))
+}
+
+pub async fn get_digest(State(state): State<AppState>, Path(id): Path<i64>) -> ApiResult<Digest> {
+    let item = sqlx::query_as::<_, Digest>(
+        "select id, kind, period_start, period_end, content, create_time, update_time from digest where id = ?",
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| ApiResponse::<Digest>::err(ApiCode::DbGetFailed, "db query failed"))?;
+    match item {
+        Some(v) => Ok(ApiResponse::ok(v)),
+        None => Err(ApiResponse::err(ApiCode::NotFound, "not found")),
+    }
+}
+
+pub async fn generate_weekly_digest(
+    State(state): State<AppState>,
+    Query(query): Query<GenerateWeeklyQuery>,
+) -> ApiResult<Digest> {
+    let days = match query.date.as_deref() {
+        Some(date) => date_util::parse_date(date)
+            .ok_or_else(|| ApiResponse::<Digest>::err(ApiCode::Validation, "invalid date"))?,
+        None => date_util::local_days(date_util::now_secs(), state.config.utc_offset_minutes) - 7,
+    };
+    let week_start = days - date_util::weekday_from_days(days);
+    info!(
+        "生成周报 week_start={}",
+        date_util::date_from_days(week_start)
+    );
+    let item = digest::generate_weekly(&state, week_start)
+        .await
+        .map_err(|_| ApiResponse::<Digest>::err(ApiCode::DbInsertFailed, "db insert failed"))?;
+    Ok(ApiResponse::ok(item))
+}
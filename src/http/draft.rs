@@ -0,0 +1,190 @@
+use crate::app_state::AppState;
+use crate::http::journal::{self, Journal};
+use crate::http::repo_sync::{self, SyncTrigger};
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::util::date_util;
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SaveDraftReq {
+    pub content: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PromoteQuery {
+    pub auto_sync: Option<bool>,
+}
+
+/// 自动保存的草稿，和正式内容分开存放，不修改 `update_time`、不进入同步
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Draft {
+    pub date: String,
+    pub content: String,
+    /// 开始写草稿时正式日记的 `update_time`，当时还没有日记为 null
+    pub base_update_time: Option<i64>,
+    pub update_time: i64,
+    /// 草稿开始后正式内容又被修改过（其他设备保存、同步拉取等），提升前需要确认
+    pub stale: bool,
+}
+
+/// 保存草稿，内容没变时不写库，前端可以频繁调用
+#[utoipa::path(
+    put,
+    path = "/journal/date/{date}/draft",
+    tag = "journal",
+    params(("date" = String, Path, description = "yyyy-MM-dd")),
+    request_body = SaveDraftReq,
+    responses((status = 200, body = ApiResponse<Draft>))
+)]
+pub async fn save_draft(
+    State(state): State<AppState>,
+    Path(date): Path<String>,
+    Json(req): Json<SaveDraftReq>,
+) -> ApiResult<Draft> {
+    if date_util::parse_date(&date).is_none() {
+        return Err(ApiResponse::<Draft>::err(
+            ApiCode::Validation,
+            "date must be yyyy-MM-dd",
+        ));
+    }
+    // 密文每次加密都不同，内容没变时沿用库里的密文，下面的比较才能生效
+    let mut content = state.cipher.seal(&req.content);
+    if state.cipher.enabled() {
+        let existing =
+            sqlx::query_scalar::<_, String>("select content from journal_draft where date = ?")
+                .bind(&date)
+                .fetch_optional(&state.db)
+                .await
+                .map_err(|_| {
+                    ApiResponse::<Draft>::err(ApiCode::DbQueryFailed, "db query failed")
+                })?;
+        if let Some(existing) = existing
+            && state.cipher.open(existing.clone()) == req.content
+        {
+            content = existing;
+        }
+    }
+    sqlx::query(
+        r#"
+        insert into journal_draft (date, content, base_update_time, update_time)
+        values (?1, ?2, (select update_time from journal where date = ?1), ?3)
+        on conflict (date) do update set content = excluded.content, update_time = excluded.update_time
+        where journal_draft.content <> excluded.content
+        "#,
+    )
+    .bind(&date)
+    .bind(content)
+    .bind(date_util::now_secs())
+    .execute(&state.db)
+    .await
+    .map_err(|_| ApiResponse::<Draft>::err(ApiCode::DbUpdateFailed, "db update failed"))?;
+    let draft = load(&state, &date)
+        .await
+        .map_err(|_| ApiResponse::<Draft>::err(ApiCode::DbQueryFailed, "db query failed"))?
+        .ok_or_else(|| ApiResponse::<Draft>::err(ApiCode::DbQueryFailed, "db query failed"))?;
+    Ok(ApiResponse::ok(draft))
+}
+
+#[utoipa::path(
+    get,
+    path = "/journal/date/{date}/draft",
+    tag = "journal",
+    params(("date" = String, Path, description = "yyyy-MM-dd")),
+    responses((status = 200, description = "没有草稿时 code 为 404", body = ApiResponse<Draft>))
+)]
+pub async fn get_draft(
+    State(state): State<AppState>,
+    Path(date): Path<String>,
+) -> ApiResult<Draft> {
+    let draft = load(&state, &date)
+        .await
+        .map_err(|_| ApiResponse::<Draft>::err(ApiCode::DbQueryFailed, "db query failed"))?
+        .ok_or_else(|| ApiResponse::<Draft>::err(ApiCode::NotFound, "draft not found"))?;
+    Ok(ApiResponse::ok(draft))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/journal/date/{date}/draft",
+    tag = "journal",
+    params(("date" = String, Path, description = "yyyy-MM-dd")),
+    responses((status = 200, description = "`data` 为 null", body = ApiResponse<serde_json::Value>))
+)]
+pub async fn delete_draft(
+    State(state): State<AppState>,
+    Path(date): Path<String>,
+) -> ApiResult<()> {
+    discard(&state, &date)
+        .await
+        .map_err(|_| ApiResponse::<()>::err(ApiCode::DbDeleteFailed, "db delete failed"))?;
+    Ok(ApiResponse::ok(()))
+}
+
+/// 用草稿覆盖当天的正式内容并删除草稿，当天没有日记时新建；
+/// 直接 `PUT /journal/{id}` 不会清掉草稿，其他设备的保存会让草稿变成 `stale`
+#[utoipa::path(
+    post,
+    path = "/journal/date/{date}/draft/promote",
+    tag = "journal",
+    params(("date" = String, Path, description = "yyyy-MM-dd"), PromoteQuery),
+    responses((status = 200, body = ApiResponse<Journal>))
+)]
+pub async fn promote_draft(
+    State(state): State<AppState>,
+    Path(date): Path<String>,
+    Query(query): Query<PromoteQuery>,
+) -> ApiResult<Journal> {
+    let draft = load(&state, &date)
+        .await
+        .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbQueryFailed, "db query failed"))?
+        .ok_or_else(|| ApiResponse::<Journal>::err(ApiCode::NotFound, "draft not found"))?;
+    let journal = journal::replace_date_content(&state, &date, &draft.content)
+        .await
+        .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbUpdateFailed, "db update failed"))?;
+    if let Err(e) = discard(&state, &date).await {
+        warn!("remove promoted draft {} failed: {}", date, e);
+    }
+    info!(
+        "草稿提升为正式内容 date={}, id={}, stale={}",
+        date, journal.id, draft.stale
+    );
+    if query.auto_sync.unwrap_or(false) {
+        repo_sync::queue_sync(&state, SyncTrigger::AutoSync).await;
+    }
+    Ok(ApiResponse::ok(journal))
+}
+
+async fn discard(state: &AppState, date: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("delete from journal_draft where date = ?")
+        .bind(date)
+        .execute(&state.db)
+        .await?;
+    Ok(())
+}
+
+async fn load(state: &AppState, date: &str) -> Result<Option<Draft>, sqlx::Error> {
+    let row = sqlx::query_as::<_, (String, String, Option<i64>, i64, Option<i64>)>(
+        r#"
+        select d.date, d.content, d.base_update_time, d.update_time, j.update_time
+        from journal_draft d left join journal j on j.date = d.date
+        where d.date = ?
+        "#,
+    )
+    .bind(date)
+    .fetch_optional(&state.db)
+    .await?;
+    Ok(row.map(
+        |(date, content, base_update_time, update_time, current)| Draft {
+            stale: current.is_some() && current != base_update_time,
+            date,
+            content: state.cipher.open(content),
+            base_update_time,
+            update_time,
+        },
+    ))
+}
@@ -0,0 +1,166 @@
+use crate::app_state::AppState;
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::util::file_util;
+use axum::extract::{Query, State};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::{HashMap, HashSet};
+use tracing::info;
+
+/// 近似比较时使用的字符 n-gram 长度
+const SHINGLE_SIZE: usize = 3;
+/// 开启近似比较时最多参与比较的篇数，两两比较是平方复杂度
+const MAX_SIMILAR_ENTRIES: usize = 5000;
+
+#[derive(Debug, Deserialize)]
+pub struct DuplicatesQuery {
+    /// 0~1，给出时额外返回相似度不低于该值的日记对
+    pub threshold: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateEntry {
+    pub id: i64,
+    pub date: String,
+    #[serde(skip)]
+    pub content: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    /// `exact` 内容相同（忽略空白差异），`similar` 超过相似度阈值
+    pub kind: &'static str,
+    pub similarity: f64,
+    pub entries: Vec<DuplicateEntry>,
+}
+
+/// 查找不同日期间内容相同或相近的日记，通常是导入规则改动后重复导入留下的
+pub async fn list_duplicates(
+    State(state): State<AppState>,
+    Query(query): Query<DuplicatesQuery>,
+) -> ApiResult<Vec<DuplicateGroup>> {
+    let threshold = match query.threshold {
+        Some(v) if !(0.0..=1.0).contains(&v) => {
+            return Err(ApiResponse::<Vec<DuplicateGroup>>::err(
+                ApiCode::Validation,
+                "threshold must be between 0 and 1",
+            ));
+        }
+        v => v,
+    };
+    info!("查找重复日记 threshold={:?}", threshold);
+
+    let rows = sqlx::query_as::<_, DuplicateEntry>(
+        "select id, date, content from journal where trim(content) <> '' order by date asc, id asc",
+    )
+    .fetch_all(&state.read_db)
+    .await
+    .map_err(|_| ApiResponse::<Vec<DuplicateGroup>>::err(ApiCode::DbListFailed, "db query failed"))?
+    .into_iter()
+    .map(|mut v| {
+        v.content = state.cipher.open(v.content);
+        v
+    })
+    // 密文在 sql 里总是非空，解密后再筛一次
+    .filter(|v| !v.content.trim().is_empty())
+    .collect::<Vec<_>>();
+
+    let groups = state
+        .blocking
+        .run("duplicate report", move || find_duplicates(rows, threshold))
+        .await
+        .map_err(|_| {
+            ApiResponse::<Vec<DuplicateGroup>>::err(ApiCode::BadRequest, "duplicate task failed")
+        })?;
+    Ok(ApiResponse::ok(groups))
+}
+
+fn find_duplicates(rows: Vec<DuplicateEntry>, threshold: Option<f64>) -> Vec<DuplicateGroup> {
+    let normalized = rows
+        .iter()
+        .map(|v| normalize(&v.content))
+        .collect::<Vec<_>>();
+
+    let mut by_hash: HashMap<String, Vec<usize>> = HashMap::new();
+    for (idx, text) in normalized.iter().enumerate() {
+        by_hash
+            .entry(file_util::file_hash(text))
+            .or_default()
+            .push(idx);
+    }
+    let mut exact = by_hash
+        .into_values()
+        .filter(|v| v.len() > 1)
+        .collect::<Vec<_>>();
+    exact.sort();
+
+    let mut groups = Vec::new();
+    // 同一组完全相同的日记只保留第一篇参与近似比较
+    let mut skip: HashSet<usize> = HashSet::new();
+    for members in &exact {
+        skip.extend(members.iter().skip(1));
+        groups.push(DuplicateGroup {
+            kind: "exact",
+            similarity: 1.0,
+            entries: members.iter().map(|i| rows[*i].clone()).collect(),
+        });
+    }
+
+    let Some(threshold) = threshold else {
+        return groups;
+    };
+    let candidates = (0..rows.len())
+        .filter(|i| !skip.contains(i))
+        .take(MAX_SIMILAR_ENTRIES)
+        .collect::<Vec<_>>();
+    let shingles = candidates
+        .iter()
+        .map(|i| shingle_set(&normalized[*i]))
+        .collect::<Vec<_>>();
+    for a in 0..candidates.len() {
+        for b in a + 1..candidates.len() {
+            let (sa, sb) = (&shingles[a], &shingles[b]);
+            if sa.is_empty() || sb.is_empty() {
+                continue;
+            }
+            // jaccard 的上界是 min/max，达不到阈值的不用算交集
+            let (small, large) = if sa.len() <= sb.len() {
+                (sa, sb)
+            } else {
+                (sb, sa)
+            };
+            if (small.len() as f64) < threshold * large.len() as f64 {
+                continue;
+            }
+            let inter = small.iter().filter(|v| large.contains(*v)).count();
+            let union = sa.len() + sb.len() - inter;
+            let similarity = inter as f64 / union as f64;
+            if similarity >= threshold {
+                groups.push(DuplicateGroup {
+                    kind: "similar",
+                    similarity: (similarity * 1000.0).round() / 1000.0,
+                    entries: vec![rows[candidates[a]].clone(), rows[candidates[b]].clone()],
+                });
+            }
+        }
+    }
+    groups
+}
+
+/// 合并连续空白，避免换行和缩进差异影响比较
+fn normalize(content: &str) -> String {
+    content.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn shingle_set(text: &str) -> HashSet<String> {
+    let chars = text.chars().collect::<Vec<_>>();
+    if chars.len() < SHINGLE_SIZE {
+        return HashSet::from([text.to_string()]);
+    }
+    chars
+        .windows(SHINGLE_SIZE)
+        .map(|w| w.iter().collect::<String>())
+        .collect()
+}
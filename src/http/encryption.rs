@@ -0,0 +1,98 @@
+use crate::app_state::AppState;
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::util::crypto;
+use axum::extract::State;
+use serde::Serialize;
+use tracing::info;
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrateResp {
+    pub journals: u64,
+    pub history: u64,
+    pub trash: u64,
+    pub drafts: u64,
+}
+
+/// 把开启 `[encryption]` 之前写入的明文正文加密，已加密的跳过，可以重复执行；
+/// 不修改 `update_time`，同步不会因此认为日记被改过
+pub async fn migrate_encryption(State(state): State<AppState>) -> ApiResult<MigrateResp> {
+    if !state.cipher.enabled() {
+        return Err(ApiResponse::<MigrateResp>::err(
+            ApiCode::BadRequest,
+            "encryption disabled in config",
+        ));
+    }
+    info!("加密已有日记正文");
+    let mut resp = MigrateResp::default();
+    for (table, count) in [
+        ("journal", &mut resp.journals),
+        ("journal_history", &mut resp.history),
+        ("journal_trash", &mut resp.trash),
+        ("journal_draft", &mut resp.drafts),
+    ] {
+        *count = seal_table(&state, table).await.map_err(|_| {
+            ApiResponse::<MigrateResp>::err(ApiCode::DbUpdateFailed, "db update failed")
+        })?;
+    }
+    seal_merge_details(&state).await.map_err(|_| {
+        ApiResponse::<MigrateResp>::err(ApiCode::DbUpdateFailed, "db update failed")
+    })?;
+    info!(
+        "加密完成 journals={}, history={}, trash={}, drafts={}",
+        resp.journals, resp.history, resp.trash, resp.drafts
+    );
+    Ok(ApiResponse::ok(resp))
+}
+
+async fn seal_table(state: &AppState, table: &str) -> Result<u64, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (i64, String)>(&format!(
+        "select rowid, content from {} order by rowid",
+        table
+    ))
+    .fetch_all(&state.db)
+    .await?;
+    let mut tx = state.db.begin().await?;
+    let mut sealed = 0;
+    for (rowid, content) in rows {
+        if crypto::is_sealed(&content) {
+            continue;
+        }
+        sqlx::query(&format!("update {} set content = ? where rowid = ?", table))
+            .bind(state.cipher.seal(&content))
+            .bind(rowid)
+            .execute(&mut *tx)
+            .await?;
+        sealed += 1;
+    }
+    tx.commit().await?;
+    Ok(sealed)
+}
+
+/// 合并记录的 `detail.sourceContent` 是被合并日记的正文，也要加密
+async fn seal_merge_details(state: &AppState) -> Result<(), sqlx::Error> {
+    let rows = sqlx::query_as::<_, (i64, String)>(
+        "select id, detail from journal_history where action = 'merge' and detail is not null",
+    )
+    .fetch_all(&state.db)
+    .await?;
+    let mut tx = state.db.begin().await?;
+    for (id, detail) in rows {
+        let Ok(mut value) = serde_json::from_str::<serde_json::Value>(&detail) else {
+            continue;
+        };
+        let Some(content) = value.get("sourceContent").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        if crypto::is_sealed(content) {
+            continue;
+        }
+        value["sourceContent"] = state.cipher.seal(content).into();
+        sqlx::query("update journal_history set detail = ? where id = ?")
+            .bind(value.to_string())
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
@@ -0,0 +1,34 @@
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::util::i18n;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorEntry {
+    pub code: i32,
+    /// 和 `ApiResponse.error` 相同
+    pub error: &'static str,
+    /// 按请求的 `Accept-Language` 翻译
+    pub description: String,
+}
+
+/// 列出所有 `code` 及其 `error` 标识，客户端可以据此生成错误处理表
+#[utoipa::path(
+    get,
+    path = "/errors",
+    tag = "meta",
+    security(()),
+    responses((status = 200, body = ApiResponse<Vec<ErrorEntry>>))
+)]
+pub async fn list_errors() -> ApiResult<Vec<ErrorEntry>> {
+    let items = ApiCode::ALL
+        .iter()
+        .map(|c| ErrorEntry {
+            code: c.code(),
+            error: c.slug(),
+            description: i18n::text(c.description()),
+        })
+        .collect();
+    Ok(ApiResponse::ok(items))
+}
@@ -0,0 +1,156 @@
+use crate::app_state::AppState;
+use crate::http::journal::Journal;
+use crate::http::repo_sync;
+use crate::util::date_util;
+use async_zip::tokio::write::ZipFileWriter;
+use async_zip::{Compression, ZipDateTimeBuilder, ZipEntryBuilder};
+use axum::body::{Body, Bytes};
+use axum::extract::{Query, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use futures_util::{StreamExt, stream};
+use serde::Deserialize;
+use std::future::Future;
+use tokio::io::DuplexStream;
+use tokio_util::io::ReaderStream;
+use tracing::{info, warn};
+
+/// 管道缓冲区大小，写端写满后等待客户端读取，峰值内存与导出大小无关
+const STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// 数据库行到响应体之间最多缓存的分块数
+const STREAM_CHANNEL_SIZE: usize = 16;
+
+pub type StreamZipWriter = ZipFileWriter<DuplexStream>;
+
+#[derive(Debug, Deserialize)]
+pub struct ExportZipQuery {
+    /// 输出路径模板，例如 `{yyyy}/{MM}/{dd}.md`，不传时与同步输出一致
+    pub template: Option<String>,
+}
+
+/// 在后台任务中生成 zip，边写边作为 chunked body 发送，`build` 结束前需要调用 `close`
+///
+/// 响应头已经发出，中途出错只能截断下载并记录日志
+pub fn stream_zip<F, Fut>(build: F) -> Body
+where
+    F: FnOnce(StreamZipWriter) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), String>> + Send + 'static,
+{
+    let (writer, reader) = tokio::io::duplex(STREAM_BUFFER_SIZE);
+    tokio::spawn(async move {
+        if let Err(e) = build(ZipFileWriter::with_tokio(writer)).await {
+            warn!("stream zip aborted: {}", e);
+        }
+    });
+    Body::from_stream(ReaderStream::new(reader))
+}
+
+/// 写入一个完整的 zip 条目
+pub async fn write_zip_entry(
+    zip: &mut StreamZipWriter,
+    name: &str,
+    compression: Compression,
+    bytes: &[u8],
+) -> Result<(), String> {
+    let secs = date_util::now_secs();
+    let (y, m, d) = date_util::civil_from_days(secs.div_euclid(86_400));
+    let sod = secs.rem_euclid(86_400);
+    let modified = ZipDateTimeBuilder::new()
+        .year(y as i32)
+        .month(m as u32)
+        .day(d as u32)
+        .hour((sod / 3600) as u32)
+        .minute((sod % 3600 / 60) as u32)
+        .second((sod % 60) as u32)
+        .build();
+    let entry = ZipEntryBuilder::new(name.into(), compression).last_modification_date(modified);
+    zip.write_entry_whole(entry, bytes)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 以 json 数组导出全部日记，逐行从数据库读取并分块发送
+pub async fn export_json(State(state): State<AppState>) -> Response {
+    info!("导出全部日记 json");
+    let (tx, rx) = tokio::sync::mpsc::channel::<std::io::Result<Bytes>>(STREAM_CHANNEL_SIZE);
+    tokio::spawn(async move {
+        let mut rows = sqlx::query_as::<_, Journal>(
+            "select id, content, date, create_time, update_time, metadata, word_count, reading_minutes from journal order by date asc, id asc",
+        )
+        .fetch(&state.db);
+        let mut first = true;
+        while let Some(row) = rows.next().await {
+            let chunk = match row {
+                Ok(journal) => {
+                    let journal = journal.decrypted(&state);
+                    let mut chunk = if first { "\n" } else { ",\n" }.to_string();
+                    first = false;
+                    chunk.push_str(&serde_json::to_string(&journal).unwrap_or_default());
+                    Ok(Bytes::from(chunk))
+                }
+                Err(e) => {
+                    warn!("导出日记 json 中断: {}", e);
+                    Err(std::io::Error::other(e))
+                }
+            };
+            let failed = chunk.is_err();
+            if tx.send(chunk).await.is_err() || failed {
+                return;
+            }
+        }
+    });
+    let rows = stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|c| (c, rx)) });
+    let body = stream::once(async { Ok(Bytes::from_static(b"[")) })
+        .chain(rows)
+        .chain(stream::once(async { Ok(Bytes::from_static(b"\n]\n")) }));
+
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/json"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"daylog.json\"",
+            ),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+/// 把全部日记按同步使用的路径模板打包成 zip 下载，不需要配置 git 仓库也能备份
+pub async fn export_zip(
+    State(state): State<AppState>,
+    Query(query): Query<ExportZipQuery>,
+) -> Response {
+    let files = match repo_sync::render_export_files(&state, query.template.as_deref()).await {
+        Ok(v) => v,
+        Err(e) => return e.into_response(),
+    };
+    info!(
+        "导出日记 zip template={:?}, files={}",
+        query.template,
+        files.len()
+    );
+    let body = stream_zip(move |mut zip| async move {
+        for (path, content) in files {
+            write_zip_entry(&mut zip, &path, Compression::Deflate, content.as_bytes()).await?;
+        }
+        zip.close().await.map_err(|e| e.to_string())?;
+        Ok(())
+    });
+    let disposition = format!(
+        "attachment; filename=\"daylog-{}.zip\"",
+        state.config.today()
+    );
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response()
+}
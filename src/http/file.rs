@@ -1,14 +1,28 @@
 use crate::app_state::AppState;
+use crate::error::DayLogError;
+use crate::http::conditional::{self, ByteRange};
+use crate::http::journal::JournalMetadata;
 use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
 use crate::util;
+use crate::util::file_util::StreamHasher;
+use crate::util::thumbnail::{self, ThumbSize};
 use axum::Json;
-use axum::extract::{Multipart, State};
-use axum::http::StatusCode;
+use axum::body::{Body, Bytes};
+use axum::extract::multipart::Field;
+use axum::extract::{Multipart, Path as UrlPath, Query, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use std::path::PathBuf;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::process::Command;
+use tokio_util::io::ReaderStream;
 use tracing::{info, warn};
+use utoipa::{IntoParams, ToSchema};
 
 static FILE_SEQ: AtomicU64 = AtomicU64::new(0);
 
@@ -19,11 +33,45 @@ struct SaveTarget {
     uri_prefix: &'static str,
 }
 
+struct BlobMeta<'a> {
+    original_name: &'a str,
+    mime: &'a str,
+    oid: &'a str,
+    size: u64,
+}
+
 #[derive(Debug, FromRow)]
 struct FileBlobRow {
     uri: String,
 }
 
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalFile {
+    /// `GET /files/by-id/{id}` 使用的记录 id
+    pub id: i64,
+    pub uri: String,
+    /// picture/media/file
+    pub kind: String,
+    pub mime: String,
+    pub size: i64,
+    pub original_name: String,
+    /// `content` 正文引用，`attachment` 显式附件
+    #[sqlx(default)]
+    pub source: String,
+    /// 通过 `/files/` 或 `GET /files/by-id/{id}` 完整读取的次数，不含 304 和 `Range` 请求
+    pub access_count: i64,
+    pub last_access_time: Option<i64>,
+}
+
+/// multipart 中的每个文件分别保存，`data` 是第一个文件的 uri，`msg` 是逗号分隔的全部 uri
+#[utoipa::path(
+    post,
+    path = "/upload",
+    tag = "file",
+    request_body(content_type = "multipart/form-data", description = "一个或多个文件字段"),
+    responses((status = 200, body = ApiResponse<String>))
+)]
 pub async fn upload_file(
     State(state): State<AppState>,
     mut multipart: Multipart,
@@ -53,73 +101,10 @@ pub async fn upload_file(
             .content_type()
             .map(|v| v.to_string())
             .unwrap_or_else(|| "application/octet-stream".to_string());
-        let target = resolve_target(&state, Some(mime.as_str()));
 
-        let bytes = field.bytes().await.map_err(|_| {
-            ApiResponse::<String>::err(ApiCode::BadRequest, "read upload bytes failed")
-        })?;
-
-        let oid = util::file_util::file_hash(&bytes);
-
-        if let Some(uri) = find_existing_uri(&state, &target.kind, &oid)
+        let uri = receive_field(&state, field, &original_name, &mime)
             .await
-            .map_err(|_| {
-                ApiResponse::<String>::err(ApiCode::DbQueryFailed, "query file hash failed")
-            })?
-        {
-            uploaded_uris.push(uri);
-            continue;
-        }
-
-        let file_name = unique_file_name(&original_name);
-        let mut full_path = PathBuf::from(&target.path);
-        full_path.push(&file_name);
-        util::file_util::create_file(&full_path, &bytes)
-            .await
-            .map_err(|_| {
-                ApiResponse::<String>::err(ApiCode::FileWriteFailed, "save file failed")
-            })?;
-
-        let uri = format!("{}/{}", target.uri_prefix, file_name);
-
-        let ts = now_ts();
-        let file_path = full_path.to_string_lossy().to_string();
-        let insert_result = sqlx::query(
-            r#"
-            insert into file_blob (
-                kind, algo, oid, mime, size, original_name, uri, file_path, create_time, update_time
-            ) values (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-            "#,
-        )
-        .bind(&target.kind)
-        .bind("sha256")
-        .bind(&oid)
-        .bind(&mime)
-        .bind(bytes.len() as i64)
-        .bind(&original_name)
-        .bind(&uri)
-        .bind(file_path)
-        .bind(ts)
-        .bind(ts)
-        .execute(&state.db)
-        .await;
-
-        if insert_result.is_err() {
-            if let Some(existing_uri) = find_existing_uri(&state, &target.kind, &oid)
-                .await
-                .map_err(|_| {
-                    ApiResponse::<String>::err(ApiCode::DbQueryFailed, "query file hash failed")
-                })?
-            {
-                uploaded_uris.push(existing_uri);
-                continue;
-            }
-            return Err(ApiResponse::<String>::err(
-                ApiCode::DbInsertFailed,
-                "save file metadata failed",
-            ));
-        }
-
+            .map_err(|(code, msg)| ApiResponse::<String>::err(code, msg))?;
         uploaded_uris.push(uri);
     }
 
@@ -138,11 +123,1011 @@ pub async fn upload_file(
         Json(ApiResponse {
             data: Some(first_uri),
             code: 200,
+            error: None,
             msg,
         }),
     ))
 }
 
+/// 把上传字段分块写入临时文件并同时计算哈希，大文件只经过内存一次
+async fn receive_field(
+    state: &AppState,
+    mut field: Field<'_>,
+    original_name: &str,
+    mime: &str,
+) -> Result<String, (ApiCode, &'static str)> {
+    let tmp_dir = PathBuf::from(state.config.get_tmp_path());
+    util::file_util::ensure_path(&tmp_dir)
+        .await
+        .map_err(|_| (ApiCode::FileWriteFailed, "save file failed"))?;
+    let tmp_path = tmp_dir.join(format!(
+        "upload_{}.part",
+        FILE_SEQ.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let received = async {
+        let mut file = tokio::fs::File::create(&tmp_path)
+            .await
+            .map_err(|_| (ApiCode::FileWriteFailed, "save file failed"))?;
+        let mut hasher = StreamHasher::new();
+        while let Some(chunk) = field.chunk().await.map_err(|e| match e.status() {
+            StatusCode::PAYLOAD_TOO_LARGE => {
+                (ApiCode::PayloadTooLarge, "file exceeds upload_file_limit")
+            }
+            _ => (ApiCode::BadRequest, "read upload bytes failed"),
+        })? {
+            hasher.update(&chunk);
+            file.write_all(&chunk)
+                .await
+                .map_err(|_| (ApiCode::FileWriteFailed, "save file failed"))?;
+        }
+        file.flush()
+            .await
+            .map_err(|_| (ApiCode::FileWriteFailed, "save file failed"))?;
+        Ok(hasher)
+    }
+    .await;
+
+    let result = match received {
+        Ok(hasher) => {
+            let size = hasher.size();
+            let oid = hasher.finish();
+            store_received_file(state, original_name, mime, &tmp_path, &oid, size).await
+        }
+        Err(e) => Err(e),
+    };
+    // 成功时已被移动，失败或去重时在这里清理
+    let _ = tokio::fs::remove_file(&tmp_path).await;
+    result
+}
+
+/// 保存上传文件并记录到 `file_blob`，相同内容的文件会直接复用已有的 uri
+pub async fn store_file(
+    state: &AppState,
+    original_name: &str,
+    mime: &str,
+    bytes: Bytes,
+) -> Result<String, (ApiCode, &'static str)> {
+    let target = resolve_target(state, Some(mime));
+    let hash_input = bytes.clone();
+    let oid = state
+        .blocking
+        .run("file hash", move || util::file_util::file_hash(hash_input))
+        .await
+        .map_err(|_| (ApiCode::FileWriteFailed, "hash file failed"))?;
+
+    if let Some(uri) = find_existing_uri(state, &target.kind, &oid)
+        .await
+        .map_err(|_| (ApiCode::DbQueryFailed, "query file hash failed"))?
+    {
+        return Ok(uri);
+    }
+
+    let file_name = unique_file_name(original_name);
+    let mut full_path = PathBuf::from(&target.path);
+    full_path.push(&file_name);
+    util::file_util::create_file(&full_path, &bytes)
+        .await
+        .map_err(|_| (ApiCode::FileWriteFailed, "save file failed"))?;
+
+    let meta = BlobMeta {
+        original_name,
+        mime,
+        oid: &oid,
+        size: bytes.len() as u64,
+    };
+    record_blob(state, &target, &file_name, &full_path, &meta).await
+}
+
+/// 已经写入临时文件并算好哈希的上传，去重后移动到目标目录
+async fn store_received_file(
+    state: &AppState,
+    original_name: &str,
+    mime: &str,
+    tmp_path: &Path,
+    oid: &str,
+    size: u64,
+) -> Result<String, (ApiCode, &'static str)> {
+    let target = resolve_target(state, Some(mime));
+    if let Some(uri) = find_existing_uri(state, &target.kind, oid)
+        .await
+        .map_err(|_| (ApiCode::DbQueryFailed, "query file hash failed"))?
+    {
+        return Ok(uri);
+    }
+
+    let file_name = unique_file_name(original_name);
+    let mut full_path = PathBuf::from(&target.path);
+    full_path.push(&file_name);
+    if tokio::fs::rename(tmp_path, &full_path).await.is_err() {
+        // 临时目录和目标目录不在同一个文件系统时退化为复制
+        tokio::fs::copy(tmp_path, &full_path)
+            .await
+            .map_err(|_| (ApiCode::FileWriteFailed, "save file failed"))?;
+    }
+
+    let meta = BlobMeta {
+        original_name,
+        mime,
+        oid,
+        size,
+    };
+    record_blob(state, &target, &file_name, &full_path, &meta).await
+}
+
+async fn record_blob(
+    state: &AppState,
+    target: &SaveTarget,
+    file_name: &str,
+    full_path: &Path,
+    meta: &BlobMeta<'_>,
+) -> Result<String, (ApiCode, &'static str)> {
+    let BlobMeta {
+        original_name,
+        mime,
+        oid,
+        size,
+    } = *meta;
+    let uri = format!("{}/{}", target.uri_prefix, file_name);
+    scan_file(state, full_path, original_name).await?;
+
+    let optimized = match target.kind.as_str() {
+        "picture" if mime == "image/gif" => {
+            enforce_media_policy(state, true, file_name, full_path, original_name).await?
+        }
+        "picture" => optimize_picture(state, file_name, full_path, size).await,
+        "media" => enforce_media_policy(state, false, file_name, full_path, original_name).await?,
+        _ => None,
+    };
+    let (oid, size, source_oid, original_path) = match &optimized {
+        Some(v) => (
+            v.oid.as_str(),
+            v.size,
+            Some(oid),
+            v.original_path
+                .as_deref()
+                .map(|p| state.config.to_stored_path(p)),
+        ),
+        None => (oid, size, None, None),
+    };
+
+    let ts = now_ts();
+    let file_path = state.config.to_stored_path(full_path);
+    let insert_result = sqlx::query(
+        r#"
+        insert into file_blob (
+            kind, algo, oid, mime, size, original_name, uri, file_path, source_oid, original_path,
+            create_time, update_time
+        ) values (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&target.kind)
+    .bind("sha256")
+    .bind(oid)
+    .bind(mime)
+    .bind(size as i64)
+    .bind(original_name)
+    .bind(&uri)
+    .bind(file_path)
+    .bind(source_oid)
+    .bind(original_path)
+    .bind(ts)
+    .bind(ts)
+    .execute(&state.db)
+    .await;
+
+    if insert_result.is_err() {
+        // 这次写入的文件（以及压缩、转码前移到 original/ 的原文件）都没有记录引用，先删掉再返回
+        let _ = tokio::fs::remove_file(full_path).await;
+        if let Some(original) = optimized.as_ref().and_then(|v| v.original_path.as_deref()) {
+            let _ = tokio::fs::remove_file(original).await;
+        }
+        if let Some(existing_uri) = find_existing_uri(state, &target.kind, oid)
+            .await
+            .map_err(|_| (ApiCode::DbQueryFailed, "query file hash failed"))?
+        {
+            return Ok(existing_uri);
+        }
+        return Err((ApiCode::DbInsertFailed, "save file metadata failed"));
+    }
+
+    if target.kind == "picture" {
+        save_thumbnails(state, &uri, full_path).await;
+    }
+    Ok(uri)
+}
+
+struct Optimized {
+    /// 压缩后文件的哈希和大小
+    oid: String,
+    size: u64,
+    original_path: Option<PathBuf>,
+}
+
+/// 按 `upload.optimize_min_bytes` 重新压缩较大的图片，压缩后的内容替换 `full_path`；
+/// 不是 jpeg/png、解码失败或没有变小时保持原样返回 None
+async fn optimize_picture(
+    state: &AppState,
+    file_name: &str,
+    full_path: &Path,
+    size: u64,
+) -> Option<Optimized> {
+    let cfg = &state.config.upload;
+    if cfg.optimize_min_bytes == 0 || size <= cfg.optimize_min_bytes {
+        return None;
+    }
+    let src = full_path.to_path_buf();
+    let quality = cfg.optimize_quality;
+    let recompressed = match state
+        .blocking
+        .run("recompress", move || {
+            util::recompress::recompress(&src, quality)
+        })
+        .await
+    {
+        Ok(Ok(Some(v))) => v,
+        Ok(Ok(None)) => return None,
+        Ok(Err(e)) => {
+            info!("skip recompress for {}: {}", full_path.display(), e);
+            return None;
+        }
+        Err(e) => {
+            warn!("recompress task for {} failed: {}", full_path.display(), e);
+            return None;
+        }
+    };
+
+    // 先写到旁边再改名替换，中途失败时原文件不受影响
+    let tmp_path = full_path.with_file_name(format!("{}.optimized", file_name));
+    if let Err(e) = util::file_util::create_file(&tmp_path, &recompressed.bytes).await {
+        warn!("write recompressed {} failed: {}", tmp_path.display(), e);
+        return None;
+    }
+    let original_path = match replace_file(
+        state,
+        file_name,
+        full_path,
+        &tmp_path,
+        cfg.optimize_keep_original,
+    )
+    .await
+    {
+        Ok(v) => v,
+        Err(e) => {
+            warn!(
+                "replace {} with recompressed failed: {}",
+                full_path.display(),
+                e
+            );
+            return None;
+        }
+    };
+    let new_size = recompressed.bytes.len() as u64;
+    info!("压缩图片 {}: {} -> {} bytes", file_name, size, new_size);
+    Some(Optimized {
+        oid: recompressed.oid,
+        size: new_size,
+        original_path,
+    })
+}
+
+/// 用 `tmp_path` 替换 `full_path`，`keep_original` 时原文件移到 `{base_path}/original/` 并返回新位置；
+/// 失败时删除 `tmp_path`，`full_path` 保持原文件
+async fn replace_file(
+    state: &AppState,
+    file_name: &str,
+    full_path: &Path,
+    tmp_path: &Path,
+    keep_original: bool,
+) -> std::io::Result<Option<PathBuf>> {
+    let mut original_path = None;
+    if keep_original {
+        let path = PathBuf::from(state.config.get_original_path()).join(file_name);
+        let moved = match util::file_util::ensure_file_path(&path).await {
+            Ok(_) => tokio::fs::rename(full_path, &path).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = moved {
+            let _ = tokio::fs::remove_file(tmp_path).await;
+            return Err(e);
+        }
+        original_path = Some(path);
+    }
+    if let Err(e) = tokio::fs::rename(tmp_path, full_path).await {
+        if let Some(path) = &original_path {
+            let _ = tokio::fs::rename(path, full_path).await;
+        }
+        let _ = tokio::fs::remove_file(tmp_path).await;
+        return Err(e);
+    }
+    Ok(original_path)
+}
+
+/// 按 `[media]` 检查 gif 和视频的尺寸、码率；超出时按 `over_limit` 拒绝上传（删除文件），
+/// 或者转码后替换 `full_path`，返回转码后文件的哈希和大小
+async fn enforce_media_policy(
+    state: &AppState,
+    gif: bool,
+    file_name: &str,
+    full_path: &Path,
+    original_name: &str,
+) -> Result<Option<Optimized>, (ApiCode, &'static str)> {
+    let cfg = &state.config.media;
+    let (max_px, max_kbps) = if gif {
+        (cfg.gif_max_px, 0)
+    } else {
+        (cfg.video_max_px, cfg.video_max_kbps)
+    };
+    if max_px == 0 && max_kbps == 0 {
+        return Ok(None);
+    }
+    let timeout = Duration::from_secs(cfg.timeout_secs.max(1));
+    let reject = async |reason: String, msg: &'static str| {
+        let _ = tokio::fs::remove_file(full_path).await;
+        warn!(
+            "upload rejected by media policy: name={}, file={}, {}",
+            original_name,
+            full_path.display(),
+            reason
+        );
+        Err((ApiCode::FileRejected, msg))
+    };
+    let info = match media_info(state, gif, full_path, timeout).await {
+        Ok(v) => v,
+        Err(e) => return reject(e.to_string(), "media probe failed").await,
+    };
+    if !info.exceeds(max_px, max_kbps) {
+        return Ok(None);
+    }
+    let over = format!(
+        "{}x{} {}kbps exceeds {}px {}kbps",
+        info.width,
+        info.height,
+        info.kbps.unwrap_or(0),
+        max_px,
+        max_kbps
+    );
+    let command = cfg.transcode_command.trim();
+    if cfg.over_limit.trim() != "transcode" || command.is_empty() {
+        return reject(over, "media exceeds size limit").await;
+    }
+
+    // 上限为 0 的一项保持原样
+    let target_px = match max_px {
+        0 => info.longest_side(),
+        v => v.min(info.longest_side()),
+    };
+    let target_kbps = match (max_kbps, info.kbps) {
+        (0, v) => v.unwrap_or(0),
+        (v, Some(actual)) => v.min(actual),
+        (v, None) => v,
+    };
+    // 保留扩展名，转码程序据此选择输出格式
+    let tmp_path = full_path.with_file_name(format!("transcoded_{}", file_name));
+    let transcoded = async {
+        util::media::transcode(
+            command,
+            full_path,
+            &tmp_path,
+            target_px,
+            target_kbps,
+            timeout,
+        )
+        .await?;
+        let v = media_info(state, gif, &tmp_path, timeout).await?;
+        if v.exceeds(max_px, max_kbps) {
+            return Err(DayLogError::Validation(format!(
+                "transcoded {}x{} {}kbps still exceeds limit",
+                v.width,
+                v.height,
+                v.kbps.unwrap_or(0)
+            )));
+        }
+        let src = tmp_path.clone();
+        let oid = state
+            .blocking
+            .run("media_hash", move || util::file_util::hash_file(&src))
+            .await??;
+        let size = tokio::fs::metadata(&tmp_path).await?.len();
+        Ok::<_, DayLogError>((v, oid, size))
+    }
+    .await;
+    let (new_info, oid, size) = match transcoded {
+        Ok(v) => v,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            let reason = format!("{}, transcode failed: {}", over, e);
+            return reject(reason, "media transcode failed").await;
+        }
+    };
+    let original_path =
+        match replace_file(state, file_name, full_path, &tmp_path, cfg.keep_original).await {
+            Ok(v) => v,
+            Err(e) => {
+                warn!(
+                    "replace {} with transcoded failed: {}",
+                    full_path.display(),
+                    e
+                );
+                let _ = tokio::fs::remove_file(full_path).await;
+                return Err((ApiCode::FileWriteFailed, "save file failed"));
+            }
+        };
+    info!(
+        "转码 {}: {}x{} -> {}x{}, {} bytes",
+        file_name, info.width, info.height, new_info.width, new_info.height, size
+    );
+    Ok(Some(Optimized {
+        oid,
+        size,
+        original_path,
+    }))
+}
+
+/// gif 读文件头，视频用 `media.probe_command`
+async fn media_info(
+    state: &AppState,
+    gif: bool,
+    path: &Path,
+    timeout: Duration,
+) -> Result<util::media::MediaInfo, DayLogError> {
+    if !gif {
+        let command = state.config.media.probe_command.trim();
+        return util::media::probe(command, path, timeout).await;
+    }
+    let src = path.to_path_buf();
+    state
+        .blocking
+        .run("media_probe", move || util::media::gif_info(&src))
+        .await?
+}
+
+/// 生成缩略图并记到 `file_blob`；解码失败（例如 heic、svg）时只记日志，读取时退回原图
+async fn save_thumbnails(state: &AppState, uri: &str, full_path: &Path) {
+    let cfg = &state.config.upload;
+    let (small_px, medium_px) = (cfg.thumb_small_px, cfg.thumb_medium_px);
+    if small_px == 0 && medium_px == 0 {
+        return;
+    }
+    let src = full_path.to_path_buf();
+    let dir = PathBuf::from(state.config.get_thumb_path());
+    let thumbs = match state
+        .blocking
+        .run("thumbnail", move || {
+            thumbnail::generate(&src, &dir, small_px, medium_px)
+        })
+        .await
+    {
+        Ok(Ok(v)) => v,
+        Ok(Err(e)) => {
+            info!("skip thumbnail for {}: {}", uri, e);
+            return;
+        }
+        Err(e) => {
+            warn!("thumbnail task for {} failed: {}", uri, e);
+            return;
+        }
+    };
+    let stored = |v: Option<PathBuf>| v.map(|p| state.config.to_stored_path(&p));
+    if let Err(e) = sqlx::query(
+        "update file_blob set thumb_small_path = ?, thumb_medium_path = ? where uri = ?",
+    )
+    .bind(stored(thumbs.small))
+    .bind(stored(thumbs.medium))
+    .bind(uri)
+    .execute(&state.db)
+    .await
+    {
+        warn!("record thumbnail for {} failed: {}", uri, e);
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PictureQuery {
+    /// `thumb` 小图，`medium` 中图；没有对应的缩略图时返回原图
+    pub size: Option<String>,
+}
+
+/// 上传的图片，带 `size` 时优先返回上传时生成的缩略图
+#[utoipa::path(
+    get,
+    path = "/files/picture/{name}",
+    tag = "file",
+    params(("name" = String, Path), PictureQuery),
+    responses(
+        (status = 200, description = "图片内容", content_type = "image/*"),
+        (status = 304, description = "`If-None-Match` 或 `If-Modified-Since` 命中"),
+        (status = 404, description = "没有上传记录或文件不存在")
+    )
+)]
+pub async fn serve_picture(
+    State(state): State<AppState>,
+    UrlPath(name): UrlPath<String>,
+    Query(query): Query<PictureQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let size = match query.size.as_deref() {
+        None => None,
+        Some(raw) => match ThumbSize::parse(raw) {
+            Some(v) => Some(v),
+            None => {
+                return ApiResponse::<()>::err(ApiCode::Validation, "size must be thumb or medium")
+                    .into_response();
+            }
+        },
+    };
+    serve_uri(&state, &format!("/files/picture/{}", name), size, &headers).await
+}
+
+/// 上传的视频，支持 `Range`
+#[utoipa::path(
+    get,
+    path = "/files/media/{name}",
+    tag = "file",
+    params(("name" = String, Path)),
+    responses(
+        (status = 200, description = "视频内容", content_type = "video/*"),
+        (status = 206, description = "`Range` 请求的一段"),
+        (status = 404, description = "没有上传记录或文件不存在")
+    )
+)]
+pub async fn serve_media(
+    State(state): State<AppState>,
+    UrlPath(name): UrlPath<String>,
+    headers: HeaderMap,
+) -> Response {
+    serve_uri(&state, &format!("/files/media/{}", name), None, &headers).await
+}
+
+/// 上传的其他文件
+#[utoipa::path(
+    get,
+    path = "/files/file/{name}",
+    tag = "file",
+    params(("name" = String, Path)),
+    responses(
+        (status = 200, description = "文件内容", content_type = "application/octet-stream"),
+        (status = 404, description = "没有上传记录或文件不存在")
+    )
+)]
+pub async fn serve_attachment(
+    State(state): State<AppState>,
+    UrlPath(name): UrlPath<String>,
+    headers: HeaderMap,
+) -> Response {
+    serve_uri(&state, &format!("/files/file/{}", name), None, &headers).await
+}
+
+#[derive(Debug, FromRow)]
+struct ServedBlob {
+    id: i64,
+    mime: String,
+    oid: String,
+    file_path: String,
+    create_time: i64,
+    thumb_small_path: Option<String>,
+    thumb_medium_path: Option<String>,
+}
+
+/// `/files/` 下的地址只按 `file_blob` 的记录查找文件，没有记录的（包括目录中的其他文件）返回 404
+async fn serve_uri(
+    state: &AppState,
+    uri: &str,
+    size: Option<ThumbSize>,
+    headers: &HeaderMap,
+) -> Response {
+    let row = sqlx::query_as::<_, ServedBlob>(
+        "select id, mime, oid, file_path, create_time, thumb_small_path, thumb_medium_path from file_blob where uri = ? limit 1",
+    )
+    .bind(uri)
+    .fetch_optional(&state.db)
+    .await;
+    let blob = match row {
+        Ok(Some(v)) => v,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            warn!("query file {} failed: {}", uri, e);
+            return ApiResponse::<()>::err(ApiCode::DbQueryFailed, "db query failed")
+                .into_response();
+        }
+    };
+
+    let thumb = match size {
+        Some(ThumbSize::Small) => blob.thumb_small_path.as_deref(),
+        Some(ThumbSize::Medium) => blob.thumb_medium_path.as_deref(),
+        None => None,
+    }
+    .map(|v| state.config.resolve_stored_path(v))
+    .filter(|v| v.is_file());
+    let sent = match (&thumb, size) {
+        (Some(path), Some(size)) => {
+            let mime = if path.extension().is_some_and(|v| v == "png") {
+                "image/png"
+            } else {
+                "image/jpeg"
+            };
+            let etag = format!("\"{}-{}\"", blob.oid, size.suffix());
+            send_blob(headers, path, mime, &etag, blob.create_time).await
+        }
+        _ => {
+            let path = state.config.resolve_stored_path(&blob.file_path);
+            let etag = format!("\"{}\"", blob.oid);
+            send_blob(headers, &path, &blob.mime, &etag, blob.create_time).await
+        }
+    };
+    let resp = sent.unwrap_or_else(|e| {
+        warn!("serve file {} failed: {}", uri, e);
+        StatusCode::NOT_FOUND.into_response()
+    });
+    record_access(state, blob.id, resp.status()).await;
+    resp
+}
+
+/// 完整返回内容时累加读取次数，304 和拖动视频时的 `Range` 请求不计，避免每次请求都写库；失败只记日志
+async fn record_access(state: &AppState, id: i64, status: StatusCode) {
+    if status != StatusCode::OK {
+        return;
+    }
+    if let Err(e) = sqlx::query(
+        "update file_blob set access_count = access_count + 1, last_access_time = ? where id = ?",
+    )
+    .bind(now_ts())
+    .bind(id)
+    .execute(&state.db)
+    .await
+    {
+        warn!("record file #{} access failed: {}", id, e);
+    }
+}
+
+/// 执行 `upload.scan_command`，未通过的文件移入隔离目录；命令无法执行时删除文件并拒绝，不放行未扫描的文件
+async fn scan_file(
+    state: &AppState,
+    full_path: &Path,
+    original_name: &str,
+) -> Result<(), (ApiCode, &'static str)> {
+    let cfg = &state.config.upload;
+    let command = cfg.scan_command.trim();
+    if command.is_empty() {
+        return Ok(());
+    }
+    let path = full_path.to_string_lossy();
+    let mut args = command
+        .split_whitespace()
+        .map(|v| v.replace("{path}", &path))
+        .collect::<Vec<_>>();
+    if !command.contains("{path}") {
+        args.push(path.to_string());
+    }
+    let (program, args) = args
+        .split_first()
+        .ok_or((ApiCode::FileWriteFailed, "file scan failed"))?;
+    let run = Command::new(program).args(args).kill_on_drop(true).output();
+    let timeout = Duration::from_secs(cfg.scan_timeout_secs.max(1));
+    let reason = match tokio::time::timeout(timeout, run).await {
+        Ok(Ok(out)) if out.status.success() => return Ok(()),
+        Ok(Ok(out)) => format!(
+            "exit with {}: {}",
+            out.status,
+            String::from_utf8_lossy(&out.stdout).trim()
+        ),
+        Err(_) => format!("timeout after {}s", timeout.as_secs()),
+        Ok(Err(e)) => {
+            warn!("run upload scan command failed: {}", e);
+            let _ = tokio::fs::remove_file(full_path).await;
+            return Err((ApiCode::FileWriteFailed, "file scan failed"));
+        }
+    };
+
+    let quarantine = PathBuf::from(state.config.get_quarantine_path());
+    let moved = match full_path.file_name() {
+        Some(name) if util::file_util::ensure_path(&quarantine).await.is_ok() => {
+            tokio::fs::rename(full_path, quarantine.join(name))
+                .await
+                .is_ok()
+        }
+        _ => false,
+    };
+    if !moved {
+        let _ = tokio::fs::remove_file(full_path).await;
+    }
+    warn!(
+        "upload rejected by scanner: name={}, file={}, quarantined={}, {}",
+        original_name, path, moved, reason
+    );
+    Err((ApiCode::FileRejected, "file rejected by scanner"))
+}
+
+/// 按 `file_blob` 记录提供文件：`Content-Type` 取保存的 mime，`ETag` 取内容哈希，
+/// 支持条件请求和单段 `Range`，视频可以拖动进度
+#[utoipa::path(
+    get,
+    path = "/files/by-id/{id}",
+    tag = "file",
+    params(("id" = i64, Path)),
+    responses(
+        (status = 200, description = "文件内容", content_type = "application/octet-stream"),
+        (status = 206, description = "`Range` 请求的一段"),
+        (status = 304, description = "`If-None-Match` 或 `If-Modified-Since` 命中"),
+        (status = 404, description = "记录或文件不存在"),
+        (status = 416, description = "`Range` 超出文件大小")
+    )
+)]
+pub async fn serve_file_by_id(
+    State(state): State<AppState>,
+    UrlPath(id): UrlPath<i64>,
+    headers: HeaderMap,
+) -> Response {
+    let row = sqlx::query_as::<_, (String, String, String, i64)>(
+        "select mime, oid, file_path, create_time from file_blob where id = ?",
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await;
+    let (mime, oid, file_path, create_time) = match row {
+        Ok(Some(v)) => v,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(_) => {
+            return ApiResponse::<()>::err(ApiCode::DbQueryFailed, "db query failed")
+                .into_response();
+        }
+    };
+    let path = state.config.resolve_stored_path(&file_path);
+    let etag = format!("\"{}\"", oid);
+    let resp = send_blob(&headers, &path, &mime, &etag, create_time)
+        .await
+        .unwrap_or_else(|e| {
+            warn!("serve file #{} {} failed: {}", id, path.display(), e);
+            StatusCode::NOT_FOUND.into_response()
+        });
+    record_access(&state, id, resp.status()).await;
+    resp
+}
+
+/// 文件内容不会变（改动会生成新的记录），`create_time` 就是内容的修改时间；
+/// 支持条件请求和单段 `Range`。文件打不开时返回 Err，由调用方决定响应
+async fn send_blob(
+    headers: &HeaderMap,
+    path: &Path,
+    mime: &str,
+    etag: &str,
+    create_time: i64,
+) -> std::io::Result<Response> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let size = file.metadata().await?.len();
+
+    let last_modified =
+        httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_secs(create_time.max(0) as u64));
+    let common = [
+        (header::ETAG, etag.to_string()),
+        (header::LAST_MODIFIED, last_modified.clone()),
+        (header::ACCEPT_RANGES, "bytes".to_string()),
+        (header::CACHE_CONTROL, "no-cache".to_string()),
+    ];
+    if conditional::not_modified(headers, etag, Some(&last_modified)) {
+        return Ok((StatusCode::NOT_MODIFIED, common).into_response());
+    }
+
+    let (status, start, len) =
+        match conditional::byte_range(headers, size, etag, Some(&last_modified)) {
+            ByteRange::Full => (StatusCode::OK, 0, size),
+            ByteRange::Partial(start, end) => (StatusCode::PARTIAL_CONTENT, start, end - start + 1),
+            ByteRange::Unsatisfiable => {
+                return Ok((
+                    StatusCode::RANGE_NOT_SATISFIABLE,
+                    common,
+                    [(header::CONTENT_RANGE, format!("bytes */{}", size))],
+                )
+                    .into_response());
+            }
+        };
+    if start > 0 {
+        file.seek(SeekFrom::Start(start)).await?;
+    }
+    let mut resp = (
+        status,
+        common,
+        [
+            (header::CONTENT_TYPE, mime.to_string()),
+            (header::CONTENT_LENGTH, len.to_string()),
+        ],
+        Body::from_stream(ReaderStream::new(file.take(len))),
+    )
+        .into_response();
+    if status == StatusCode::PARTIAL_CONTENT
+        && let Ok(v) =
+            HeaderValue::from_str(&format!("bytes {}-{}/{}", start, start + len - 1, size))
+    {
+        resp.headers_mut().insert(header::CONTENT_RANGE, v);
+    }
+    if !inline_safe(mime) {
+        resp.headers_mut().insert(
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_static("attachment"),
+        );
+        resp.headers_mut().insert(
+            header::CONTENT_SECURITY_POLICY,
+            HeaderValue::from_static("sandbox"),
+        );
+    }
+    Ok(resp)
+}
+
+/// mime 是上传时客户端给的，html、svg 这类能执行脚本的内容在同源下打开就是存储型 xss，
+/// 只有图片、音视频直接显示，其余按附件下载并禁止执行脚本
+fn inline_safe(mime: &str) -> bool {
+    let mime = mime.trim().to_ascii_lowercase();
+    let essence = mime.split(';').next().unwrap_or("").trim();
+    (essence.starts_with("image/") && essence != "image/svg+xml")
+        || essence.starts_with("video/")
+        || essence.starts_with("audio/")
+}
+
+/// 某一天的日记引用到的文件，正文中的在前，按出现顺序去重
+#[utoipa::path(
+    get,
+    path = "/journal/{id}/files",
+    tag = "file",
+    params(("id" = i64, Path)),
+    responses((status = 200, body = ApiResponse<Vec<JournalFile>>))
+)]
+pub async fn list_journal_files(
+    State(state): State<AppState>,
+    UrlPath(id): UrlPath<i64>,
+) -> ApiResult<Vec<JournalFile>> {
+    info!("获取日记文件 id: {}", id);
+    let row = sqlx::query_as::<_, (String, Option<String>)>(
+        "select content, metadata from journal where id = ?",
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| ApiResponse::<Vec<JournalFile>>::err(ApiCode::DbGetFailed, "db query failed"))?
+    .ok_or_else(|| ApiResponse::<Vec<JournalFile>>::err(ApiCode::NotFound, "not found"))?;
+    let (content, metadata) = row;
+    let content = state.cipher.open(content);
+    let refs = journal_file_refs(&content, &JournalMetadata::parse(metadata.as_deref()));
+
+    let mut files = Vec::with_capacity(refs.len());
+    for (uri, source) in refs {
+        let row = sqlx::query_as::<_, JournalFile>(
+            "select id, uri, kind, mime, size, original_name, access_count, last_access_time from file_blob where uri = ? limit 1",
+        )
+        .bind(&uri)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| {
+            ApiResponse::<Vec<JournalFile>>::err(ApiCode::DbQueryFailed, "db query failed")
+        })?;
+        if let Some(mut file) = row {
+            file.source = source.to_string();
+            files.push(file);
+        }
+    }
+    Ok(ApiResponse::ok(files))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FileReferences {
+    pub id: i64,
+    pub uri: String,
+    /// 日记在前，各自按日期倒序；为空时删除文件不影响任何日记
+    pub references: Vec<FileReference>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FileReference {
+    /// 日记 id；`trashed` 时是回收站记录的 id
+    pub id: i64,
+    pub date: String,
+    /// `content` 正文引用，`attachment` 显式附件
+    pub source: String,
+    /// 在回收站中；清空回收站前，引用的文件不会被清理
+    pub trashed: bool,
+}
+
+#[derive(Debug, FromRow)]
+struct ReferenceRow {
+    id: i64,
+    date: String,
+    content: String,
+    metadata: Option<String>,
+    trashed: bool,
+}
+
+/// 正文或附件引用了这个文件的日记和回收站记录，删除文件前用来提示，也可以核对回收站清理
+#[utoipa::path(
+    get,
+    path = "/files/{id}/references",
+    tag = "file",
+    params(("id" = i64, Path, description = "`file_blob` 记录 id")),
+    responses((status = 200, body = ApiResponse<FileReferences>))
+)]
+pub async fn list_file_references(
+    State(state): State<AppState>,
+    UrlPath(id): UrlPath<i64>,
+) -> ApiResult<FileReferences> {
+    info!("获取文件引用 id: {}", id);
+    let uri = sqlx::query_scalar::<_, String>("select uri from file_blob where id = ?")
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| ApiResponse::<FileReferences>::err(ApiCode::DbGetFailed, "db query failed"))?
+        .ok_or_else(|| ApiResponse::<FileReferences>::err(ApiCode::NotFound, "file not found"))?;
+    // 正文加密时 sql 里查不到，全部取出解密后再比对
+    let filter = (!state.cipher.enabled()).then_some(uri.as_str());
+    let rows = sqlx::query_as::<_, ReferenceRow>(
+        r#"
+        select id, date, content, metadata, 0 as trashed from journal
+        where ?1 is null or instr(content, ?1) > 0 or instr(coalesce(metadata, ''), ?1) > 0
+        union all
+        select id, date, content, metadata, 1 as trashed from journal_trash
+        where ?1 is null or instr(content, ?1) > 0 or instr(coalesce(metadata, ''), ?1) > 0
+        order by trashed asc, date desc
+        "#,
+    )
+    .bind(filter)
+    .fetch_all(&state.read_db)
+    .await
+    .map_err(|_| ApiResponse::<FileReferences>::err(ApiCode::DbListFailed, "db query failed"))?;
+
+    let mut references = Vec::new();
+    for row in rows {
+        let content = state.cipher.open(row.content);
+        let metadata = JournalMetadata::parse(row.metadata.as_deref());
+        // instr 会命中更长的文件名，按解析出的 uri 精确比对
+        if let Some((_, source)) = journal_file_refs(&content, &metadata)
+            .into_iter()
+            .find(|(v, _)| *v == uri)
+        {
+            references.push(FileReference {
+                id: row.id,
+                date: row.date,
+                source: source.to_string(),
+                trashed: row.trashed,
+            });
+        }
+    }
+    Ok(ApiResponse::ok(FileReferences {
+        id,
+        uri,
+        references,
+    }))
+}
+
+/// 日记引用的文件 uri 去重后的列表，第二项是来源：正文 `content` 或 `metadata.attachments`
+pub fn journal_file_refs(content: &str, metadata: &JournalMetadata) -> Vec<(String, &'static str)> {
+    let mut refs: Vec<(String, &'static str)> = Vec::new();
+    for uri in file_uris(content) {
+        if !refs.iter().any(|(v, _)| *v == uri) {
+            refs.push((uri, "content"));
+        }
+    }
+    for uri in metadata.attachments.iter().flatten() {
+        if !refs.iter().any(|(v, _)| v == uri) {
+            refs.push((uri.clone(), "attachment"));
+        }
+    }
+    refs
+}
+
+/// 提取文本中出现的 `/files/...` 地址，markdown 链接、图片和 html 属性都能识别
+pub fn file_uris(content: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut rest = content;
+    while let Some(pos) = rest.find("/files/") {
+        let tail = &rest[pos..];
+        let end = tail
+            .find(|c: char| c.is_whitespace() || matches!(c, ')' | ']' | '"' | '\'' | '<' | '>'))
+            .unwrap_or(tail.len());
+        out.push(tail[..end].to_string());
+        rest = &tail[end..];
+    }
+    out
+}
+
 fn resolve_target(state: &AppState, content_type: Option<&str>) -> SaveTarget {
     match content_type {
         Some(v) if v.starts_with("image/") => SaveTarget {
@@ -169,7 +1154,7 @@ async fn find_existing_uri(
     oid: &str,
 ) -> Result<Option<String>, sqlx::Error> {
     let row = sqlx::query_as::<_, FileBlobRow>(
-        "select uri from file_blob where kind = ? and algo = 'sha256' and oid = ? limit 1",
+        "select uri from file_blob where kind = ?1 and algo = 'sha256' and (oid = ?2 or source_oid = ?2) limit 1",
     )
     .bind(kind)
     .bind(oid)
@@ -178,11 +1163,11 @@ async fn find_existing_uri(
     Ok(row.map(|v| v.uri))
 }
 
-fn sanitize_file_name(name: &str) -> String {
+pub fn sanitize_file_name(name: &str) -> String {
     let normalized = name.replace('\\', "/");
     let base = normalized
         .split('/')
-        .last()
+        .next_back()
         .unwrap_or("")
         .replace("..", "")
         .trim()
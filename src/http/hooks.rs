@@ -0,0 +1,179 @@
+use crate::app_state::AppState;
+use crate::http::auth;
+use crate::http::journal::{self, Journal};
+use crate::http::quick::{self, QuickQuery};
+use crate::http::resp::{ApiCode, ApiResponse};
+use crate::http::settings;
+use crate::util::date_util;
+use axum::Json;
+use axum::body::Bytes;
+use axum::extract::{ConnectInfo, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde_json::Value;
+use std::net::SocketAddr;
+use tracing::{info, warn};
+
+/// 给 IFTTT / Zapier 等自动化平台用的写入接口，json 字段按 settings 中的 `ingestMapping` 映射；
+/// 令牌错误和其他接口一样计入锁定，锁定期间返回 423
+pub async fn ingest(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Query(query): Query<QuickQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Json<ApiResponse<Journal>>), Response> {
+    let expected = state.config.hooks.token.trim();
+    if expected.is_empty() {
+        return Err(status_err(
+            StatusCode::NOT_FOUND,
+            ApiCode::NotFound,
+            "ingest hook disabled",
+        ));
+    }
+    let provided = query
+        .token
+        .or_else(|| quick::header_token(&headers))
+        .unwrap_or_default();
+    let provided = provided.trim();
+    let attempt = auth::TokenAttempt::begin(&state, &headers, peer, provided).await?;
+    if !quick::token_eq(provided, expected) {
+        warn!("ingest hook rejected: invalid token, ip={}", attempt.ip());
+        attempt
+            .failed(&state, provided, "POST", "/hooks/ingest")
+            .await;
+        return Err(status_err(
+            StatusCode::UNAUTHORIZED,
+            ApiCode::Unauthorized,
+            "invalid token",
+        ));
+    }
+    attempt.succeeded(&state).await;
+
+    let payload = serde_json::from_slice::<Value>(&body).map_err(|_| {
+        status_err(
+            StatusCode::BAD_REQUEST,
+            ApiCode::BadRequest,
+            "invalid json body",
+        )
+    })?;
+    let mapping = settings::load_ingest_mapping(&state)
+        .await
+        .unwrap_or_else(settings::default_ingest_mapping);
+
+    let text = field_text(&payload, &mapping.text_field).unwrap_or_default();
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(status_err(
+            StatusCode::BAD_REQUEST,
+            ApiCode::Validation,
+            &format!("field '{}' required", mapping.text_field),
+        ));
+    }
+    let date = match lookup(&payload, &mapping.date_field) {
+        Some(value) if !value.is_null() => resolve_date(value, state.config.utc_offset_minutes)
+            .ok_or_else(|| {
+                status_err(
+                    StatusCode::BAD_REQUEST,
+                    ApiCode::Validation,
+                    &format!("field '{}' is not a valid date", mapping.date_field),
+                )
+            })?,
+        _ => state.config.today(),
+    };
+    let title = field_text(&payload, &mapping.title_field)
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty());
+    let content = match title {
+        Some(title) => format!("## {}\n\n{}", title, text),
+        None => text.to_string(),
+    };
+
+    let result = if mapping.mode == "create" {
+        // 和 `POST /journal` 一致，当天已有日记时不覆盖，自动化重复触发也不会丢内容
+        let existed = journal::find_journal_by_date(&state, &date)
+            .await
+            .map_err(|_| {
+                status_err(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ApiCode::DbQueryFailed,
+                    "db query failed",
+                )
+            })?;
+        if let Some(existed) = existed {
+            warn!(
+                "ingest hook create skipped: {} exists (#{})",
+                date, existed.id
+            );
+            return Err(status_err(
+                StatusCode::CONFLICT,
+                ApiCode::Conflict,
+                &format!("journal for {} already exists (#{})", date, existed.id),
+            ));
+        }
+        journal::replace_date_content(&state, &date, &content).await
+    } else {
+        journal::append_to_date(&state, &date, &content).await
+    };
+    let journal = result.map_err(|_| {
+        status_err(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiCode::DbUpdateFailed,
+            "db update failed",
+        )
+    })?;
+    info!(
+        "ingest hook mode={}, date={}, len={}",
+        mapping.mode,
+        date,
+        content.chars().count()
+    );
+    Ok(ApiResponse::ok(journal))
+}
+
+/// 自动化平台依赖 http 状态码判断成败，这里不沿用统一的 200
+fn status_err(status: StatusCode, code: ApiCode, msg: &str) -> Response {
+    let (_, body) = ApiResponse::<Journal>::err(code, msg);
+    (status, body).into_response()
+}
+
+/// 按 `a.b.0` 形式的路径取值
+fn lookup<'a>(payload: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.is_empty() {
+        return None;
+    }
+    path.split('.').try_fold(payload, |v, key| match v {
+        Value::Object(map) => map.get(key),
+        Value::Array(arr) => arr.get(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+fn field_text(payload: &Value, path: &str) -> Option<String> {
+    match lookup(payload, path)? {
+        Value::String(v) => Some(v.clone()),
+        Value::Null => None,
+        other => Some(other.to_string()),
+    }
+}
+
+/// 支持 `yyyy-MM-dd` 开头的字符串（含 ISO 时间）和 unix 秒/毫秒时间戳
+fn resolve_date(value: &Value, utc_offset_minutes: i32) -> Option<String> {
+    let secs = match value {
+        Value::Number(n) => n.as_i64()?,
+        Value::String(s) => {
+            let s = s.trim();
+            if let Some(days) = s.get(0..10).and_then(date_util::parse_date) {
+                return Some(date_util::date_from_days(days));
+            }
+            s.parse::<i64>().ok()?
+        }
+        _ => return None,
+    };
+    let secs = if secs > 100_000_000_000 {
+        secs / 1000
+    } else {
+        secs
+    };
+    Some(date_util::date_of(secs, utc_offset_minutes))
+}
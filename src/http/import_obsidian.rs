@@ -0,0 +1,735 @@
+use crate::app_state::AppState;
+use crate::http::file;
+use crate::http::import_zip::SkipDetail;
+use crate::http::journal::{self, UpsertEntry};
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult, multipart_read_code};
+use crate::util::date_util;
+use axum::body::Bytes;
+use axum::extract::{Multipart, State};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{Cursor, Read};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use zip::ZipArchive;
+
+/// 日记插件没有设置格式时 Obsidian 使用的默认值
+const DEFAULT_FORMAT: &str = "YYYY-MM-DD";
+const DAILY_NOTES_SETTINGS: &str = ".obsidian/daily-notes.json";
+const PERIODIC_NOTES_SETTINGS: &str = ".obsidian/plugins/periodic-notes/data.json";
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportObsidianResp {
+    /// 日记所在目录，相对 vault 根目录
+    pub folder: String,
+    /// moment.js 风格的日记文件名格式
+    pub format: String,
+    pub total_markdown_files: usize,
+    /// 不在日记目录或文件名不符合格式的其他笔记
+    pub ignored_notes: usize,
+    pub imported_count: usize,
+    /// 从压缩包中上传的图片和附件
+    pub asset_count: usize,
+    pub skipped_count: usize,
+    pub skipped_details: Vec<SkipDetail>,
+    pub dry_run: bool,
+}
+
+/// multipart 中 `options` 字段的 json，例如 `{"folder":"Journal","format":"YYYY/MM/YYYY-MM-DD"}`；
+/// 不填 `folder` `format` 时读取压缩包中 `.obsidian` 下日记插件的设置
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ObsidianOptions {
+    pub folder: Option<String>,
+    pub format: Option<String>,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// `daily-notes.json` 以及 periodic-notes 插件中 `daily` 的设置
+#[derive(Debug, Default, Deserialize)]
+struct DailyNotesSettings {
+    folder: Option<String>,
+    format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PeriodicNotesSettings {
+    daily: Option<DailyNotesSettings>,
+}
+
+#[derive(Debug)]
+struct DailyNote {
+    /// vault 根目录下的相对路径
+    path: String,
+    date: String,
+    content: String,
+}
+
+#[derive(Debug)]
+struct ParsedVault {
+    folder: String,
+    format: String,
+    total_markdown_files: usize,
+    ignored_notes: usize,
+    notes: Vec<DailyNote>,
+    /// 日记中嵌入的附件，vault 相对路径 -> 文件内容
+    assets: BTreeMap<String, Vec<u8>>,
+    index: AssetIndex,
+    skipped_details: Vec<SkipDetail>,
+}
+
+/// 按 Obsidian 的规则查找 `![[...]]` 引用的附件
+#[derive(Debug, Default)]
+struct AssetIndex {
+    /// 小写的 vault 相对路径 -> 原路径
+    by_path: HashMap<String, String>,
+    /// 小写的文件名 -> 原路径，同名时取路径最短的
+    by_name: HashMap<String, String>,
+}
+
+/// 正文中的 `[[target#anchor|alias]]`，前面有 `!` 时是嵌入
+#[derive(Debug)]
+struct WikiLink<'a> {
+    embed: bool,
+    target: &'a str,
+    anchor: Option<&'a str>,
+    alias: Option<&'a str>,
+}
+
+/// 导入 Obsidian vault 的压缩包：按日记插件的目录和文件名格式识别日记，
+/// `![[image.png]]` 嵌入的附件上传后改成 `/files/...` 链接，`[[wikilink]]` 改成纯文本
+#[utoipa::path(
+    post,
+    path = "/journal/import/obsidian",
+    tag = "import",
+    request_body(
+        content_type = "multipart/form-data",
+        description = "`file`: vault 的 zip 压缩包；`options`: `ObsidianOptions` 的 json，可不填"
+    ),
+    responses((status = 200, body = ApiResponse<ImportObsidianResp>))
+)]
+pub async fn import_obsidian(
+    State(state): State<AppState>,
+    mut multipart: Multipart,
+) -> ApiResult<ImportObsidianResp> {
+    let mut zip_file: Option<Vec<u8>> = None;
+    let mut options_raw: Option<String> = None;
+
+    while let Some(field) = multipart.next_field().await.map_err(|_| {
+        ApiResponse::<ImportObsidianResp>::err(ApiCode::BadRequest, "invalid multipart data")
+    })? {
+        let name = field.name().unwrap_or("").to_string();
+
+        if name == "file" || field.file_name().is_some() {
+            zip_file = Some(
+                field
+                    .bytes()
+                    .await
+                    .map_err(|e| {
+                        ApiResponse::<ImportObsidianResp>::err(
+                            multipart_read_code(&e),
+                            "read zip file failed",
+                        )
+                    })?
+                    .to_vec(),
+            );
+        } else if name == "options" {
+            options_raw = Some(field.text().await.map_err(|_| {
+                ApiResponse::<ImportObsidianResp>::err(ApiCode::BadRequest, "read options failed")
+            })?);
+        }
+    }
+
+    let zip_file = zip_file.ok_or_else(|| {
+        ApiResponse::<ImportObsidianResp>::err(ApiCode::FileMissing, "zip file required")
+    })?;
+    let options = match options_raw.as_deref().map(str::trim) {
+        Some(raw) if !raw.is_empty() => {
+            serde_json::from_str::<ObsidianOptions>(raw).map_err(|e| {
+                ApiResponse::<ImportObsidianResp>::err(
+                    ApiCode::Validation,
+                    &format!("invalid options: {}", e),
+                )
+            })?
+        }
+        _ => ObsidianOptions::default(),
+    };
+    let dry_run = options.dry_run;
+
+    let size_limit = state.config.upload_file_limit;
+    let mut vault = state
+        .blocking
+        .run("obsidian import parse", move || {
+            parse_vault(zip_file, options, size_limit)
+        })
+        .await
+        .map_err(|_| {
+            ApiResponse::<ImportObsidianResp>::err(ApiCode::BadRequest, "parse vault task failed")
+        })?
+        .map_err(|msg| ApiResponse::<ImportObsidianResp>::err(ApiCode::BadRequest, &msg))?;
+
+    let mut uploaded: HashMap<String, String> = HashMap::new();
+    if !dry_run {
+        for (path, bytes) in std::mem::take(&mut vault.assets) {
+            let name = file::sanitize_file_name(file_name_of(&path));
+            match file::store_file(&state, &name, mime_of(&path), Bytes::from(bytes)).await {
+                Ok(uri) => {
+                    uploaded.insert(path, uri);
+                }
+                Err((_, msg)) => {
+                    warn!("obsidian import asset skipped: {} => {}", path, msg);
+                    vault.skipped_details.push(SkipDetail {
+                        path,
+                        reason: msg.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    // 正常情况下一天只有一篇，front matter 等原因重复时按路径顺序合并
+    let mut days: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for note in &vault.notes {
+        let dir = parent_dir(&note.path);
+        let content = rewrite_links(&note.content, |link| {
+            if link.embed
+                && let Some(path) = vault.index.resolve(link.target, dir)
+            {
+                return uploaded
+                    .get(path)
+                    .map(|uri| asset_markdown(link, path, uri));
+            }
+            if link.embed && link.is_asset() {
+                return None;
+            }
+            Some(link.display())
+        });
+        days.entry(note.date.clone())
+            .or_default()
+            .push(content.trim().to_string());
+    }
+    let entries = days
+        .into_iter()
+        .map(|(date, sections)| UpsertEntry {
+            date,
+            content: sections.join("\n\n"),
+            metadata: None,
+            metadata_patch: None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut skipped_details = vault.skipped_details;
+    let imported_count = if dry_run {
+        entries.len()
+    } else {
+        let report = journal::upsert_by_date_batch(&state, &entries, "obsidian import").await;
+        for idx in report.failed {
+            let detail = SkipDetail {
+                path: entries[idx].date.clone(),
+                reason: "db upsert failed".to_string(),
+            };
+            warn!(
+                "obsidian import skipped: {} => {}",
+                detail.path, detail.reason
+            );
+            skipped_details.push(detail);
+        }
+        report.upserted
+    };
+
+    let resp = ImportObsidianResp {
+        folder: vault.folder,
+        format: vault.format,
+        total_markdown_files: vault.total_markdown_files,
+        ignored_notes: vault.ignored_notes,
+        imported_count,
+        asset_count: uploaded.len(),
+        skipped_count: skipped_details.len(),
+        skipped_details,
+        dry_run,
+    };
+    info!(
+        "导入 Obsidian 完成 folder='{}', format='{}', total_md={}, ignored={}, imported={}, assets={}, skipped={}, dry_run={}",
+        resp.folder,
+        resp.format,
+        resp.total_markdown_files,
+        resp.ignored_notes,
+        resp.imported_count,
+        resp.asset_count,
+        resp.skipped_count,
+        resp.dry_run
+    );
+    Ok(ApiResponse::ok(resp))
+}
+
+fn parse_vault(
+    zip_file: Vec<u8>,
+    options: ObsidianOptions,
+    size_limit: usize,
+) -> Result<ParsedVault, String> {
+    let mut archive = ZipArchive::new(Cursor::new(zip_file.as_slice()))
+        .map_err(|_| "invalid zip file".to_string())?;
+    let names = archive
+        .file_names()
+        .map(|v| v.replace('\\', "/"))
+        .collect::<Vec<_>>();
+    let root = vault_root(&names);
+
+    // vault 相对路径 -> 压缩包中的下标
+    let mut files = BTreeMap::new();
+    for idx in 0..archive.len() {
+        let entry = archive
+            .by_index(idx)
+            .map_err(|_| "read zip entry failed".to_string())?;
+        if !entry.is_file() {
+            continue;
+        }
+        let name = entry.name().replace('\\', "/");
+        if let Some(rel) = name.strip_prefix(root.as_str()) {
+            files.insert(rel.to_string(), idx);
+        }
+    }
+
+    let settings = match (&options.folder, &options.format) {
+        (Some(_), Some(_)) => DailyNotesSettings::default(),
+        _ => load_settings(&mut archive, &files),
+    };
+    let folder = options
+        .folder
+        .or(settings.folder)
+        .unwrap_or_default()
+        .trim()
+        .trim_matches('/')
+        .to_string();
+    let format = options
+        .format
+        .or(settings.format)
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| DEFAULT_FORMAT.to_string());
+    let matcher = daily_note_regex(&folder, &format)?;
+
+    let mut index = AssetIndex::default();
+    let mut note_paths = Vec::new();
+    for path in files.keys() {
+        if path.starts_with(".obsidian/") || path.starts_with(".trash/") {
+            continue;
+        }
+        if path.to_ascii_lowercase().ends_with(".md") {
+            note_paths.push(path.clone());
+        } else {
+            index.insert(path);
+        }
+    }
+
+    let mut notes = Vec::new();
+    let mut skipped_details = Vec::new();
+    let mut ignored_notes = 0usize;
+    for path in &note_paths {
+        let Some(caps) = matcher.captures(path) else {
+            ignored_notes += 1;
+            continue;
+        };
+        let Some(date) = captured_date(&caps) else {
+            skipped_details.push(SkipDetail {
+                path: path.clone(),
+                reason: format!(
+                    "file name does not form a valid date with format {}",
+                    format
+                ),
+            });
+            continue;
+        };
+        let content = read_text(&mut archive, files[path])?;
+        notes.push(DailyNote {
+            path: path.clone(),
+            date,
+            content,
+        });
+    }
+
+    let mut assets = BTreeMap::new();
+    for note in &notes {
+        let dir = parent_dir(&note.path);
+        rewrite_links(&note.content, |link| {
+            if !link.embed {
+                return None;
+            }
+            match index.resolve(link.target, dir) {
+                Some(path) if !assets.contains_key(path) => {
+                    match read_asset(&mut archive, files[path], size_limit) {
+                        Ok(bytes) => {
+                            assets.insert(path.to_string(), bytes);
+                        }
+                        Err(reason) => skipped_details.push(SkipDetail {
+                            path: path.to_string(),
+                            reason,
+                        }),
+                    }
+                }
+                Some(_) => {}
+                None if link.is_asset() => skipped_details.push(SkipDetail {
+                    path: note.path.clone(),
+                    reason: format!("embedded file not found: {}", link.target),
+                }),
+                None => {}
+            }
+            None
+        });
+    }
+
+    Ok(ParsedVault {
+        folder,
+        format,
+        total_markdown_files: note_paths.len(),
+        ignored_notes,
+        notes,
+        assets,
+        index,
+        skipped_details,
+    })
+}
+
+/// `.obsidian` 所在目录是 vault 根目录；没有时如果所有文件都在同一个顶层目录下，就以它为根
+fn vault_root(names: &[String]) -> String {
+    let config_root = names
+        .iter()
+        .filter_map(|v| {
+            let idx = v.find(".obsidian/")?;
+            (idx == 0 || v[..idx].ends_with('/')).then(|| v[..idx].to_string())
+        })
+        .min_by_key(|v| v.len());
+    if let Some(root) = config_root {
+        return root;
+    }
+    let mut tops = names.iter().map(|v| v.split_once('/').map(|(top, _)| top));
+    match tops.next() {
+        Some(Some(first)) if tops.all(|v| v == Some(first)) => format!("{}/", first),
+        _ => String::new(),
+    }
+}
+
+fn load_settings(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    files: &BTreeMap<String, usize>,
+) -> DailyNotesSettings {
+    let mut read = |path: &str| read_text(archive, *files.get(path)?).ok();
+    if let Some(settings) = read(PERIODIC_NOTES_SETTINGS)
+        .and_then(|v| serde_json::from_str::<PeriodicNotesSettings>(&v).ok())
+        .and_then(|v| v.daily)
+        .filter(|v| v.format.as_deref().is_some_and(|f| !f.trim().is_empty()))
+    {
+        return settings;
+    }
+    read(DAILY_NOTES_SETTINGS)
+        .and_then(|v| serde_json::from_str::<DailyNotesSettings>(&v).ok())
+        .unwrap_or_default()
+}
+
+fn read_text(archive: &mut ZipArchive<Cursor<&[u8]>>, idx: usize) -> Result<String, String> {
+    let mut file = archive
+        .by_index(idx)
+        .map_err(|_| "read zip entry failed".to_string())?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)
+        .map_err(|_| "read markdown content failed".to_string())?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+fn read_asset(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    idx: usize,
+    size_limit: usize,
+) -> Result<Vec<u8>, String> {
+    let mut file = archive
+        .by_index(idx)
+        .map_err(|_| "read zip entry failed".to_string())?;
+    if file.size() > size_limit as u64 {
+        return Err("attachment exceeds upload_file_limit".to_string());
+    }
+    let mut buf = Vec::with_capacity(file.size() as usize);
+    file.read_to_end(&mut buf)
+        .map_err(|_| "read attachment failed".to_string())?;
+    Ok(buf)
+}
+
+/// moment.js 格式转成匹配 vault 相对路径的正则，支持年月日和星期的常用标记，`[...]` 中是原样文字
+fn daily_note_regex(folder: &str, format: &str) -> Result<Regex, String> {
+    const TOKENS: [(&str, &str, Option<&str>); 16] = [
+        ("YYYY", r"\d{4}", Some("year")),
+        ("YY", r"\d{2}", Some("yy")),
+        ("MMMM", r"[^\W\d_]+", Some("month_name")),
+        ("MMM", r"[^\W\d_]+", Some("month_name")),
+        ("MM", r"\d{2}", Some("month")),
+        ("M", r"\d{1,2}", Some("month")),
+        ("Do", r"\d{1,2}", Some("day")),
+        ("DD", r"\d{2}", Some("day")),
+        ("D", r"\d{1,2}", Some("day")),
+        ("dddd", r"[^\W\d_]+", None),
+        ("ddd", r"[^\W\d_]+", None),
+        ("dd", r"[^\W\d_]+", None),
+        ("d", r"\d", None),
+        ("ww", r"\d{2}", None),
+        ("w", r"\d{1,2}", None),
+        ("Q", r"\d", None),
+    ];
+    let mut pattern = String::from("^");
+    if !folder.is_empty() {
+        pattern.push_str(&regex::escape(folder));
+        pattern.push('/');
+    }
+    // (正则, 字段名)，字段名为 None 的是原样文字或不关心的标记
+    let mut parts: Vec<(String, Option<&str>)> = Vec::new();
+    let mut rest = format;
+    while let Some(c) = rest.chars().next() {
+        if c == '[' {
+            let end = rest.find(']').unwrap_or(rest.len());
+            parts.push((regex::escape(&rest[1..end]), None));
+            rest = rest.get(end + 1..).unwrap_or("");
+            continue;
+        }
+        if let Some((token, re, name)) = TOKENS.iter().find(|(t, _, _)| rest.starts_with(t)) {
+            parts.push((re.to_string(), *name));
+            if *token == "Do" {
+                parts.push(("(?:st|nd|rd|th)".to_string(), None));
+            }
+            rest = &rest[token.len()..];
+            continue;
+        }
+        parts.push((regex::escape(&c.to_string()), None));
+        rest = &rest[c.len_utf8()..];
+    }
+    // `YYYY/MM/YYYY-MM-DD` 这种目录和文件名都有日期的，以最后出现的（文件名中的）为准
+    let mut seen = HashSet::new();
+    let mut groups = Vec::with_capacity(parts.len());
+    for (re, name) in parts.into_iter().rev() {
+        groups.push(match name.filter(|v| seen.insert(*v)) {
+            Some(name) => format!("(?P<{}>{})", name, re),
+            None if name.is_some() => format!("(?:{})", re),
+            None => re,
+        });
+    }
+    groups.reverse();
+    pattern.push_str(&groups.concat());
+    pattern.push_str(r"\.(?i:md)$");
+
+    let has_year = seen.contains("year") || seen.contains("yy");
+    let has_month = seen.contains("month") || seen.contains("month_name");
+    if !has_year || !has_month || !seen.contains("day") {
+        return Err(format!(
+            "daily note format must contain year, month and day: {}",
+            format
+        ));
+    }
+    Regex::new(&pattern).map_err(|e| format!("invalid daily note format {}: {}", format, e))
+}
+
+fn captured_date(caps: &Captures) -> Option<String> {
+    let year = match (caps.name("year"), caps.name("yy")) {
+        (Some(v), _) => v.as_str().parse::<i64>().ok()?,
+        (None, Some(v)) => 2000 + v.as_str().parse::<i64>().ok()?,
+        _ => return None,
+    };
+    let month = match caps.name("month") {
+        Some(v) => v.as_str().parse::<i64>().ok()?,
+        None => {
+            let name = caps.name("month_name")?.as_str().to_lowercase();
+            MONTHS.iter().position(|m| name.starts_with(m))? as i64 + 1
+        }
+    };
+    let day = caps.name("day")?.as_str().parse::<i64>().ok()?;
+    let date = format!("{:04}-{:02}-{:02}", year, month, day);
+    // 2 月 30 日这种日期换算回来会变成另一天
+    let days = date_util::parse_date(&date)?;
+    (date_util::date_from_days(days) == date).then_some(date)
+}
+
+impl AssetIndex {
+    fn insert(&mut self, path: &str) {
+        self.by_path.insert(path.to_lowercase(), path.to_string());
+        let name = file_name_of(path).to_lowercase();
+        match self.by_name.get(&name) {
+            Some(old) if old.len() <= path.len() => {}
+            _ => {
+                self.by_name.insert(name, path.to_string());
+            }
+        }
+    }
+
+    /// 依次按相对笔记所在目录、相对 vault 根目录、文件名查找
+    fn resolve(&self, target: &str, note_dir: &str) -> Option<&str> {
+        let target = target
+            .trim()
+            .trim_start_matches("./")
+            .trim_start_matches('/');
+        if target.is_empty() {
+            return None;
+        }
+        let lower = target.to_lowercase();
+        let relative = (!note_dir.is_empty())
+            .then(|| {
+                self.by_path
+                    .get(&format!("{}/{}", note_dir.to_lowercase(), lower))
+            })
+            .flatten();
+        relative
+            .or_else(|| self.by_path.get(&lower))
+            .or_else(|| self.by_name.get(file_name_of(&lower)))
+            .map(String::as_str)
+    }
+}
+
+impl<'a> WikiLink<'a> {
+    fn parse(inner: &'a str, embed: bool) -> Option<WikiLink<'a>> {
+        if inner.contains('[') {
+            return None;
+        }
+        let (target, alias) = match inner.split_once('|') {
+            Some((target, alias)) => (target, Some(alias.trim())),
+            None => (inner, None),
+        };
+        let (target, anchor) = match target.split_once('#') {
+            Some((target, anchor)) => (target.trim(), Some(anchor.trim())),
+            None => (target.trim(), None),
+        };
+        if target.is_empty() && anchor.is_none_or(str::is_empty) {
+            return None;
+        }
+        Some(WikiLink {
+            embed,
+            target,
+            anchor,
+            alias,
+        })
+    }
+
+    /// 有扩展名且不是 `.md` 的是附件，其余是笔记
+    fn is_asset(&self) -> bool {
+        file_name_of(self.target)
+            .rsplit_once('.')
+            .is_some_and(|(_, ext)| !ext.eq_ignore_ascii_case("md"))
+    }
+
+    /// 链接显示的文字：别名，或者笔记名加标题
+    fn display(&self) -> String {
+        if let Some(alias) = self.alias.filter(|v| !v.is_empty()) {
+            return alias.to_string();
+        }
+        let name = file_name_of(self.target);
+        let name = name.strip_suffix(".md").unwrap_or(name);
+        match self.anchor.filter(|v| !v.is_empty() && !v.starts_with('^')) {
+            Some(heading) if name.is_empty() => heading.to_string(),
+            Some(heading) => format!("{} > {}", name, heading),
+            None => name.to_string(),
+        }
+    }
+}
+
+/// 图片嵌入成 `![alt](uri)`，其他附件成普通链接；`|300` `|300x200` 这样的别名是显示尺寸，不作为 alt
+fn asset_markdown(link: &WikiLink, path: &str, uri: &str) -> String {
+    let is_size = |v: &str| {
+        v.split('x')
+            .all(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+    };
+    let name = file_name_of(path);
+    let label = link
+        .alias
+        .filter(|v| !v.is_empty() && !is_size(v))
+        .unwrap_or_else(|| name.rsplit_once('.').map_or(name, |(stem, _)| stem));
+    if mime_of(path).starts_with("image/") {
+        format!("![{}]({})", label, uri)
+    } else {
+        format!("[{}]({})", label, uri)
+    }
+}
+
+/// 逐个替换正文中的 `[[...]]` 和 `![[...]]`，`f` 返回 None 时保留原文；代码块和行内代码中的不处理
+fn rewrite_links(content: &str, mut f: impl FnMut(&WikiLink) -> Option<String>) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut fence: Option<&str> = None;
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            out.push_str(line);
+            continue;
+        }
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            fence = Some(marker);
+            out.push_str(line);
+            continue;
+        }
+        // 反引号之间是行内代码
+        for (i, part) in line.split('`').enumerate() {
+            if i > 0 {
+                out.push('`');
+            }
+            if i % 2 == 1 {
+                out.push_str(part);
+            } else {
+                rewrite_segment(part, &mut f, &mut out);
+            }
+        }
+    }
+    out
+}
+
+fn rewrite_segment(text: &str, f: &mut impl FnMut(&WikiLink) -> Option<String>, out: &mut String) {
+    let mut rest = text;
+    while let Some(start) = rest.find("[[") {
+        let Some(len) = rest[start + 2..].find("]]") else {
+            break;
+        };
+        let end = start + 2 + len + 2;
+        let embed = rest[..start].ends_with('!');
+        let link_start = if embed { start - 1 } else { start };
+        out.push_str(&rest[..link_start]);
+        match WikiLink::parse(&rest[start + 2..end - 2], embed).and_then(|link| f(&link)) {
+            Some(v) => out.push_str(&v),
+            None => out.push_str(&rest[link_start..end]),
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+}
+
+fn file_name_of(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn parent_dir(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+fn mime_of(path: &str) -> &'static str {
+    let ext = file_name_of(path)
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "avif" => "image/avif",
+        "mp4" | "m4v" => "video/mp4",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
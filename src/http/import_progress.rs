@@ -0,0 +1,211 @@
+use crate::app_state::AppState;
+use crate::http::import_zip::{ImportJournalResp, SkipDetail};
+use crate::http::resp::{ApiCode, ApiResponse};
+use crate::job;
+use crate::util::date_util;
+use axum::extract::{Path, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use futures_util::{StreamExt, stream};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{LazyLock, Mutex};
+use tokio::sync::broadcast;
+
+/// 导入结束后进度保留这么久，客户端晚一点连上也能拿到结果
+const KEEP_SECS: i64 = 3600;
+const CHANNEL_SIZE: usize = 256;
+
+static IMPORTS: LazyLock<Mutex<HashMap<i64, Progress>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// SSE 事件，`event` 字段是 `name()`，`data` 是 json
+#[derive(Debug, Clone)]
+pub enum ImportEvent {
+    /// 压缩包中 markdown 文件的个数
+    Started {
+        total: usize,
+    },
+    /// 解析完一个 markdown 文件
+    File {
+        path: String,
+        processed: usize,
+        total: usize,
+    },
+    Skipped(SkipDetail),
+    /// 和同步导入接口的返回相同
+    Finished(ImportJournalResp),
+    Failed {
+        message: String,
+    },
+    /// 服务重启过，进度已经不在内存中
+    Expired {
+        status: String,
+    },
+}
+
+impl ImportEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            ImportEvent::Started { .. } => "started",
+            ImportEvent::File { .. } => "file",
+            ImportEvent::Skipped(_) => "skipped",
+            ImportEvent::Finished(_) => "finished",
+            ImportEvent::Failed { .. } => "failed",
+            ImportEvent::Expired { .. } => "expired",
+        }
+    }
+
+    fn is_final(&self) -> bool {
+        matches!(
+            self,
+            ImportEvent::Finished(_) | ImportEvent::Failed { .. } | ImportEvent::Expired { .. }
+        )
+    }
+
+    fn to_sse(&self) -> Event {
+        let data = match self {
+            ImportEvent::Started { total } => serde_json::json!({ "total": total }),
+            ImportEvent::File {
+                path,
+                processed,
+                total,
+            } => serde_json::json!({ "path": path, "processed": processed, "total": total }),
+            ImportEvent::Skipped(v) => serde_json::to_value(v).unwrap_or_default(),
+            ImportEvent::Finished(v) => serde_json::to_value(v).unwrap_or_default(),
+            ImportEvent::Failed { message } => serde_json::json!({ "message": message }),
+            ImportEvent::Expired { status } => serde_json::json!({ "status": status }),
+        };
+        Event::default().event(self.name()).data(data.to_string())
+    }
+}
+
+struct Progress {
+    /// 已发出的事件，新连上的客户端先收到这些
+    history: Vec<ImportEvent>,
+    tx: broadcast::Sender<ImportEvent>,
+    finish_time: Option<i64>,
+}
+
+impl Progress {
+    fn new() -> Progress {
+        Progress {
+            history: Vec::new(),
+            tx: broadcast::channel(CHANNEL_SIZE).0,
+            finish_time: None,
+        }
+    }
+}
+
+/// 某个导入任务的进度出口，可以在 rayon 线程中使用
+#[derive(Debug, Clone, Copy)]
+pub struct ProgressSink {
+    job_id: i64,
+}
+
+impl ProgressSink {
+    /// 在导入任务中创建，顺带清理早已结束的进度
+    pub fn for_current_job() -> Option<ProgressSink> {
+        let job_id = job::current_id()?;
+        let now = date_util::now_secs();
+        let mut imports = IMPORTS.lock().unwrap();
+        imports.retain(|_, v| v.finish_time.is_none_or(|t| t > now - KEEP_SECS));
+        // 任务开始前连上的客户端已经订阅了这个 channel，保留它；失败后重跑时丢掉上一次的事件
+        let progress = imports.entry(job_id).or_insert_with(Progress::new);
+        if progress.finish_time.take().is_some() {
+            progress.history.clear();
+        }
+        Some(ProgressSink { job_id })
+    }
+
+    pub fn emit(&self, event: ImportEvent) {
+        let mut imports = IMPORTS.lock().unwrap();
+        let Some(progress) = imports.get_mut(&self.job_id) else {
+            return;
+        };
+        if event.is_final() {
+            progress.finish_time = Some(date_util::now_secs());
+        }
+        // 没有客户端连着时 send 返回 Err，忽略即可
+        let _ = progress.tx.send(event.clone());
+        progress.history.push(event);
+    }
+}
+
+/// 后台导入的进度，依次推送 `started` `file` `skipped`，最后是 `finished` 或 `failed`；
+/// 连上时导入已经开始或结束，会先补发之前的事件
+#[utoipa::path(
+    get,
+    path = "/journal/import/{job_id}/events",
+    tag = "import",
+    params(("job_id" = i64, Path)),
+    responses(
+        (status = 200, description = "`text/event-stream`，`finished` 事件的 data 是 `ImportJournalResp`", content_type = "text/event-stream"),
+    )
+)]
+pub async fn import_events(State(state): State<AppState>, Path(job_id): Path<i64>) -> Response {
+    let subscribed = {
+        let imports = IMPORTS.lock().unwrap();
+        imports
+            .get(&job_id)
+            .map(|v| (v.history.clone(), v.tx.subscribe()))
+    };
+    let (history, rx) = match subscribed {
+        Some((history, rx)) => (history, Some(rx)),
+        None => {
+            let record = match job::get(&state, job_id).await {
+                Ok(Some(v)) if v.kind == "zip_import" => v,
+                Ok(_) => {
+                    return ApiResponse::<()>::err(ApiCode::NotFound, "import job not found")
+                        .into_response();
+                }
+                Err(_) => {
+                    return ApiResponse::<()>::err(ApiCode::DbQueryFailed, "db query failed")
+                        .into_response();
+                }
+            };
+            let event = match record.status.as_str() {
+                // 还没轮到执行或刚开始
+                job::STATUS_IDLE | job::STATUS_RUNNING => None,
+                job::STATUS_FAILED => Some(ImportEvent::Failed {
+                    message: record.last_error.unwrap_or_default(),
+                }),
+                _ => Some(ImportEvent::Expired {
+                    status: record.status,
+                }),
+            };
+            match event {
+                Some(event) => (vec![event], None),
+                None => {
+                    // 任务开始时才会创建进度，先占位订阅
+                    let mut imports = IMPORTS.lock().unwrap();
+                    let progress = imports.entry(job_id).or_insert_with(Progress::new);
+                    (progress.history.clone(), Some(progress.tx.subscribe()))
+                }
+            }
+        }
+    };
+
+    let finished = history.last().is_some_and(ImportEvent::is_final);
+    let replay = stream::iter(history);
+    let live = stream::unfold(rx.filter(|_| !finished), |rx| async move {
+        let mut rx = rx?;
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    let next = (!event.is_final()).then_some(rx);
+                    return Some((event, next));
+                }
+                // 客户端太慢时丢掉中间的进度，最终结果仍会送达
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    let events = replay
+        .chain(live)
+        .map(|event| Ok::<_, Infallible>(event.to_sse()));
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
@@ -0,0 +1,504 @@
+use crate::app_state::AppState;
+use crate::http::file;
+use crate::http::import_zip::SkipDetail;
+use crate::http::journal::{self, UpsertEntry};
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult, multipart_read_code};
+use crate::util::outbound;
+use axum::body::Bytes;
+use axum::extract::{Multipart, State};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+const BLOGGER_KIND_POST: &str = "http://schemas.google.com/blogger/2008/kind#post";
+const WP_NS: &str = "http://wordpress.org/export/";
+const CONTENT_NS: &str = "http://purl.org/rss/1.0/modules/content/";
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportWordpressResp {
+    pub source: String,
+    pub date_field: String,
+    pub total_posts: usize,
+    pub imported_posts: usize,
+    pub imported_days: usize,
+    pub media_count: usize,
+    pub skipped_count: usize,
+    pub skipped_details: Vec<SkipDetail>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateField {
+    Publish,
+    Modified,
+}
+
+impl DateField {
+    fn parse(raw: &str) -> Option<DateField> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "" | "publish" | "published" | "post_date" => Some(DateField::Publish),
+            "modified" | "updated" | "post_modified" => Some(DateField::Modified),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            DateField::Publish => "publish",
+            DateField::Modified => "modified",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ParsedPost {
+    /// 标题或链接，用于跳过原因
+    label: String,
+    title: String,
+    date: String,
+    html: String,
+}
+
+#[derive(Debug)]
+struct ParseFeedResult {
+    source: &'static str,
+    total_posts: usize,
+    posts: Vec<ParsedPost>,
+    skipped_details: Vec<SkipDetail>,
+}
+
+/// 导入 WordPress 导出的 WXR 或 Blogger 导出的 Atom 文件，同一天的多篇文章合并为一篇日记
+#[utoipa::path(
+    post,
+    path = "/journal/import/wordpress",
+    tag = "import",
+    request_body(
+        content_type = "multipart/form-data",
+        description = "`file`: 导出的 xml；`date_field`: `publish`（默认）或 `modified`；`fetch_media`: 是否下载文章中的图片，默认 true"
+    ),
+    responses((status = 200, body = ApiResponse<ImportWordpressResp>))
+)]
+pub async fn import_wordpress(
+    State(state): State<AppState>,
+    mut multipart: Multipart,
+) -> ApiResult<ImportWordpressResp> {
+    let mut xml_file: Option<Vec<u8>> = None;
+    let mut date_field_raw: Option<String> = None;
+    let mut fetch_media_raw: Option<String> = None;
+
+    while let Some(field) = multipart.next_field().await.map_err(|_| {
+        ApiResponse::<ImportWordpressResp>::err(ApiCode::BadRequest, "invalid multipart data")
+    })? {
+        let name = field.name().unwrap_or("").to_string();
+
+        if name == "file" || field.file_name().is_some() {
+            xml_file = Some(
+                field
+                    .bytes()
+                    .await
+                    .map_err(|e| {
+                        ApiResponse::<ImportWordpressResp>::err(
+                            multipart_read_code(&e),
+                            "read export file failed",
+                        )
+                    })?
+                    .to_vec(),
+            );
+        } else if name == "date_field" {
+            date_field_raw = Some(field.text().await.map_err(|_| {
+                ApiResponse::<ImportWordpressResp>::err(
+                    ApiCode::BadRequest,
+                    "read date_field failed",
+                )
+            })?);
+        } else if name == "fetch_media" {
+            fetch_media_raw = Some(field.text().await.map_err(|_| {
+                ApiResponse::<ImportWordpressResp>::err(
+                    ApiCode::BadRequest,
+                    "read fetch_media failed",
+                )
+            })?);
+        }
+    }
+
+    let xml_file = xml_file.ok_or_else(|| {
+        ApiResponse::<ImportWordpressResp>::err(ApiCode::FileMissing, "export file required")
+    })?;
+    let date_field =
+        DateField::parse(date_field_raw.as_deref().unwrap_or("")).ok_or_else(|| {
+            ApiResponse::<ImportWordpressResp>::err(
+                ApiCode::Validation,
+                "date_field must be publish or modified",
+            )
+        })?;
+    let fetch_media = !matches!(
+        fetch_media_raw.as_deref().map(str::trim),
+        Some("false") | Some("0")
+    );
+
+    let parse_result = state
+        .blocking
+        .run("wordpress import parse", move || {
+            parse_feed(&xml_file, date_field)
+        })
+        .await
+        .map_err(|_| {
+            ApiResponse::<ImportWordpressResp>::err(ApiCode::BadRequest, "parse export task failed")
+        })?
+        .map_err(|msg| ApiResponse::<ImportWordpressResp>::err(ApiCode::BadRequest, &msg))?;
+
+    let mut media_cache: HashMap<String, String> = HashMap::new();
+    let mut skipped_details = parse_result.skipped_details;
+    let imported_posts = parse_result.posts.len();
+
+    // 同一天的文章按导出顺序合并
+    let mut days: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for post in parse_result.posts {
+        let mut markdown = html_to_markdown(&post.html);
+        if fetch_media {
+            for src in image_sources(&post.html) {
+                if !markdown.contains(&src) {
+                    continue;
+                }
+                let uri = match media_cache.get(&src) {
+                    Some(v) => v.clone(),
+                    None => match fetch_media_file(&state, &src).await {
+                        Ok(uri) => {
+                            media_cache.insert(src.clone(), uri.clone());
+                            uri
+                        }
+                        Err(reason) => {
+                            warn!("wordpress import media skipped: {} => {}", src, reason);
+                            skipped_details.push(SkipDetail {
+                                path: src.clone(),
+                                reason,
+                            });
+                            continue;
+                        }
+                    },
+                };
+                markdown = markdown.replace(&src, &uri);
+            }
+        }
+        let section = if post.title.is_empty() {
+            markdown.trim().to_string()
+        } else {
+            format!("## {}\n\n{}", post.title, markdown.trim())
+        };
+        info!("wordpress import post date={} {}", post.date, post.label);
+        days.entry(post.date).or_default().push(section);
+    }
+
+    let entries = days
+        .into_iter()
+        .map(|(date, sections)| UpsertEntry {
+            date,
+            content: sections.join("\n\n"),
+            metadata: None,
+            metadata_patch: None,
+        })
+        .collect::<Vec<_>>();
+    let report = journal::upsert_by_date_batch(&state, &entries, "wordpress import").await;
+    let imported_days = report.upserted;
+    for idx in report.failed {
+        let detail = SkipDetail {
+            path: entries[idx].date.clone(),
+            reason: "db upsert failed".to_string(),
+        };
+        warn!(
+            "wordpress import skipped: {} => {}",
+            detail.path, detail.reason
+        );
+        skipped_details.push(detail);
+    }
+
+    let resp = ImportWordpressResp {
+        source: parse_result.source.to_string(),
+        date_field: date_field.name().to_string(),
+        total_posts: parse_result.total_posts,
+        imported_posts,
+        imported_days,
+        media_count: media_cache.len(),
+        skipped_count: skipped_details.len(),
+        skipped_details,
+    };
+    info!(
+        "导入博客完成 source={}, posts={}, imported_posts={}, days={}, media={}, skipped={}",
+        resp.source,
+        resp.total_posts,
+        resp.imported_posts,
+        resp.imported_days,
+        resp.media_count,
+        resp.skipped_count
+    );
+    Ok(ApiResponse::ok(resp))
+}
+
+fn parse_feed(bytes: &[u8], date_field: DateField) -> Result<ParseFeedResult, String> {
+    let text = std::str::from_utf8(bytes).map_err(|_| "export file must be utf-8".to_string())?;
+    let doc = roxmltree::Document::parse_with_options(
+        text,
+        roxmltree::ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        },
+    )
+    .map_err(|e| format!("invalid xml: {}", e))?;
+    let root = doc.root_element();
+    match root.tag_name().name() {
+        "rss" => Ok(parse_wxr(root, date_field)),
+        "feed" => Ok(parse_blogger(root, date_field)),
+        other => Err(format!(
+            "unsupported export root <{}>, expect WordPress WXR or Blogger Atom",
+            other
+        )),
+    }
+}
+
+fn parse_wxr(root: roxmltree::Node, date_field: DateField) -> ParseFeedResult {
+    let mut posts = Vec::new();
+    let mut skipped_details = Vec::new();
+    let mut total_posts = 0usize;
+    let items = root
+        .descendants()
+        .filter(|n| n.has_tag_name("item") && n.tag_name().namespace().is_none());
+    for item in items {
+        if child_text(item, Some(WP_NS), "post_type") != "post" {
+            continue;
+        }
+        total_posts += 1;
+        let title = child_text(item, None, "title").trim().to_string();
+        let label = label_of(&title, &child_text(item, None, "link"));
+        let status = child_text(item, Some(WP_NS), "status");
+        if matches!(
+            status.as_str(),
+            "draft" | "auto-draft" | "trash" | "inherit"
+        ) {
+            skipped_details.push(SkipDetail {
+                path: label,
+                reason: format!("status {}", status),
+            });
+            continue;
+        }
+        let raw_date = match date_field {
+            DateField::Publish => child_text(item, Some(WP_NS), "post_date"),
+            DateField::Modified => child_text(item, Some(WP_NS), "post_modified"),
+        };
+        let Some(date) = leading_date(&raw_date) else {
+            skipped_details.push(SkipDetail {
+                path: label,
+                reason: format!("invalid date '{}'", raw_date),
+            });
+            continue;
+        };
+        let html = child_text(item, Some(CONTENT_NS), "encoded");
+        posts.push(ParsedPost {
+            label,
+            title,
+            date,
+            html: autop(&html),
+        });
+    }
+    ParseFeedResult {
+        source: "wordpress",
+        total_posts,
+        posts,
+        skipped_details,
+    }
+}
+
+fn parse_blogger(root: roxmltree::Node, date_field: DateField) -> ParseFeedResult {
+    let mut posts = Vec::new();
+    let mut skipped_details = Vec::new();
+    let mut total_posts = 0usize;
+    for entry in root.children().filter(|n| n.has_tag_name("entry")) {
+        let is_post = entry.children().any(|n| {
+            n.has_tag_name("category")
+                && n.attribute("scheme")
+                    .is_some_and(|v| v.ends_with("/kind") || v.ends_with("#kind"))
+                && n.attribute("term") == Some(BLOGGER_KIND_POST)
+        });
+        if !is_post {
+            continue;
+        }
+        total_posts += 1;
+        let title = child_text(entry, None, "title").trim().to_string();
+        let label = label_of(&title, &child_text(entry, None, "id"));
+        let is_draft = entry
+            .descendants()
+            .any(|n| n.has_tag_name("draft") && n.text().map(str::trim) == Some("yes"));
+        if is_draft {
+            skipped_details.push(SkipDetail {
+                path: label,
+                reason: "status draft".to_string(),
+            });
+            continue;
+        }
+        let raw_date = match date_field {
+            DateField::Publish => child_text(entry, None, "published"),
+            DateField::Modified => child_text(entry, None, "updated"),
+        };
+        let Some(date) = leading_date(&raw_date) else {
+            skipped_details.push(SkipDetail {
+                path: label,
+                reason: format!("invalid date '{}'", raw_date),
+            });
+            continue;
+        };
+        posts.push(ParsedPost {
+            label,
+            title,
+            date,
+            html: child_text(entry, None, "content"),
+        });
+    }
+    ParseFeedResult {
+        source: "blogger",
+        total_posts,
+        posts,
+        skipped_details,
+    }
+}
+
+/// 直接子节点的文本，`ns` 按前缀匹配（wxr 的命名空间带版本号），
+/// 为 None 时只匹配无命名空间或 atom 默认命名空间的节点
+fn child_text(node: roxmltree::Node, ns: Option<&str>, name: &str) -> String {
+    node.children()
+        .find(|n| {
+            n.is_element()
+                && n.tag_name().name() == name
+                && match ns {
+                    Some(ns) => n.tag_name().namespace().is_some_and(|v| v.starts_with(ns)),
+                    None => n
+                        .tag_name()
+                        .namespace()
+                        .is_none_or(|v| v == "http://www.w3.org/2005/Atom"),
+                }
+        })
+        .map(|n| {
+            n.children()
+                .filter_map(|c| c.text())
+                .collect::<Vec<_>>()
+                .concat()
+        })
+        .unwrap_or_default()
+}
+
+fn label_of(title: &str, fallback: &str) -> String {
+    if title.is_empty() {
+        fallback.trim().to_string()
+    } else {
+        title.to_string()
+    }
+}
+
+/// 取 `2024-01-05 10:00:00` / `2024-01-05T10:00:00.000-08:00` 开头的日期，保留作者本地日期
+fn leading_date(raw: &str) -> Option<String> {
+    let date = raw.trim().get(0..10)?;
+    let (y, rest) = date.split_once('-')?;
+    let (m, d) = rest.split_once('-')?;
+    let valid = y.len() == 4
+        && m.len() == 2
+        && d.len() == 2
+        && [y, m, d]
+            .iter()
+            .all(|v| v.chars().all(|c| c.is_ascii_digit()))
+        && (1..=12).contains(&m.parse::<u32>().ok()?)
+        && (1..=31).contains(&d.parse::<u32>().ok()?)
+        && y != "0000";
+    valid.then(|| date.to_string())
+}
+
+/// WordPress 正文通常没有 `<p>`，按空行分段，否则转 markdown 时换行会丢失
+fn autop(html: &str) -> String {
+    if html.contains("<p") || !html.contains('\n') {
+        return html.to_string();
+    }
+    html.replace("\r\n", "\n")
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(|v| format!("<p>{}</p>", v.replace('\n', "<br>")))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn html_to_markdown(html: &str) -> String {
+    html2md::parse_html(html)
+}
+
+/// 提取 html 中 `<img src>` 引用的远程地址
+fn image_sources(html: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let lower = html.to_ascii_lowercase();
+    let mut pos = 0usize;
+    while let Some(start) = lower[pos..].find("<img") {
+        let tag_start = pos + start;
+        let tag_end = lower[tag_start..]
+            .find('>')
+            .map(|v| tag_start + v)
+            .unwrap_or(lower.len());
+        let tag = &html[tag_start..tag_end];
+        let tag_lower = &lower[tag_start..tag_end];
+        if let Some(idx) = tag_lower.find("src=") {
+            let rest = &tag[idx + 4..];
+            let src = match rest.chars().next() {
+                Some(q @ ('"' | '\'')) => rest[1..].split(q).next().unwrap_or(""),
+                _ => rest.split([' ', '/', '>']).next().unwrap_or(""),
+            };
+            let src = src.replace("&amp;", "&");
+            if (src.starts_with("http://") || src.starts_with("https://")) && !out.contains(&src) {
+                out.push(src);
+            }
+        }
+        pos = tag_end;
+    }
+    out
+}
+
+async fn fetch_media_file(state: &AppState, url: &str) -> Result<String, String> {
+    let mut resp = state
+        .outbound
+        .get(url)
+        .map_err(|e| format!("download refused: {}", e))?
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("download failed: {}", outbound::error_message(e)))?;
+    let mime = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.split(';').next().unwrap_or(v).trim().to_string())
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    // 先看 `Content-Length`，再边读边检查，超过 `upload_file_limit` 立即中止，不把整个响应读进内存
+    let limit = state.config.upload_file_limit;
+    if resp.content_length().is_some_and(|v| v > limit as u64) {
+        return Err("media exceeds upload_file_limit".to_string());
+    }
+    let mut buf = Vec::new();
+    while let Some(chunk) = resp
+        .chunk()
+        .await
+        .map_err(|e| format!("download failed: {}", outbound::error_message(e)))?
+    {
+        if buf.len() + chunk.len() > limit {
+            return Err("media exceeds upload_file_limit".to_string());
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    let bytes = Bytes::from(buf);
+    let name = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|v| v.rsplit('/').next())
+        .unwrap_or("");
+    let mut name = file::sanitize_file_name(name);
+    if name.is_empty() {
+        name = "media".to_string();
+    }
+    file::store_file(state, &name, &mime, bytes)
+        .await
+        .map_err(|(_, msg)| msg.to_string())
+}
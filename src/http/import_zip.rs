@@ -1,17 +1,28 @@
 use crate::app_state::AppState;
-use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::error::DayLogError;
+use crate::http::date_pattern::{self, ImportPattern, PathFields, PathMatch};
+use crate::http::import_progress::{ImportEvent, ProgressSink};
+use crate::http::journal::{self, JournalMetadata, UpsertEntry};
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult, multipart_read_code};
 use crate::http::settings;
 use crate::http::settings::DatePlaceholders;
+use crate::job::{self, JobFuture};
+use crate::util::{date_util, file_util, front_matter, token};
 use axum::extract::{Multipart, State};
-use serde::Serialize;
-use std::collections::HashSet;
+use encoding_rs::Encoding;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::io::{Cursor, Read};
-use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::task;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tracing::{info, warn};
+use utoipa::ToSchema;
 use zip::ZipArchive;
 
-#[derive(Debug, Serialize)]
+const IMPORT_JOB: &str = "zip_import";
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ImportJournalResp {
     pub total_markdown_files: usize,
@@ -21,9 +32,63 @@ pub struct ImportJournalResp {
     pub skipped_paths: Vec<String>,
     pub skipped_details: Vec<SkipDetail>,
     pub patterns: Vec<String>,
+    pub strategy: ImportStrategy,
+    /// 为 true 时只解析不写库，`importedCount` 是将会写入的篇数
+    pub dry_run: bool,
+    pub encoding: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportJobResp {
+    pub job_id: i64,
+}
+
+/// 检查过的导入参数，后台导入时随任务保存
+#[derive(Debug, Serialize, Deserialize)]
+struct ImportPlan {
+    strategy: ImportStrategy,
+    patterns: Vec<String>,
+    dry_run: bool,
+    encoding: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ImportJobPayload {
+    path: String,
+    #[serde(flatten)]
+    plan: ImportPlan,
+}
+
+/// multipart 中 `options` 字段的 json，例如
+/// `{"strategy":"skip","patterns":["{yyyy}/{MM}/{dd}.md"],"dryRun":true,"encoding":"gbk"}`，
+/// 未知字段和非法取值都返回 400；没有 `options` 时仍然读取旧的 `patterns` 字段
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ImportOptions {
+    #[serde(default)]
+    pub strategy: ImportStrategy,
+    pub patterns: Option<Vec<String>>,
+    #[serde(default)]
+    pub dry_run: bool,
+    /// WHATWG 编码名，例如 `utf-8` `gbk` `utf-16le`，默认 utf-8
+    pub encoding: Option<String>,
+}
+
+/// 同一天已有日记时的处理方式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStrategy {
+    /// 以导入内容覆盖
+    #[default]
+    Overwrite,
+    /// 保留已有日记，跳过导入
+    Skip,
+    /// 追加到已有内容之后，中间空一行
+    Append,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SkipDetail {
     pub path: String,
@@ -35,6 +100,7 @@ struct ParsedEntry {
     path: String,
     date: String,
     content: String,
+    fields: PathFields,
 }
 
 #[derive(Debug)]
@@ -45,16 +111,130 @@ struct ParseZipResult {
     skipped_details: Vec<SkipDetail>,
 }
 
+/// 导入 markdown 压缩包，按 `patterns` 从路径中识别日期
+#[utoipa::path(
+    post,
+    path = "/journal/import/zip",
+    tag = "import",
+    request_body(
+        content_type = "multipart/form-data",
+        description = "`file`: zip 压缩包；`options`: `ImportOptions` 的 json"
+    ),
+    responses((status = 200, body = ApiResponse<ImportJournalResp>))
+)]
 pub async fn import_journal_zip(
     State(state): State<AppState>,
-    mut multipart: Multipart,
+    multipart: Multipart,
 ) -> ApiResult<ImportJournalResp> {
+    let (zip_file, plan) = read_request(&state, multipart)
+        .await
+        .map_err(|(code, msg)| ApiResponse::<ImportJournalResp>::err(code, &msg))?;
+    let resp = run_import(&state, zip_file, &plan, None).await?;
+    Ok(ApiResponse::ok(resp))
+}
+
+/// 参数和同步导入相同，检查通过后把压缩包存到临时目录并在后台导入，
+/// 进度通过 `GET /journal/import/{jobId}/events` 获取
+#[utoipa::path(
+    post,
+    path = "/journal/import/zip/async",
+    tag = "import",
+    request_body(
+        content_type = "multipart/form-data",
+        description = "和 `POST /journal/import/zip` 相同"
+    ),
+    responses((status = 200, body = ApiResponse<ImportJobResp>))
+)]
+pub async fn import_journal_zip_async(
+    State(state): State<AppState>,
+    multipart: Multipart,
+) -> ApiResult<ImportJobResp> {
+    let (zip_file, plan) = read_request(&state, multipart)
+        .await
+        .map_err(|(code, msg)| ApiResponse::<ImportJobResp>::err(code, &msg))?;
+    let tmp_dir = state.config.get_tmp_path();
+    let write_failed =
+        |_| ApiResponse::<ImportJobResp>::err(ApiCode::FileWriteFailed, "save file failed");
+    file_util::ensure_path(&tmp_dir)
+        .await
+        .map_err(write_failed)?;
+    let path = PathBuf::from(&tmp_dir).join(format!(
+        "import_{}_{}.zip",
+        date_util::now_secs(),
+        token::random_token(6)
+    ));
+    tokio::fs::write(&path, &zip_file)
+        .await
+        .map_err(write_failed)?;
+    let payload = ImportJobPayload {
+        path: path.to_string_lossy().to_string(),
+        plan,
+    };
+    let payload = serde_json::to_string(&payload).unwrap_or_default();
+    let job_id = job::enqueue(&state, IMPORT_JOB, Some(payload), 0)
+        .await
+        .map_err(|_| {
+            ApiResponse::<ImportJobResp>::err(ApiCode::DbInsertFailed, "db insert failed")
+        })?;
+    info!(
+        "zip import queued as job {}, size={}",
+        job_id,
+        zip_file.len()
+    );
+    Ok(ApiResponse::ok(ImportJobResp { job_id }))
+}
+
+pub fn register_jobs() {
+    job::register(IMPORT_JOB, run_import_job);
+}
+
+fn run_import_job(state: AppState, payload: Option<String>) -> JobFuture {
+    Box::pin(async move {
+        let progress = ProgressSink::for_current_job();
+        let payload = serde_json::from_str::<ImportJobPayload>(payload.as_deref().unwrap_or(""))
+            .map_err(|e| format!("invalid payload: {}", e))?;
+        let result = match tokio::fs::read(&payload.path).await {
+            Ok(zip_file) => run_import(&state, zip_file, &payload.plan, progress)
+                .await
+                .map_err(String::from),
+            Err(e) => Err(format!("read uploaded zip failed: {}", e)),
+        };
+        let _ = tokio::fs::remove_file(&payload.path).await;
+        match result {
+            Ok(resp) => {
+                if let Some(progress) = progress {
+                    progress.emit(ImportEvent::Finished(resp));
+                }
+                Ok(())
+            }
+            Err(message) => {
+                if let Some(progress) = progress {
+                    progress.emit(ImportEvent::Failed {
+                        message: message.clone(),
+                    });
+                }
+                Err(message)
+            }
+        }
+    })
+}
+
+/// 读取 multipart 并检查参数，参数有误时在上传后立即返回 400
+async fn read_request(
+    state: &AppState,
+    mut multipart: Multipart,
+) -> Result<(Vec<u8>, ImportPlan), (ApiCode, String)> {
+    let bad_request = |msg: &str| (ApiCode::BadRequest, msg.to_string());
+    let invalid = |e: DayLogError| (e.code(), e.to_string());
     let mut zip_file: Option<Vec<u8>> = None;
     let mut patterns_raw: Option<String> = None;
+    let mut options_raw: Option<String> = None;
 
-    while let Some(field) = multipart.next_field().await.map_err(|_| {
-        ApiResponse::<ImportJournalResp>::err(ApiCode::BadRequest, "invalid multipart data")
-    })? {
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|_| bad_request("invalid multipart data"))?
+    {
         let name = field.name().unwrap_or("").to_string();
 
         if name == "file" || field.file_name().is_some() {
@@ -62,92 +242,149 @@ pub async fn import_journal_zip(
                 field
                     .bytes()
                     .await
-                    .map_err(|_| {
-                        ApiResponse::<ImportJournalResp>::err(
-                            ApiCode::BadRequest,
-                            "read zip file failed",
-                        )
-                    })?
+                    .map_err(|e| (multipart_read_code(&e), "read zip file failed".to_string()))?
                     .to_vec(),
             );
         } else if name == "patterns" {
-            patterns_raw = Some(field.text().await.map_err(|_| {
-                ApiResponse::<ImportJournalResp>::err(ApiCode::BadRequest, "read patterns failed")
-            })?);
+            patterns_raw = Some(
+                field
+                    .text()
+                    .await
+                    .map_err(|_| bad_request("read patterns failed"))?,
+            );
+        } else if name == "options" {
+            options_raw = Some(
+                field
+                    .text()
+                    .await
+                    .map_err(|_| bad_request("read options failed"))?,
+            );
         }
     }
 
-    let zip_file = zip_file.ok_or_else(|| {
-        ApiResponse::<ImportJournalResp>::err(ApiCode::FileMissing, "zip file required")
-    })?;
+    let zip_file =
+        zip_file.ok_or_else(|| (ApiCode::FileMissing, "zip file required".to_string()))?;
+    let options = parse_options(options_raw.as_deref(), patterns_raw.is_some()).map_err(invalid)?;
+    if patterns_raw.is_some() {
+        warn!("zip import: multipart field `patterns` is deprecated, use options.patterns");
+    }
+    let encoding = resolve_encoding(options.encoding.as_deref()).map_err(invalid)?;
 
-    let date_placeholders = settings::load_date_placeholders(&state)
+    let date_placeholders = settings::load_date_placeholders(state)
         .await
         .unwrap_or_else(settings::default_date_placeholders);
-    let default_patterns = settings::load_import_patterns(&state)
+    let default_patterns = settings::load_import_patterns(state)
         .await
         .unwrap_or_else(|| settings::default_import_patterns_by(&date_placeholders));
-    let patterns = normalize_patterns(
-        patterns_raw.as_deref(),
-        default_patterns,
-        &date_placeholders,
-    )
-    .map_err(|msg| ApiResponse::<ImportJournalResp>::err(ApiCode::BadRequest, &msg))?;
+    let patterns = match options.patterns {
+        Some(list) => normalize_pattern_list(list, default_patterns, &date_placeholders),
+        None => normalize_patterns(
+            patterns_raw.as_deref(),
+            default_patterns,
+            &date_placeholders,
+        ),
+    }
+    .map_err(invalid)?;
+
+    Ok((
+        zip_file,
+        ImportPlan {
+            strategy: options.strategy,
+            patterns,
+            dry_run: options.dry_run,
+            encoding: encoding.name().to_string(),
+        },
+    ))
+}
 
-    let patterns_for_parse = patterns.clone();
-    let placeholders_for_parse = date_placeholders.clone();
-    let parse_result = task::spawn_blocking(move || {
-        parse_zip(zip_file, &patterns_for_parse, &placeholders_for_parse)
-    })
-    .await
-    .map_err(|_| {
-        ApiResponse::<ImportJournalResp>::err(ApiCode::BadRequest, "parse zip task failed")
-    })?
-    .map_err(|msg| ApiResponse::<ImportJournalResp>::err(ApiCode::BadRequest, &msg))?;
+async fn run_import(
+    state: &AppState,
+    zip_file: Vec<u8>,
+    plan: &ImportPlan,
+    progress: Option<ProgressSink>,
+) -> Result<ImportJournalResp, DayLogError> {
+    let encoding = resolve_encoding(Some(&plan.encoding))?;
+    let date_placeholders = settings::load_date_placeholders(state)
+        .await
+        .unwrap_or_else(settings::default_date_placeholders);
+
+    let patterns_for_parse = plan.patterns.clone();
+    let parse_result = state
+        .blocking
+        .run("zip import parse", move || {
+            parse_zip(
+                zip_file,
+                &patterns_for_parse,
+                &date_placeholders,
+                encoding,
+                progress,
+            )
+        })
+        .await??;
 
-    let mut imported_count = 0usize;
+    let skip = |details: &mut Vec<SkipDetail>, detail: SkipDetail| {
+        if let Some(progress) = progress {
+            progress.emit(ImportEvent::Skipped(detail.clone()));
+        }
+        details.push(detail);
+    };
     let mut skipped_details = parse_result.skipped_details;
-    let ts = now_ts();
-
-    for entry in parse_result.entries {
-        let exist_id =
-            sqlx::query_scalar::<_, i64>("select id from journal where date = ? limit 1")
-                .bind(&entry.date)
-                .fetch_optional(&state.db)
-                .await;
-
-        let result = match exist_id {
-            Ok(Some(id)) => {
-                sqlx::query("update journal set content = ?, update_time = ? where id = ?")
-                    .bind(entry.content)
-                    .bind(ts)
-                    .bind(id)
-                    .execute(&state.db)
-                    .await
+    let mut parsed = parse_result.entries;
+    if plan.strategy != ImportStrategy::Overwrite {
+        let dates = parsed.iter().map(|v| v.date.clone()).collect::<Vec<_>>();
+        let mut existing = load_existing_content(state, &dates).await?;
+        let mut kept = Vec::with_capacity(parsed.len());
+        for mut entry in parsed {
+            match (plan.strategy, existing.get(&entry.date)) {
+                (ImportStrategy::Skip, Some(_)) => skip(
+                    &mut skipped_details,
+                    SkipDetail {
+                        path: entry.path,
+                        reason: format!("journal for {} already exists", entry.date),
+                    },
+                ),
+                (ImportStrategy::Append, Some(old)) if !old.trim().is_empty() => {
+                    entry.content = format!("{}\n\n{}", old.trim_end(), entry.content);
+                    // 压缩包里同一天有多个文件时依次追加
+                    existing.insert(entry.date.clone(), entry.content.clone());
+                    kept.push(entry);
+                }
+                _ => {
+                    if plan.strategy == ImportStrategy::Append {
+                        existing.insert(entry.date.clone(), entry.content.clone());
+                    }
+                    kept.push(entry);
+                }
             }
-            Ok(None) => sqlx::query(
-                "insert into journal (content, date, create_time, update_time) values (?, ?, ?, ?)",
-            )
-            .bind(entry.content)
-            .bind(entry.date)
-            .bind(ts)
-            .bind(ts)
-            .execute(&state.db)
-            .await,
-            Err(e) => Err(e),
-        };
+        }
+        parsed = kept;
+    }
 
-        if result.is_ok() {
-            imported_count += 1;
-        } else {
+    let mut paths = Vec::with_capacity(parsed.len());
+    let mut entries = Vec::with_capacity(parsed.len());
+    for entry in parsed {
+        paths.push(entry.path);
+        entries.push(UpsertEntry {
+            date: entry.date,
+            content: entry.content,
+            metadata: None,
+            metadata_patch: JournalMetadata::patch_from_path(entry.fields),
+        });
+    }
+    let imported_count = if plan.dry_run {
+        entries.len()
+    } else {
+        let report = journal::upsert_by_date_batch(state, &entries, "zip import").await;
+        for idx in report.failed {
             let detail = SkipDetail {
-                path: entry.path,
-                reason: "db insert failed".to_string(),
+                path: paths[idx].clone(),
+                reason: "db upsert failed".to_string(),
             };
             warn!("zip import skipped: {} => {}", detail.path, detail.reason);
-            skipped_details.push(detail);
+            skip(&mut skipped_details, detail);
         }
-    }
+        report.upserted
+    };
 
     let skipped_paths = skipped_details
         .iter()
@@ -161,25 +398,81 @@ pub async fn import_journal_zip(
         skipped_count: skipped_details.len(),
         skipped_paths,
         skipped_details,
-        patterns,
+        patterns: plan.patterns.clone(),
+        strategy: plan.strategy,
+        dry_run: plan.dry_run,
+        encoding: encoding.name().to_ascii_lowercase(),
     };
 
     info!(
-        "导入日记完成 total_md={}, matched={}, imported={}, skipped={}",
-        resp.total_markdown_files, resp.matched_files, resp.imported_count, resp.skipped_count
+        "导入日记完成 total_md={}, matched={}, imported={}, skipped={}, strategy={:?}, dry_run={}",
+        resp.total_markdown_files,
+        resp.matched_files,
+        resp.imported_count,
+        resp.skipped_count,
+        resp.strategy,
+        resp.dry_run
     );
     for detail in &resp.skipped_details {
         info!("导入跳过 path='{}' reason='{}'", detail.path, detail.reason);
     }
-    Ok(ApiResponse::ok(resp))
+    Ok(resp)
+}
+
+/// 同时给出 `options` 和旧的 `patterns` 字段时报错，避免两处规则不一致
+fn parse_options(
+    raw: Option<&str>,
+    has_legacy_patterns: bool,
+) -> Result<ImportOptions, DayLogError> {
+    let Some(raw) = raw.map(str::trim).filter(|v| !v.is_empty()) else {
+        return Ok(ImportOptions::default());
+    };
+    let options = serde_json::from_str::<ImportOptions>(raw)
+        .map_err(|e| DayLogError::BadRequest(format!("invalid options: {}", e)))?;
+    if has_legacy_patterns {
+        return Err(DayLogError::BadRequest(
+            "use either options.patterns or the patterns field, not both".to_string(),
+        ));
+    }
+    Ok(options)
+}
+
+fn resolve_encoding(label: Option<&str>) -> Result<&'static Encoding, DayLogError> {
+    match label.map(str::trim).filter(|v| !v.is_empty()) {
+        None => Ok(encoding_rs::UTF_8),
+        Some(v) => Encoding::for_label(v.as_bytes()).ok_or_else(|| {
+            DayLogError::Validation(format!("invalid options: unsupported encoding '{}'", v))
+        }),
+    }
+}
+
+/// 已有日记的内容，按日期索引
+async fn load_existing_content(
+    state: &AppState,
+    dates: &[String],
+) -> Result<HashMap<String, String>, sqlx::Error> {
+    if dates.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let dates = serde_json::to_string(dates).unwrap_or_else(|_| "[]".to_string());
+    let rows = sqlx::query_as::<_, (String, String)>(
+        "select date, content from journal where date in (select value from json_each(?))",
+    )
+    .bind(dates)
+    .fetch_all(&state.db)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(date, content)| (date, state.cipher.open(content)))
+        .collect())
 }
 
 fn normalize_patterns(
     input: Option<&str>,
     default_patterns: Vec<String>,
     placeholders: &DatePlaceholders,
-) -> Result<Vec<String>, String> {
-    let mut patterns = if let Some(raw) = input {
+) -> Result<Vec<String>, DayLogError> {
+    let patterns = if let Some(raw) = input {
         let trimmed = raw.trim();
         if trimmed.is_empty() {
             default_patterns
@@ -187,99 +480,99 @@ fn normalize_patterns(
             arr
         } else {
             trimmed
-                .split(|c| c == '\n' || c == ',' || c == ';')
+                .split(['\n', ',', ';'])
                 .map(|v| v.trim().to_string())
                 .collect()
         }
     } else {
         default_patterns
     };
+    normalize_pattern_list(patterns, Vec::new(), placeholders)
+}
 
+/// 去掉空白和重复的规则，列表为空时使用默认规则
+fn normalize_pattern_list(
+    mut patterns: Vec<String>,
+    default_patterns: Vec<String>,
+    placeholders: &DatePlaceholders,
+) -> Result<Vec<String>, DayLogError> {
     patterns.retain(|v| !v.trim().is_empty());
+    if patterns.is_empty() {
+        patterns = default_patterns;
+    }
     let mut uniq = HashSet::new();
     patterns.retain(|v| uniq.insert(v.clone()));
 
     if patterns.is_empty() {
-        return Err("patterns required".to_string());
+        return Err(DayLogError::Validation("patterns required".to_string()));
     }
 
     for p in &patterns {
-        validate_pattern(p, placeholders)?;
+        date_pattern::validate_import_pattern(p, placeholders).map_err(DayLogError::Validation)?;
     }
 
     Ok(patterns)
 }
 
-fn validate_pattern(pattern: &str, placeholders: &DatePlaceholders) -> Result<(), String> {
-    let has_year = pattern.contains(&placeholders.yyyy);
-    let has_month = pattern.contains(&placeholders.mm) || pattern.contains(&placeholders.m);
-    let has_day = pattern.contains(&placeholders.dd) || pattern.contains(&placeholders.d);
-    let has_ymd = has_year && has_month && has_day;
-    let has_date = pattern.contains(&placeholders.date);
-    if !has_ymd && !has_date {
-        return Err(format!(
-            "invalid pattern '{}' , required placeholders: {}+{}|{}+{}|{} or {}",
-            pattern,
-            placeholders.yyyy,
-            placeholders.mm,
-            placeholders.m,
-            placeholders.dd,
-            placeholders.d,
-            placeholders.date
-        ));
-    }
-    Ok(())
-}
-
+/// 每个 rayon 工作线程持有一份 archive 的克隆（共享已解析的中央目录），按下标并行解压和匹配，
+/// collect 保持下标顺序，结果与串行解析一致
 fn parse_zip(
     zip_file: Vec<u8>,
     patterns: &[String],
     placeholders: &DatePlaceholders,
-) -> Result<ParseZipResult, String> {
-    let mut archive =
-        ZipArchive::new(Cursor::new(zip_file)).map_err(|_| "invalid zip file".to_string())?;
+    encoding: &'static Encoding,
+    progress: Option<ProgressSink>,
+) -> Result<ParseZipResult, DayLogError> {
+    let patterns = date_pattern::compile_import_patterns(patterns, placeholders)
+        .map_err(DayLogError::Validation)?;
+    let archive = ZipArchive::new(Cursor::new(zip_file.as_slice()))
+        .map_err(|_| DayLogError::BadRequest("invalid zip file".to_string()))?;
+
+    let total = archive
+        .file_names()
+        .filter(|v| v.to_ascii_lowercase().ends_with(".md"))
+        .count();
+    if let Some(progress) = progress {
+        progress.emit(ImportEvent::Started { total });
+    }
+    let processed = AtomicUsize::new(0);
+    let parsed = (0..archive.len())
+        .into_par_iter()
+        .map_init(
+            || archive.clone(),
+            |archive, idx| {
+                let item = parse_zip_entry(archive, idx, &patterns, placeholders, encoding)?;
+                if let (Some(progress), Some(item)) = (progress, &item) {
+                    let (path, skipped) = match item {
+                        Ok(entry) => (&entry.path, None),
+                        Err(detail) => (&detail.path, Some(detail)),
+                    };
+                    progress.emit(ImportEvent::File {
+                        path: path.clone(),
+                        processed: processed.fetch_add(1, Ordering::Relaxed) + 1,
+                        total,
+                    });
+                    if let Some(detail) = skipped {
+                        progress.emit(ImportEvent::Skipped(detail.clone()));
+                    }
+                }
+                Ok(item)
+            },
+        )
+        .collect::<Result<Vec<_>, DayLogError>>()?;
 
     let mut entries = Vec::new();
     let mut skipped_details = Vec::new();
     let mut total_markdown_files = 0usize;
-
-    for idx in 0..archive.len() {
-        let mut file = archive
-            .by_index(idx)
-            .map_err(|_| "read zip entry failed".to_string())?;
-        if !file.is_file() {
-            continue;
-        }
-
-        let path = file.name().replace('\\', "/");
-        if !path.to_ascii_lowercase().ends_with(".md") {
-            continue;
-        }
+    for item in parsed.into_iter().flatten() {
         total_markdown_files += 1;
-
-        let date = match extract_date_from_path(&path, patterns, placeholders) {
-            Ok(v) => v,
-            Err(reason) => {
-                let detail = SkipDetail {
-                    path: path.clone(),
-                    reason,
-                };
+        match item {
+            Ok(entry) => entries.push(entry),
+            Err(detail) => {
                 warn!("zip import skipped: {} => {}", detail.path, detail.reason);
                 skipped_details.push(detail);
-                continue;
             }
-        };
-
-        let mut buf = Vec::new();
-        file.read_to_end(&mut buf)
-            .map_err(|_| "read markdown content failed".to_string())?;
-        let content = String::from_utf8_lossy(&buf).to_string();
-
-        entries.push(ParsedEntry {
-            path,
-            date,
-            content,
-        });
+        }
     }
 
     Ok(ParseZipResult {
@@ -290,283 +583,50 @@ fn parse_zip(
     })
 }
 
-fn extract_date_from_path(
-    path: &str,
-    patterns: &[String],
-    placeholders: &DatePlaceholders,
-) -> Result<String, String> {
-    let mut reasons = Vec::new();
-    for pattern in patterns {
-        match match_path_with_pattern(path, pattern, placeholders) {
-            Ok(date) => return Ok(date),
-            Err(reason) => reasons.push(format!("[{}] {}", pattern, reason)),
-        }
-    }
-    Err(format!("path not match patterns: {}", reasons.join(" | ")))
-}
-
-fn match_path_with_pattern(
-    path: &str,
-    pattern: &str,
-    placeholders: &DatePlaceholders,
-) -> Result<String, String> {
-    let path_tokens: Vec<&str> = path.split('/').collect();
-    let pattern_tokens: Vec<&str> = pattern.split('/').collect();
-
-    if path_tokens.len() < pattern_tokens.len() {
-        return Err(format!(
-            "path segment count too short (path={}, pattern={})",
-            path_tokens.len(),
-            pattern_tokens.len()
-        ));
-    }
-    let start = path_tokens.len() - pattern_tokens.len();
-    let tail_tokens = &path_tokens[start..];
-
-    let mut yyyy: Option<String> = None;
-    let mut mm: Option<String> = None;
-    let mut dd: Option<String> = None;
-
-    for (actual, template) in tail_tokens.iter().zip(pattern_tokens.iter()) {
-        capture_component(actual, template, placeholders, &mut yyyy, &mut mm, &mut dd)?;
-    }
-
-    let yyyy = yyyy.ok_or_else(|| "missing yyyy from path".to_string())?;
-    let mm = mm.ok_or_else(|| "missing month from path".to_string())?;
-    let dd = dd.ok_or_else(|| "missing day from path".to_string())?;
-
-    if !valid_date_parts(&yyyy, &mm, &dd) {
-        return Err(format!("invalid date parts: {}-{}-{}", yyyy, mm, dd));
-    }
-
-    Ok(format!("{}-{}-{}", yyyy, mm, dd))
-}
-
-fn capture_component(
-    actual: &str,
-    template: &str,
+/// 非 markdown 文件返回 None，路径匹配失败返回 `Some(Err)`
+fn parse_zip_entry(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    idx: usize,
+    patterns: &[ImportPattern],
     placeholders: &DatePlaceholders,
-    yyyy: &mut Option<String>,
-    mm: &mut Option<String>,
-    dd: &mut Option<String>,
-) -> Result<(), String> {
-    let mut i = 0usize;
-    let mut j = 0usize;
-    let t = template.as_bytes();
-    let a = actual.as_bytes();
-
-    while i < t.len() {
-        if t[i] == b'{' {
-            let end = match template[i..].find('}') {
-                Some(v) => i + v,
-                None => return Err(format!("invalid template component: {}", template)),
-            };
-            let key = &template[i + 1..end];
-            i = end + 1;
-
-            let next_literal = template[i..].chars().next();
-            let value_end = if let Some(ch) = next_literal {
-                match actual[j..].find(ch) {
-                    Some(pos) => j + pos,
-                    None => {
-                        return Err(format!(
-                            "missing literal '{}' after placeholder {{{}}} in '{}'",
-                            ch, key, actual
-                        ));
-                    }
-                }
-            } else {
-                actual.len()
-            };
-            if value_end < j {
-                return Err("invalid placeholder range".to_string());
-            }
-            let val = &actual[j..value_end];
-            j = value_end;
-
-            assign_placeholder(key, val, placeholders, yyyy, mm, dd)
-                .map_err(|e| format!("placeholder {{{}}} parse failed: {}", key, e))?;
-        } else {
-            if j >= a.len() || t[i] != a[j] {
-                return Err(format!(
-                    "literal mismatch at '{}' expect '{}'",
-                    actual, t[i] as char
-                ));
-            }
-            i += 1;
-            j += 1;
-        }
-    }
-
-    if j == a.len() {
-        Ok(())
-    } else {
-        Err(format!("component length mismatch: '{}'", actual))
+    encoding: &'static Encoding,
+) -> Result<Option<Result<ParsedEntry, SkipDetail>>, DayLogError> {
+    let mut file = archive
+        .by_index(idx)
+        .map_err(|_| DayLogError::BadRequest("read zip entry failed".to_string()))?;
+    if !file.is_file() {
+        return Ok(None);
     }
-}
 
-fn assign_placeholder(
-    key: &str,
-    val: &str,
-    placeholders: &DatePlaceholders,
-    yyyy: &mut Option<String>,
-    mm: &mut Option<String>,
-    dd: &mut Option<String>,
-) -> Result<(), String> {
-    if val.chars().any(|c| !c.is_ascii_digit()) {
-        return Err(format!("value '{}' contains non-digit", val));
+    let path = file.name().replace('\\', "/");
+    if !path.to_ascii_lowercase().ends_with(".md") {
+        return Ok(None);
     }
 
-    let yyyy_key = placeholder_key(&placeholders.yyyy)?;
-    let mm_key = placeholder_key(&placeholders.mm)?;
-    let m_key = placeholder_key(&placeholders.m)?;
-    let dd_key = placeholder_key(&placeholders.dd)?;
-    let d_key = placeholder_key(&placeholders.d)?;
-    let date_key = placeholder_key(&placeholders.date)?;
-
-    match key {
-        _ if key == yyyy_key => {
-            if val.len() != 4 {
-                return Err("yyyy must be 4 digits".to_string());
-            }
-            *yyyy = Some(val.to_string());
-            Ok(())
-        }
-        _ if key == mm_key => {
-            let Some(m) = normalize_month_or_day(val, 1, 12) else {
-                return Err("month out of range (1..12)".to_string());
-            };
-            if merge_or_check(mm, m) {
-                Ok(())
-            } else {
-                Err("month conflict with another placeholder".to_string())
-            }
-        }
-        _ if key == m_key => {
-            let Some(m) = normalize_month_or_day(val, 1, 12) else {
-                return Err("month out of range (1..12)".to_string());
-            };
-            if merge_or_check(mm, m) {
-                Ok(())
-            } else {
-                Err("month conflict with another placeholder".to_string())
-            }
-        }
-        _ if key == dd_key => {
-            let Some(d) = normalize_month_or_day(val, 1, 31) else {
-                return Err("day out of range (1..31)".to_string());
-            };
-            if merge_or_check(dd, d) {
-                Ok(())
-            } else {
-                Err("day conflict with another placeholder".to_string())
-            }
-        }
-        _ if key == d_key => {
-            let Some(d) = normalize_month_or_day(val, 1, 31) else {
-                return Err("day out of range (1..31)".to_string());
-            };
-            if merge_or_check(dd, d) {
-                Ok(())
-            } else {
-                Err("day conflict with another placeholder".to_string())
-            }
-        }
-        _ if key == date_key => {
-            let Some((py, pm, pd)) = parse_date_value(val) else {
-                return Err("unsupported date format".to_string());
-            };
-            if !merge_or_check(yyyy, py) || !merge_or_check(mm, pm) || !merge_or_check(dd, pd) {
-                return Err("date conflicts with yyyy/MM/dd placeholders".to_string());
-            }
-            Ok(())
-        }
-        _ => Err(format!("unsupported placeholder: {}", key)),
-    }
-}
-
-fn placeholder_key(token: &str) -> Result<&str, String> {
-    if !(token.starts_with('{') && token.ends_with('}') && token.len() >= 3) {
-        return Err(format!("invalid placeholder token '{}'", token));
-    }
-    Ok(&token[1..token.len() - 1])
-}
-
-fn merge_or_check(slot: &mut Option<String>, value: String) -> bool {
-    if let Some(existing) = slot.as_ref() {
-        existing == &value
-    } else {
-        *slot = Some(value);
-        true
-    }
-}
-
-fn parse_date_value(v: &str) -> Option<(String, String, String)> {
-    let s = v.trim();
-    if s.len() == 8 && s.chars().all(|c| c.is_ascii_digit()) {
-        let y = &s[0..4];
-        let m = &s[4..6];
-        let d = &s[6..8];
-        if valid_date_parts(y, m, d) {
-            return Some((y.to_string(), m.to_string(), d.to_string()));
-        }
-        return None;
-    }
-
-    for sep in ['-', '_', '.'] {
-        let parts: Vec<&str> = s.split(sep).collect();
-        if parts.len() != 3 {
-            continue;
-        }
-        let y = parts[0];
-        let m = parts[1];
-        let d = parts[2];
-        let normalized_m = normalize_month_or_day(m, 1, 12);
-        let normalized_d = normalize_month_or_day(d, 1, 31);
-        if y.len() == 4
-            && y.chars().all(|c| c.is_ascii_digit())
-            && normalized_m.is_some()
-            && normalized_d.is_some()
-        {
-            let mm = normalized_m.unwrap();
-            let dd = normalized_d.unwrap();
-            if valid_date_parts(y, &mm, &dd) {
-                return Some((y.to_string(), mm, dd));
-            }
-        }
-    }
-
-    None
-}
-
-fn normalize_month_or_day(v: &str, min: u32, max: u32) -> Option<String> {
-    if v.is_empty() || v.len() > 2 || v.chars().any(|c| !c.is_ascii_digit()) {
-        return None;
-    }
-    let n = v.parse::<u32>().ok()?;
-    if n < min || n > max {
-        return None;
-    }
-    Some(format!("{:02}", n))
-}
-
-fn valid_date_parts(yyyy: &str, mm: &str, dd: &str) -> bool {
-    let year = yyyy.parse::<i32>().ok();
-    let month = mm.parse::<u32>().ok();
-    let day = dd.parse::<u32>().ok();
-
-    if year.is_none() || month.is_none() || day.is_none() {
-        return false;
-    }
-
-    let month = month.unwrap();
-    let day = day.unwrap();
-    month >= 1 && month <= 12 && day >= 1 && day <= 31
-}
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)
+        .map_err(|_| DayLogError::BadRequest("read markdown content failed".to_string()))?;
+    // 有 BOM 时按 BOM 识别编码，无法解码的字节替换为 U+FFFD
+    let (content, _, _) = encoding.decode(&buf);
+    let content = content.into_owned();
+
+    // front matter 中的日期优先，路径匹配失败时也能导入
+    let front_matter_date = front_matter::date(&content);
+    let matched = match date_pattern::match_import_path(&path, patterns, placeholders) {
+        Ok(v) => v,
+        Err(reason) => match &front_matter_date {
+            Some(date) => PathMatch {
+                date: date.clone(),
+                fields: PathFields::default(),
+            },
+            None => return Ok(Some(Err(SkipDetail { path, reason }))),
+        },
+    };
 
-fn now_ts() -> i64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64
+    Ok(Some(Ok(ParsedEntry {
+        path,
+        date: front_matter_date.unwrap_or(matched.date),
+        content,
+        fields: matched.fields,
+    })))
 }
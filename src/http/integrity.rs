@@ -0,0 +1,271 @@
+use crate::app_state::AppState;
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::sync;
+use crate::util::date_util;
+use crate::util::file_util::hash_file;
+use axum::extract::{Query, State};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use tracing::{info, warn};
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyQuery {
+    /// 从同步仓库中找内容相同的文件补回丢失或损坏的文件
+    #[serde(default)]
+    pub repair: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyResp {
+    pub checked: usize,
+    pub missing: usize,
+    pub corrupted: usize,
+    pub repaired: usize,
+    pub issues: Vec<FileIssue>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileIssue {
+    pub uri: String,
+    pub file_path: String,
+    /// `missing` 文件不存在，`corrupted` 内容哈希与 `file_blob.oid` 不一致
+    pub status: &'static str,
+    pub expected_oid: String,
+    pub actual_oid: Option<String>,
+    /// 补回时使用的同步仓库中的文件
+    pub repaired_from: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairPathsResp {
+    /// 保存绝对路径、或相对路径接到 `base_path` 下找不到文件的记录数
+    pub checked: usize,
+    /// 改成相对 `base_path` 的记录数
+    pub relocated: usize,
+    /// 在 `base_path` 下找不到对应文件的 uri
+    pub unresolved: Vec<String>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+struct BlobRow {
+    uri: String,
+    oid: String,
+    size: i64,
+    file_path: String,
+}
+
+/// 重新计算每个已保存文件的 sha256 并与 `file_blob.oid` 比对，找出被删除或位腐烂的文件
+pub async fn verify_files(
+    State(state): State<AppState>,
+    Query(query): Query<VerifyQuery>,
+) -> ApiResult<VerifyResp> {
+    let mut rows = sqlx::query_as::<_, BlobRow>(
+        "select uri, oid, size, file_path from file_blob where algo = 'sha256' order by id asc",
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| ApiResponse::<VerifyResp>::err(ApiCode::DbListFailed, "db query failed"))?;
+    for row in &mut rows {
+        row.file_path = state
+            .config
+            .resolve_stored_path(&row.file_path)
+            .to_string_lossy()
+            .to_string();
+    }
+    info!("校验文件 count={}, repair={}", rows.len(), query.repair);
+
+    let repo_path = (query.repair && state.config.sync.enabled)
+        .then(|| PathBuf::from(state.config.get_sync_repo_path()));
+    let resp = state
+        .blocking
+        .run("file verify", move || verify(rows, repo_path.as_deref()))
+        .await
+        .map_err(|_| {
+            ApiResponse::<VerifyResp>::err(ApiCode::FileMissing, "file verify task failed")
+        })?;
+    if !resp.issues.is_empty() {
+        warn!(
+            "文件校验发现问题 missing={}, corrupted={}, repaired={}",
+            resp.missing, resp.corrupted, resp.repaired
+        );
+    }
+    Ok(ApiResponse::ok(resp))
+}
+
+/// 修复 `base_path` 挪动后失效的绝对路径，以及接到 `base_path` 下找不到文件的相对路径（例如相对旧工作目录的
+/// `.daylog/file/x.png`）：依次去掉路径开头的目录，在 `base_path` 下找到同名文件就改成相对路径
+pub async fn repair_file_paths(State(state): State<AppState>) -> ApiResult<RepairPathsResp> {
+    let rows = sqlx::query_as::<_, (i64, String, String)>(
+        "select id, uri, file_path from file_blob order by id asc",
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| ApiResponse::<RepairPathsResp>::err(ApiCode::DbListFailed, "db query failed"))?;
+    info!("修复文件路径 count={}", rows.len());
+
+    let base = PathBuf::from(&state.config.base_path);
+    let found = state
+        .blocking
+        .run("file path repair", move || {
+            rows.into_iter()
+                .filter(|(_, _, file_path)| {
+                    Path::new(file_path).is_absolute() || !base.join(file_path).is_file()
+                })
+                .map(|(id, uri, file_path)| (id, uri, relocate(&base, Path::new(&file_path))))
+                .collect::<Vec<_>>()
+        })
+        .await
+        .map_err(|_| {
+            ApiResponse::<RepairPathsResp>::err(
+                ApiCode::FileMissing,
+                "file path repair task failed",
+            )
+        })?;
+
+    let mut resp = RepairPathsResp {
+        checked: found.len(),
+        relocated: 0,
+        unresolved: Vec::new(),
+    };
+    for (id, uri, rel_path) in found {
+        let Some(rel_path) = rel_path else {
+            resp.unresolved.push(uri);
+            continue;
+        };
+        sqlx::query("update file_blob set file_path = ?, update_time = ? where id = ?")
+            .bind(rel_path)
+            .bind(date_util::now_secs())
+            .bind(id)
+            .execute(&state.db)
+            .await
+            .map_err(|_| {
+                ApiResponse::<RepairPathsResp>::err(ApiCode::DbUpdateFailed, "db update failed")
+            })?;
+        resp.relocated += 1;
+    }
+    if !resp.unresolved.is_empty() {
+        warn!("有 {} 个文件在 base_path 下找不到", resp.unresolved.len());
+    }
+    Ok(ApiResponse::ok(resp))
+}
+
+/// 旧路径 `/old/base/files/a.png` 依次尝试 `old/base/files/a.png` `base/files/a.png` `files/a.png` ...
+fn relocate(base: &Path, old: &Path) -> Option<String> {
+    if let Ok(rel) = old.strip_prefix(base) {
+        return Some(rel.to_string_lossy().to_string());
+    }
+    let parts = old
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(v) => Some(v),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    (0..parts.len()).find_map(|i| {
+        let rel = parts[i..].iter().collect::<PathBuf>();
+        base.join(&rel)
+            .is_file()
+            .then(|| rel.to_string_lossy().to_string())
+    })
+}
+
+fn verify(rows: Vec<BlobRow>, repo_path: Option<&Path>) -> VerifyResp {
+    let checked = rows.len();
+    let mut issues = rows
+        .into_par_iter()
+        .filter_map(|row| {
+            let (status, actual_oid) = match hash_file(Path::new(&row.file_path)) {
+                Ok(v) if v == row.oid => return None,
+                Ok(v) => ("corrupted", Some(v)),
+                Err(_) => ("missing", None),
+            };
+            Some((row, status, actual_oid))
+        })
+        .collect::<Vec<_>>();
+    issues.sort_by(|a, b| a.0.uri.cmp(&b.0.uri));
+
+    let candidates = match repo_path {
+        Some(root) if !issues.is_empty() => {
+            let _guard = sync::repo_read_guard();
+            let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+            if let Err(e) = collect_files(root, &mut by_size) {
+                warn!("scan sync repo for repair failed: {}", e);
+            }
+            by_size
+        }
+        _ => HashMap::new(),
+    };
+
+    let mut resp = VerifyResp {
+        checked,
+        missing: 0,
+        corrupted: 0,
+        repaired: 0,
+        issues: Vec::with_capacity(issues.len()),
+    };
+    for (row, status, actual_oid) in issues {
+        if status == "missing" {
+            resp.missing += 1;
+        } else {
+            resp.corrupted += 1;
+        }
+        let repaired_from = candidates
+            .get(&(row.size as u64))
+            .and_then(|paths| repair(&row, paths));
+        if repaired_from.is_some() {
+            resp.repaired += 1;
+        }
+        resp.issues.push(FileIssue {
+            uri: row.uri,
+            file_path: row.file_path,
+            status,
+            expected_oid: row.oid,
+            actual_oid,
+            repaired_from,
+        });
+    }
+    resp
+}
+
+/// 在大小相同的候选文件中找哈希一致的复制回去
+fn repair(row: &BlobRow, paths: &[PathBuf]) -> Option<String> {
+    let _guard = sync::repo_read_guard();
+    let source = paths
+        .iter()
+        .find(|p| hash_file(p).is_ok_and(|v| v == row.oid))?;
+    let target = Path::new(&row.file_path);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).ok()?;
+    }
+    match fs::copy(source, target) {
+        Ok(_) => Some(source.to_string_lossy().to_string()),
+        Err(e) => {
+            warn!("repair {} from {} failed: {}", row.uri, source.display(), e);
+            None
+        }
+    }
+}
+
+fn collect_files(dir: &Path, out: &mut HashMap<u64, Vec<PathBuf>>) -> io::Result<()> {
+    for item in fs::read_dir(dir)? {
+        let item = item?;
+        let path = item.path();
+        let file_type = item.file_type()?;
+        if file_type.is_dir() {
+            if path.file_name().and_then(|v| v.to_str()) != Some(".git") {
+                collect_files(&path, out)?;
+            }
+        } else if file_type.is_file() {
+            out.entry(item.metadata()?.len()).or_default().push(path);
+        }
+    }
+    Ok(())
+}
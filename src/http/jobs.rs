@@ -0,0 +1,22 @@
+use crate::app_state::AppState;
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::job::{self, JobRecord};
+use axum::extract::{Path, State};
+use tracing::warn;
+
+/// 定时任务的计划、上次结果和最近的一次性任务
+pub async fn list_jobs(State(state): State<AppState>) -> ApiResult<Vec<JobRecord>> {
+    let items = job::list(&state).await.map_err(|_| {
+        ApiResponse::<Vec<JobRecord>>::err(ApiCode::DbListFailed, "db query failed")
+    })?;
+    Ok(ApiResponse::ok(items))
+}
+
+/// 立即执行一次，任务在后台运行，结果通过 `GET /admin/jobs` 查看
+pub async fn run_job(State(state): State<AppState>, Path(id): Path<i64>) -> ApiResult<JobRecord> {
+    let record = job::trigger(&state, id).await.map_err(|msg| {
+        warn!("触发任务失败: {}", msg);
+        ApiResponse::<JobRecord>::err(ApiCode::BadRequest, &msg)
+    })?;
+    Ok(ApiResponse::ok(record))
+}
@@ -1,12 +1,34 @@
 use crate::app_state::AppState;
+use crate::http::cursor::{Cursor, SortKey};
+use crate::http::date_pattern::{self, PathFields};
+use crate::http::repo_sync::SyncTrigger;
 use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::http::{conditional, cursor, repo_sync};
+use crate::job::{self, JobFuture};
+use crate::util::words::WordCount;
+use crate::util::{date_util, front_matter, markdown};
 use axum::Json;
 use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::Response;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use std::time::{SystemTime, UNIX_EPOCH};
-use tracing::info;
-#[derive(Debug, Serialize, FromRow)]
+use tracing::{info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+/// 批量导入时每个事务提交的条数
+const UPSERT_CHUNK_SIZE: usize = 500;
+/// 补算旧数据 `word_count` `reading_minutes` 的一次性任务
+const COUNT_BACKFILL_JOB: &str = "journal_counts";
+const COUNT_BACKFILL_BATCH: i64 = 200;
+/// 写入 `journal.word_count` `journal.reading_minutes` 的值，按去掉 markdown 语法后的文字计算
+pub fn text_counts(content: &str) -> (i64, i64) {
+    let count = WordCount::of(&markdown::plain_text(content));
+    (count.total(), count.reading_minutes())
+}
+
+#[derive(Debug, Serialize, FromRow, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Journal {
     pub id: i64,
@@ -14,27 +36,442 @@ pub struct Journal {
     pub date: String,
     pub create_time: i64,
     pub update_time: i64,
+    #[serde(serialize_with = "serialize_metadata")]
+    #[schema(value_type = JournalMetadata)]
+    pub metadata: Option<String>,
+    /// 字数，中日韩文字按字、其他文字按词计算；旧数据补算完成前为 null
+    #[sqlx(default)]
+    pub word_count: Option<i64>,
+    /// 预计阅读分钟数，有内容时至少为 1
+    #[sqlx(default)]
+    pub reading_minutes: Option<i64>,
+    /// 仅 `fields=summary` 时返回：content 是否被截断
+    #[sqlx(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<bool>,
+    /// 仅 `render=html` 时返回：渲染后的 html
+    #[sqlx(skip)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub html: Option<String>,
+}
+
+/// 存在 `journal.metadata` 列中的 json
+#[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub longitude: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub place_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned: Option<bool>,
+    /// 心情，内容由客户端决定，例如 emoji 或 1-5 的分数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mood: Option<String>,
+    /// 正文之外显式挂在这一天的文件 uri
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachments: Option<Vec<String>>,
+    /// 导入时从文件名取出，同步输出路径模板中的 `{title}` `{slug}` 使用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slug: Option<String>,
+    /// 其他客户端写入的字段原样保留
+    #[serde(flatten)]
+    #[schema(ignore)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-#[derive(Debug, Deserialize)]
+impl JournalMetadata {
+    pub fn parse(raw: Option<&str>) -> JournalMetadata {
+        raw.and_then(|v| serde_json::from_str::<JournalMetadata>(v).ok())
+            .unwrap_or_default()
+    }
+
+    /// 同步到仓库时写入 front matter 的字段
+    pub fn front_matter_pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = Vec::new();
+        if let (Some(lat), Some(lng)) = (self.latitude, self.longitude) {
+            pairs.push(("latitude", lat.to_string()));
+            pairs.push(("longitude", lng.to_string()));
+        }
+        if let Some(place) = self.place_name.as_deref() {
+            pairs.push(("place_name", front_matter::quote(place)));
+        }
+        if self.pinned == Some(true) {
+            pairs.push(("pinned", "true".to_string()));
+        }
+        if let Some(mood) = self.mood.as_deref() {
+            pairs.push(("mood", front_matter::quote(mood)));
+        }
+        pairs
+    }
+
+    /// 读取 `front_matter_pairs` 写出的 front matter，含有其他 key 时返回 None
+    pub fn from_front_matter(pairs: &[(String, String)]) -> Option<JournalMetadata> {
+        let mut metadata = JournalMetadata::default();
+        for (key, value) in pairs {
+            match key.as_str() {
+                "latitude" => metadata.latitude = Some(value.parse().ok()?),
+                "longitude" => metadata.longitude = Some(value.parse().ok()?),
+                "place_name" => metadata.place_name = Some(value.clone()),
+                "pinned" => metadata.pinned = Some(value.parse().ok()?),
+                "mood" => metadata.mood = Some(value.clone()),
+                _ => return None,
+            }
+        }
+        Some(metadata)
+    }
+
+    pub fn path_fields(&self) -> PathFields {
+        PathFields {
+            title: self.title.clone(),
+            slug: self.slug.clone(),
+        }
+    }
+
+    /// 导入时合并到已有 metadata 上的标题和 slug，路径中没有时返回 None
+    pub fn patch_from_path(fields: PathFields) -> Option<String> {
+        JournalMetadata {
+            title: fields.title,
+            slug: fields.slug,
+            ..Default::default()
+        }
+        .to_json()
+    }
+
+    pub fn to_json(&self) -> Option<String> {
+        let value = serde_json::to_value(self).ok()?;
+        if value.as_object().is_some_and(|v| v.is_empty()) {
+            return None;
+        }
+        Some(value.to_string())
+    }
+}
+
+fn serialize_metadata<S: serde::Serializer>(
+    raw: &Option<String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    JournalMetadata::parse(raw.as_deref()).serialize(serializer)
+}
+
+/// 批量导入的一条日记，`metadata` 为 None 时保留已有的 metadata
+#[derive(Debug)]
+pub struct UpsertEntry {
+    pub date: String,
+    pub content: String,
+    pub metadata: Option<String>,
+    /// 在 `metadata` 的结果上再合并的字段，例如从文件名取出的标题和 slug
+    pub metadata_patch: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct UpsertReport {
+    pub upserted: usize,
+    /// 所在批次回滚的条目下标
+    pub failed: Vec<usize>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateJournalReq {
     pub content: String,
     pub date: String,
     pub auto_sync: Option<bool>,
+    /// 当天已有日记时覆盖，默认返回 409，避免重复提交覆盖掉已有内容
+    #[serde(default)]
+    pub overwrite: bool,
+    #[serde(flatten)]
+    pub metadata: MetadataReq,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateJournalReq {
     pub content: Option<String>,
     pub date: Option<String>,
     pub auto_sync: Option<bool>,
+    #[serde(flatten)]
+    pub metadata: MetadataReq,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct MetadataReq {
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub place_name: Option<String>,
+    pub pinned: Option<bool>,
+    /// 传空字符串清空
+    pub mood: Option<String>,
+    /// 传空数组清空
+    pub attachments: Option<Vec<String>>,
+    /// 传空字符串清空
+    pub title: Option<String>,
+    pub slug: Option<String>,
+}
+
+impl MetadataReq {
+    fn is_empty(&self) -> bool {
+        self.latitude.is_none()
+            && self.longitude.is_none()
+            && self.place_name.is_none()
+            && self.pinned.is_none()
+            && self.mood.is_none()
+            && self.attachments.is_none()
+            && self.title.is_none()
+            && self.slug.is_none()
+    }
+
+    /// 会改变同步输出文件名的字段
+    fn touches_path(&self) -> bool {
+        self.title.is_some() || self.slug.is_some()
+    }
+
+    fn validate(&self) -> Result<(), &'static str> {
+        if self.latitude.is_some() != self.longitude.is_some() {
+            return Err("latitude and longitude must be provided together");
+        }
+        if let Some(lat) = self.latitude
+            && !(-90.0..=90.0).contains(&lat)
+        {
+            return Err("latitude out of range (-90..90)");
+        }
+        if let Some(lng) = self.longitude
+            && !(-180.0..=180.0).contains(&lng)
+        {
+            return Err("longitude out of range (-180..180)");
+        }
+        if let Some(attachments) = self.attachments.as_ref()
+            && attachments.iter().any(|v| !v.starts_with("/files/"))
+        {
+            return Err("attachments must be /files/ uris");
+        }
+        if self
+            .slug
+            .as_deref()
+            .is_some_and(|v| date_pattern::slugify(v) != v.trim())
+        {
+            return Err("slug may only contain lowercase letters, digits and '-'");
+        }
+        Ok(())
+    }
+
+    /// 合并到已有的 metadata 上，返回新的 json（全部清空时为 `{}`）
+    fn merge_into(&self, raw: Option<&str>) -> String {
+        let mut metadata = JournalMetadata::parse(raw);
+        if self.latitude.is_some() {
+            metadata.latitude = self.latitude;
+            metadata.longitude = self.longitude;
+        }
+        if let Some(place) = self.place_name.as_ref() {
+            let place = place.trim();
+            metadata.place_name = (!place.is_empty()).then(|| place.to_string());
+        }
+        if let Some(pinned) = self.pinned {
+            metadata.pinned = pinned.then_some(true);
+        }
+        if let Some(mood) = self.mood.as_ref() {
+            let mood = mood.trim();
+            metadata.mood = (!mood.is_empty()).then(|| mood.to_string());
+        }
+        if let Some(attachments) = self.attachments.as_ref() {
+            let mut list: Vec<String> = Vec::new();
+            for uri in attachments {
+                if !list.contains(uri) {
+                    list.push(uri.clone());
+                }
+            }
+            metadata.attachments = (!list.is_empty()).then_some(list);
+        }
+        if let Some(title) = self.title.as_ref() {
+            let title = title.trim();
+            metadata.title = (!title.is_empty()).then(|| title.to_string());
+        }
+        if let Some(slug) = self.slug.as_ref() {
+            let slug = slug.trim();
+            metadata.slug = (!slug.is_empty()).then(|| slug.to_string());
+        }
+        metadata.to_json().unwrap_or_else(|| "{}".to_string())
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MergeJournalReq {
+    pub source_id: i64,
+    pub target_id: i64,
+    /// 默认空一行
+    pub separator: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct MoveQuery {
+    pub date: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct MapQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct NeighborsQuery {
+    /// yyyy-MM-dd，这一天本身不必有日记
+    pub date: Option<String>,
+}
+
+/// 前后最近的有日记的日期，没有时为 null
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalNeighbors {
+    pub date: String,
+    pub prev: Option<String>,
+    pub next: Option<String>,
+}
+
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalLocation {
+    pub id: i64,
+    pub date: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub place_name: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct ListQuery {
     pub date: Option<String>,
     pub page: Option<i64>,
+    /// 游标分页，值为上一页响应头 `X-Next-Cursor` 或某篇日记的 id、日期；有它时忽略 `page`
+    pub after: Option<String>,
     pub size: Option<i64>,
+    /// `summary` 时 content 只返回前 `summary_len` 个字符，并附带字数
+    pub fields: Option<String>,
+    pub summary_len: Option<i64>,
+    /// `html` 时附带渲染后的 html，`fields=summary` 时忽略
+    pub render: Option<String>,
+    /// 只返回带有该标签的日记，不区分大小写
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct RecentQuery {
+    /// `updated`（默认）或 `created`
+    pub by: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct GetQuery {
+    /// `html` 时附带渲染后的 html
+    pub render: Option<String>,
+}
+
+fn wants_html(render: Option<&str>) -> bool {
+    render.map(str::trim) == Some("html")
+}
+
+/// 注册字数补算任务，需要在 `job::spawn` 之前调用
+pub fn register_jobs() {
+    job::register(COUNT_BACKFILL_JOB, run_count_backfill_job);
+}
+
+/// 有缺少字数的日记（升级前的数据、从回收站恢复的日记）时排队补算
+pub async fn queue_count_backfill(state: &AppState) {
+    let missing = sqlx::query_scalar::<_, bool>(
+        "select exists(select 1 from journal where word_count is null)",
+    )
+    .fetch_one(&state.db)
+    .await;
+    match missing {
+        Ok(true) => {
+            if let Err(e) = job::enqueue_unless_pending(state, COUNT_BACKFILL_JOB, None, 1).await {
+                warn!("word count backfill queue failed: {}", e);
+            }
+        }
+        Ok(false) => {}
+        Err(e) => warn!("word count backfill check failed: {}", e),
+    }
+}
+
+fn run_count_backfill_job(state: AppState, _payload: Option<String>) -> JobFuture {
+    Box::pin(async move {
+        let filled = backfill_counts(&state).await.map_err(|e| e.to_string())?;
+        info!("日记字数补算完成 count={}", filled);
+        Ok(())
+    })
+}
+
+/// 分批补算，不修改 `update_time`
+async fn backfill_counts(state: &AppState) -> Result<usize, sqlx::Error> {
+    let mut filled = 0;
+    loop {
+        let rows = sqlx::query_as::<_, (i64, String)>(
+            "select id, content from journal where word_count is null order by id limit ?",
+        )
+        .bind(COUNT_BACKFILL_BATCH)
+        .fetch_all(&state.db)
+        .await?;
+        if rows.is_empty() {
+            return Ok(filled);
+        }
+        let mut tx = state.db.begin().await?;
+        for (id, content) in rows {
+            let (words, minutes) = text_counts(&state.cipher.open(content));
+            sqlx::query("update journal set word_count = ?, reading_minutes = ? where id = ?")
+                .bind(words)
+                .bind(minutes)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            filled += 1;
+        }
+        tx.commit().await?;
+    }
+}
+
+/// `[from, to)` 内每篇日记的日期和字数，空字符串表示不限
+pub async fn date_word_counts(
+    state: &AppState,
+    from: &str,
+    to: &str,
+) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    sqlx::query_as::<_, (String, i64)>(
+        "select date, coalesce(word_count, 0) from journal where (? = '' or date >= ?) and (? = '' or date < ?) order by date",
+    )
+    .bind(from)
+    .bind(from)
+    .bind(to)
+    .bind(to)
+    .fetch_all(&state.read_db)
+    .await
+}
+
+impl Journal {
+    /// 读库后解密正文，见 `util::crypto`
+    pub fn decrypted(mut self, state: &AppState) -> Journal {
+        self.content = state.cipher.open(std::mem::take(&mut self.content));
+        self
+    }
+
+    /// 正文加密时 sql 截不了摘要，取出全文后在这里截
+    fn summarize(&mut self, len: usize) {
+        let cut = self.content.char_indices().nth(len).map(|(i, _)| i);
+        self.truncated = Some(cut.is_some());
+        if let Some(i) = cut {
+            self.content.truncate(i);
+        }
+    }
+
+    /// 从 `render_cache` 取渲染结果，未命中时渲染
+    fn attach_html(&mut self, state: &AppState) {
+        let html = state
+            .render_cache
+            .get_or_render(self.id, self.update_time, &self.content);
+        self.html = Some(html.to_string());
+    }
 }
 
 fn now_ts() -> i64 {
@@ -44,124 +481,341 @@ fn now_ts() -> i64 {
         .as_secs() as i64
 }
 
+#[utoipa::path(
+    post,
+    path = "/journal",
+    tag = "journal",
+    request_body = CreateJournalReq,
+    responses((status = 200, description = "当天已有日记且没有 `overwrite` 时 code 为 409", body = ApiResponse<Journal>))
+)]
 pub async fn create_journal(
     State(state): State<AppState>,
     Json(req): Json<CreateJournalReq>,
 ) -> ApiResult<Journal> {
     let auto_sync = req.auto_sync.unwrap_or(false);
-    info!("创建/覆盖日记 date={}, auto_sync={}", req.date, auto_sync);
+    info!(
+        "创建/覆盖日记 date={}, auto_sync={}, overwrite={}",
+        req.date, auto_sync, req.overwrite
+    );
+    req.metadata
+        .validate()
+        .map_err(|msg| ApiResponse::<Journal>::err(ApiCode::Validation, msg))?;
     let ts = now_ts();
-    let existed = sqlx::query_scalar::<_, i64>("select id from journal where date = ? limit 1")
-        .bind(&req.date)
-        .fetch_optional(&state.db)
+    let (words, minutes) = text_counts(&req.content);
+    let existed = find_journal_by_date(&state, &req.date)
         .await
         .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbQueryFailed, "db query failed"))?;
 
-    if let Some(id) = existed {
-        sqlx::query("update journal set content = ?, update_time = ? where id = ?")
-            .bind(&req.content)
+    if let Some(existed) = existed {
+        let id = existed.id;
+        if !req.overwrite {
+            return Err(ApiResponse::<Journal>::err(
+                ApiCode::Conflict,
+                &format!(
+                    "journal for {} already exists (#{}), update it or set overwrite=true",
+                    req.date, id
+                ),
+            ));
+        }
+        let metadata = if req.metadata.is_empty() {
+            existed.metadata
+        } else {
+            Some(req.metadata.merge_into(existed.metadata.as_deref()))
+        };
+        sqlx::query(
+            "update journal set content = ?, word_count = ?, reading_minutes = ?, metadata = ?, update_time = ?, update_utc_offset = ? where id = ?",
+        )
+            .bind(state.cipher.seal(&req.content))
+            .bind(words)
+            .bind(minutes)
+            .bind(metadata)
             .bind(ts)
+            .bind(state.config.utc_offset_minutes)
             .bind(id)
             .execute(&state.db)
             .await
             .map_err(|_| {
                 ApiResponse::<Journal>::err(ApiCode::DbUpdateFailed, "db update failed")
             })?;
+        state.render_cache.invalidate(id);
 
         let journal = sqlx::query_as::<_, Journal>(
-            "select id, content, date, create_time, update_time from journal where id = ?",
+            "select id, content, date, create_time, update_time, metadata, word_count, reading_minutes from journal where id = ?",
         )
         .bind(id)
         .fetch_one(&state.db)
         .await
-        .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbQueryFailed, "db query failed"))?;
+        .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbQueryFailed, "db query failed"))?
+        .decrypted(&state);
 
+        if auto_sync {
+            repo_sync::queue_sync(&state, SyncTrigger::AutoSync).await;
+        }
         return Ok(ApiResponse::ok(journal));
     }
 
     let result = sqlx::query(
-        "insert into journal (content, date, create_time, update_time) values (?, ?, ?, ?)",
+        "insert into journal (content, word_count, reading_minutes, date, create_time, update_time, metadata, create_utc_offset, update_utc_offset) values (?, ?, ?, ?, ?, ?, ?, ?, ?)",
     )
-    .bind(&req.content)
+    .bind(state.cipher.seal(&req.content))
+    .bind(words)
+    .bind(minutes)
     .bind(&req.date)
     .bind(ts)
     .bind(ts)
+    .bind((!req.metadata.is_empty()).then(|| req.metadata.merge_into(None)))
+    .bind(state.config.utc_offset_minutes)
+    .bind(state.config.utc_offset_minutes)
     .execute(&state.db)
     .await
-    .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbInsertFailed, "db insert failed"))?;
+    .map_err(|e| {
+        // 并发的重复提交在唯一索引上冲突
+        if e.as_database_error()
+            .is_some_and(|v| v.is_unique_violation())
+        {
+            ApiResponse::<Journal>::err(
+                ApiCode::Conflict,
+                &format!("journal for {} already exists", req.date),
+            )
+        } else {
+            ApiResponse::<Journal>::err(ApiCode::DbInsertFailed, "db insert failed")
+        }
+    })?;
 
     let id = result.last_insert_rowid();
     let journal = sqlx::query_as::<_, Journal>(
-        "select id, content, date, create_time, update_time from journal where id = ?",
+        "select id, content, date, create_time, update_time, metadata, word_count, reading_minutes from journal where id = ?",
     )
     .bind(id)
     .fetch_one(&state.db)
     .await
-    .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbQueryFailed, "db query failed"))?;
+    .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbQueryFailed, "db query failed"))?
+    .decrypted(&state);
 
+    if auto_sync {
+        repo_sync::queue_sync(&state, SyncTrigger::AutoSync).await;
+    }
     Ok(ApiResponse::ok(journal))
 }
 
+/// 支持 `If-None-Match`，列表没有变化时返回 304；
+/// 按月查询时按日期排序，游标是日期，其余按 id 排序，游标是 id
+#[utoipa::path(
+    get,
+    path = "/journal",
+    tag = "journal",
+    params(ListQuery),
+    responses(
+        (status = 200, body = ApiResponse<Vec<Journal>>, headers(
+            ("X-Next-Cursor" = String, description = "取满一页时下一页的 `after`")
+        )),
+        (status = 304, description = "列表没有变化")
+    )
+)]
 pub async fn list_journals(
     State(state): State<AppState>,
     Query(query): Query<ListQuery>,
-) -> ApiResult<Vec<Journal>> {
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ApiResponse<Vec<Journal>>>)> {
     let page = query.page.unwrap_or(1).clamp(1, 1000);
     let size = query.size.unwrap_or(10).clamp(1, 100);
-    info!("获取日记 page: {}, size: {}", page, size);
+    let summary = query.fields.as_deref().map(str::trim) == Some("summary");
+    let summary_len = query.summary_len.unwrap_or(200).clamp(1, 2000);
+    let after = match query.after.as_deref().filter(|v| !v.trim().is_empty()) {
+        Some(v) => Some(Cursor::parse(v).ok_or_else(|| {
+            ApiResponse::<Vec<Journal>>::err(
+                ApiCode::Validation,
+                "after must be a journal id or yyyy-MM-dd",
+            )
+        })?),
+        None => None,
+    };
+    info!(
+        "获取日记 page: {}, size: {}, after: {:?}, summary: {}, tag: {:?}",
+        page, size, after, summary, query.tag
+    );
 
-    let journals = if let Some(date) = query.date {
+    // 加密后 sql 看到的是密文，取全文解密后再截摘要
+    let columns = if summary && !state.cipher.enabled() {
+        format!(
+            "id, substr(content, 1, {len}) as content, date, create_time, update_time, metadata, word_count, reading_minutes, length(content) > {len} as truncated",
+            len = summary_len,
+        )
+    } else {
+        "id, content, date, create_time, update_time, metadata, word_count, reading_minutes"
+            .to_string()
+    };
+
+    let mut conditions = Vec::new();
+    let mut params = Vec::new();
+    let mut order = "id";
+    let (mut key, mut desc) = (SortKey::Id, false);
+    if let Some(date) = query.date {
         let date = date.trim().to_string();
         if date.len() == 7 {
-            let like = format!("{}-%", date);
-            sqlx::query_as::<_, Journal>(
-                "select id, content, date, create_time, update_time from journal where date like ? order by date asc, id asc limit ? offset ?",
-            )
-                .bind(like)
-                .bind(size)
-                .bind((page - 1) * size)
-                .fetch_all(&state.db)
-                .await
+            conditions.push("date like ?".to_string());
+            params.push(format!("{}-%", date));
+            order = "date asc, id asc";
+            key = SortKey::Date;
         } else {
-            sqlx::query_as::<_, Journal>(
-                "select id, content, date, create_time, update_time from journal where date = ? order by id desc limit ? offset ?",
-            )
-                .bind(date)
-                .bind(size)
-                .bind((page - 1) * size)
-                .fetch_all(&state.db)
-                .await
+            conditions.push("date = ?".to_string());
+            params.push(date);
+            order = "id desc";
+            desc = true;
         }
+    }
+    if let Some(tag) = query
+        .tag
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+    {
+        conditions.push(
+            "id in (select jt.journal_id from journal_tag jt join tag t on t.id = jt.tag_id where t.name = ?)"
+                .to_string(),
+        );
+        params.push(tag.to_string());
+    }
+    if let Some(after) = &after {
+        let (condition, param) = after.condition(key, desc);
+        conditions.push(condition);
+        params.push(param);
+    }
+    let filter = if conditions.is_empty() {
+        String::new()
     } else {
-        sqlx::query_as::<_, Journal>(
-            "select id, content, date, create_time, update_time from journal order by id  limit ? offset ?",
-        )
-            .bind(size)
-            .bind((page - 1) * size)
-            .fetch_all(&state.db)
-            .await
+        format!("where {}", conditions.join(" and "))
+    };
+    let sql = format!(
+        "select {} from journal {} order by {} limit ? offset ?",
+        columns, filter, order
+    );
+    let mut q = sqlx::query_as::<_, Journal>(&sql);
+    for param in params {
+        q = q.bind(param);
     }
+    let offset = if after.is_some() {
+        0
+    } else {
+        (page - 1) * size
+    };
+    let journals: Vec<Journal> = q
+        .bind(size)
+        .bind(offset)
+        .fetch_all(&state.read_db)
+        .await
         .map_err(|_| ApiResponse::<Vec<Journal>>::err(ApiCode::DbListFailed, "db query failed"))?;
 
+    let mut journals = journals
+        .into_iter()
+        .map(|v| v.decrypted(&state))
+        .collect::<Vec<_>>();
+    if summary && state.cipher.enabled() {
+        for journal in journals.iter_mut() {
+            journal.summarize(summary_len as usize);
+        }
+    }
+    if !summary && wants_html(query.render.as_deref()) {
+        for journal in journals.iter_mut() {
+            journal.attach_html(&state);
+        }
+    }
+    let last_modified = journals.iter().map(|v| v.update_time).max();
+    let next = journals
+        .last()
+        .filter(|_| journals.len() as i64 == size)
+        .map(|v| Cursor::of(key, v.id, &v.date));
+    let mut resp = conditional::ok_json(&headers, journals, last_modified);
+    cursor::set_next(&mut resp, next);
+    Ok(resp)
+}
+
+/// 最近编辑或最近创建的日记，按对应时间倒序
+#[utoipa::path(
+    get,
+    path = "/journal/recent",
+    tag = "journal",
+    params(RecentQuery),
+    responses((status = 200, body = ApiResponse<Vec<Journal>>))
+)]
+pub async fn list_recent_journals(
+    State(state): State<AppState>,
+    Query(query): Query<RecentQuery>,
+) -> ApiResult<Vec<Journal>> {
+    let limit = query.limit.unwrap_or(10).clamp(1, 100);
+    let column = match query.by.as_deref().map(str::trim) {
+        None | Some("") | Some("updated") => "update_time",
+        Some("created") => "create_time",
+        Some(_) => {
+            return Err(ApiResponse::<Vec<Journal>>::err(
+                ApiCode::Validation,
+                "by must be updated or created",
+            ));
+        }
+    };
+    info!("获取最近日记 by: {}, limit: {}", column, limit);
+
+    let journals = sqlx::query_as::<_, Journal>(&format!(
+        "select id, content, date, create_time, update_time, metadata, word_count, reading_minutes from journal order by {} desc, id desc limit ?",
+        column
+    ))
+    .bind(limit)
+    .fetch_all(&state.read_db)
+    .await
+    .map_err(|_| ApiResponse::<Vec<Journal>>::err(ApiCode::DbListFailed, "db query failed"))?
+    .into_iter()
+    .map(|v| v.decrypted(&state))
+    .collect();
+
     Ok(ApiResponse::ok(journals))
 }
 
-pub async fn get_journal(State(state): State<AppState>, Path(id): Path<i64>) -> ApiResult<Journal> {
+#[utoipa::path(
+    get,
+    path = "/journal/{id}",
+    tag = "journal",
+    params(("id" = i64, Path), GetQuery),
+    responses(
+        (status = 200, body = ApiResponse<Journal>),
+        (status = 304, description = "`If-None-Match` 命中")
+    )
+)]
+pub async fn get_journal(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(query): Query<GetQuery>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ApiResponse<Journal>>)> {
     info!("获取日记 id: {}", id);
     let journal = sqlx::query_as::<_, Journal>(
-        "select id, content, date, create_time, update_time from journal where id = ?",
+        "select id, content, date, create_time, update_time, metadata, word_count, reading_minutes from journal where id = ?",
     )
     .bind(id)
     .fetch_optional(&state.db)
     .await
-    .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbGetFailed, "db query failed"))?;
+    .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbGetFailed, "db query failed"))?
+    .map(|v| v.decrypted(&state));
 
     match journal {
-        Some(journal) => Ok(ApiResponse::ok(journal)),
+        Some(mut journal) => {
+            if wants_html(query.render.as_deref()) {
+                journal.attach_html(&state);
+            }
+            let last_modified = Some(journal.update_time);
+            Ok(conditional::ok_json(&headers, journal, last_modified))
+        }
         None => Err(ApiResponse::err(ApiCode::NotFound, "not found")),
     }
 }
 
+#[utoipa::path(
+    put,
+    path = "/journal/{id}",
+    tag = "journal",
+    params(("id" = i64, Path)),
+    request_body = UpdateJournalReq,
+    responses((status = 200, body = ApiResponse<Journal>))
+)]
 pub async fn update_journal(
     State(state): State<AppState>,
     Path(id): Path<i64>,
@@ -169,12 +823,15 @@ pub async fn update_journal(
 ) -> ApiResult<Journal> {
     let auto_sync = req.auto_sync.unwrap_or(false);
     info!("更新日记 id={}, auto_sync={}", id, auto_sync);
-    if req.content.is_none() && req.date.is_none() {
+    if req.content.is_none() && req.date.is_none() && req.metadata.is_empty() {
         return Err(ApiResponse::<Journal>::err(
-            ApiCode::BadRequest,
-            "content or date required",
+            ApiCode::Validation,
+            "content, date or metadata required",
         ));
     }
+    req.metadata
+        .validate()
+        .map_err(|msg| ApiResponse::<Journal>::err(ApiCode::Validation, msg))?;
 
     if let Some(date) = req.date.as_ref() {
         let conflict = sqlx::query_scalar::<_, i64>(
@@ -187,19 +844,48 @@ pub async fn update_journal(
         .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbQueryFailed, "db query failed"))?;
         if conflict.is_some() {
             return Err(ApiResponse::<Journal>::err(
-                ApiCode::BadRequest,
+                ApiCode::Conflict,
                 "date already exists, one day only one journal",
             ));
         }
     }
 
+    let current = if req.date.is_none() && req.metadata.is_empty() {
+        None
+    } else {
+        let (date, current) = sqlx::query_as::<_, (String, Option<String>)>(
+            "select date, metadata from journal where id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbQueryFailed, "db query failed"))?
+        .ok_or_else(|| ApiResponse::<Journal>::err(ApiCode::NotFound, "not found"))?;
+        // 日期、标题或 slug 改动后文件名可能变化，旧文件在下次同步时删除，文件名没变时会被覆盖而不是删除
+        let moved = req.date.as_deref().is_some_and(|v| v != date);
+        if moved || req.metadata.touches_path() {
+            repo_sync::record_stale_date(&state, &date, current.as_deref()).await;
+        }
+        current
+    };
+    let metadata = if req.metadata.is_empty() {
+        None
+    } else {
+        Some(req.metadata.merge_into(current.as_deref()))
+    };
+
     let ts = now_ts();
+    let counts = req.content.as_deref().map(text_counts);
     let result = sqlx::query(
-        "update journal set content = coalesce(?, content), date = coalesce(?, date), update_time = ? where id = ?",
+        "update journal set content = coalesce(?, content), word_count = coalesce(?, word_count), reading_minutes = coalesce(?, reading_minutes), date = coalesce(?, date), metadata = coalesce(?, metadata), update_time = ?, update_utc_offset = ? where id = ?",
     )
-        .bind(req.content)
+        .bind(req.content.as_deref().map(|v| state.cipher.seal(v)))
+        .bind(counts.map(|v| v.0))
+        .bind(counts.map(|v| v.1))
         .bind(req.date)
+        .bind(metadata)
         .bind(ts)
+        .bind(state.config.utc_offset_minutes)
         .bind(id)
         .execute(&state.db)
         .await
@@ -208,28 +894,574 @@ pub async fn update_journal(
     if result.rows_affected() == 0 {
         return Err(ApiResponse::<Journal>::err(ApiCode::NotFound, "not found"));
     }
+    state.render_cache.invalidate(id);
 
     let journal = sqlx::query_as::<_, Journal>(
-        "select id, content, date, create_time, update_time from journal where id = ?",
+        "select id, content, date, create_time, update_time, metadata, word_count, reading_minutes from journal where id = ?",
     )
     .bind(id)
     .fetch_one(&state.db)
     .await
-    .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbUpdateGetFailed, "db query failed"))?;
+    .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbUpdateGetFailed, "db query failed"))?
+    .decrypted(&state);
 
+    if auto_sync {
+        repo_sync::queue_sync(&state, SyncTrigger::AutoSync).await;
+    }
     Ok(ApiResponse::ok(journal))
 }
 
+#[utoipa::path(
+    get,
+    path = "/journal/map",
+    tag = "journal",
+    params(MapQuery),
+    responses((status = 200, body = ApiResponse<Vec<JournalLocation>>))
+)]
+pub async fn list_journal_map(
+    State(state): State<AppState>,
+    Query(query): Query<MapQuery>,
+) -> ApiResult<Vec<JournalLocation>> {
+    let from = query.from.unwrap_or_default();
+    let to = query.to.unwrap_or_default();
+    info!("获取日记地图 from={}, to={}", from, to);
+    let items = sqlx::query_as::<_, JournalLocation>(
+        r#"
+        select id, date,
+            json_extract(metadata, '$.latitude') as latitude,
+            json_extract(metadata, '$.longitude') as longitude,
+            json_extract(metadata, '$.placeName') as place_name
+        from journal
+        where json_valid(metadata)
+            and json_extract(metadata, '$.latitude') is not null
+            and json_extract(metadata, '$.longitude') is not null
+            and (? = '' or date >= ?)
+            and (? = '' or date <= ?)
+        order by date asc
+        "#,
+    )
+    .bind(&from)
+    .bind(&from)
+    .bind(&to)
+    .bind(&to)
+    .fetch_all(&state.read_db)
+    .await
+    .map_err(|_| {
+        ApiResponse::<Vec<JournalLocation>>::err(ApiCode::DbListFailed, "db query failed")
+    })?;
+
+    Ok(ApiResponse::ok(items))
+}
+
+/// 阅读页的上一篇、下一篇，月份里日记稀疏时不用反复查列表
+#[utoipa::path(
+    get,
+    path = "/journal/neighbors",
+    tag = "journal",
+    params(NeighborsQuery),
+    responses((status = 200, body = ApiResponse<JournalNeighbors>))
+)]
+pub async fn journal_neighbors(
+    State(state): State<AppState>,
+    Query(query): Query<NeighborsQuery>,
+) -> ApiResult<JournalNeighbors> {
+    let date = query.date.unwrap_or_default().trim().to_string();
+    if date.len() != 10 || date_util::parse_date(&date).is_none() {
+        return Err(ApiResponse::<JournalNeighbors>::err(
+            ApiCode::Validation,
+            "date must be yyyy-MM-dd",
+        ));
+    }
+    let (prev, next) = sqlx::query_as::<_, (Option<String>, Option<String>)>(
+        r#"
+        select
+            (select max(date) from journal where date < ?1),
+            (select min(date) from journal where date > ?1)
+        "#,
+    )
+    .bind(&date)
+    .fetch_one(&state.read_db)
+    .await
+    .map_err(|_| ApiResponse::<JournalNeighbors>::err(ApiCode::DbQueryFailed, "db query failed"))?;
+    Ok(ApiResponse::ok(JournalNeighbors { date, prev, next }))
+}
+
+pub async fn find_journal_by_date(
+    state: &AppState,
+    date: &str,
+) -> Result<Option<Journal>, sqlx::Error> {
+    let journal = sqlx::query_as::<_, Journal>(
+        "select id, content, date, create_time, update_time, metadata, word_count, reading_minutes from journal where date = ? limit 1",
+    )
+    .bind(date)
+    .fetch_optional(&state.db)
+    .await?;
+    Ok(journal.map(|v| v.decrypted(state)))
+}
+
+async fn load_by_id(state: &AppState, id: i64) -> Result<Journal, sqlx::Error> {
+    let journal = sqlx::query_as::<_, Journal>(
+        "select id, content, date, create_time, update_time, metadata, word_count, reading_minutes from journal where id = ?",
+    )
+    .bind(id)
+    .fetch_one(&state.db)
+    .await?;
+    Ok(journal.decrypted(state))
+}
+
+/// 将 `text` 追加到 `date` 当天的日记末尾，当天没有日记时新建
+pub async fn append_to_date(
+    state: &AppState,
+    date: &str,
+    text: &str,
+) -> Result<Journal, sqlx::Error> {
+    let db = &state.db;
+    let offset = state.config.utc_offset_minutes;
+    let ts = now_ts();
+    let (words, minutes) = text_counts(text);
+    let id = match find_journal_by_date(state, date).await? {
+        Some(journal) => {
+            let content = if journal.content.trim().is_empty() {
+                text.to_string()
+            } else {
+                format!("{}\n\n{}", journal.content.trim_end(), text)
+            };
+            let (words, minutes) = text_counts(&content);
+            sqlx::query(
+                "update journal set content = ?, word_count = ?, reading_minutes = ?, update_time = ?, update_utc_offset = ? where id = ?",
+            )
+                .bind(state.cipher.seal(&content))
+                .bind(words)
+                .bind(minutes)
+                .bind(ts)
+                .bind(offset)
+                .bind(journal.id)
+                .execute(db)
+                .await?;
+            state.render_cache.invalidate(journal.id);
+            journal.id
+        }
+        None => sqlx::query(
+            "insert into journal (content, word_count, reading_minutes, date, create_time, update_time, create_utc_offset, update_utc_offset) values (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(state.cipher.seal(text))
+        .bind(words)
+        .bind(minutes)
+        .bind(date)
+        .bind(ts)
+        .bind(ts)
+        .bind(offset)
+        .bind(offset)
+        .execute(db)
+        .await?
+        .last_insert_rowid(),
+    };
+    load_by_id(state, id).await
+}
+
+/// 用 `content` 覆盖 `date` 当天的日记，当天没有日记时新建
+pub async fn replace_date_content(
+    state: &AppState,
+    date: &str,
+    content: &str,
+) -> Result<Journal, sqlx::Error> {
+    let db = &state.db;
+    let offset = state.config.utc_offset_minutes;
+    let ts = now_ts();
+    let (words, minutes) = text_counts(content);
+    let id = match find_journal_by_date(state, date).await? {
+        Some(journal) => {
+            sqlx::query(
+                "update journal set content = ?, word_count = ?, reading_minutes = ?, update_time = ?, update_utc_offset = ? where id = ?",
+            )
+                .bind(state.cipher.seal(content))
+                .bind(words)
+                .bind(minutes)
+                .bind(ts)
+                .bind(offset)
+                .bind(journal.id)
+                .execute(db)
+                .await?;
+            state.render_cache.invalidate(journal.id);
+            journal.id
+        }
+        None => sqlx::query(
+            "insert into journal (content, word_count, reading_minutes, date, create_time, update_time, create_utc_offset, update_utc_offset) values (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(state.cipher.seal(content))
+        .bind(words)
+        .bind(minutes)
+        .bind(date)
+        .bind(ts)
+        .bind(ts)
+        .bind(offset)
+        .bind(offset)
+        .execute(db)
+        .await?
+        .last_insert_rowid(),
+    };
+    load_by_id(state, id).await
+}
+
+/// 按日期批量 upsert，每 `UPSERT_CHUNK_SIZE` 条一个事务，失败的批次整体回滚并记入 `failed`
+pub async fn upsert_by_date_batch(
+    state: &AppState,
+    entries: &[UpsertEntry],
+    source: &str,
+) -> UpsertReport {
+    let ts = now_ts();
+    let mut report = UpsertReport::default();
+    let mut done = 0usize;
+    for (idx, chunk) in entries.chunks(UPSERT_CHUNK_SIZE).enumerate() {
+        let offset = idx * UPSERT_CHUNK_SIZE;
+        match upsert_chunk(state, chunk, ts).await {
+            Ok(()) => report.upserted += chunk.len(),
+            Err(e) => {
+                warn!(
+                    "{} upsert chunk rolled back: rows {}..{}, {}",
+                    source,
+                    offset,
+                    offset + chunk.len(),
+                    e
+                );
+                report.failed.extend(offset..offset + chunk.len());
+            }
+        }
+        done += chunk.len();
+        info!("{} 导入进度 {}/{}", source, done, entries.len());
+    }
+    if report.upserted > 0 {
+        state.render_cache.clear();
+    }
+    report
+}
+
+async fn upsert_chunk(state: &AppState, chunk: &[UpsertEntry], ts: i64) -> Result<(), sqlx::Error> {
+    let mut tx = state.db.begin().await?;
+    for entry in chunk {
+        let (words, minutes) = text_counts(&entry.content);
+        // 密文每次加密都不同，解密后内容没变时沿用库里的密文，下面的比较才能生效
+        let mut content = state.cipher.seal(&entry.content);
+        if state.cipher.enabled() {
+            let existing =
+                sqlx::query_scalar::<_, String>("select content from journal where date = ?")
+                    .bind(&entry.date)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+            if let Some(existing) = existing
+                && state.cipher.open(existing.clone()) == entry.content
+            {
+                content = existing;
+            }
+        }
+        sqlx::query(
+            r#"
+            insert into journal (
+                content, date, create_time, update_time, metadata, create_utc_offset, update_utc_offset,
+                word_count, reading_minutes
+            )
+            values (?1, ?2, ?3, ?3, case when ?5 is null then ?4 else json_patch(coalesce(?4, '{}'), ?5) end, ?6, ?6, ?7, ?8)
+            on conflict(date) do update set
+                content = excluded.content,
+                word_count = excluded.word_count,
+                reading_minutes = excluded.reading_minutes,
+                metadata = case
+                    when ?5 is null then coalesce(?4, journal.metadata)
+                    else json_patch(coalesce(?4, journal.metadata, '{}'), ?5)
+                end,
+                update_time = excluded.update_time,
+                update_utc_offset = excluded.update_utc_offset
+            -- 内容没变时保留原来的更新时间，重复导入不会让所有日记看起来都被改过
+            where journal.content is not excluded.content
+                or case
+                    when ?5 is null then coalesce(?4, journal.metadata)
+                    else json_patch(coalesce(?4, journal.metadata, '{}'), ?5)
+                end is not journal.metadata
+            "#,
+        )
+        .bind(content)
+        .bind(&entry.date)
+        .bind(ts)
+        .bind(&entry.metadata)
+        .bind(&entry.metadata_patch)
+        .bind(state.config.utc_offset_minutes)
+        .bind(words)
+        .bind(minutes)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+/// 修改日记日期，旧日期在同步仓库里的文件会在下次同步时删除
+#[utoipa::path(
+    post,
+    path = "/journal/{id}/move",
+    tag = "journal",
+    params(("id" = i64, Path), MoveQuery),
+    responses((status = 200, body = ApiResponse<Journal>))
+)]
+pub async fn move_journal(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(query): Query<MoveQuery>,
+) -> ApiResult<Journal> {
+    let date = query.date.trim().to_string();
+    info!("移动日记 id={} -> {}", id, date);
+    if date_util::parse_date(&date).is_none() {
+        return Err(ApiResponse::<Journal>::err(
+            ApiCode::Validation,
+            "date must be yyyy-MM-dd",
+        ));
+    }
+    let (old_date, old_metadata) = sqlx::query_as::<_, (String, Option<String>)>(
+        "select date, metadata from journal where id = ?",
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbQueryFailed, "db query failed"))?
+    .ok_or_else(|| ApiResponse::<Journal>::err(ApiCode::NotFound, "not found"))?;
+
+    if old_date != date {
+        let conflict = find_journal_by_date(&state, &date)
+            .await
+            .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbQueryFailed, "db query failed"))?;
+        if conflict.is_some() {
+            return Err(ApiResponse::<Journal>::err(
+                ApiCode::Conflict,
+                "date already exists, one day only one journal",
+            ));
+        }
+        sqlx::query(
+            "update journal set date = ?, update_time = ?, update_utc_offset = ? where id = ?",
+        )
+        .bind(&date)
+        .bind(now_ts())
+        .bind(state.config.utc_offset_minutes)
+        .bind(id)
+        .execute(&state.db)
+        .await
+        .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbUpdateFailed, "db update failed"))?;
+        state.render_cache.invalidate(id);
+        repo_sync::record_stale_date(&state, &old_date, old_metadata.as_deref()).await;
+    }
+
+    let journal = sqlx::query_as::<_, Journal>(
+        "select id, content, date, create_time, update_time, metadata, word_count, reading_minutes from journal where id = ?",
+    )
+    .bind(id)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbUpdateGetFailed, "db query failed"))?
+    .decrypted(&state);
+    Ok(ApiResponse::ok(journal))
+}
+
+/// 把 `source_id` 的内容接到 `target_id` 末尾，合并附件和标签后删除 source，
+/// 合并前的 target 和 source 记录在 `journal_history`
+#[utoipa::path(
+    post,
+    path = "/journal/merge",
+    tag = "journal",
+    request_body = MergeJournalReq,
+    responses((status = 200, body = ApiResponse<Journal>))
+)]
+pub async fn merge_journals(
+    State(state): State<AppState>,
+    Json(req): Json<MergeJournalReq>,
+) -> ApiResult<Journal> {
+    info!("合并日记 source={} target={}", req.source_id, req.target_id);
+    if req.source_id == req.target_id {
+        return Err(ApiResponse::<Journal>::err(
+            ApiCode::Validation,
+            "source_id and target_id must differ",
+        ));
+    }
+    let state = &state;
+    let load = |id: i64| async move {
+        let journal = sqlx::query_as::<_, Journal>(
+            "select id, content, date, create_time, update_time, metadata, word_count, reading_minutes from journal where id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await?;
+        Ok::<_, sqlx::Error>(journal.map(|v| v.decrypted(state)))
+    };
+    let source = load(req.source_id)
+        .await
+        .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbQueryFailed, "db query failed"))?
+        .ok_or_else(|| ApiResponse::<Journal>::err(ApiCode::NotFound, "source not found"))?;
+    let target = load(req.target_id)
+        .await
+        .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbQueryFailed, "db query failed"))?
+        .ok_or_else(|| ApiResponse::<Journal>::err(ApiCode::NotFound, "target not found"))?;
+
+    let separator = req.separator.as_deref().unwrap_or("\n\n");
+    let content = match (target.content.trim(), source.content.trim()) {
+        ("", _) => source.content.clone(),
+        (_, "") => target.content.clone(),
+        _ => format!(
+            "{}{}{}",
+            target.content.trim_end(),
+            separator,
+            source.content.trim_start()
+        ),
+    };
+    let (words, minutes) = text_counts(&content);
+    let metadata = merge_metadata(target.metadata.as_deref(), source.metadata.as_deref());
+    let detail = serde_json::json!({
+        "sourceId": source.id,
+        "sourceDate": source.date,
+        "sourceContent": state.cipher.seal(&source.content),
+        "sourceMetadata": source.metadata,
+    })
+    .to_string();
+
+    let ts = now_ts();
+    let merged = async {
+        let mut tx = state.db.begin().await?;
+        sqlx::query(
+            "insert into journal_history (journal_id, action, content, metadata, detail, create_time) values (?, 'merge', ?, ?, ?, ?)",
+        )
+        .bind(target.id)
+        .bind(state.cipher.seal(&target.content))
+        .bind(&target.metadata)
+        .bind(detail)
+        .bind(ts)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "update journal set content = ?, word_count = ?, reading_minutes = ?, metadata = ?, update_time = ?, update_utc_offset = ? where id = ?",
+        )
+        .bind(state.cipher.seal(&content))
+        .bind(words)
+        .bind(minutes)
+        .bind(metadata)
+        .bind(ts)
+        .bind(state.config.utc_offset_minutes)
+        .bind(target.id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("delete from journal where id = ?")
+            .bind(source.id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("delete from journal_review where journal_id = ?")
+            .bind(source.id)
+            .execute(&mut *tx)
+            .await?;
+        // 被合并日记的标签转到目标日记上
+        sqlx::query(
+            "insert or ignore into journal_tag (journal_id, tag_id, create_time) select ?, tag_id, create_time from journal_tag where journal_id = ?",
+        )
+        .bind(target.id)
+        .bind(source.id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("delete from journal_tag where journal_id = ?")
+            .bind(source.id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
+    .await;
+    merged.map_err(|e| {
+        warn!("合并日记失败: {}", e);
+        ApiResponse::<Journal>::err(ApiCode::DbUpdateFailed, "db update failed")
+    })?;
+    state.render_cache.invalidate(source.id);
+    state.render_cache.invalidate(target.id);
+    repo_sync::record_stale_date(state, &source.date, source.metadata.as_deref()).await;
+
+    let journal = load(target.id)
+        .await
+        .map_err(|_| ApiResponse::<Journal>::err(ApiCode::DbUpdateGetFailed, "db query failed"))?
+        .ok_or_else(|| ApiResponse::<Journal>::err(ApiCode::NotFound, "not found"))?;
+    Ok(ApiResponse::ok(journal))
+}
+
+/// target 的字段优先，附件和标签取并集
+fn merge_metadata(target: Option<&str>, source: Option<&str>) -> Option<String> {
+    let mut merged = JournalMetadata::parse(target);
+    let source = JournalMetadata::parse(source);
+    if merged.latitude.is_none() {
+        merged.latitude = source.latitude;
+        merged.longitude = source.longitude;
+    }
+    if merged.place_name.is_none() {
+        merged.place_name = source.place_name.clone();
+    }
+    if merged.pinned.is_none() {
+        merged.pinned = source.pinned;
+    }
+    let mut attachments = merged.attachments.take().unwrap_or_default();
+    for uri in source.attachments.unwrap_or_default() {
+        if !attachments.contains(&uri) {
+            attachments.push(uri);
+        }
+    }
+    merged.attachments = (!attachments.is_empty()).then_some(attachments);
+    for (key, value) in source.extra {
+        match (merged.extra.get_mut(&key), value) {
+            (Some(serde_json::Value::Array(list)), serde_json::Value::Array(extra)) => {
+                for item in extra {
+                    if !list.contains(&item) {
+                        list.push(item);
+                    }
+                }
+            }
+            (Some(_), _) => {}
+            (None, value) => {
+                merged.extra.insert(key, value);
+            }
+        }
+    }
+    merged.to_json()
+}
+
+#[utoipa::path(
+    delete,
+    path = "/journal/{id}",
+    tag = "journal",
+    params(("id" = i64, Path)),
+    responses((status = 200, description = "`data` 为 null", body = ApiResponse<serde_json::Value>))
+)]
 pub async fn delete_journal(State(state): State<AppState>, Path(id): Path<i64>) -> ApiResult<()> {
+    let db_err = |_| ApiResponse::<()>::err(ApiCode::DbDeleteFailed, "db delete failed");
+    let mut tx = state.db.begin().await.map_err(db_err)?;
+    // `retention.trash_days` 大于 0 时先放进回收站
+    if state.config.retention.trash_days > 0 {
+        sqlx::query(
+            "insert into journal_trash (journal_id, date, content, metadata, create_time, update_time, delete_time, tag_ids) select id, date, content, metadata, create_time, update_time, ?, (select json_group_array(tag_id) from journal_tag where journal_id = journal.id) from journal where id = ?",
+        )
+        .bind(now_ts())
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+    }
     let result = sqlx::query("delete from journal where id = ?")
         .bind(id)
-        .execute(&state.db)
+        .execute(&mut *tx)
         .await
-        .map_err(|_| ApiResponse::<()>::err(ApiCode::DbDeleteFailed, "db delete failed"))?;
+        .map_err(db_err)?;
 
     if result.rows_affected() == 0 {
         return Err(ApiResponse::<()>::err(ApiCode::NotFound, "not found"));
     }
+    tx.commit().await.map_err(db_err)?;
+    state.render_cache.invalidate(id);
+
+    let _ = sqlx::query("delete from journal_review where journal_id = ?")
+        .bind(id)
+        .execute(&state.db)
+        .await;
+    // 放进回收站时标签 id 已经记在 `journal_trash.tag_ids`，恢复时重新挂上
+    let _ = sqlx::query("delete from journal_tag where journal_id = ?")
+        .bind(id)
+        .execute(&state.db)
+        .await;
 
     Ok(ApiResponse::ok(()))
 }
@@ -0,0 +1,19 @@
+use crate::app_state::AppState;
+use crate::util::i18n::{self, Locale};
+use axum::extract::{Request, State};
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::Response;
+
+/// 按 `Accept-Language` 选出本次请求的语言，处理函数里的 `ApiResponse::err` 据此翻译 `msg`；
+/// 没有支持的语言时用配置的 `locale`，它也无效时为英文
+pub async fn negotiate(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let locale = request
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(Locale::from_accept_language)
+        .or_else(|| Locale::parse(&state.config.locale))
+        .unwrap_or(Locale::En);
+    i18n::scope(locale, next.run(request)).await
+}
@@ -1,7 +1,44 @@
-mod file;
+mod archive;
+mod auth;
+mod backup;
+mod badge;
+mod book;
+mod capabilities;
+mod conditional;
+mod cursor;
+mod date_pattern;
+mod db_backup;
+mod digest;
+mod draft;
+mod duplicates;
+mod encryption;
+mod errors;
+mod export;
+pub mod file;
+mod hooks;
+mod import_obsidian;
+mod import_progress;
+mod import_wordpress;
 mod import_zip;
-mod journal;
+mod integrity;
+mod jobs;
+pub mod journal;
+mod locale;
+mod month;
+mod openapi;
+mod public;
+mod quick;
+mod rate_limit;
 mod repo_sync;
 mod resp;
+mod review;
+mod search;
+mod security;
 pub mod server;
 mod settings;
+mod setup;
+mod share;
+mod stats;
+mod status;
+pub mod tag;
+mod trash;
@@ -0,0 +1,153 @@
+use crate::app_state::AppState;
+use crate::http::file;
+use crate::http::journal::JournalMetadata;
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::util::{date_util, markdown};
+use axum::extract::{Path, State};
+use serde::Serialize;
+use tracing::info;
+use utoipa::ToSchema;
+
+const SUMMARY_CHARS: usize = 80;
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MonthView {
+    pub month: String,
+    /// 有日记的天数
+    pub journal_count: i64,
+    pub word_count: i64,
+    /// 这个月的每一天，没有日记的日子也在里面
+    pub days: Vec<MonthDay>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MonthDay {
+    pub date: String,
+    /// 0 为周一，6 为周日
+    pub weekday: i64,
+    pub exists: bool,
+    pub id: Option<i64>,
+    /// `metadata.title`，没有时取正文开头的标题行
+    pub title: Option<String>,
+    /// 正文的纯文本开头
+    pub summary: Option<String>,
+    pub word_count: i64,
+    pub reading_minutes: i64,
+    /// `metadata.mood`
+    pub mood: Option<String>,
+    /// 正文引用和 `metadata.attachments` 中的文件数，不检查文件是否还在
+    pub attachment_count: i64,
+}
+
+/// 日历页一次取完一个月需要的数据
+#[utoipa::path(
+    get,
+    path = "/journal/month/{month}",
+    tag = "journal",
+    params(("month" = String, Path, description = "yyyy-MM")),
+    responses((status = 200, body = ApiResponse<MonthView>))
+)]
+pub async fn month_view(
+    State(state): State<AppState>,
+    Path(month): Path<String>,
+) -> ApiResult<MonthView> {
+    let month = month.trim().to_string();
+    let (first, next) = month_range(&month).ok_or_else(|| {
+        ApiResponse::<MonthView>::err(ApiCode::Validation, "month must be yyyy-MM")
+    })?;
+    info!("获取月视图 month={}", month);
+
+    let rows = sqlx::query_as::<_, (i64, String, String, Option<String>, i64, i64)>(
+        "select id, date, content, metadata, coalesce(word_count, 0), coalesce(reading_minutes, 0) from journal where date like ? order by date",
+    )
+    .bind(format!("{}-%", month))
+    .fetch_all(&state.read_db)
+    .await
+    .map_err(|_| ApiResponse::<MonthView>::err(ApiCode::DbListFailed, "db query failed"))?;
+
+    let mut days = (first..next)
+        .map(|d| MonthDay {
+            date: date_util::date_from_days(d),
+            weekday: date_util::weekday_from_days(d),
+            exists: false,
+            id: None,
+            title: None,
+            summary: None,
+            word_count: 0,
+            reading_minutes: 0,
+            mood: None,
+            attachment_count: 0,
+        })
+        .collect::<Vec<_>>();
+    let (mut journal_count, mut word_count) = (0, 0);
+    for (id, date, content, metadata, words, minutes) in rows {
+        let Some(day) = days.iter_mut().find(|v| v.date == date) else {
+            continue;
+        };
+        let content = state.cipher.open(content);
+        let metadata = JournalMetadata::parse(metadata.as_deref());
+        day.exists = true;
+        day.id = Some(id);
+        day.title = metadata.title.clone().or_else(|| heading(&content));
+        day.summary = Some(summary(&content));
+        day.word_count = words;
+        day.reading_minutes = minutes;
+        day.mood = metadata.mood.clone();
+        day.attachment_count = file::journal_file_refs(&content, &metadata).len() as i64;
+        journal_count += 1;
+        word_count += words;
+    }
+
+    Ok(ApiResponse::ok(MonthView {
+        month,
+        journal_count,
+        word_count,
+        days,
+    }))
+}
+
+/// 这个月第一天和下个月第一天的天数
+fn month_range(month: &str) -> Option<(i64, i64)> {
+    let (y, m) = month.split_once('-')?;
+    if y.len() != 4 || m.len() != 2 {
+        return None;
+    }
+    let (y, m) = (y.parse::<i64>().ok()?, m.parse::<i64>().ok()?);
+    if !(1..=12).contains(&m) {
+        return None;
+    }
+    let next = if m == 12 { (y + 1, 1) } else { (y, m + 1) };
+    Some((
+        date_util::days_from_civil(y, m, 1),
+        date_util::days_from_civil(next.0, next.1, 1),
+    ))
+}
+
+/// 第一个非空行是 markdown 标题时作为标题
+fn heading(content: &str) -> Option<String> {
+    let line = content.lines().map(str::trim).find(|v| !v.is_empty())?;
+    let text = line.trim_start_matches('#');
+    (text.len() < line.len() && text.starts_with(' '))
+        .then(|| text.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn summary(content: &str) -> String {
+    let text = markdown::plain_text(content);
+    let mut out = String::new();
+    for (i, word) in text.split_whitespace().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        out.push_str(word);
+        if out.chars().count() >= SUMMARY_CHARS {
+            break;
+        }
+    }
+    match out.char_indices().nth(SUMMARY_CHARS) {
+        Some((i, _)) => format!("{}…", &out[..i]),
+        None => out,
+    }
+}
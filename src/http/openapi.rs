@@ -0,0 +1,83 @@
+use crate::http::{
+    draft, errors, file, import_obsidian, import_progress, import_wordpress, import_zip, journal,
+    month, repo_sync, search, settings,
+};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+/// 新增或修改接口时同步更新这里的 `paths`，请求和返回结构上的 `ToSchema` 会自动带上
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "DayLog API",
+        description = "除文件下载和 SSE 外，接口都返回 `ApiResponse`：http 状态码总是 200，\
+            `code` 为 200 表示成功，失败时 `error` 是对应的英文标识，完整列表见 `GET /errors`，\
+            `msg` 是错误说明，按 `Accept-Language` 返回英文或中文。\
+            开启 `[auth]` 时用 `Authorization: Bearer <token>` 或 `X-DayLog-Token` 请求头传令牌。"
+    ),
+    paths(
+        journal::create_journal,
+        journal::list_journals,
+        journal::list_recent_journals,
+        journal::list_journal_map,
+        journal::journal_neighbors,
+        search::search_journals,
+        month::month_view,
+        journal::get_journal,
+        journal::update_journal,
+        journal::delete_journal,
+        journal::move_journal,
+        journal::merge_journals,
+        draft::get_draft,
+        draft::save_draft,
+        draft::delete_draft,
+        draft::promote_draft,
+        settings::get_settings,
+        settings::update_settings,
+        file::upload_file,
+        file::serve_file_by_id,
+        file::serve_picture,
+        file::serve_media,
+        file::serve_attachment,
+        file::list_file_references,
+        file::list_journal_files,
+        repo_sync::sync_journal,
+        repo_sync::diagnose_sync,
+        import_zip::import_journal_zip,
+        import_zip::import_journal_zip_async,
+        import_progress::import_events,
+        import_wordpress::import_wordpress,
+        import_obsidian::import_obsidian,
+        errors::list_errors,
+    ),
+    components(schemas(import_zip::ImportOptions, import_obsidian::ObsidianOptions)),
+    modifiers(&BearerAuth),
+    security(("token" = [])),
+    tags(
+        (name = "journal", description = "日记和自动保存的草稿"),
+        (name = "settings", description = "保存在数据库中的设置"),
+        (name = "file", description = "上传和读取附件"),
+        (name = "sync", description = "git 仓库同步"),
+        (name = "import", description = "从压缩包或其他应用导入"),
+        (name = "meta", description = "接口本身的说明"),
+    )
+)]
+struct ApiDoc;
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// `GET /api-docs/openapi.json` 返回文档，`/api-docs` 是内置的 Swagger UI
+pub fn swagger_ui() -> SwaggerUi {
+    SwaggerUi::new("/api-docs").url("/api-docs/openapi.json", ApiDoc::openapi())
+}
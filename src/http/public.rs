@@ -0,0 +1,49 @@
+use crate::app_state::AppState;
+use crate::http::resp::{ApiCode, ApiResponse};
+use crate::http::{badge, rate_limit};
+use axum::Router;
+use axum::extract::State;
+use axum::http::{HeaderValue, header};
+use axum::middleware;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use serde::Serialize;
+use tracing::warn;
+
+const STATS_MAX_AGE: &str = "public, max-age=300";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicStats {
+    pub streak: i64,
+    pub total: i64,
+}
+
+/// 不需要鉴权的接口；没有开启的接口不注册，请求返回 404
+pub fn router(state: &AppState) -> Router<AppState> {
+    let mut router = Router::new();
+    if state.config.public.stats {
+        router = router.route("/public/stats", get(public_stats));
+    }
+    router.layer(middleware::from_fn_with_state(
+        state.clone(),
+        rate_limit::limit,
+    ))
+}
+
+/// 只有汇总数字，不含日期和正文，可以放到个人主页上展示
+pub async fn public_stats(State(state): State<AppState>) -> Response {
+    let mut resp = match badge::load_counts(&state).await {
+        Ok((streak, total)) => ApiResponse::ok(PublicStats { streak, total }).into_response(),
+        Err(e) => {
+            warn!("读取公开统计失败: {}", e);
+            ApiResponse::<PublicStats>::err(ApiCode::DbQueryFailed, "db query failed")
+                .into_response()
+        }
+    };
+    resp.headers_mut().insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(STATS_MAX_AGE),
+    );
+    resp
+}
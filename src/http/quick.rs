@@ -0,0 +1,107 @@
+use crate::app_state::AppState;
+use crate::config::app_config::TokenScope;
+use crate::http::{auth, journal};
+use axum::Form;
+use axum::extract::{ConnectInfo, FromRequest, Query, Request, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use tracing::{info, warn};
+
+pub const TOKEN_HEADER: &str = "x-daylog-token";
+
+#[derive(Debug, Deserialize)]
+pub struct QuickQuery {
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuickForm {
+    pub text: String,
+}
+
+/// 给 iOS 快捷指令 / Tasker 用的追加接口，请求和响应都是纯文本；
+/// 令牌错误和其他接口一样计入锁定，锁定期间返回 423
+pub async fn quick_append(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Query(query): Query<QuickQuery>,
+    request: Request,
+) -> Response {
+    let expected = state.config.quick.token.trim();
+    if expected.is_empty() && !state.config.auth.enabled() {
+        return (StatusCode::NOT_FOUND, "quick append disabled").into_response();
+    }
+    let provided = query
+        .token
+        .or_else(|| header_token(request.headers()))
+        .unwrap_or_default();
+    let provided = provided.trim();
+    let attempt = match auth::TokenAttempt::begin(&state, request.headers(), peer, provided).await {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+    // 也接受带 `upload-only` 或更高权限的 api 令牌
+    let allowed = (!expected.is_empty() && token_eq(provided, expected))
+        || auth::authenticate(&state, provided, Some(attempt.ip()))
+            .await
+            .is_some_and(|v| v.allows(TokenScope::UploadOnly));
+    if !allowed {
+        warn!("quick append rejected: invalid token, ip={}", attempt.ip());
+        attempt.failed(&state, provided, "POST", "/quick").await;
+        return (StatusCode::UNAUTHORIZED, "invalid token").into_response();
+    }
+    attempt.succeeded(&state).await;
+
+    let is_form = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.starts_with("application/x-www-form-urlencoded"))
+        .unwrap_or(false);
+    let text = if is_form {
+        match Form::<QuickForm>::from_request(request, &state).await {
+            Ok(Form(form)) => form.text,
+            Err(_) => return (StatusCode::BAD_REQUEST, "invalid form body").into_response(),
+        }
+    } else {
+        match String::from_request(request, &state).await {
+            Ok(v) => v,
+            Err(_) => return (StatusCode::BAD_REQUEST, "invalid text body").into_response(),
+        }
+    };
+
+    let text = text.trim();
+    if text.is_empty() {
+        return (StatusCode::BAD_REQUEST, "text required").into_response();
+    }
+
+    let today = state.config.today();
+    match journal::append_to_date(&state, &today, text).await {
+        Ok(_) => {
+            info!("quick append date={}, len={}", today, text.chars().count());
+            (StatusCode::OK, format!("appended to {}", today)).into_response()
+        }
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "db update failed").into_response(),
+    }
+}
+
+pub fn header_token(headers: &HeaderMap) -> Option<String> {
+    if let Some(v) = headers.get(TOKEN_HEADER).and_then(|v| v.to_str().ok()) {
+        return Some(v.to_string());
+    }
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|v| v.to_string())
+}
+
+pub fn token_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
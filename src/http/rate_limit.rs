@@ -0,0 +1,114 @@
+use crate::app_state::AppState;
+use crate::http::auth;
+use crate::http::resp::{ApiCode, ApiResponse};
+use crate::util::date_util;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{HeaderValue, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{LazyLock, Mutex};
+use tracing::warn;
+
+const WINDOW_SECS: i64 = 60;
+/// 计数表超过这么多项时清掉已过期的窗口
+const PRUNE_THRESHOLD: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Bucket {
+    Default,
+    Upload,
+    Sync,
+    Public,
+}
+
+struct Window {
+    start: i64,
+    count: u32,
+}
+
+static WINDOWS: LazyLock<Mutex<HashMap<(String, Bucket), Window>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 按 ip 和路由分组计数，每组一分钟一个窗口；放在鉴权之前，猜令牌的请求也会被限制。
+/// `/public` 下不需要鉴权的接口单独计数，不和私有接口共用额度
+pub async fn limit(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(bucket) = bucket_of(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let cfg = &state.config.rate_limit;
+    let max = match bucket {
+        Bucket::Default => cfg.per_minute,
+        Bucket::Upload => cfg.upload_per_minute,
+        Bucket::Sync => cfg.sync_per_minute,
+        Bucket::Public => state.config.public.per_minute,
+    };
+    if max == 0 {
+        return next.run(request).await;
+    }
+    let ip = auth::client_ip(
+        request.headers(),
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|v| v.0),
+        state.config.auth.trust_forwarded_for,
+    );
+    match hit(&ip, bucket, max, date_util::now_secs()) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            warn!(
+                "rate limited: ip={}, {} {}, retry after {}s",
+                ip,
+                request.method(),
+                request.uri().path(),
+                retry_after
+            );
+            let (_, body) = ApiResponse::<()>::err(
+                ApiCode::QuotaExceeded,
+                "too many requests, try again later",
+            );
+            let mut resp = (StatusCode::TOO_MANY_REQUESTS, body).into_response();
+            if let Ok(v) = HeaderValue::from_str(&retry_after.to_string()) {
+                resp.headers_mut().insert(header::RETRY_AFTER, v);
+            }
+            resp
+        }
+    }
+}
+
+/// 前端页面和静态文件不计数
+fn bucket_of(path: &str) -> Option<Bucket> {
+    let file = path.starts_with("/files/") && !path.ends_with("/references");
+    if path == "/" || path.starts_with("/static/") || file {
+        return None;
+    }
+    Some(match path {
+        "/upload" => Bucket::Upload,
+        "/sync/journal" => Bucket::Sync,
+        _ if path.starts_with("/public/") => Bucket::Public,
+        _ => Bucket::Default,
+    })
+}
+
+/// 计数加一，超过 `max` 时返回窗口剩余的秒数
+fn hit(ip: &str, bucket: Bucket, max: u32, now: i64) -> Result<(), i64> {
+    let mut windows = WINDOWS.lock().unwrap();
+    if windows.len() > PRUNE_THRESHOLD {
+        windows.retain(|_, w| w.start + WINDOW_SECS > now);
+    }
+    let window = windows.entry((ip.to_string(), bucket)).or_insert(Window {
+        start: now,
+        count: 0,
+    });
+    if window.start + WINDOW_SECS <= now {
+        window.start = now;
+        window.count = 0;
+    }
+    if window.count >= max {
+        return Err(window.start + WINDOW_SECS - now);
+    }
+    window.count += 1;
+    Ok(())
+}
@@ -1,33 +1,70 @@
 use crate::app_state::AppState;
-use crate::config::app_config::SyncConfig;
+use crate::archive;
+use crate::config::app_config::{SyncConfig, SyncOutput};
+use crate::error::DayLogError;
+use crate::http::date_pattern::{self, PathFields, PathMatch};
+use crate::http::journal::{self, JournalMetadata, UpsertEntry};
 use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
 use crate::http::settings;
 use crate::http::settings::DatePlaceholders;
+use crate::http::tag;
+use crate::job::{self, JobDef, JobFuture};
+use crate::notify::{self, NotifyEvent};
+use crate::sync::git::{self, AuthMode};
+use crate::sync::{self, PushInput, SyncBackend, SyncOutputFile};
+use crate::util::{crypto, date_util, front_matter, markdown};
 use axum::extract::State;
-use git2::{
-    BranchType, Cred, FetchOptions, PushOptions, RemoteCallbacks, Repository, Signature,
-    build::CheckoutBuilder, build::RepoBuilder,
-};
+use git2::{Cred, RemoteCallbacks};
+use rayon::prelude::*;
 use serde::Serialize;
 use sqlx::FromRow;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
 use std::fs;
-use std::path::{Component, Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::task;
-use tracing::{error, info, warn};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+use tracing::{debug, error, info, warn};
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SyncResp {
     pub pushed: bool,
     pub commit_id: String,
+    /// 改写了上一次定时同步的提交（`sync.squash_window`）
+    pub squashed: bool,
+    /// 第一个输出目标，`outputs` 中是全部
     pub file_path: String,
     pub format: String,
+    pub outputs: Vec<SyncOutputResp>,
+    /// `two_way` 模式下从仓库拉取更新的日记数
+    pub pulled: usize,
+    /// 两边都改过的日记及处理结果
+    pub conflicts: Vec<SyncConflict>,
     pub message: String,
 }
 
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflict {
+    pub date: String,
+    pub path: String,
+    /// `remote` 用了仓库中的版本，`local` 保留数据库中的版本，`both` 合并了两边
+    pub resolution: &'static str,
+    pub local_update_time: i64,
+    /// 仓库中最后一次改动这个文件的提交时间
+    pub remote_commit_time: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncOutputResp {
+    pub path_template: String,
+    pub format: String,
+    /// 本次写出的文件数
+    pub files: usize,
+}
+
 #[derive(Debug, Clone, Serialize, FromRow)]
 #[serde(rename_all = "camelCase")]
 struct JournalRow {
@@ -36,31 +73,101 @@ struct JournalRow {
     date: String,
     create_time: i64,
     update_time: i64,
+    metadata: Option<String>,
+    /// 只给 `index.json` 用，不写进同步的日记文件
+    #[sqlx(default)]
+    #[serde(skip)]
+    word_count: Option<i64>,
 }
 
-#[derive(Clone)]
-struct SyncTaskInput {
-    cfg: SyncConfig,
-    repo_path: PathBuf,
-    output_files: Vec<SyncOutputFile>,
-    commit_message: String,
+/// 触发同步的来源，每种来源可以配置单独的提交信息模板
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncTrigger {
+    /// `POST /sync/journal`
+    Manual,
+    /// 保存日记时带 `auto_sync: true`
+    AutoSync,
+    /// `sync.interval_minutes` 定时同步
+    Scheduled,
+    /// 启动导入后的回写，`sync.sync_on_startup`
+    Startup,
 }
 
-struct SyncTaskOutput {
-    pushed: bool,
-    commit_id: String,
+impl SyncTrigger {
+    pub const ALL: [SyncTrigger; 4] = [
+        SyncTrigger::Manual,
+        SyncTrigger::AutoSync,
+        SyncTrigger::Scheduled,
+        SyncTrigger::Startup,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SyncTrigger::Manual => "manual",
+            SyncTrigger::AutoSync => "auto_sync",
+            SyncTrigger::Scheduled => "scheduled",
+            SyncTrigger::Startup => "startup",
+        }
+    }
 }
 
-#[derive(Clone)]
-struct SyncOutputFile {
-    rel_path: PathBuf,
-    content: String,
+/// `sync.index_file` 开启时写在输出根目录的索引文件
+const INDEX_FILE_NAME: &str = "index.json";
+/// `sync.readme_file` 开启时写在输出根目录的目录页
+const README_FILE_NAME: &str = "README.md";
+/// 正文没有标题时取第一行的前多少个字符
+const README_TITLE_CHARS: usize = 40;
+
+/// 上一次定时同步推送的提交，`sync.squash_window` 内的下一次定时同步会改写它
+#[derive(Debug, Default)]
+struct SyncState {
+    squash_base: Option<SquashBase>,
+}
+
+#[derive(Debug, Clone)]
+struct SquashBase {
+    commit_id: String,
+    /// 这一串被改写的提交中第一次提交的时间，窗口从这里开始算
+    since: i64,
 }
 
+static SYNC_LOCK: LazyLock<tokio::sync::Mutex<SyncState>> =
+    LazyLock::new(|| tokio::sync::Mutex::new(SyncState::default()));
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum AuthMode {
-    Password,
-    Ssh,
+enum ConflictStrategy {
+    RemoteWins,
+    LocalWins,
+    NewestWins,
+    KeepBoth,
+}
+
+impl ConflictStrategy {
+    fn parse(s: &str) -> Result<Self, DayLogError> {
+        match s.trim() {
+            "remote_wins" => Ok(ConflictStrategy::RemoteWins),
+            "local_wins" => Ok(ConflictStrategy::LocalWins),
+            "newest_wins" => Ok(ConflictStrategy::NewestWins),
+            "keep_both" => Ok(ConflictStrategy::KeepBoth),
+            other => Err(DayLogError::Validation(format!(
+                "invalid sync.conflict_strategy: {} (expected remote_wins, local_wins, newest_wins or keep_both)",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct PullReport {
+    pulled: usize,
+    conflicts: Vec<SyncConflict>,
+}
+
+#[derive(Debug, FromRow)]
+struct LocalJournalState {
+    date: String,
+    content: String,
+    update_time: i64,
 }
 
 #[derive(Debug)]
@@ -68,6 +175,8 @@ struct StartupImportEntry {
     path: String,
     date: String,
     content: String,
+    metadata: Option<String>,
+    fields: PathFields,
 }
 
 #[derive(Debug)]
@@ -85,7 +194,7 @@ struct StartupImportParseResult {
     entries: Vec<StartupImportEntry>,
 }
 
-pub async fn startup_sync_to_db(state: &AppState) -> Result<(), String> {
+pub async fn startup_sync_to_db(state: &AppState) -> Result<(), DayLogError> {
     let cfg = state.config.sync.clone();
     if !cfg.enabled {
         info!("startup sync skipped: sync.enabled=false");
@@ -95,84 +204,73 @@ pub async fn startup_sync_to_db(state: &AppState) -> Result<(), String> {
         info!("startup sync skipped: sync.repo_url is empty");
         return Ok(());
     }
-    let auth_mode = resolve_auth_mode(&cfg)?;
-    validate_auth_config(&cfg, auth_mode)?;
+    let backend = sync::backend(&cfg, &state.http)?;
+    if is_two_way(&cfg)? {
+        // 双向同步不整体导入，按冲突策略拉取仓库中改过的日记
+        if cfg.sync_on_startup {
+            run_sync(state, SyncTrigger::Startup).await?;
+        } else {
+            let strategy = ConflictStrategy::parse(&cfg.conflict_strategy)?;
+            let _lock = SYNC_LOCK.lock().await;
+            let report = pull_remote_changes(state, backend, &cfg, strategy).await?;
+            info!(
+                "startup two-way pull done: pulled={}, conflicts={}",
+                report.pulled,
+                report.conflicts.len()
+            );
+        }
+        return Ok(());
+    }
 
     let date_placeholders = settings::default_date_placeholders();
-    let mut patterns = cfg
-        .import_patterns
-        .iter()
-        .map(|v| v.trim().to_string())
-        .filter(|v| !v.is_empty())
-        .collect::<Vec<_>>();
-    if patterns.is_empty() {
-        patterns.push(cfg.output_path.clone());
-    }
-    for p in &patterns {
-        validate_startup_import_pattern(p, &date_placeholders)?;
-    }
+    let patterns = import_patterns(&cfg, &date_placeholders)?;
 
     let repo_path = PathBuf::from(state.config.get_sync_repo_path());
-    let cfg_for_task = cfg.clone();
     let repo_path_for_task = repo_path.clone();
-    task::spawn_blocking(move || prepare_repo_for_import(&cfg_for_task, &repo_path_for_task))
-        .await
-        .map_err(|_| "startup sync task join failed".to_string())??;
+    state
+        .blocking
+        .run("startup prepare repo", move || {
+            backend.pull(&repo_path_for_task)
+        })
+        .await??;
 
     let patterns_for_task = patterns.clone();
     let placeholders_for_task = date_placeholders.clone();
     let repo_path_for_scan = repo_path.clone();
-    let parse_result = task::spawn_blocking(move || {
-        scan_repo_markdown_entries(
-            repo_path_for_scan.as_path(),
-            &patterns_for_task,
-            &placeholders_for_task,
-        )
-    })
-    .await
-    .map_err(|_| "startup import scan task join failed".to_string())??;
-
-    let ts = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs() as i64)
-        .unwrap_or(0);
-    let mut imported_count = 0usize;
-    for entry in parse_result.entries {
-        let exist_id =
-            sqlx::query_scalar::<_, i64>("select id from journal where date = ? limit 1")
-                .bind(&entry.date)
-                .fetch_optional(&state.db)
-                .await
-                .map_err(|_| "startup import query failed".to_string())?;
-
-        let result = match exist_id {
-            Some(id) => {
-                sqlx::query("update journal set content = ?, update_time = ? where id = ?")
-                    .bind(&entry.content)
-                    .bind(ts)
-                    .bind(id)
-                    .execute(&state.db)
-                    .await
-            }
-            None => sqlx::query(
-                "insert into journal (content, date, create_time, update_time) values (?, ?, ?, ?)",
+    let parse_result = state
+        .blocking
+        .run("startup import scan", move || {
+            scan_repo_markdown_entries(
+                repo_path_for_scan.as_path(),
+                &patterns_for_task,
+                &placeholders_for_task,
             )
-            .bind(&entry.content)
-            .bind(&entry.date)
-            .bind(ts)
-            .bind(ts)
-            .execute(&state.db)
-            .await,
-        };
+        })
+        .await??;
 
-        if result.is_ok() {
-            imported_count += 1;
-        } else {
-            warn!(
-                "startup import failed to upsert journal: date={}, path={}",
-                entry.date, entry.path
-            );
+    // 已归档并移出数据库的日记不从仓库导回
+    let archived_before = archive::removed_before(state).await.unwrap_or_default();
+    let mut paths = Vec::with_capacity(parse_result.entries.len());
+    let mut entries = Vec::with_capacity(parse_result.entries.len());
+    for entry in parse_result.entries {
+        if entry.date < archived_before {
+            continue;
         }
+        paths.push(entry.path);
+        entries.push(UpsertEntry {
+            date: entry.date,
+            content: entry.content,
+            metadata: entry.metadata,
+            metadata_patch: JournalMetadata::patch_from_path(entry.fields),
+        });
+    }
+    let report = journal::upsert_by_date_batch(state, &entries, "startup import").await;
+    let imported_count = report.upserted;
+    for idx in report.failed {
+        warn!(
+            "startup import failed to upsert journal: date={}, path={}",
+            entries[idx].date, paths[idx]
+        );
     }
 
     let summary = StartupImportScanResult {
@@ -188,36 +286,233 @@ pub async fn startup_sync_to_db(state: &AppState) -> Result<(), String> {
         summary.skipped_count,
         repo_path.display()
     );
+    if cfg.sync_on_startup {
+        run_sync(state, SyncTrigger::Startup).await?;
+    }
     Ok(())
 }
 
-fn prepare_repo_for_import(cfg: &SyncConfig, repo_path: &Path) -> Result<(), String> {
-    if let Some(parent) = repo_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+/// 从仓库导入时匹配的路径：`sync.import_patterns`，没有配置时用按天分文件的输出目标
+fn import_patterns(
+    cfg: &SyncConfig,
+    placeholders: &DatePlaceholders,
+) -> Result<Vec<String>, DayLogError> {
+    let mut patterns = cfg
+        .import_patterns
+        .iter()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect::<Vec<_>>();
+    if patterns.is_empty() {
+        if cfg.outputs.is_empty() {
+            patterns.push(cfg.output_path.clone());
+        } else {
+            // 汇总文件不对应单篇日记，只用按天分文件的目标
+            patterns.extend(
+                cfg.outputs
+                    .iter()
+                    .map(|v| v.path_template.trim().to_string())
+                    .filter(|v| date_pattern::contains_date_placeholder(v, placeholders)),
+            );
+        }
+    }
+    for p in &patterns {
+        date_pattern::validate_import_pattern(p, placeholders).map_err(DayLogError::Validation)?;
+    }
+    Ok(patterns)
+}
+
+fn is_two_way(cfg: &SyncConfig) -> Result<bool, DayLogError> {
+    match cfg.mode.trim() {
+        "push" => Ok(false),
+        "two_way" => Ok(true),
+        other => Err(DayLogError::Validation(format!(
+            "invalid sync.mode: {} (expected push or two_way)",
+            other
+        ))),
     }
-    let repo = if repo_path.join(".git").exists() {
-        Repository::open(repo_path).map_err(|e| e.message().to_string())?
-    } else {
-        clone_repo(cfg, repo_path)?
-    };
-    checkout_and_fast_forward(&repo, cfg)
 }
 
+/// 拉取仓库中的日记，和上次双向同步之后的改动比较：只有仓库改过的导入，只有本地改过的留给推送，
+/// 两边都改过且内容不同的按冲突策略处理；等待删除的旧路径不导入
+async fn pull_remote_changes(
+    state: &AppState,
+    backend: Arc<dyn SyncBackend>,
+    cfg: &SyncConfig,
+    strategy: ConflictStrategy,
+) -> Result<PullReport, DayLogError> {
+    let placeholders = settings::default_date_placeholders();
+    let patterns = import_patterns(cfg, &placeholders)?;
+    let since = settings::load_sync_last_two_way(state).await.unwrap_or(0);
+    let repo_path = PathBuf::from(state.config.get_sync_repo_path());
+    let (parsed, commit_times) = state
+        .blocking
+        .run("two-way sync pull", move || {
+            backend.pull(&repo_path)?;
+            let parsed = scan_repo_markdown_entries(&repo_path, &patterns, &placeholders)?;
+            let paths = parsed
+                .entries
+                .iter()
+                .map(|v| v.path.clone())
+                .collect::<Vec<_>>();
+            let times = backend.modified_times(&repo_path, &paths)?;
+            Ok::<_, DayLogError>((parsed, times))
+        })
+        .await??;
+
+    let local =
+        sqlx::query_as::<_, LocalJournalState>("select date, content, update_time from journal")
+            .fetch_all(&state.db)
+            .await?
+            .into_iter()
+            .map(|mut v| {
+                v.content = state.cipher.open(v.content);
+                (v.date.clone(), v)
+            })
+            .collect::<HashMap<_, _>>();
+    let pending = sqlx::query_scalar::<_, String>("select rel_path from sync_pending_delete")
+        .fetch_all(&state.db)
+        .await?
+        .into_iter()
+        .collect::<HashSet<_>>();
+    let archived_before = archive::removed_before(state).await.unwrap_or_default();
+
+    let mut report = PullReport::default();
+    let mut entries = Vec::new();
+    for mut entry in parsed.entries {
+        if entry.date < archived_before || pending.contains(&entry.path) {
+            continue;
+        }
+        // `encryption.sync_encrypted` 时仓库里是密文
+        entry.content = state.cipher.open(entry.content);
+        let remote_time = commit_times.get(&entry.path).copied().unwrap_or(0);
+        // 为 Some 时是 `keep_both` 合并后的内容
+        let merged = match local.get(&entry.date) {
+            None => None,
+            Some(l) if l.content.trim_end() == entry.content.trim_end() => continue,
+            // 之前已经用 `keep_both` 合并过这个版本
+            Some(l)
+                if l.content
+                    .trim_end()
+                    .ends_with(conflict_suffix(&entry.content, &entry.path).trim_end()) =>
+            {
+                continue;
+            }
+            Some(_) if remote_time < since => continue,
+            Some(l) if l.update_time < since => None,
+            Some(l) => {
+                let resolution = match strategy {
+                    ConflictStrategy::RemoteWins => "remote",
+                    ConflictStrategy::LocalWins => "local",
+                    ConflictStrategy::NewestWins if remote_time > l.update_time => "remote",
+                    ConflictStrategy::NewestWins => "local",
+                    ConflictStrategy::KeepBoth => "both",
+                };
+                warn!(
+                    "two-way sync conflict: date={}, path={}, local_update_time={}, remote_commit_time={}, resolution={}",
+                    entry.date, entry.path, l.update_time, remote_time, resolution
+                );
+                report.conflicts.push(SyncConflict {
+                    date: entry.date.clone(),
+                    path: entry.path.clone(),
+                    resolution,
+                    local_update_time: l.update_time,
+                    remote_commit_time: remote_time,
+                });
+                match resolution {
+                    "remote" => None,
+                    "both" => Some(merge_conflict(&l.content, &entry.content, &entry.path)),
+                    _ => continue,
+                }
+            }
+        };
+        entries.push(match merged {
+            Some(content) => UpsertEntry {
+                date: entry.date,
+                content,
+                metadata: None,
+                metadata_patch: None,
+            },
+            None => UpsertEntry {
+                date: entry.date,
+                content: entry.content,
+                metadata: entry.metadata,
+                metadata_patch: JournalMetadata::patch_from_path(entry.fields),
+            },
+        });
+    }
+    if entries.is_empty() {
+        return Ok(report);
+    }
+    let result = journal::upsert_by_date_batch(state, &entries, "two-way sync").await;
+    if let Some(idx) = result.failed.first() {
+        return Err(DayLogError::Internal(format!(
+            "{} of {} pulled journals failed to save, first date={}",
+            result.failed.len(),
+            entries.len(),
+            entries[*idx].date
+        )));
+    }
+    report.pulled = result.upserted;
+    Ok(report)
+}
+
+/// `keep_both` 把仓库中的版本接在本地内容之后
+fn merge_conflict(local: &str, remote: &str, path: &str) -> String {
+    format!("{}{}", local.trim_end(), conflict_suffix(remote, path))
+}
+
+fn conflict_suffix(remote: &str, path: &str) -> String {
+    format!(
+        "\n\n---\n\n> 同步冲突：以下是仓库中 {} 的版本\n\n{}",
+        path,
+        remote.trim_start()
+    )
+}
+
+/// 路径匹配和文件读取用 rayon 并行，去重按排序后的路径顺序串行处理，结果与串行扫描一致
 fn scan_repo_markdown_entries(
     repo_root: &Path,
     patterns: &[String],
     placeholders: &DatePlaceholders,
-) -> Result<StartupImportParseResult, String> {
+) -> Result<StartupImportParseResult, DayLogError> {
+    let patterns = date_pattern::compile_import_patterns(patterns, placeholders)
+        .map_err(DayLogError::Validation)?;
+    let _guard = sync::repo_read_guard();
     let mut markdown_files = Vec::new();
     collect_markdown_files(repo_root, repo_root, &mut markdown_files)?;
+    markdown_files.sort();
+
+    // 要看 front matter 中的日期，所有 markdown 都需要读取
+    let parsed = markdown_files
+        .par_iter()
+        .map(|rel_path| {
+            let rel = rel_path.to_string_lossy().replace('\\', "/");
+            let full_path = repo_root.join(rel_path);
+            let raw = fs::read_to_string(&full_path)
+                .map_err(|e| DayLogError::io(format_args!("read {}", full_path.display()), e))?;
+            let front_matter_date = front_matter::date(&raw);
+            let matched = match date_pattern::match_import_path(&rel, &patterns, placeholders) {
+                Ok(v) => Ok(PathMatch {
+                    date: front_matter_date.unwrap_or(v.date),
+                    fields: v.fields,
+                }),
+                Err(reason) => front_matter_date
+                    .map(|date| PathMatch {
+                        date,
+                        fields: PathFields::default(),
+                    })
+                    .ok_or(reason),
+            };
+            Ok((rel, raw, matched))
+        })
+        .collect::<Result<Vec<_>, DayLogError>>()?;
 
     let mut entries = Vec::new();
     let mut skipped_count = 0usize;
     let mut dates = HashSet::new();
-
-    for rel_path in markdown_files {
-        let rel = rel_path.to_string_lossy().replace('\\', "/");
-        let date = match extract_date_from_path(&rel, patterns, placeholders) {
+    for (rel, raw, matched) in parsed {
+        let matched = match matched {
             Ok(v) => v,
             Err(reason) => {
                 skipped_count += 1;
@@ -226,19 +521,21 @@ fn scan_repo_markdown_entries(
             }
         };
 
-        if !dates.insert(date.clone()) {
+        if !dates.insert(matched.date.clone()) {
             skipped_count += 1;
-            warn!("startup import skip duplicate date={} path={}", date, rel);
+            warn!(
+                "startup import skip duplicate date={} path={}",
+                matched.date, rel
+            );
             continue;
         }
-
-        let full_path = repo_root.join(&rel_path);
-        let content = fs::read_to_string(&full_path)
-            .map_err(|e| format!("read markdown failed: {} ({})", full_path.display(), e))?;
+        let (content, metadata) = split_synced_front_matter(raw);
         entries.push(StartupImportEntry {
             path: rel,
-            date,
+            date: matched.date,
             content,
+            metadata,
+            fields: matched.fields,
         });
     }
 
@@ -254,12 +551,11 @@ fn collect_markdown_files(
     root: &Path,
     current: &Path,
     out: &mut Vec<PathBuf>,
-) -> Result<(), String> {
+) -> Result<(), DayLogError> {
     let rd = fs::read_dir(current)
-        .map_err(|e| format!("read dir failed: {} ({})", current.display(), e))?;
+        .map_err(|e| DayLogError::io(format_args!("read dir {}", current.display()), e))?;
     for item in rd {
-        let item = item.map_err(|e| e.to_string())?;
-        let path = item.path();
+        let path = item?.path();
         if path.is_dir() {
             if path.file_name().and_then(|v| v.to_str()) == Some(".git") {
                 continue;
@@ -278,383 +574,546 @@ fn collect_markdown_files(
         if !is_md {
             continue;
         }
-        let rel = path
-            .strip_prefix(root)
-            .map_err(|_| format!("strip prefix failed: {}", path.display()))?;
+        let rel = path.strip_prefix(root).map_err(|_| {
+            DayLogError::Internal(format!("strip prefix failed: {}", path.display()))
+        })?;
         out.push(rel.to_path_buf());
     }
     Ok(())
 }
 
-fn validate_startup_import_pattern(
-    pattern: &str,
-    placeholders: &DatePlaceholders,
-) -> Result<(), String> {
-    let has_year = pattern.contains(&placeholders.yyyy);
-    let has_month = pattern.contains(&placeholders.mm) || pattern.contains(&placeholders.m);
-    let has_day = pattern.contains(&placeholders.dd) || pattern.contains(&placeholders.d);
-    let has_ymd = has_year && has_month && has_day;
-    let has_date = pattern.contains(&placeholders.date);
-    if !has_ymd && !has_date {
-        return Err(format!(
-            "invalid import pattern '{}' , required placeholders: {}+{}|{}+{}|{} or {}",
-            pattern,
-            placeholders.yyyy,
-            placeholders.mm,
-            placeholders.m,
-            placeholders.dd,
-            placeholders.d,
-            placeholders.date
-        ));
-    }
-    Ok(())
+/// 立即同步到 git 仓库，`two_way` 模式下先拉取仓库中的修改
+#[utoipa::path(
+    post,
+    path = "/sync/journal",
+    tag = "sync",
+    responses((status = 200, description = "同步失败时 code 为 3001", body = ApiResponse<SyncResp>))
+)]
+pub async fn sync_journal(State(state): State<AppState>) -> ApiResult<SyncResp> {
+    Ok(ApiResponse::ok(
+        run_sync(&state, SyncTrigger::Manual).await?,
+    ))
 }
 
-fn extract_date_from_path(
-    path: &str,
-    patterns: &[String],
-    placeholders: &DatePlaceholders,
-) -> Result<String, String> {
-    let mut reasons = Vec::new();
-    for pattern in patterns {
-        match match_path_with_pattern(path, pattern, placeholders) {
-            Ok(date) => return Ok(date),
-            Err(reason) => reasons.push(format!("[{}] {}", pattern, reason)),
-        }
-    }
-    Err(format!("path not match patterns: {}", reasons.join(" | ")))
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncDiagnoseResp {
+    /// 去掉了 url 中的用户名和密码
+    pub repo_url: String,
+    /// `ssh` `https` `http` `local`
+    pub transport: &'static str,
+    /// 按 `sync.auth_method` 实际使用的方式
+    pub configured_mode: String,
+    pub attempts: Vec<AuthAttempt>,
 }
 
-fn match_path_with_pattern(
-    path: &str,
-    pattern: &str,
-    placeholders: &DatePlaceholders,
-) -> Result<String, String> {
-    let path_tokens: Vec<&str> = path.split('/').collect();
-    let pattern_tokens: Vec<&str> = pattern.split('/').collect();
-
-    if path_tokens.len() < pattern_tokens.len() {
-        return Err(format!(
-            "path segment count too short (path={}, pattern={})",
-            path_tokens.len(),
-            pattern_tokens.len()
-        ));
-    }
-    let start = path_tokens.len() - pattern_tokens.len();
-    let tail_tokens = &path_tokens[start..];
-
-    let mut yyyy: Option<String> = None;
-    let mut mm: Option<String> = None;
-    let mut dd: Option<String> = None;
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthAttempt {
+    /// `none` `ssh_agent` `ssh_key` `token` `password`
+    pub mode: &'static str,
+    pub ok: bool,
+    /// 配置不全或传输方式不支持时不尝试
+    pub skipped: bool,
+    /// libgit2 错误分类，例如 `Ssh` `Http` `Net`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_class: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
 
-    for (actual, template) in tail_tokens.iter().zip(pattern_tokens.iter()) {
-        capture_component(actual, template, placeholders, &mut yyyy, &mut mm, &mut dd)?;
+/// 依次用每种认证方式只连接远端（不拉取），报告哪种可用，定位认证失败的原因；只支持 git 后端
+#[utoipa::path(
+    get,
+    path = "/sync/diagnose",
+    tag = "sync",
+    responses((status = 200, body = ApiResponse<SyncDiagnoseResp>))
+)]
+pub async fn diagnose_sync(State(state): State<AppState>) -> ApiResult<SyncDiagnoseResp> {
+    let cfg = state.config.sync.clone();
+    if cfg.backend.trim() != "git" {
+        return Err(ApiResponse::<SyncDiagnoseResp>::err(
+            ApiCode::BadRequest,
+            "sync diagnose only supports the git backend",
+        ));
     }
-
-    let yyyy = yyyy.ok_or_else(|| "missing yyyy from path".to_string())?;
-    let mm = mm.ok_or_else(|| "missing month from path".to_string())?;
-    let dd = dd.ok_or_else(|| "missing day from path".to_string())?;
-
-    if !valid_date_parts(&yyyy, &mm, &dd) {
-        return Err(format!("invalid date parts: {}-{}-{}", yyyy, mm, dd));
+    if cfg.repo_url.trim().is_empty() {
+        return Err(ApiResponse::<SyncDiagnoseResp>::err(
+            ApiCode::BadRequest,
+            "sync.repo_url is required",
+        ));
     }
-
-    Ok(format!("{}-{}-{}", yyyy, mm, dd))
+    info!(
+        "诊断同步认证 repo_url={}",
+        strip_url_credentials(&cfg.repo_url)
+    );
+    let resp = state
+        .blocking
+        .run("sync diagnose", move || diagnose_auth(&cfg))
+        .await
+        .map_err(|_| {
+            ApiResponse::<SyncDiagnoseResp>::err(ApiCode::SyncFailed, "diagnose task failed")
+        })?;
+    Ok(ApiResponse::ok(resp))
 }
 
-fn capture_component(
-    actual: &str,
-    template: &str,
-    placeholders: &DatePlaceholders,
-    yyyy: &mut Option<String>,
-    mm: &mut Option<String>,
-    dd: &mut Option<String>,
-) -> Result<(), String> {
-    let mut i = 0usize;
-    let mut j = 0usize;
-    let t = template.as_bytes();
-    let a = actual.as_bytes();
-
-    while i < t.len() {
-        if t[i] == b'{' {
-            let end = match template[i..].find('}') {
-                Some(v) => i + v,
-                None => return Err(format!("invalid template component: {}", template)),
-            };
-            let key = &template[i + 1..end];
-            i = end + 1;
-
-            let next_literal = template[i..].chars().next();
-            let value_end = if let Some(ch) = next_literal {
-                match actual[j..].find(ch) {
-                    Some(pos) => j + pos,
-                    None => {
-                        return Err(format!(
-                            "missing literal '{}' after placeholder {{{}}} in '{}'",
-                            ch, key, actual
-                        ));
-                    }
-                }
-            } else {
-                actual.len()
-            };
-            if value_end < j {
-                return Err("invalid placeholder range".to_string());
-            }
-            let val = &actual[j..value_end];
-            j = value_end;
+fn diagnose_auth(cfg: &SyncConfig) -> SyncDiagnoseResp {
+    let url = cfg.repo_url.trim();
+    let lower = url.to_ascii_lowercase();
+    let transport = if lower.starts_with("https://") {
+        "https"
+    } else if lower.starts_with("http://") {
+        "http"
+    } else if lower.starts_with("ssh://") || (url.contains('@') && url.contains(':')) {
+        "ssh"
+    } else {
+        "local"
+    };
+    let configured_mode = match git::resolve_auth_mode(cfg) {
+        Ok(AuthMode::Password) => "password".to_string(),
+        Ok(AuthMode::Ssh) => "ssh".to_string(),
+        Err(e) => e.to_string(),
+    };
 
-            assign_placeholder(key, val, placeholders, yyyy, mm, dd)
-                .map_err(|e| format!("placeholder {{{}}} parse failed: {}", key, e))?;
-        } else {
-            if j >= a.len() || t[i] != a[j] {
-                return Err(format!(
-                    "literal mismatch at '{}' expect '{}'",
-                    actual, t[i] as char
-                ));
-            }
-            i += 1;
-            j += 1;
-        }
+    let mut attempts = Vec::new();
+    let candidates: &[&'static str] = match transport {
+        "ssh" => &["ssh_agent", "ssh_key"],
+        "local" => &["none"],
+        _ => &["token", "password"],
+    };
+    for mode in candidates {
+        let cred = diagnose_credential(cfg, mode);
+        let Some(cred) = cred else {
+            attempts.push(AuthAttempt {
+                mode,
+                ok: false,
+                skipped: true,
+                error_class: None,
+                error_code: None,
+                message: Some("not configured".to_string()),
+            });
+            continue;
+        };
+        let result = try_connect(url, cred);
+        attempts.push(match result {
+            Ok(()) => AuthAttempt {
+                mode,
+                ok: true,
+                skipped: false,
+                error_class: None,
+                error_code: None,
+                message: None,
+            },
+            Err(e) => AuthAttempt {
+                mode,
+                ok: false,
+                skipped: false,
+                error_class: Some(format!("{:?}", e.class())),
+                error_code: Some(format!("{:?}", e.code())),
+                message: Some(sanitize_git_message(cfg, e.message())),
+            },
+        });
     }
-
-    if j == a.len() {
-        Ok(())
-    } else {
-        Err(format!("component length mismatch: '{}'", actual))
+    SyncDiagnoseResp {
+        repo_url: strip_url_credentials(url),
+        transport,
+        configured_mode,
+        attempts,
     }
 }
 
-fn assign_placeholder(
-    key: &str,
-    val: &str,
-    placeholders: &DatePlaceholders,
-    yyyy: &mut Option<String>,
-    mm: &mut Option<String>,
-    dd: &mut Option<String>,
-) -> Result<(), String> {
-    if val.chars().any(|c| !c.is_ascii_digit()) {
-        return Err(format!("value '{}' contains non-digit", val));
-    }
+type CredFn = Box<dyn Fn(Option<&str>) -> Result<Cred, git2::Error>>;
 
-    let yyyy_key = placeholder_key(&placeholders.yyyy)?;
-    let mm_key = placeholder_key(&placeholders.mm)?;
-    let m_key = placeholder_key(&placeholders.m)?;
-    let dd_key = placeholder_key(&placeholders.dd)?;
-    let d_key = placeholder_key(&placeholders.d)?;
-    let date_key = placeholder_key(&placeholders.date)?;
-
-    match key {
-        _ if key == yyyy_key => {
-            if val.len() != 4 {
-                return Err("yyyy must be 4 digits".to_string());
-            }
-            *yyyy = Some(val.to_string());
-            Ok(())
+/// 诊断时每种方式使用的凭据，配置不全时返回 None，`none` 不需要凭据
+fn diagnose_credential(cfg: &SyncConfig, mode: &str) -> Option<Option<CredFn>> {
+    let ssh_username = cfg.ssh_username.trim().to_string();
+    let pick_user = move |user: Option<&str>| {
+        if ssh_username.is_empty() {
+            user.unwrap_or("git").to_string()
+        } else {
+            ssh_username.clone()
         }
-        _ if key == mm_key || key == m_key => {
-            let Some(m) = normalize_month_or_day(val, 1, 12) else {
-                return Err("month out of range (1..12)".to_string());
-            };
-            if merge_or_check(mm, m) {
-                Ok(())
-            } else {
-                Err("month conflict with another placeholder".to_string())
+    };
+    match mode {
+        "none" => Some(None),
+        "ssh_agent" => Some(Some(Box::new(move |user| {
+            Cred::ssh_key_from_agent(&pick_user(user))
+        }))),
+        "ssh_key" => {
+            let key = cfg.ssh_private_key_path.trim().to_string();
+            if key.is_empty() {
+                return None;
             }
+            let public_key = cfg.ssh_public_key_path.trim().to_string();
+            let passphrase = cfg.ssh_passphrase.trim().to_string();
+            Some(Some(Box::new(move |user| {
+                Cred::ssh_key(
+                    &pick_user(user),
+                    (!public_key.is_empty()).then(|| Path::new(&public_key)),
+                    Path::new(&key),
+                    (!passphrase.is_empty()).then_some(passphrase.as_str()),
+                )
+            })))
         }
-        _ if key == dd_key || key == d_key => {
-            let Some(d) = normalize_month_or_day(val, 1, 31) else {
-                return Err("day out of range (1..31)".to_string());
-            };
-            if merge_or_check(dd, d) {
-                Ok(())
-            } else {
-                Err("day conflict with another placeholder".to_string())
+        // 个人访问令牌放在 sync.password，用户名按托管平台惯例任意
+        "token" => {
+            let token = cfg.password.trim().to_string();
+            if token.is_empty() {
+                return None;
             }
+            Some(Some(Box::new(move |_| {
+                Cred::userpass_plaintext("x-access-token", &token)
+            })))
         }
-        _ if key == date_key => {
-            let Some((py, pm, pd)) = parse_date_value(val) else {
-                return Err("unsupported date format".to_string());
-            };
-            if !merge_or_check(yyyy, py) || !merge_or_check(mm, pm) || !merge_or_check(dd, pd) {
-                return Err("date conflicts with yyyy/MM/dd placeholders".to_string());
+        "password" => {
+            let username = cfg.username.trim().to_string();
+            let password = cfg.password.clone();
+            if username.is_empty() || password.is_empty() {
+                return None;
             }
-            Ok(())
+            Some(Some(Box::new(move |_| {
+                Cred::userpass_plaintext(&username, &password)
+            })))
         }
-        _ => Err(format!("unsupported placeholder: {}", key)),
+        _ => None,
     }
 }
 
-fn placeholder_key(token: &str) -> Result<&str, String> {
-    if !(token.starts_with('{') && token.ends_with('}') && token.len() >= 3) {
-        return Err(format!("invalid placeholder token '{}'", token));
+/// 只建立连接并读取远端引用，凭据被拒绝时不重复尝试
+fn try_connect(url: &str, cred: Option<CredFn>) -> Result<(), git2::Error> {
+    let mut remote = git2::Remote::create_detached(url)?;
+    let mut cb = RemoteCallbacks::new();
+    if let Some(cred) = cred {
+        let mut tried = false;
+        cb.credentials(move |_url, user, _allowed| {
+            if tried {
+                return Err(git2::Error::from_str("credentials rejected by remote"));
+            }
+            tried = true;
+            cred(user)
+        });
     }
-    Ok(&token[1..token.len() - 1])
+    let mut conn = remote.connect_auth(git2::Direction::Fetch, Some(cb), None)?;
+    conn.remote().list()?;
+    Ok(())
 }
 
-fn merge_or_check(slot: &mut Option<String>, value: String) -> bool {
-    if let Some(existing) = slot.as_ref() {
-        existing == &value
-    } else {
-        *slot = Some(value);
-        true
+fn strip_url_credentials(url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, rest)) => match rest.split_once('/') {
+            Some((host, path)) => match host.rsplit_once('@') {
+                Some((_, host)) => format!("{}://{}/{}", scheme, host, path),
+                None => url.to_string(),
+            },
+            None => url.to_string(),
+        },
+        None => url.to_string(),
     }
 }
 
-fn parse_date_value(v: &str) -> Option<(String, String, String)> {
-    let s = v.trim();
-    if s.len() == 8 && s.chars().all(|c| c.is_ascii_digit()) {
-        let y = &s[0..4];
-        let m = &s[4..6];
-        let d = &s[6..8];
-        if valid_date_parts(y, m, d) {
-            return Some((y.to_string(), m.to_string(), d.to_string()));
+/// libgit2 的报错里可能带出 url 中的凭据或密码，返回前替换掉
+fn sanitize_git_message(cfg: &SyncConfig, message: &str) -> String {
+    let mut message = message.replace(cfg.repo_url.trim(), &strip_url_credentials(&cfg.repo_url));
+    for secret in [&cfg.password, &cfg.ssh_passphrase] {
+        if !secret.trim().is_empty() {
+            message = message.replace(secret.trim(), "***");
         }
-        return None;
     }
+    message
+}
 
-    for sep in ['-', '_', '.'] {
-        let parts: Vec<&str> = s.split(sep).collect();
-        if parts.len() != 3 {
-            continue;
-        }
-        let y = parts[0];
-        let m = parts[1];
-        let d = parts[2];
-        let normalized_m = normalize_month_or_day(m, 1, 12);
-        let normalized_d = normalize_month_or_day(d, 1, 31);
-        if y.len() == 4
-            && y.chars().all(|c| c.is_ascii_digit())
-            && normalized_m.is_some()
-            && normalized_d.is_some()
-        {
-            let mm = normalized_m.unwrap();
-            let dd = normalized_d.unwrap();
-            if valid_date_parts(y, &mm, &dd) {
-                return Some((y.to_string(), mm, dd));
-            }
-        }
-    }
+/// 一次性同步任务的类型，payload 为 `SyncTrigger::as_str`
+const SYNC_ONCE_JOB: &str = "sync_once";
 
-    None
+/// 注册同步相关的一次性任务，需要在 `job::spawn` 之前调用
+pub fn register_jobs() {
+    job::register(SYNC_ONCE_JOB, run_sync_once_job);
 }
 
-fn normalize_month_or_day(v: &str, min: u32, max: u32) -> Option<String> {
-    if v.is_empty() || v.len() > 2 || v.chars().any(|c| !c.is_ascii_digit()) {
-        return None;
-    }
-    let n = v.parse::<u32>().ok()?;
-    if n < min || n > max {
-        return None;
+/// 在后台同步一次，已经有排队中的同步时不重复入队；失败按任务重试，结果在 `GET /admin/jobs` 查看
+pub async fn queue_sync(state: &AppState, trigger: SyncTrigger) {
+    match job::enqueue_unless_pending(state, SYNC_ONCE_JOB, Some(trigger.as_str().to_string()), 1)
+        .await
+    {
+        Ok(id) => debug!("{} sync queued as job {}", trigger.as_str(), id),
+        Err(e) => warn!("{} sync queue failed: {}", trigger.as_str(), e),
     }
-    Some(format!("{:02}", n))
 }
 
-fn valid_date_parts(yyyy: &str, mm: &str, dd: &str) -> bool {
-    let year = yyyy.parse::<i32>().ok();
-    let month = mm.parse::<u32>().ok();
-    let day = dd.parse::<u32>().ok();
+fn run_sync_once_job(state: AppState, payload: Option<String>) -> JobFuture {
+    Box::pin(async move {
+        let trigger = SyncTrigger::ALL
+            .into_iter()
+            .find(|v| Some(v.as_str()) == payload.as_deref())
+            .unwrap_or(SyncTrigger::AutoSync);
+        run_sync(&state, trigger)
+            .await
+            .map(|_| ())
+            .map_err(String::from)
+    })
+}
 
-    if year.is_none() || month.is_none() || day.is_none() {
-        return false;
+/// 按 `sync.interval_minutes` 定时同步
+pub fn scheduled_job(state: &AppState) -> Option<JobDef> {
+    let minutes = state.config.sync.interval_minutes;
+    if !state.config.sync.enabled || minutes == 0 {
+        return None;
     }
-    let month = month.unwrap();
-    let day = day.unwrap();
-    month >= 1 && month <= 12 && day >= 1 && day <= 31
+    Some(JobDef {
+        name: "sync".to_string(),
+        kind: "sync",
+        schedule: format!("@every {}m", minutes),
+        handler: run_scheduled_job,
+        max_retries: 2,
+        catch_up: true,
+    })
 }
 
-pub async fn sync_journal(State(state): State<AppState>) -> ApiResult<SyncResp> {
+fn run_scheduled_job(state: AppState, _: Option<String>) -> JobFuture {
+    Box::pin(async move {
+        run_sync(&state, SyncTrigger::Scheduled)
+            .await
+            .map(|_| ())
+            .map_err(String::from)
+    })
+}
+
+pub async fn run_sync(state: &AppState, trigger: SyncTrigger) -> Result<SyncResp, DayLogError> {
+    // 同一个本地仓库同时只能有一次同步
+    let mut sync_state = SYNC_LOCK.lock().await;
     let cfg = state.config.sync.clone();
-    let sync_output_path = settings::load_sync_output_path(&state)
-        .await
-        .unwrap_or_else(|| cfg.output_path.clone());
-    let sync_commit_template = settings::load_sync_commit_message(&state)
-        .await
-        .unwrap_or_else(|| cfg.commit_message.clone());
-    let date_placeholders = settings::load_date_placeholders(&state)
+    let targets = load_output_targets(state).await;
+    let sync_commit_template =
+        match settings::load_trigger_commit_message(state, trigger.as_str()).await {
+            Some(v) => v,
+            None => settings::load_sync_commit_message(state)
+                .await
+                .unwrap_or_else(|| cfg.commit_message.clone()),
+        };
+    let date_placeholders = settings::load_date_placeholders(state)
         .await
         .unwrap_or_else(settings::default_date_placeholders);
     info!(
-        "journal sync start: enabled={}, branch={}, output_path={}, format={}",
-        cfg.enabled, cfg.branch, sync_output_path, cfg.output_format
+        "journal sync start: trigger={}, enabled={}, branch={}, outputs={}",
+        trigger.as_str(),
+        cfg.enabled,
+        cfg.branch,
+        targets
+            .iter()
+            .map(|v| format!("{}:{}", v.format, v.path_template))
+            .collect::<Vec<_>>()
+            .join(",")
     );
     if !cfg.enabled {
         info!("journal sync skipped: disabled in config");
-        return Err(ApiResponse::<SyncResp>::err(
-            ApiCode::BadRequest,
-            "sync disabled in config",
+        return Err(DayLogError::BadRequest(
+            "sync disabled in config".to_string(),
         ));
     }
-    if cfg.repo_url.trim().is_empty() {
-        return Err(ApiResponse::<SyncResp>::err(
-            ApiCode::BadRequest,
-            "sync.repo_url is required",
-        ));
+    let backend = sync::backend(&cfg, &state.http)?;
+    let two_way = is_two_way(&cfg)?;
+
+    let started = date_util::now_secs();
+    let mut pull = PullReport::default();
+    if two_way {
+        let strategy = ConflictStrategy::parse(&cfg.conflict_strategy)?;
+        pull = pull_remote_changes(state, backend.clone(), &cfg, strategy)
+            .await
+            .inspect_err(|e| {
+                error!("journal sync pull failed: {}", e);
+                notify_sync_failed(state, &e.to_string());
+            })?;
+        info!(
+            "journal sync pulled: journals={}, conflicts={}",
+            pull.pulled,
+            pull.conflicts.len()
+        );
     }
-    let auth_mode = resolve_auth_mode(&cfg)
-        .map_err(|msg| ApiResponse::<SyncResp>::err(ApiCode::BadRequest, &msg))?;
-    validate_auth_config(&cfg, auth_mode)
-        .map_err(|msg| ApiResponse::<SyncResp>::err(ApiCode::BadRequest, &msg))?;
 
+    // `encryption.sync_encrypted` 时仓库里保留库中的密文，否则写出明文
+    let keep_sealed = state.config.encryption.sync_encrypted;
     let journals = sqlx::query_as::<_, JournalRow>(
-        "select id, content, date, create_time, update_time from journal order by date asc, id asc",
+        "select id, content, date, create_time, update_time, metadata, word_count from journal order by date asc, id asc",
     )
     .fetch_all(&state.db)
-    .await
-    .map_err(|_| ApiResponse::<SyncResp>::err(ApiCode::DbListFailed, "db query failed"))?;
+    .await?
+    .into_iter()
+    .map(|mut v| {
+        if !keep_sealed {
+            v.content = state.cipher.open(v.content);
+        }
+        v
+    })
+    .collect::<Vec<_>>();
     info!("journal sync query done: rows={}", journals.len());
 
-    let output_format = normalize_format(&cfg.output_format).map_err(|msg| {
-        ApiResponse::<SyncResp>::err(
-            ApiCode::BadRequest,
-            &format!("invalid output_format: {}", msg),
-        )
-    })?;
-    let output_files = build_output_files(
-        &sync_output_path,
-        &output_format,
-        &journals,
-        &date_placeholders,
-    )
-    .map_err(|msg| ApiResponse::<SyncResp>::err(ApiCode::BadRequest, &msg))?;
-    let commit_message = resolve_commit_message(
+    let mut target_files = Vec::with_capacity(targets.len());
+    let mut outputs = Vec::with_capacity(targets.len());
+    for target in &targets {
+        let format = normalize_format(&target.format).map_err(|e| {
+            DayLogError::Validation(format!(
+                "invalid output format for {}: {}",
+                target.path_template, e
+            ))
+        })?;
+        let files = build_output_files(
+            &target.path_template,
+            &format,
+            &journals,
+            &date_placeholders,
+        )?;
+        let overlap = files.iter().find(|f| {
+            target_files
+                .iter()
+                .flatten()
+                .any(|v: &SyncOutputFile| v.rel_path == f.rel_path)
+        });
+        if let Some(f) = overlap {
+            return Err(DayLogError::Validation(format!(
+                "output targets write the same file: {}",
+                f.rel_path.display()
+            )));
+        }
+        outputs.push(SyncOutputResp {
+            path_template: target.path_template.clone(),
+            format,
+            files: files.len(),
+        });
+        target_files.push(files);
+    }
+    let mut output_files = Vec::new();
+    // index.json 和 README.md 按第一个按天分文件的目标生成
+    let idx = targets
+        .iter()
+        .position(|v| date_pattern::contains_date_placeholder(&v.path_template, &date_placeholders))
+        .unwrap_or(0);
+    let highlights = if cfg.index_file || cfg.readme_file {
+        tag::highlight_ids(state).await?
+    } else {
+        HashSet::new()
+    };
+    if cfg.index_file {
+        let index = build_index_file(
+            &targets[idx].path_template,
+            &journals,
+            &target_files[idx],
+            &highlights,
+        )?;
+        output_files.push(index);
+    }
+    if cfg.readme_file {
+        let readme = build_readme_file(
+            &targets[idx].path_template,
+            &journals,
+            &target_files[idx],
+            &highlights,
+        );
+        output_files.push(readme);
+    }
+    if let Some(f) = output_files.iter().find(|f| {
+        target_files
+            .iter()
+            .flatten()
+            .any(|v| v.rel_path == f.rel_path)
+    }) {
+        return Err(DayLogError::Validation(format!(
+            "generated {} conflicts with an output target",
+            f.rel_path.display()
+        )));
+    }
+    output_files.extend(target_files.into_iter().flatten());
+    let mut commit_message = resolve_commit_message(
         &sync_commit_template,
+        trigger,
         journals.len(),
         &journals,
         &date_placeholders,
     );
+    let trailers = cfg
+        .commit_trailers
+        .iter()
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(|v| resolve_commit_message(v, trigger, journals.len(), &journals, &date_placeholders))
+        .collect::<Vec<_>>();
+    if !trailers.is_empty() {
+        commit_message = format!("{}\n\n{}", commit_message.trim_end(), trailers.join("\n"));
+    }
+    let pending = sqlx::query_scalar::<_, String>("select rel_path from sync_pending_delete")
+        .fetch_all(&state.db)
+        .await?;
+    // 旧路径又被其他日记占用时只需要覆盖，不能删除
+    let stale_paths = pending
+        .iter()
+        .filter_map(|p| sync::validate_rel_path(p).ok())
+        .filter(|p| !output_files.iter().any(|f| f.rel_path == *p))
+        .collect::<Vec<_>>();
     let repo_path = PathBuf::from(state.config.get_sync_repo_path());
     info!(
-        "journal sync prepared: repo_path={}, output_files={}, commit_message={}",
+        "journal sync prepared: repo_path={}, output_files={}, stale_files={}, commit_message={}",
         repo_path.display(),
         output_files.len(),
+        stale_paths.len(),
         commit_message
     );
 
-    let task_input = SyncTaskInput {
-        cfg: cfg.clone(),
-        repo_path,
+    let now = date_util::now_secs();
+    let squash_base = sync_state
+        .squash_base
+        .clone()
+        .filter(|_| trigger == SyncTrigger::Scheduled && cfg.squash_window > 0)
+        .filter(|v| now - v.since < cfg.squash_window as i64 * 60);
+    let task_input = PushInput {
         output_files,
+        stale_paths,
         commit_message,
+        squash_onto: squash_base.as_ref().map(|v| v.commit_id.clone()),
     };
 
-    let task_result = task::spawn_blocking(move || execute_sync(task_input))
+    let result = state
+        .blocking
+        .run("journal sync", move || {
+            backend.push(&repo_path, &task_input)
+        })
         .await
-        .map_err(|_| {
-            error!("journal sync failed: sync task join failed");
-            ApiResponse::<SyncResp>::err(ApiCode::SyncFailed, "sync task join failed")
+        .map_err(DayLogError::from)
+        .and_then(|v| v)
+        .inspect_err(|e| {
+            error!("journal sync failed: {}", e);
+            notify_sync_failed(state, &e.to_string());
         })?;
 
-    let result = task_result.map_err(|msg| {
-        error!("journal sync failed: {}", msg);
-        ApiResponse::<SyncResp>::err(ApiCode::SyncFailed, &msg)
-    })?;
+    if result.pushed {
+        sync_state.squash_base = (trigger == SyncTrigger::Scheduled).then(|| SquashBase {
+            commit_id: result.commit_id.clone(),
+            since: match (&squash_base, result.squashed) {
+                (Some(base), true) => base.since,
+                _ => now,
+            },
+        });
+    }
+
+    for path in &pending {
+        let _ = sqlx::query("delete from sync_pending_delete where rel_path = ?")
+            .bind(path)
+            .execute(&state.db)
+            .await;
+    }
+    if two_way && let Err(e) = settings::save_sync_last_two_way(state, started).await {
+        warn!("save two-way sync time failed: {}", e);
+    }
 
     let resp = SyncResp {
         pushed: result.pushed,
         commit_id: result.commit_id,
-        file_path: sync_output_path,
-        format: output_format,
+        squashed: result.squashed,
+        file_path: outputs[0].path_template.clone(),
+        format: outputs[0].format.clone(),
+        outputs,
+        pulled: pull.pulled,
+        conflicts: pull.conflicts,
         message: if result.pushed {
             "sync success".to_string()
         } else {
@@ -665,34 +1124,182 @@ pub async fn sync_journal(State(state): State<AppState>) -> ApiResult<SyncResp>
         "journal sync result: pushed={}, path={}",
         resp.pushed, resp.file_path
     );
-    Ok(ApiResponse::ok(resp))
+    Ok(resp)
 }
 
-fn validate_rel_path(input: &str) -> Result<PathBuf, String> {
-    let p = Path::new(input.trim());
-    if input.trim().is_empty() {
-        return Err("path is empty".to_string());
+/// 生效的输出目标：配置了 `sync.outputs` 时按它，否则是设置中的输出路径加 `sync.output_format`
+async fn load_output_targets(state: &AppState) -> Vec<SyncOutput> {
+    let cfg = &state.config.sync;
+    if !cfg.outputs.is_empty() {
+        return cfg.outputs.clone();
     }
-    if p.is_absolute() {
-        return Err("absolute path is not allowed".to_string());
+    let path_template = settings::load_sync_output_path(state)
+        .await
+        .unwrap_or_else(|| cfg.output_path.clone());
+    vec![SyncOutput {
+        path_template,
+        format: cfg.output_format.clone(),
+    }]
+}
+
+/// 按输出路径模板把全部日记渲染成 (相对路径, 内容)，给 zip 导出用，不需要配置同步仓库；
+/// 不传模板时使用第一个同步输出目标的路径
+pub async fn render_export_files(
+    state: &AppState,
+    template: Option<&str>,
+) -> Result<Vec<(String, String)>, DayLogError> {
+    let placeholders = settings::load_date_placeholders(state)
+        .await
+        .unwrap_or_else(settings::default_date_placeholders);
+    let template = match template.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(v) => v.to_string(),
+        None => load_output_targets(state)
+            .await
+            .into_iter()
+            .next()
+            .map(|v| v.path_template)
+            .unwrap_or_else(|| state.config.sync.output_path.clone()),
+    };
+    validate_output_path(&template, &placeholders)
+        .map_err(|e| DayLogError::Validation(format!("invalid template: {}", e)))?;
+
+    let journals = sqlx::query_as::<_, JournalRow>(
+        "select id, content, date, create_time, update_time, metadata, word_count from journal order by date asc, id asc",
+    )
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|mut v| {
+        v.content = state.cipher.open(v.content);
+        v
+    })
+    .collect::<Vec<_>>();
+    if journals.is_empty() {
+        return Ok(Vec::new());
     }
-    for c in p.components() {
-        if matches!(c, Component::ParentDir) {
-            return Err("parent dir is not allowed".to_string());
+    let files = build_output_files(&template, "markdown", &journals, &placeholders)?;
+    Ok(files
+        .into_iter()
+        .map(|v| (v.rel_path.to_string_lossy().replace('\\', "/"), v.content))
+        .collect())
+}
+
+/// 记下 `date` 在同步仓库中对应的文件，下次同步时删除；
+/// 只有按日期分文件的输出才需要，`metadata` 是改动前的，用于渲染 `{title}` `{slug}`
+pub async fn record_stale_date(state: &AppState, date: &str, metadata: Option<&str>) {
+    let placeholders = settings::load_date_placeholders(state)
+        .await
+        .unwrap_or_else(settings::default_date_placeholders);
+    let fields = JournalMetadata::parse(metadata).path_fields();
+    for target in load_output_targets(state).await {
+        let Ok(format) = normalize_format(&target.format) else {
+            continue;
+        };
+        if !date_pattern::contains_date_placeholder(&target.path_template, &placeholders) {
+            continue;
+        }
+        let Ok(path) =
+            date_pattern::render_path_template(&target.path_template, date, &fields, &placeholders)
+        else {
+            continue;
+        };
+        let Ok(rel_path) =
+            sync::validate_rel_path(&path).and_then(|v| output_file_path(&v, &format))
+        else {
+            continue;
+        };
+        let rel_path = rel_path.to_string_lossy().to_string();
+        if let Err(e) = sqlx::query(
+            "insert into sync_pending_delete (rel_path, create_time) values (?, ?) on conflict(rel_path) do nothing",
+        )
+        .bind(&rel_path)
+        .bind(date_util::now_secs())
+        .execute(&state.db)
+        .await
+        {
+            warn!("record stale sync path {} failed: {}", rel_path, e);
         }
     }
-    Ok(p.to_path_buf())
 }
 
-fn normalize_format(s: &str) -> Result<String, String> {
+fn notify_sync_failed(state: &AppState, reason: &str) {
+    notify::spawn_send(
+        state,
+        NotifyEvent::SyncFailed {
+            reason: reason.to_string(),
+        },
+    );
+}
+
+/// 保存设置时检查输出路径模板：占位符都能识别，渲染结果是仓库内以 `.md` 结尾的相对路径
+pub fn validate_output_path(
+    template: &str,
+    placeholders: &DatePlaceholders,
+) -> Result<(), DayLogError> {
+    date_pattern::check_path_placeholders(template, placeholders)
+        .map_err(DayLogError::Validation)?;
+    let named = PathFields {
+        title: Some("title".to_string()),
+        slug: Some("slug".to_string()),
+    };
+    // 有无标题、slug 时 `[...]` 的取舍不同，两种都要是合法路径
+    for fields in [PathFields::default(), named] {
+        let path =
+            date_pattern::render_path_template(template, "2024-01-01", &fields, placeholders)
+                .map_err(DayLogError::Validation)?;
+        let rel_path = sync::validate_rel_path(&path)?;
+        ensure_md_path(&rel_path)?;
+    }
+    Ok(())
+}
+
+fn normalize_format(s: &str) -> Result<String, DayLogError> {
     let v = s.trim().to_ascii_lowercase();
     match v.as_str() {
         "md" | "markdown" => Ok("markdown".to_string()),
-        _ => Err("supported: markdown only".to_string()),
+        "json" => Ok("json".to_string()),
+        "html" | "htm" => Ok("html".to_string()),
+        _ => Err(DayLogError::Validation(
+            "supported: markdown, json, html".to_string(),
+        )),
+    }
+}
+
+fn format_extension(format: &str) -> &'static str {
+    match format {
+        "json" => "json",
+        "html" => "html",
+        _ => "md",
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonJournal<'a> {
+    date: &'a str,
+    content: &'a str,
+    create_time: i64,
+    update_time: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<serde_json::Value>,
+}
+
+impl<'a> From<&'a JournalRow> for JsonJournal<'a> {
+    fn from(j: &'a JournalRow) -> Self {
+        Self {
+            date: &j.date,
+            content: &j.content,
+            create_time: j.create_time,
+            update_time: j.update_time,
+            metadata: j
+                .metadata
+                .as_deref()
+                .and_then(|v| serde_json::from_str(v).ok()),
+        }
     }
 }
 
-fn render_journals(format: &str, journals: &[JournalRow]) -> Result<String, String> {
+fn render_journals(format: &str, journals: &[JournalRow]) -> Result<String, DayLogError> {
     match format {
         "markdown" => {
             let mut out = String::from("# DayLog Journals\n\n");
@@ -703,12 +1310,82 @@ fn render_journals(format: &str, journals: &[JournalRow]) -> Result<String, Stri
             }
             Ok(out)
         }
-        _ => Err("unsupported format".to_string()),
+        "json" => {
+            let items = journals.iter().map(JsonJournal::from).collect::<Vec<_>>();
+            let mut out = serde_json::to_string_pretty(&items)
+                .map_err(|e| DayLogError::Internal(e.to_string()))?;
+            out.push('\n');
+            Ok(out)
+        }
+        "html" => {
+            let mut body = String::from("<h1>DayLog Journals</h1>\n");
+            for j in journals {
+                body.push_str(&format!(
+                    "<article id=\"{0}\">\n<h2><time datetime=\"{0}\">{0}</time></h2>\n{1}</article>\n",
+                    markdown::escape_html(&j.date),
+                    markdown::to_html(&j.content, false)
+                ));
+            }
+            Ok(html_document("DayLog Journals", &body))
+        }
+        _ => Err(DayLogError::Validation("unsupported format".to_string())),
+    }
+}
+
+/// 按天分文件时单篇日记的内容
+fn render_single(format: &str, j: &JournalRow) -> Result<String, DayLogError> {
+    match format {
+        "markdown" => Ok(render_single_markdown(j)),
+        "json" => {
+            let mut out = serde_json::to_string_pretty(&JsonJournal::from(j))
+                .map_err(|e| DayLogError::Internal(e.to_string()))?;
+            out.push('\n');
+            Ok(out)
+        }
+        "html" => {
+            let date = markdown::escape_html(&j.date);
+            let time = format!("<time datetime=\"{0}\">{0}</time>", date);
+            let title = JournalMetadata::parse(j.metadata.as_deref())
+                .title
+                .filter(|v| !v.trim().is_empty());
+            let header = match &title {
+                Some(v) => format!("<h1>{}</h1>\n<p>{}</p>", markdown::escape_html(v), time),
+                None => format!("<h1>{}</h1>", time),
+            };
+            let body = format!(
+                "<article>\n{}\n{}</article>\n",
+                header,
+                markdown::to_html(&j.content, false)
+            );
+            Ok(html_document(title.as_deref().unwrap_or(&j.date), &body))
+        }
+        _ => Err(DayLogError::Validation("unsupported format".to_string())),
     }
 }
 
 fn render_single_markdown(j: &JournalRow) -> String {
-    j.content.clone()
+    let metadata = JournalMetadata::parse(j.metadata.as_deref());
+    front_matter::render(&metadata.front_matter_pairs(), &j.content)
+}
+
+/// 日记中的原始 html 会被转义，生成的页面可以直接当静态站点发布
+fn html_document(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+        markdown::escape_html(title),
+        body
+    )
+}
+
+/// 拆出 `render_single_markdown` 写入的 front matter，其他 front matter 原样保留在正文中
+fn split_synced_front_matter(raw: String) -> (String, Option<String>) {
+    let Some((pairs, body)) = front_matter::split(&raw) else {
+        return (raw, None);
+    };
+    match JournalMetadata::from_front_matter(&pairs) {
+        Some(metadata) if !pairs.is_empty() => (body.to_string(), metadata.to_json()),
+        _ => (raw, None),
+    }
 }
 
 fn build_output_files(
@@ -716,119 +1393,241 @@ fn build_output_files(
     format: &str,
     journals: &[JournalRow],
     placeholders: &DatePlaceholders,
-) -> Result<Vec<SyncOutputFile>, String> {
-    if format == "markdown" && contains_date_placeholder(output_path, placeholders) {
+) -> Result<Vec<SyncOutputFile>, DayLogError> {
+    if date_pattern::contains_date_placeholder(output_path, placeholders) {
         let mut files = Vec::new();
         for j in journals {
-            let path = resolve_output_path_template(output_path, &j.date, placeholders)?;
-            let rel_path =
-                validate_rel_path(&path).map_err(|e| format!("invalid output_path: {}", e))?;
-            ensure_md_path(rel_path.as_path())?;
+            let fields = JournalMetadata::parse(j.metadata.as_deref()).path_fields();
+            let path =
+                date_pattern::render_path_template(output_path, &j.date, &fields, placeholders)
+                    .map_err(DayLogError::Validation)?;
+            let rel_path = sync::validate_rel_path(&path)
+                .map_err(|e| DayLogError::Validation(format!("invalid output_path: {}", e)))?;
             files.push(SyncOutputFile {
-                rel_path,
-                content: render_single_markdown(j),
+                rel_path: output_file_path(&rel_path, format)?,
+                content: render_single(format, j)?,
             });
         }
         if files.is_empty() {
-            return Err("no journals to sync for markdown template output".to_string());
+            return Err(DayLogError::BadRequest(format!(
+                "no journals to sync for {} template output",
+                format
+            )));
         }
         return Ok(files);
     }
 
-    let rel_path =
-        validate_rel_path(output_path).map_err(|e| format!("invalid output_path: {}", e))?;
-    ensure_md_path(rel_path.as_path())?;
+    let rel_path = sync::validate_rel_path(output_path)
+        .map_err(|e| DayLogError::Validation(format!("invalid output_path: {}", e)))?;
+    let rel_path = output_file_path(&rel_path, format)?;
     let content = render_journals(format, journals)?;
     Ok(vec![SyncOutputFile { rel_path, content }])
 }
 
-fn ensure_md_path(path: &Path) -> Result<(), String> {
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexEntry {
+    /// 相对 index.json 所在目录
+    path: String,
+    update_time: i64,
+    word_count: i64,
+    /// 精选，见 `tag::highlight_ids`
+    highlight: bool,
+}
+
+/// 在输出路径模板第一个含占位符的目录之前生成 `index.json`，日期 -> 文件路径、更新时间、字数、是否精选
+fn build_index_file(
+    output_path: &str,
+    journals: &[JournalRow],
+    files: &[SyncOutputFile],
+    highlights: &HashSet<i64>,
+) -> Result<SyncOutputFile, DayLogError> {
+    let root = output_root(output_path);
+    let per_journal = files.len() == journals.len();
+    let mut entries = BTreeMap::new();
+    for (idx, j) in journals.iter().enumerate() {
+        let file = if per_journal { &files[idx] } else { &files[0] };
+        let path = file.rel_path.strip_prefix(&root).unwrap_or(&file.rel_path);
+        entries.insert(
+            j.date.clone(),
+            IndexEntry {
+                path: path.to_string_lossy().replace('\\', "/"),
+                update_time: j.update_time,
+                // `encryption.sync_encrypted` 时 content 是密文，用写入时算好的字数
+                word_count: j
+                    .word_count
+                    .unwrap_or_else(|| journal::text_counts(&j.content).0),
+                highlight: highlights.contains(&j.id),
+            },
+        );
+    }
+    let mut content =
+        serde_json::to_string_pretty(&entries).map_err(|e| DayLogError::Internal(e.to_string()))?;
+    content.push('\n');
+    Ok(SyncOutputFile {
+        rel_path: root.join(INDEX_FILE_NAME),
+        content,
+    })
+}
+
+/// 输出路径模板第一个含占位符的目录之前的部分
+fn output_root(output_path: &str) -> PathBuf {
+    let mut parts = output_path.trim().split('/').collect::<Vec<_>>();
+    parts.pop();
+    parts
+        .into_iter()
+        .take_while(|v| !v.contains(['{', '[']))
+        .collect::<PathBuf>()
+}
+
+/// 在输出根目录生成 `README.md` 目录：先列精选，再按年 -> 月列出每天的链接和标题，新的在前
+fn build_readme_file(
+    output_path: &str,
+    journals: &[JournalRow],
+    files: &[SyncOutputFile],
+    highlights: &HashSet<i64>,
+) -> SyncOutputFile {
+    let root = output_root(output_path);
+    let per_journal = files.len() == journals.len();
+    let link = |idx: usize| {
+        let file = if per_journal { &files[idx] } else { &files[0] };
+        let path = file.rel_path.strip_prefix(&root).unwrap_or(&file.rel_path);
+        readme_link(&path.to_string_lossy())
+    };
+    let mut content = format!("# DayLog\n\n共 {} 篇日记\n", journals.len());
+    let mut pinned = journals
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, j)| highlights.contains(&j.id))
+        .peekable();
+    if pinned.peek().is_some() {
+        content.push_str("\n## 精选\n\n");
+        for (idx, j) in pinned {
+            content.push_str(&format!(
+                "- [{}]({}) {}\n",
+                j.date,
+                link(idx),
+                readme_title(j)
+            ));
+        }
+    }
+    let (mut year, mut month) = ("", "");
+    for (idx, j) in journals.iter().enumerate().rev() {
+        if j.date.len() < 10 {
+            continue;
+        }
+        if year != &j.date[..4] {
+            year = &j.date[..4];
+            content.push_str(&format!("\n## {}\n", year));
+        }
+        if month != &j.date[..7] {
+            month = &j.date[..7];
+            content.push_str(&format!("\n### {}\n\n", month));
+        }
+        content.push_str(&format!(
+            "- [{}]({}) {}\n",
+            &j.date[5..],
+            link(idx),
+            readme_title(j)
+        ));
+    }
+    SyncOutputFile {
+        rel_path: root.join(README_FILE_NAME),
+        content,
+    }
+}
+
+/// metadata 中的标题，没有时取正文第一个标题或第一行；`encryption.sync_encrypted` 时正文是密文，不取正文
+fn readme_title(j: &JournalRow) -> String {
+    let title = JournalMetadata::parse(j.metadata.as_deref())
+        .title
+        .or_else(|| {
+            if crypto::is_sealed(&j.content) {
+                return None;
+            }
+            j.content
+                .lines()
+                .map(str::trim)
+                .find(|v| !v.is_empty())
+                .map(|v| v.trim_start_matches('#').trim().to_string())
+        })
+        .unwrap_or_default();
+    let mut short = title.chars().take(README_TITLE_CHARS).collect::<String>();
+    if short.len() < title.len() {
+        short.push('…');
+    }
+    short
+        .replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+}
+
+/// markdown 链接中的路径，空格和括号需要转义
+fn readme_link(path: &str) -> String {
+    path.replace('\\', "/")
+        .replace(' ', "%20")
+        .replace('(', "%28")
+        .replace(')', "%29")
+}
+
+/// 模板可以沿用 `.md` 结尾，写出时换成格式对应的扩展名
+fn output_file_path(path: &Path, format: &str) -> Result<PathBuf, DayLogError> {
+    let ext = format_extension(format);
     let ok = path
         .extension()
         .and_then(|s| s.to_str())
-        .map(|s| s.eq_ignore_ascii_case("md"))
-        .unwrap_or(false);
+        .is_some_and(|s| s.eq_ignore_ascii_case("md") || s.eq_ignore_ascii_case(ext));
     if ok {
-        Ok(())
+        Ok(path.with_extension(ext))
     } else {
-        Err(format!("output path must end with .md: {}", path.display()))
+        Err(DayLogError::Validation(format!(
+            "output path must end with .md or .{}: {}",
+            ext,
+            path.display()
+        )))
     }
 }
 
-fn contains_date_placeholder(path: &str, placeholders: &DatePlaceholders) -> bool {
-    [
-        placeholders.yyyy.as_str(),
-        placeholders.mm.as_str(),
-        placeholders.m.as_str(),
-        placeholders.dd.as_str(),
-        placeholders.d.as_str(),
-        placeholders.date.as_str(),
-    ]
-    .iter()
-    .any(|k| path.contains(k))
-}
-
-fn resolve_output_path_template(
-    template: &str,
-    date: &str,
-    placeholders: &DatePlaceholders,
-) -> Result<String, String> {
-    let parts: Vec<&str> = date.split('-').collect();
-    if parts.len() != 3 {
-        return Err(format!("invalid journal date: {}", date));
-    }
-    let yyyy = parts[0];
-    let mm = parts[1];
-    let dd = parts[2];
-    if yyyy.len() != 4
-        || mm.len() != 2
-        || dd.len() != 2
-        || !yyyy.chars().all(|c| c.is_ascii_digit())
-        || !mm.chars().all(|c| c.is_ascii_digit())
-        || !dd.chars().all(|c| c.is_ascii_digit())
-    {
-        return Err(format!("invalid journal date: {}", date));
+fn ensure_md_path(path: &Path) -> Result<(), DayLogError> {
+    let ok = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.eq_ignore_ascii_case("md"))
+        .unwrap_or(false);
+    if ok {
+        Ok(())
+    } else {
+        Err(DayLogError::Validation(format!(
+            "output path must end with .md: {}",
+            path.display()
+        )))
     }
-    let m = mm
-        .parse::<u32>()
-        .map_err(|_| format!("invalid month: {}", mm))?;
-    let d = dd
-        .parse::<u32>()
-        .map_err(|_| format!("invalid day: {}", dd))?;
-    let mut out = template.to_string();
-    out = out.replace(&placeholders.yyyy, yyyy);
-    out = out.replace(&placeholders.mm, mm);
-    out = out.replace(&placeholders.m, &m.to_string());
-    out = out.replace(&placeholders.dd, dd);
-    out = out.replace(&placeholders.d, &d.to_string());
-    out = out.replace(&placeholders.date, date);
-    Ok(out)
 }
 
 fn resolve_commit_message(
     template: &str,
+    trigger: SyncTrigger,
     count: usize,
     journals: &[JournalRow],
     placeholders: &DatePlaceholders,
 ) -> String {
-    let ts = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
-    let (yyyy, mm, dd, m, d, date) = now_date_tokens();
-    let (journal_dd, journal_d) =
-        latest_journal_day_tokens(journals).unwrap_or((dd.clone(), d.clone()));
-    template
+    let ts = date_util::now_secs();
+    let today = date_util::date_of(ts, 0);
+    let (journal_dd, journal_d) = latest_journal_day_tokens(journals).unwrap_or_else(|| {
+        (
+            today[8..].to_string(),
+            today[8..].trim_start_matches('0').to_string(),
+        )
+    });
+    let message = template
         .replace(&placeholders.timestamp, &ts.to_string())
         .replace(&placeholders.count, &count.to_string())
-        .replace(&placeholders.yyyy, &yyyy)
-        .replace(&placeholders.mm, &mm)
-        .replace(&placeholders.m, &m)
-        .replace(&placeholders.dd, &dd)
-        .replace(&placeholders.d, &d)
-        .replace(&placeholders.date, &date)
+        .replace("{trigger}", trigger.as_str())
+        .replace("{version}", env!("CARGO_PKG_VERSION"))
         .replace("{journal_dd}", &journal_dd)
-        .replace("{journal_d}", &journal_d)
+        .replace("{journal_d}", &journal_d);
+    date_pattern::render_date_template(&message, &today, placeholders).unwrap_or(message)
 }
 
 fn latest_journal_day_tokens(journals: &[JournalRow]) -> Option<(String, String)> {
@@ -843,304 +1642,3 @@ fn latest_journal_day_tokens(journals: &[JournalRow]) -> Option<(String, String)
     let d_plain = dd.parse::<u32>().ok()?.to_string();
     Some((dd.to_string(), d_plain))
 }
-
-fn now_date_tokens() -> (String, String, String, String, String, String) {
-    let secs = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs() as i64)
-        .unwrap_or(0);
-    const DAY: i64 = 86_400;
-    let days = secs.div_euclid(DAY);
-    let z = days + 719_468;
-    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
-    let doe = z - era * 146_097;
-    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
-    let y = yoe + era * 400;
-    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
-    let mp = (5 * doy + 2) / 153;
-    let d = doy - (153 * mp + 2) / 5 + 1;
-    let m = mp + if mp < 10 { 3 } else { -9 };
-    let year = y + if m <= 2 { 1 } else { 0 };
-    let yyyy = year.to_string();
-    let mm = format!("{:02}", m);
-    let dd = format!("{:02}", d);
-    let m_plain = m.to_string();
-    let d_plain = d.to_string();
-    let date = format!("{}-{}-{}", yyyy, mm, dd);
-    (yyyy, mm, dd, m_plain, d_plain, date)
-}
-
-fn execute_sync(input: SyncTaskInput) -> Result<SyncTaskOutput, String> {
-    info!(
-        "execute sync: repo_path={}, branch={}, output_files={}",
-        input.repo_path.display(),
-        input.cfg.branch,
-        input.output_files.len()
-    );
-    if let Some(parent) = input.repo_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-    }
-
-    let repo = if input.repo_path.join(".git").exists() {
-        info!(
-            "execute sync: opening existing repo {}",
-            input.repo_path.display()
-        );
-        Repository::open(&input.repo_path).map_err(|e| e.message().to_string())?
-    } else {
-        info!(
-            "execute sync: cloning repo {} -> {}",
-            input.cfg.repo_url,
-            input.repo_path.display()
-        );
-        clone_repo(&input.cfg, &input.repo_path)?
-    };
-
-    info!("execute sync: fetch + fast-forward branch");
-    checkout_and_fast_forward(&repo, &input.cfg)?;
-
-    for f in &input.output_files {
-        let full_output_path = input.repo_path.join(&f.rel_path);
-        if let Some(parent) = full_output_path.parent() {
-            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-        }
-        info!(
-            "execute sync: writing output file {}",
-            full_output_path.display()
-        );
-        fs::write(&full_output_path, f.content.as_bytes()).map_err(|e| e.to_string())?;
-    }
-
-    let mut index = repo.index().map_err(|e| e.message().to_string())?;
-    for f in &input.output_files {
-        index
-            .add_path(f.rel_path.as_path())
-            .map_err(|e| e.message().to_string())?;
-    }
-    index.write().map_err(|e| e.message().to_string())?;
-
-    let tree_id = index.write_tree().map_err(|e| e.message().to_string())?;
-    let tree = repo
-        .find_tree(tree_id)
-        .map_err(|e| e.message().to_string())?;
-
-    let mut parents = Vec::new();
-    if let Ok(head) = repo.head() {
-        let commit = head.peel_to_commit().map_err(|e| e.message().to_string())?;
-        if commit.tree_id() == tree_id {
-            info!("execute sync: no file changes detected, skip commit/push");
-            return Ok(SyncTaskOutput {
-                pushed: false,
-                commit_id: "".to_string(),
-            });
-        }
-        parents.push(commit);
-    }
-
-    let sig = Signature::now(&input.cfg.author_name, &input.cfg.author_email)
-        .map_err(|e| e.message().to_string())?;
-    let parent_refs = parents.iter().collect::<Vec<_>>();
-    let commit_id = repo
-        .commit(
-            Some("HEAD"),
-            &sig,
-            &sig,
-            &input.commit_message,
-            &tree,
-            &parent_refs,
-        )
-        .map_err(|e| e.message().to_string())?;
-    info!("execute sync: commit created {}", commit_id);
-
-    info!("execute sync: pushing branch {}", input.cfg.branch);
-    push_branch(&repo, &input.cfg)?;
-    info!("execute sync: push success");
-
-    Ok(SyncTaskOutput {
-        pushed: true,
-        commit_id: commit_id.to_string(),
-    })
-}
-
-fn clone_repo(cfg: &SyncConfig, repo_path: &Path) -> Result<Repository, String> {
-    let auth_mode = resolve_auth_mode(cfg)?;
-    let cb = remote_callbacks(cfg, auth_mode);
-    let mut fetch = FetchOptions::new();
-    fetch.remote_callbacks(cb);
-
-    let mut builder = RepoBuilder::new();
-    builder.fetch_options(fetch);
-    builder.branch(cfg.branch.trim());
-    builder
-        .clone(cfg.repo_url.trim(), repo_path)
-        .map_err(|e| e.message().to_string())
-}
-
-fn checkout_and_fast_forward(repo: &Repository, cfg: &SyncConfig) -> Result<(), String> {
-    let branch_name = cfg.branch.trim();
-    let remote_branch = format!("refs/remotes/origin/{}", branch_name);
-    let local_branch = format!("refs/heads/{}", branch_name);
-
-    let auth_mode = resolve_auth_mode(cfg)?;
-    let cb = remote_callbacks(cfg, auth_mode);
-    let mut fetch_opts = FetchOptions::new();
-    fetch_opts.remote_callbacks(cb);
-
-    let mut remote = repo
-        .find_remote("origin")
-        .map_err(|e| e.message().to_string())?;
-    remote
-        .fetch(&[branch_name], Some(&mut fetch_opts), None)
-        .map_err(|e| e.message().to_string())?;
-
-    let oid = repo
-        .refname_to_id(&remote_branch)
-        .map_err(|e| e.message().to_string())?;
-    let target = repo.find_commit(oid).map_err(|e| e.message().to_string())?;
-
-    if repo.find_branch(branch_name, BranchType::Local).is_err() {
-        repo.branch(branch_name, &target, true)
-            .map_err(|e| e.message().to_string())?;
-    }
-
-    let mut local_ref = repo
-        .find_reference(&local_branch)
-        .map_err(|e| e.message().to_string())?;
-    local_ref
-        .set_target(target.id(), "fast-forward")
-        .map_err(|e| e.message().to_string())?;
-
-    repo.set_head(&local_branch)
-        .map_err(|e| e.message().to_string())?;
-    let mut checkout = CheckoutBuilder::new();
-    checkout.force();
-    repo.checkout_head(Some(&mut checkout))
-        .map_err(|e| e.message().to_string())?;
-    Ok(())
-}
-
-fn push_branch(repo: &Repository, cfg: &SyncConfig) -> Result<(), String> {
-    let auth_mode = resolve_auth_mode(cfg)?;
-    let cb = remote_callbacks(cfg, auth_mode);
-    let mut push_opts = PushOptions::new();
-    push_opts.remote_callbacks(cb);
-
-    let mut remote = repo
-        .find_remote("origin")
-        .map_err(|e| e.message().to_string())?;
-    let spec = format!("refs/heads/{0}:refs/heads/{0}", cfg.branch.trim());
-    remote
-        .push(&[&spec], Some(&mut push_opts))
-        .map_err(|e| e.message().to_string())
-}
-
-fn remote_callbacks(cfg: &SyncConfig, auth_mode: AuthMode) -> RemoteCallbacks<'static> {
-    let username = cfg.username.clone();
-    let password = cfg.password.clone();
-    let ssh_username = cfg.ssh_username.clone();
-    let ssh_private_key = cfg.ssh_private_key_path.clone();
-    let ssh_public_key = cfg.ssh_public_key_path.clone();
-    let ssh_passphrase = cfg.ssh_passphrase.clone();
-    let mut cb = RemoteCallbacks::new();
-    cb.credentials(move |_url, user, _allowed| match auth_mode {
-        AuthMode::Password => Cred::userpass_plaintext(&username, &password),
-        AuthMode::Ssh => {
-            let user_name = if !ssh_username.trim().is_empty() {
-                ssh_username.as_str()
-            } else {
-                user.unwrap_or("git")
-            };
-            let private_key = expand_tilde_path(ssh_private_key.trim())?;
-            let public_key = if ssh_public_key.trim().is_empty() {
-                None
-            } else {
-                Some(expand_tilde_path(ssh_public_key.trim())?)
-            };
-            let passphrase = if ssh_passphrase.trim().is_empty() {
-                None
-            } else {
-                Some(ssh_passphrase.as_str())
-            };
-            Cred::ssh_key(
-                user_name,
-                public_key.as_deref(),
-                Path::new(&private_key),
-                passphrase,
-            )
-        }
-    });
-    cb
-}
-
-fn resolve_auth_mode(cfg: &SyncConfig) -> Result<AuthMode, String> {
-    let method = cfg.auth_method.trim().to_ascii_lowercase();
-    match method.as_str() {
-        "password" | "userpass" | "https" => Ok(AuthMode::Password),
-        "ssh" => Ok(AuthMode::Ssh),
-        "auto" | "" => {
-            if looks_like_github_repo(&cfg.repo_url) {
-                return Ok(AuthMode::Ssh);
-            }
-            if !cfg.username.trim().is_empty() && !cfg.password.trim().is_empty() {
-                return Ok(AuthMode::Password);
-            }
-            if !cfg.ssh_private_key_path.trim().is_empty() {
-                return Ok(AuthMode::Ssh);
-            }
-            Ok(AuthMode::Password)
-        }
-        _ => Err("sync.auth_method must be one of: auto, password, ssh".to_string()),
-    }
-}
-
-fn validate_auth_config(cfg: &SyncConfig, mode: AuthMode) -> Result<(), String> {
-    match mode {
-        AuthMode::Password => {
-            if cfg.username.trim().is_empty() || cfg.password.trim().is_empty() {
-                if looks_like_github_repo(&cfg.repo_url) {
-                    return Err(
-                        "GitHub repo should use ssh auth. set sync.auth_method='ssh' and sync.ssh_private_key_path"
-                            .to_string(),
-                    );
-                }
-                return Err(
-                    "sync.username and sync.password are required for password auth".to_string(),
-                );
-            }
-            Ok(())
-        }
-        AuthMode::Ssh => {
-            if cfg.ssh_private_key_path.trim().is_empty() {
-                return Err("sync.ssh_private_key_path is required for ssh auth".to_string());
-            }
-            let key_path = expand_tilde_path(cfg.ssh_private_key_path.trim())
-                .map_err(|e| e.message().to_string())?;
-            if !Path::new(&key_path).exists() {
-                return Err(format!("ssh private key not found: {}", key_path.display()));
-            }
-            Ok(())
-        }
-    }
-}
-
-fn looks_like_github_repo(repo_url: &str) -> bool {
-    let lower = repo_url.trim().to_ascii_lowercase();
-    lower.contains("github.com")
-}
-
-fn expand_tilde_path(input: &str) -> Result<PathBuf, git2::Error> {
-    if !input.starts_with('~') {
-        return Ok(PathBuf::from(input));
-    }
-    let home = env::var("HOME")
-        .map_err(|_| git2::Error::from_str("HOME env is required when using ~ in ssh key path"))?;
-    if input == "~" {
-        return Ok(PathBuf::from(home));
-    }
-    if let Some(rest) = input.strip_prefix("~/") {
-        return Ok(Path::new(&home).join(rest));
-    }
-    Err(git2::Error::from_str(
-        "unsupported ~ path form, use ~/xxx for ssh key path",
-    ))
-}
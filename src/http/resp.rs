@@ -1,19 +1,39 @@
+use crate::error::DayLogError;
+use crate::util::i18n;
+use axum::extract::multipart::MultipartError;
+use axum::response::{IntoResponse, Response};
 use axum::{Json, http::StatusCode};
 use serde::Serialize;
+use tracing::{error, warn};
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize)]
+/// 所有 json 接口的外层，http 状态码总是 200，`code` 不是 200 时 `data` 为 null，
+/// `msg` 按请求的 `Accept-Language` 翻译，客户端应按 `code` 或 `error` 判断
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiResponse<T> {
     pub code: i32,
+    /// `code` 对应的英文标识，成功时为 null，完整列表见 `GET /errors`
+    #[schema(value_type = Option<String>)]
+    pub error: Option<&'static str>,
     pub msg: String,
     pub data: Option<T>,
 }
 
+/// 新增取值时同时加到 `ALL`，`GET /errors` 按它列出
 #[derive(Debug, Clone, Copy)]
 pub enum ApiCode {
     Ok = 200,
     BadRequest = 400,
+    Unauthorized = 401,
+    Forbidden = 403,
     NotFound = 404,
+    Conflict = 409,
+    PayloadTooLarge = 413,
+    Validation = 422,
+    Locked = 423,
+    QuotaExceeded = 429,
+    Internal = 500,
     DbInsertFailed = 1001,
     DbQueryFailed = 1002,
     DbListFailed = 1003,
@@ -23,13 +43,113 @@ pub enum ApiCode {
     DbDeleteFailed = 1007,
     FileMissing = 2001,
     FileWriteFailed = 2002,
+    FileRejected = 2003,
+    IoFailed = 2004,
     SyncFailed = 3001,
+    GitRemoteFailed = 3002,
 }
 
 impl ApiCode {
+    pub const ALL: [ApiCode; 24] = [
+        ApiCode::Ok,
+        ApiCode::BadRequest,
+        ApiCode::Unauthorized,
+        ApiCode::Forbidden,
+        ApiCode::NotFound,
+        ApiCode::Conflict,
+        ApiCode::PayloadTooLarge,
+        ApiCode::Validation,
+        ApiCode::Locked,
+        ApiCode::QuotaExceeded,
+        ApiCode::Internal,
+        ApiCode::DbInsertFailed,
+        ApiCode::DbQueryFailed,
+        ApiCode::DbListFailed,
+        ApiCode::DbGetFailed,
+        ApiCode::DbUpdateFailed,
+        ApiCode::DbUpdateGetFailed,
+        ApiCode::DbDeleteFailed,
+        ApiCode::FileMissing,
+        ApiCode::FileWriteFailed,
+        ApiCode::FileRejected,
+        ApiCode::IoFailed,
+        ApiCode::SyncFailed,
+        ApiCode::GitRemoteFailed,
+    ];
+
     pub fn code(self) -> i32 {
         self as i32
     }
+
+    /// 客户端按这个区分错误，取值确定后不再修改
+    pub fn slug(self) -> &'static str {
+        match self {
+            ApiCode::Ok => "ok",
+            ApiCode::BadRequest => "bad_request",
+            ApiCode::Unauthorized => "unauthorized",
+            ApiCode::Forbidden => "forbidden",
+            ApiCode::NotFound => "not_found",
+            ApiCode::Conflict => "conflict",
+            ApiCode::PayloadTooLarge => "payload_too_large",
+            ApiCode::Validation => "validation_failed",
+            ApiCode::Locked => "locked",
+            ApiCode::QuotaExceeded => "quota_exceeded",
+            ApiCode::Internal => "internal_error",
+            ApiCode::DbInsertFailed => "db_insert_failed",
+            ApiCode::DbQueryFailed => "db_query_failed",
+            ApiCode::DbListFailed => "db_list_failed",
+            ApiCode::DbGetFailed => "db_get_failed",
+            ApiCode::DbUpdateFailed => "db_update_failed",
+            ApiCode::DbUpdateGetFailed => "db_update_get_failed",
+            ApiCode::DbDeleteFailed => "db_delete_failed",
+            ApiCode::FileMissing => "file_missing",
+            ApiCode::FileWriteFailed => "file_write_failed",
+            ApiCode::FileRejected => "file_rejected",
+            ApiCode::IoFailed => "io_failed",
+            ApiCode::SyncFailed => "sync_failed",
+            ApiCode::GitRemoteFailed => "git_remote_failed",
+        }
+    }
+
+    /// 英文原文，按请求的语言翻译见 `i18n::text`
+    pub fn description(self) -> &'static str {
+        match self {
+            ApiCode::Ok => "Success",
+            ApiCode::BadRequest => {
+                "Malformed request: json, multipart or query could not be parsed, or the feature is disabled"
+            }
+            ApiCode::Unauthorized => "Missing or invalid token",
+            ApiCode::Forbidden => "Token lacks the scope this endpoint requires",
+            ApiCode::NotFound => "Record does not exist",
+            ApiCode::Conflict => {
+                "Conflicts with existing data, e.g. the day already has a journal or the tag name is taken"
+            }
+            ApiCode::PayloadTooLarge => "Upload exceeds `upload_file_limit`",
+            ApiCode::Validation => "A field parsed but its value is invalid, `msg` names the field",
+            ApiCode::Locked => {
+                "Temporarily locked after too many failed logins, `Retry-After` is the remaining seconds"
+            }
+            ApiCode::QuotaExceeded => "Count or rate limit exceeded",
+            ApiCode::Internal => "Unexpected server error, details are in the server log",
+            ApiCode::DbInsertFailed => "Writing to the database failed",
+            ApiCode::DbQueryFailed => "Querying the database failed",
+            ApiCode::DbListFailed => "Listing records failed",
+            ApiCode::DbGetFailed => "Reading the record failed",
+            ApiCode::DbUpdateFailed => "Updating the database failed",
+            ApiCode::DbUpdateGetFailed => "Reading the record after update failed",
+            ApiCode::DbDeleteFailed => "Deleting failed",
+            ApiCode::FileMissing => "File does not exist or the request has no file",
+            ApiCode::FileWriteFailed => "Saving the file failed",
+            ApiCode::FileRejected => {
+                "Uploaded file was rejected by `upload.scan_command` or the `[media]` size limits"
+            }
+            ApiCode::IoFailed => "Reading or writing a local file or directory failed",
+            ApiCode::SyncFailed => "Local repository operation failed during sync",
+            ApiCode::GitRemoteFailed => {
+                "Sync remote failed: clone, fetch, push or upload error, or authentication failed"
+            }
+        }
+    }
 }
 
 pub type ApiResult<T> =
@@ -41,6 +161,7 @@ impl<T: Serialize> ApiResponse<T> {
             StatusCode::OK,
             Json(ApiResponse {
                 code: ApiCode::Ok.code(),
+                error: None,
                 msg: "ok".to_string(),
                 data: Some(data),
             }),
@@ -52,9 +173,64 @@ impl<T: Serialize> ApiResponse<T> {
             StatusCode::OK,
             Json(ApiResponse {
                 code: code.code(),
-                msg: msg.to_string(),
+                error: Some(code.slug()),
+                msg: i18n::text(msg),
                 data: None,
             }),
         )
     }
 }
+
+/// 读取 multipart 字段失败时的错误码，超过 `upload_file_limit` 单独区分
+pub fn multipart_read_code(e: &MultipartError) -> ApiCode {
+    match e.status() {
+        StatusCode::PAYLOAD_TOO_LARGE => ApiCode::PayloadTooLarge,
+        _ => ApiCode::BadRequest,
+    }
+}
+
+impl DayLogError {
+    pub fn code(&self) -> ApiCode {
+        match self {
+            DayLogError::Git(_) => ApiCode::SyncFailed,
+            DayLogError::Remote(_) => ApiCode::GitRemoteFailed,
+            DayLogError::Io(_) => ApiCode::IoFailed,
+            DayLogError::Db(_) => ApiCode::DbQueryFailed,
+            DayLogError::BadRequest(_) => ApiCode::BadRequest,
+            DayLogError::Validation(_) => ApiCode::Validation,
+            DayLogError::Internal(_) => ApiCode::Internal,
+        }
+    }
+
+    /// 返回给客户端的文字；数据库、文件和内部错误只记日志，不把 sql、服务器上的绝对路径之类的细节带出去
+    fn client_message(&self) -> String {
+        match self {
+            DayLogError::Io(e) => {
+                error!("io error: {}", e);
+                "file operation failed".to_string()
+            }
+            DayLogError::Db(e) => {
+                warn!("db error: {}", e);
+                "db query failed".to_string()
+            }
+            DayLogError::Internal(e) => {
+                warn!("internal error: {}", e);
+                "internal error".to_string()
+            }
+            e => e.to_string(),
+        }
+    }
+}
+
+/// 接口返回 `ApiResult<T>` 时可以直接对 `DayLogError` 用 `?`
+impl<T: Serialize> From<DayLogError> for (StatusCode, Json<ApiResponse<T>>) {
+    fn from(e: DayLogError) -> Self {
+        ApiResponse::err(e.code(), &e.client_message())
+    }
+}
+
+impl IntoResponse for DayLogError {
+    fn into_response(self) -> Response {
+        ApiResponse::<()>::err(self.code(), &self.client_message()).into_response()
+    }
+}
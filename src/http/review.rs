@@ -0,0 +1,170 @@
+use crate::app_state::AppState;
+use crate::http::journal::Journal;
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::util::date_util;
+use axum::extract::{Path, Query, State};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tracing::info;
+
+/// 随机回顾只挑选至少这么多天以前的日记
+const RANDOM_MIN_AGE_DAYS: i64 = 30;
+/// 最近这么多天内回顾过的日记不会被随机选中
+const RANDOM_COOLDOWN_DAYS: i64 = 90;
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewQuery {
+    pub random: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewItem {
+    /// 1_week / 1_month / 1_year / random
+    pub reason: String,
+    pub review_count: i64,
+    pub journal: Journal,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewedResp {
+    pub journal_id: i64,
+    pub review_count: i64,
+    pub last_review_time: i64,
+}
+
+pub async fn list_review(
+    State(state): State<AppState>,
+    Query(query): Query<ReviewQuery>,
+) -> ApiResult<Vec<ReviewItem>> {
+    let random = query.random.unwrap_or(3).clamp(0, 20);
+    let now = date_util::now_secs();
+    let today = date_util::local_days(now, state.config.utc_offset_minutes);
+    let today_start =
+        now - date_util::local_minute_of_day(now, state.config.utc_offset_minutes) * 60;
+    let (y, m, d) = date_util::civil_from_days(today);
+    let (prev_y, prev_m) = if m == 1 { (y - 1, 12) } else { (y, m - 1) };
+    let targets = [
+        ("1_week", date_util::date_from_days(today - 7)),
+        ("1_month", format!("{:04}-{:02}-{:02}", prev_y, prev_m, d)),
+        ("1_year", format!("{:04}-{:02}-{:02}", y - 1, m, d)),
+    ];
+    info!("获取回顾列表 today={}", date_util::date_from_days(today));
+
+    let mut items = Vec::new();
+    let mut seen = HashSet::new();
+    for (reason, date) in targets {
+        let row = sqlx::query_as::<_, Journal>(
+            r#"
+            select j.id, j.content, j.date, j.create_time, j.update_time, j.metadata, j.word_count, j.reading_minutes
+            from journal j
+            left join journal_review r on r.journal_id = j.id
+            where j.date = ? and (r.last_review_time is null or r.last_review_time < ?)
+            limit 1
+            "#,
+        )
+        .bind(&date)
+        .bind(today_start)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| {
+            ApiResponse::<Vec<ReviewItem>>::err(ApiCode::DbListFailed, "db query failed")
+        })?;
+        if let Some(journal) = row
+            && seen.insert(journal.id)
+        {
+            items.push((reason.to_string(), journal.decrypted(&state)));
+        }
+    }
+
+    if random > 0 {
+        let rows = sqlx::query_as::<_, Journal>(
+            r#"
+            select j.id, j.content, j.date, j.create_time, j.update_time, j.metadata, j.word_count, j.reading_minutes
+            from journal j
+            left join journal_review r on r.journal_id = j.id
+            where j.date <= ? and (r.last_review_time is null or r.last_review_time < ?)
+            order by random()
+            limit ?
+            "#,
+        )
+        .bind(date_util::date_from_days(today - RANDOM_MIN_AGE_DAYS))
+        .bind(now - RANDOM_COOLDOWN_DAYS * 86_400)
+        .bind(random + items.len() as i64)
+        .fetch_all(&state.db)
+        .await
+        .map_err(|_| {
+            ApiResponse::<Vec<ReviewItem>>::err(ApiCode::DbListFailed, "db query failed")
+        })?;
+        for journal in rows
+            .into_iter()
+            .filter(|j| seen.insert(j.id))
+            .take(random as usize)
+        {
+            items.push(("random".to_string(), journal.decrypted(&state)));
+        }
+    }
+
+    let mut out = Vec::with_capacity(items.len());
+    for (reason, journal) in items {
+        let review_count = sqlx::query_scalar::<_, i64>(
+            "select review_count from journal_review where journal_id = ?",
+        )
+        .bind(journal.id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| ApiResponse::<Vec<ReviewItem>>::err(ApiCode::DbListFailed, "db query failed"))?
+        .unwrap_or(0);
+        out.push(ReviewItem {
+            reason,
+            review_count,
+            journal,
+        });
+    }
+    Ok(ApiResponse::ok(out))
+}
+
+pub async fn mark_reviewed(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> ApiResult<ReviewedResp> {
+    let exists = sqlx::query_scalar::<_, i64>("select id from journal where id = ?")
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|_| ApiResponse::<ReviewedResp>::err(ApiCode::DbQueryFailed, "db query failed"))?;
+    if exists.is_none() {
+        return Err(ApiResponse::err(ApiCode::NotFound, "not found"));
+    }
+
+    let ts = date_util::now_secs();
+    sqlx::query(
+        r#"
+        insert into journal_review (journal_id, review_count, last_review_time)
+        values (?, 1, ?)
+        on conflict(journal_id) do update set
+            review_count = review_count + 1,
+            last_review_time = excluded.last_review_time
+        "#,
+    )
+    .bind(id)
+    .bind(ts)
+    .execute(&state.db)
+    .await
+    .map_err(|_| ApiResponse::<ReviewedResp>::err(ApiCode::DbUpdateFailed, "db update failed"))?;
+
+    let review_count = sqlx::query_scalar::<_, i64>(
+        "select review_count from journal_review where journal_id = ?",
+    )
+    .bind(id)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|_| ApiResponse::<ReviewedResp>::err(ApiCode::DbQueryFailed, "db query failed"))?;
+
+    Ok(ApiResponse::ok(ReviewedResp {
+        journal_id: id,
+        review_count,
+        last_review_time: ts,
+    }))
+}
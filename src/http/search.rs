@@ -0,0 +1,68 @@
+use crate::app_state::AppState;
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::search::{self, ReindexReport, SearchHit};
+use axum::extract::{Query, State};
+use serde::Deserialize;
+use tracing::{info, warn};
+use utoipa::IntoParams;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SearchQuery {
+    /// 空格分开的词都要命中；`search.tokenizer = "trigram"` 时少于三个字的词搜不到
+    pub q: String,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// 全文搜索日记，按相关度排序
+#[utoipa::path(
+    get,
+    path = "/journal/search",
+    tag = "journal",
+    params(SearchQuery),
+    responses((status = 200, description = "开启 `[encryption]` 时不可用，code 为 400", body = ApiResponse<Vec<SearchHit>>))
+)]
+pub async fn search_journals(
+    State(state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+) -> ApiResult<Vec<SearchHit>> {
+    if state.cipher.enabled() {
+        return Err(ApiResponse::<Vec<SearchHit>>::err(
+            ApiCode::BadRequest,
+            "search is unavailable while encryption is enabled",
+        ));
+    }
+    let q = query.q.trim();
+    if q.is_empty() {
+        return Err(ApiResponse::<Vec<SearchHit>>::err(
+            ApiCode::Validation,
+            "q must not be empty",
+        ));
+    }
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+    info!("搜索日记 q={}, limit={}, offset={}", q, limit, offset);
+    let hits = search::search(&state, q, limit, offset)
+        .await
+        .map_err(|e| {
+            warn!("搜索日记失败: {}", e);
+            ApiResponse::<Vec<SearchHit>>::err(ApiCode::DbQueryFailed, "db query failed")
+        })?;
+    Ok(ApiResponse::ok(hits))
+}
+
+/// 按当前 `search.tokenizer` 重建全文索引
+pub async fn reindex(State(state): State<AppState>) -> ApiResult<ReindexReport> {
+    if state.cipher.enabled() {
+        return Err(ApiResponse::<ReindexReport>::err(
+            ApiCode::BadRequest,
+            "search is unavailable while encryption is enabled",
+        ));
+    }
+    info!("重建全文索引 tokenizer={}", state.config.search.tokenizer);
+    let report = search::reindex(&state).await.map_err(|e| {
+        warn!("重建全文索引失败: {}", e);
+        ApiResponse::<ReindexReport>::err(ApiCode::DbUpdateFailed, "db update failed")
+    })?;
+    Ok(ApiResponse::ok(report))
+}
@@ -0,0 +1,55 @@
+use crate::app_state::AppState;
+use crate::config::app_config::SecurityConfig;
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, header};
+use axum::middleware::Next;
+use axum::response::Response;
+
+/// 日记 markdown 会渲染成 html 展示，用 csp 限制脚本来源，降低内容里混入脚本的风险
+pub async fn security_headers(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    let cfg = &state.config.security;
+    if cfg.enabled {
+        apply(cfg, response.headers_mut());
+    }
+    response
+}
+
+fn apply(cfg: &SecurityConfig, headers: &mut HeaderMap) {
+    set_if_absent(headers, header::X_CONTENT_TYPE_OPTIONS, "nosniff");
+    set_if_absent(headers, header::REFERRER_POLICY, cfg.referrer_policy.trim());
+
+    let frame_ancestors = cfg.frame_ancestors.trim();
+    let mut csp = cfg
+        .content_security_policy
+        .trim()
+        .trim_end_matches(';')
+        .to_string();
+    if !frame_ancestors.is_empty() {
+        if !csp.is_empty() {
+            csp.push_str("; ");
+        }
+        csp.push_str("frame-ancestors ");
+        csp.push_str(frame_ancestors);
+        // 不支持 csp 的旧浏览器只认 X-Frame-Options
+        match frame_ancestors {
+            "'none'" => set_if_absent(headers, header::X_FRAME_OPTIONS, "DENY"),
+            "'self'" => set_if_absent(headers, header::X_FRAME_OPTIONS, "SAMEORIGIN"),
+            _ => {}
+        }
+    }
+    set_if_absent(headers, header::CONTENT_SECURITY_POLICY, &csp);
+}
+
+fn set_if_absent(headers: &mut HeaderMap, name: HeaderName, value: &str) {
+    if value.is_empty() || headers.contains_key(&name) {
+        return;
+    }
+    if let Ok(v) = HeaderValue::from_str(value) {
+        headers.insert(name, v);
+    }
+}
@@ -1,17 +1,47 @@
 use crate::app_state::AppState;
-use crate::http::{file, import_zip, journal, repo_sync, settings};
-use axum::routing::{get, get_service, post};
+use crate::http::{
+    archive, auth, backup, badge, book, capabilities, db_backup, digest, draft, duplicates,
+    encryption, errors, export, file, hooks, import_obsidian, import_progress, import_wordpress,
+    import_zip, integrity, jobs, journal, locale, month, openapi, public, quick, rate_limit,
+    repo_sync, review, search, security, settings, setup, share, stats, status, tag, trash,
+};
+use crate::job::{self, JobDef};
+use crate::notify::{self, NotifyEvent};
+use axum::middleware;
+use axum::routing::{delete, get, get_service, post};
 use axum::{Router, extract::DefaultBodyLimit};
 use std::io;
+use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use tower_http::services::{ServeDir, ServeFile};
 use tracing::{debug, info};
 
-pub async fn run(app_state: AppState) -> Result<(), Box<dyn std::error::Error>> {
+/// `jobs` 是其他模块的定时任务，启动同步完成后和定时同步一起开始调度
+pub async fn run(
+    app_state: AppState,
+    mut jobs: Vec<JobDef>,
+) -> Result<(), Box<dyn std::error::Error>> {
     if let Err(e) = repo_sync::startup_sync_to_db(&app_state).await {
         tracing::error!("启动同步失败: {}", e);
+        notify::spawn_send(
+            &app_state,
+            NotifyEvent::SyncFailed {
+                reason: format!("startup sync failed: {}", e),
+            },
+        );
     }
 
+    repo_sync::register_jobs();
+    import_zip::register_jobs();
+    journal::register_jobs();
+    crate::search::register_jobs();
+    jobs.extend(repo_sync::scheduled_job(&app_state));
+    job::spawn(app_state.clone(), jobs).await;
+    journal::queue_count_backfill(&app_state).await;
+    crate::search::queue_refresh(&app_state).await;
+
+    // 全局只允许普通大小的请求体，上传和导入接口单独放宽到 `upload_file_limit`
+    let upload_limit = DefaultBodyLimit::max(app_state.config.upload_file_limit);
     let port = app_state.config.port;
     let max_switch_time = app_state.config.auto_switch_port_time;
     let mut switch_time = 0;
@@ -20,42 +50,157 @@ pub async fn run(app_state: AppState) -> Result<(), Box<dyn std::error::Error>>
     let router = Router::new()
         .route_service(
             "/",
-            get_service(ServeFile::new(&app_state.config.get_index_path())),
-        )
-        .nest_service(
-            "/static",
-            ServeDir::new(&app_state.config.get_static_path()),
+            get_service(ServeFile::new(app_state.config.get_index_path())),
         )
-        .nest_service(
-            "/files/picture",
-            ServeDir::new(&app_state.config.get_picture_path()),
+        .nest_service("/static", ServeDir::new(app_state.config.get_static_path()))
+        .route("/files/picture/{*name}", get(file::serve_picture))
+        .route("/files/media/{*name}", get(file::serve_media))
+        .route("/files/file/{*name}", get(file::serve_attachment))
+        .route(
+            "/journal",
+            post(journal::create_journal).get(journal::list_journals),
         )
-        .nest_service(
-            "/files/media",
-            ServeDir::new(&app_state.config.get_media_path()),
+        .route("/journal/map", get(journal::list_journal_map))
+        .route("/journal/recent", get(journal::list_recent_journals))
+        .route("/journal/neighbors", get(journal::journal_neighbors))
+        .route("/journal/search", get(search::search_journals))
+        .route("/journal/month/{month}", get(month::month_view))
+        .route("/journal/duplicates", get(duplicates::list_duplicates))
+        .route("/journal/merge", post(journal::merge_journals))
+        .route("/journal/stats", get(stats::journal_overview))
+        .route("/journal/stats/words", get(stats::word_stats))
+        .route("/journal/stats/rhythm", get(stats::rhythm_stats))
+        .route("/journal/review", get(review::list_review))
+        .route("/journal/{id}/reviewed", post(review::mark_reviewed))
+        .route("/journal/{id}/files", get(file::list_journal_files))
+        .route(
+            "/journal/date/{date}/draft",
+            get(draft::get_draft)
+                .put(draft::save_draft)
+                .delete(draft::delete_draft),
         )
-        .nest_service(
-            "/files/file",
-            ServeDir::new(&app_state.config.get_file_path()),
+        .route(
+            "/journal/date/{date}/draft/promote",
+            post(draft::promote_draft),
         )
+        .route("/files/by-id/{id}", get(file::serve_file_by_id))
+        .route("/files/{id}/references", get(file::list_file_references))
+        .route("/journal/{id}/move", post(journal::move_journal))
         .route(
-            "/journal",
-            post(journal::create_journal).get(journal::list_journals),
+            "/journal/{id}/tags",
+            get(tag::list_journal_tags).post(tag::attach_tags),
         )
+        .route("/journal/{id}/tags/{tag_id}", delete(tag::detach_tag))
+        .route("/tag", get(tag::list_tags).post(tag::create_tag))
+        .route("/tag/{id}", delete(tag::delete_tag))
         .route(
             "/journal/{id}",
             get(journal::get_journal)
                 .put(journal::update_journal)
                 .delete(journal::delete_journal),
         )
-        .route("/journal/import/zip", post(import_zip::import_journal_zip))
+        .route(
+            "/journal/import/zip",
+            post(import_zip::import_journal_zip).layer(upload_limit),
+        )
+        .route(
+            "/journal/import/zip/async",
+            post(import_zip::import_journal_zip_async).layer(upload_limit),
+        )
+        .route(
+            "/journal/import/{job_id}/events",
+            get(import_progress::import_events),
+        )
+        .route("/journal/export/zip", get(export::export_zip))
+        .route(
+            "/journal/import/wordpress",
+            post(import_wordpress::import_wordpress).layer(upload_limit),
+        )
+        .route(
+            "/journal/import/obsidian",
+            post(import_obsidian::import_obsidian).layer(upload_limit),
+        )
         .route(
             "/settings",
             get(settings::get_settings).put(settings::update_settings),
         )
-        .route("/upload", post(file::upload_file))
+        .route("/setup/status", get(setup::setup_status))
+        .route("/setup", post(setup::apply_setup))
+        .route("/digest", get(digest::list_digests))
+        .route("/digest/weekly", post(digest::generate_weekly_digest))
+        .route("/digest/{id}", get(digest::get_digest))
+        .route("/archive", get(archive::list_archives))
+        .route("/archive/run", post(archive::run_archive))
+        .route("/archive/{id}/restore", post(archive::restore_archive))
+        .route("/backup", get(backup::list_backups))
+        .route("/backup/run", post(backup::run_backup))
+        .route("/admin/files/verify", post(integrity::verify_files))
+        .route(
+            "/admin/files/repair-paths",
+            post(integrity::repair_file_paths),
+        )
+        .route("/admin/stats", get(status::admin_stats))
+        .route("/admin/jobs", get(jobs::list_jobs))
+        .route("/admin/jobs/{id}/run", post(jobs::run_job))
+        .route("/admin/backup", get(db_backup::download_db_backup))
+        .route(
+            "/admin/restore",
+            post(db_backup::restore_db_backup).layer(upload_limit),
+        )
+        .route("/admin/search/reindex", post(search::reindex))
+        .route(
+            "/admin/encryption/migrate",
+            post(encryption::migrate_encryption),
+        )
+        .route("/trash", get(trash::list_trash))
+        .route("/trash/purge", post(trash::purge_trash))
+        .route("/trash/{id}/restore", post(trash::restore_trash))
+        .route("/export/book", get(book::export_book))
+        .route("/export/json", get(export::export_json))
+        .route("/share/month", post(share::share_month))
+        .route(
+            "/share/{token}",
+            get(share::view_share)
+                .post(share::unlock_share)
+                .delete(share::revoke_share),
+        )
+        .route("/upload", post(file::upload_file).layer(upload_limit))
+        .route("/quick", post(quick::quick_append))
+        .route("/hooks/ingest", post(hooks::ingest))
         .route("/sync/journal", post(repo_sync::sync_journal))
-        .layer(DefaultBodyLimit::max(app_state.config.upload_file_limit))
+        .route("/sync/diagnose", get(repo_sync::diagnose_sync))
+        .route("/status/blocking", get(status::blocking_stats))
+        .route("/capabilities", get(capabilities::list_capabilities))
+        .route("/errors", get(errors::list_errors))
+        .route("/auth/login", post(auth::login))
+        .route("/auth/logout", post(auth::logout))
+        .route("/auth/failures", get(auth::list_failures))
+        .route(
+            "/auth/sessions",
+            get(auth::list_sessions).post(auth::create_session),
+        )
+        .route("/auth/sessions/{id}", delete(auth::revoke_session))
+        .route("/badge/streak.json", get(badge::streak_badge))
+        .merge(openapi::swagger_ui())
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            auth::require_auth,
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            rate_limit::limit,
+        ))
+        // 公开接口在鉴权和私有接口的限流之外，单独一层
+        .merge(public::router(&app_state))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            locale::negotiate,
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            security::security_headers,
+        ))
+        .layer(DefaultBodyLimit::max(app_state.config.json_body_limit))
         .with_state(app_state);
 
     loop {
@@ -71,7 +216,11 @@ pub async fn run(app_state: AppState) -> Result<(), Box<dyn std::error::Error>>
         match TcpListener::bind(format!("0.0.0.0:{}", current_port)).await {
             Ok(listener) => {
                 info!("服务已启动 http://127.0.0.1:{}", current_port);
-                let _ = axum::serve(listener, router.into_make_service()).await?;
+                axum::serve(
+                    listener,
+                    router.into_make_service_with_connect_info::<SocketAddr>(),
+                )
+                .await?;
                 break;
             }
             Err(err) if err.kind() == std::io::ErrorKind::AddrInUse => {
@@ -1,18 +1,26 @@
 use crate::app_state::AppState;
+use crate::http::repo_sync::{self, SyncTrigger};
 use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::util::date_util;
 use axum::Json;
 use axum::extract::State;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::time::{SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
 
 pub const KEY_IMPORT_PATTERNS: &str = "import_patterns";
 pub const KEY_SYNC_OUTPUT_PATH: &str = "sync_output_path";
 pub const KEY_SYNC_COMMIT_MESSAGE: &str = "sync_commit_message";
+/// 按触发来源区分的提交信息模板，完整 key 为 `sync_commit_message_{trigger}`
+pub const KEY_SYNC_COMMIT_MESSAGE_PREFIX: &str = "sync_commit_message_";
 pub const KEY_DATE_PLACEHOLDERS: &str = "date_placeholders";
+pub const KEY_INGEST_MAPPING: &str = "ingest_mapping";
+/// 上一次双向同步开始的时间，之后改过的日记和仓库文件才算有修改
+pub const KEY_SYNC_LAST_TWO_WAY: &str = "sync_last_two_way_time";
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct DatePlaceholders {
     pub yyyy: String,
@@ -23,24 +31,61 @@ pub struct DatePlaceholders {
     pub date: String,
     pub timestamp: String,
     pub count: String,
+    /// 两位年份
+    #[serde(default = "default_yy_placeholder")]
+    pub yy: String,
+    /// ISO 周数，两位
+    #[serde(default = "default_ww_placeholder")]
+    pub ww: String,
+    /// 星期简称，例如 Mon / 周一
+    #[serde(default = "default_ddd_placeholder")]
+    pub ddd: String,
+    /// 星期全称，例如 Monday / 星期一
+    #[serde(default = "default_dddd_placeholder")]
+    pub dddd: String,
+    /// 星期名称的语言：en / zh / ja
+    #[serde(default = "default_date_locale")]
+    pub locale: String,
 }
 
-#[derive(Debug, Serialize)]
+/// `POST /hooks/ingest` 的 json 字段映射，字段名支持 `a.b` 取嵌套值
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestMapping {
+    pub text_field: String,
+    /// 为空或请求中没有该字段时写入今天
+    #[serde(default)]
+    pub date_field: String,
+    /// 不为空时作为 `## 标题` 放在正文前
+    #[serde(default)]
+    pub title_field: String,
+    /// append: 追加到当天末尾，create: 创建当天日记，当天已有日记时返回 409 不覆盖
+    #[serde(default = "default_ingest_mode")]
+    pub mode: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AppSettingsResp {
     pub import_patterns: Vec<String>,
     pub sync_output_path: String,
     pub sync_commit_message: String,
+    /// 触发来源 -> 提交信息模板，没有配置的来源使用 `sync_commit_message`
+    pub sync_commit_messages: BTreeMap<String, String>,
     pub date_placeholders: DatePlaceholders,
+    pub ingest_mapping: IngestMapping,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateSettingsReq {
     pub import_patterns: Option<Vec<String>>,
     pub sync_output_path: Option<String>,
     pub sync_commit_message: Option<String>,
+    /// 值为空字符串时删除该来源的模板
+    pub sync_commit_messages: Option<BTreeMap<String, String>>,
     pub date_placeholders: Option<DatePlaceholders>,
+    pub ingest_mapping: Option<IngestMapping>,
 }
 
 #[derive(Debug, FromRow)]
@@ -48,6 +93,12 @@ struct SettingRow {
     value: String,
 }
 
+#[utoipa::path(
+    get,
+    path = "/settings",
+    tag = "settings",
+    responses((status = 200, body = ApiResponse<AppSettingsResp>))
+)]
 pub async fn get_settings(State(state): State<AppState>) -> ApiResult<AppSettingsResp> {
     let date_placeholders = load_date_placeholders(&state)
         .await
@@ -61,24 +112,79 @@ pub async fn get_settings(State(state): State<AppState>) -> ApiResult<AppSetting
     let sync_commit_message = load_sync_commit_message(&state)
         .await
         .unwrap_or_else(|| state.config.sync.commit_message.clone());
+    let mut sync_commit_messages = BTreeMap::new();
+    for trigger in SyncTrigger::ALL {
+        if let Some(v) = load_trigger_commit_message(&state, trigger.as_str()).await {
+            sync_commit_messages.insert(trigger.as_str().to_string(), v);
+        }
+    }
+    let ingest_mapping = load_ingest_mapping(&state)
+        .await
+        .unwrap_or_else(default_ingest_mapping);
 
     Ok(ApiResponse::ok(AppSettingsResp {
         import_patterns,
         sync_output_path,
         sync_commit_message,
+        sync_commit_messages,
         date_placeholders,
+        ingest_mapping,
     }))
 }
 
+/// 只修改请求中出现的字段
+#[utoipa::path(
+    put,
+    path = "/settings",
+    tag = "settings",
+    request_body = UpdateSettingsReq,
+    responses((status = 200, body = ApiResponse<AppSettingsResp>))
+)]
 pub async fn update_settings(
     State(state): State<AppState>,
     Json(req): Json<UpdateSettingsReq>,
 ) -> ApiResult<AppSettingsResp> {
-    if let Some(placeholders) = req.date_placeholders {
-        let normalized = normalize_date_placeholders(placeholders)
-            .map_err(|msg| ApiResponse::<AppSettingsResp>::err(ApiCode::BadRequest, &msg))?;
+    let new_placeholders = req
+        .date_placeholders
+        .map(normalize_date_placeholders)
+        .transpose()
+        .map_err(|msg| ApiResponse::<AppSettingsResp>::err(ApiCode::Validation, &msg))?;
+    let sync_output_path = req
+        .sync_output_path
+        .as_deref()
+        .map(str::trim)
+        .map(str::to_string);
+    if sync_output_path.as_deref() == Some("") {
+        return Err(ApiResponse::<AppSettingsResp>::err(
+            ApiCode::Validation,
+            "syncOutputPath cannot be empty",
+        ));
+    }
+    // 输出路径或占位符任一变化，都按生效后的组合校验，避免到同步时才报错
+    if new_placeholders.is_some() || sync_output_path.is_some() {
+        let placeholders = match new_placeholders.clone() {
+            Some(v) => v,
+            None => load_date_placeholders(&state)
+                .await
+                .unwrap_or_else(default_date_placeholders),
+        };
+        let output_path = match sync_output_path.clone() {
+            Some(v) => v,
+            None => load_sync_output_path(&state)
+                .await
+                .unwrap_or_else(|| state.config.sync.output_path.clone()),
+        };
+        repo_sync::validate_output_path(&output_path, &placeholders).map_err(|msg| {
+            ApiResponse::<AppSettingsResp>::err(
+                ApiCode::Validation,
+                &format!("invalid syncOutputPath '{}': {}", output_path, msg),
+            )
+        })?;
+    }
+
+    if let Some(normalized) = new_placeholders {
         let value = serde_json::to_string(&normalized).map_err(|_| {
-            ApiResponse::<AppSettingsResp>::err(ApiCode::BadRequest, "invalid datePlaceholders")
+            ApiResponse::<AppSettingsResp>::err(ApiCode::Validation, "invalid datePlaceholders")
         })?;
         save_setting(&state, KEY_DATE_PLACEHOLDERS, &value)
             .await
@@ -97,12 +203,12 @@ pub async fn update_settings(
         cleaned.dedup();
         if cleaned.is_empty() {
             return Err(ApiResponse::<AppSettingsResp>::err(
-                ApiCode::BadRequest,
+                ApiCode::Validation,
                 "importPatterns cannot be empty",
             ));
         }
         let value = serde_json::to_string(&cleaned).map_err(|_| {
-            ApiResponse::<AppSettingsResp>::err(ApiCode::BadRequest, "invalid importPatterns")
+            ApiResponse::<AppSettingsResp>::err(ApiCode::Validation, "invalid importPatterns")
         })?;
         save_setting(&state, KEY_IMPORT_PATTERNS, &value)
             .await
@@ -111,14 +217,7 @@ pub async fn update_settings(
             })?;
     }
 
-    if let Some(path) = req.sync_output_path {
-        let value = path.trim().to_string();
-        if value.is_empty() {
-            return Err(ApiResponse::<AppSettingsResp>::err(
-                ApiCode::BadRequest,
-                "syncOutputPath cannot be empty",
-            ));
-        }
+    if let Some(value) = sync_output_path {
         save_setting(&state, KEY_SYNC_OUTPUT_PATH, &value)
             .await
             .map_err(|_| {
@@ -129,7 +228,7 @@ pub async fn update_settings(
         let value = msg.trim().to_string();
         if value.is_empty() {
             return Err(ApiResponse::<AppSettingsResp>::err(
-                ApiCode::BadRequest,
+                ApiCode::Validation,
                 "syncCommitMessage cannot be empty",
             ));
         }
@@ -140,6 +239,41 @@ pub async fn update_settings(
             })?;
     }
 
+    if let Some(messages) = req.sync_commit_messages {
+        for (trigger, msg) in messages {
+            if !SyncTrigger::ALL.iter().any(|v| v.as_str() == trigger) {
+                let names = SyncTrigger::ALL.map(|v| v.as_str()).join("/");
+                return Err(ApiResponse::<AppSettingsResp>::err(
+                    ApiCode::Validation,
+                    &format!("syncCommitMessages key must be one of {}", names),
+                ));
+            }
+            let key = format!("{}{}", KEY_SYNC_COMMIT_MESSAGE_PREFIX, trigger);
+            let value = msg.trim();
+            let result = if value.is_empty() {
+                delete_setting(&state, &key).await
+            } else {
+                save_setting(&state, &key, value).await
+            };
+            result.map_err(|_| {
+                ApiResponse::<AppSettingsResp>::err(ApiCode::DbUpdateFailed, "save settings failed")
+            })?;
+        }
+    }
+
+    if let Some(mapping) = req.ingest_mapping {
+        let normalized = normalize_ingest_mapping(mapping)
+            .map_err(|msg| ApiResponse::<AppSettingsResp>::err(ApiCode::Validation, &msg))?;
+        let value = serde_json::to_string(&normalized).map_err(|_| {
+            ApiResponse::<AppSettingsResp>::err(ApiCode::Validation, "invalid ingestMapping")
+        })?;
+        save_setting(&state, KEY_INGEST_MAPPING, &value)
+            .await
+            .map_err(|_| {
+                ApiResponse::<AppSettingsResp>::err(ApiCode::DbUpdateFailed, "save settings failed")
+            })?;
+    }
+
     get_settings(State(state)).await
 }
 
@@ -149,12 +283,34 @@ pub async fn load_sync_output_path(state: &AppState) -> Option<String> {
 pub async fn load_sync_commit_message(state: &AppState) -> Option<String> {
     load_setting(state, KEY_SYNC_COMMIT_MESSAGE).await
 }
+pub async fn load_trigger_commit_message(state: &AppState, trigger: &str) -> Option<String> {
+    load_setting(
+        state,
+        &format!("{}{}", KEY_SYNC_COMMIT_MESSAGE_PREFIX, trigger),
+    )
+    .await
+}
+pub async fn load_sync_last_two_way(state: &AppState) -> Option<i64> {
+    load_setting(state, KEY_SYNC_LAST_TWO_WAY)
+        .await?
+        .parse()
+        .ok()
+}
+pub async fn save_sync_last_two_way(state: &AppState, ts: i64) -> Result<(), sqlx::Error> {
+    save_setting(state, KEY_SYNC_LAST_TWO_WAY, &ts.to_string()).await
+}
 pub async fn load_date_placeholders(state: &AppState) -> Option<DatePlaceholders> {
     let value = load_setting(state, KEY_DATE_PLACEHOLDERS).await?;
     let parsed = serde_json::from_str::<DatePlaceholders>(&value).ok()?;
     normalize_date_placeholders(parsed).ok()
 }
 
+pub async fn load_ingest_mapping(state: &AppState) -> Option<IngestMapping> {
+    let value = load_setting(state, KEY_INGEST_MAPPING).await?;
+    let parsed = serde_json::from_str::<IngestMapping>(&value).ok()?;
+    normalize_ingest_mapping(parsed).ok()
+}
+
 pub async fn load_import_patterns(state: &AppState) -> Option<Vec<String>> {
     let value = load_setting(state, KEY_IMPORT_PATTERNS).await?;
     let arr = serde_json::from_str::<Vec<String>>(&value).ok()?;
@@ -201,9 +357,63 @@ pub fn default_date_placeholders() -> DatePlaceholders {
         date: "{date}".to_string(),
         timestamp: "{timestamp}".to_string(),
         count: "{count}".to_string(),
+        yy: default_yy_placeholder(),
+        ww: default_ww_placeholder(),
+        ddd: default_ddd_placeholder(),
+        dddd: default_dddd_placeholder(),
+        locale: default_date_locale(),
     }
 }
 
+fn default_yy_placeholder() -> String {
+    "{yy}".to_string()
+}
+
+fn default_ww_placeholder() -> String {
+    "{ww}".to_string()
+}
+
+fn default_ddd_placeholder() -> String {
+    "{ddd}".to_string()
+}
+
+fn default_dddd_placeholder() -> String {
+    "{dddd}".to_string()
+}
+
+fn default_date_locale() -> String {
+    "en".to_string()
+}
+
+pub fn default_ingest_mapping() -> IngestMapping {
+    IngestMapping {
+        text_field: "text".to_string(),
+        date_field: "date".to_string(),
+        title_field: "".to_string(),
+        mode: default_ingest_mode(),
+    }
+}
+
+fn default_ingest_mode() -> String {
+    "append".to_string()
+}
+
+fn normalize_ingest_mapping(input: IngestMapping) -> Result<IngestMapping, String> {
+    let normalized = IngestMapping {
+        text_field: input.text_field.trim().to_string(),
+        date_field: input.date_field.trim().to_string(),
+        title_field: input.title_field.trim().to_string(),
+        mode: input.mode.trim().to_ascii_lowercase(),
+    };
+    if normalized.text_field.is_empty() {
+        return Err("ingestMapping.textField cannot be empty".to_string());
+    }
+    if normalized.mode != "append" && normalized.mode != "create" {
+        return Err("ingestMapping.mode must be append or create".to_string());
+    }
+    Ok(normalized)
+}
+
 fn normalize_date_placeholders(input: DatePlaceholders) -> Result<DatePlaceholders, String> {
     let normalized = DatePlaceholders {
         yyyy: input.yyyy.trim().to_string(),
@@ -214,7 +424,18 @@ fn normalize_date_placeholders(input: DatePlaceholders) -> Result<DatePlaceholde
         date: input.date.trim().to_string(),
         timestamp: input.timestamp.trim().to_string(),
         count: input.count.trim().to_string(),
+        yy: input.yy.trim().to_string(),
+        ww: input.ww.trim().to_string(),
+        ddd: input.ddd.trim().to_string(),
+        dddd: input.dddd.trim().to_string(),
+        locale: input.locale.trim().to_ascii_lowercase(),
     };
+    if !date_util::WEEKDAY_LOCALES.contains(&normalized.locale.as_str()) {
+        return Err(format!(
+            "datePlaceholders.locale must be one of {}",
+            date_util::WEEKDAY_LOCALES.join("/")
+        ));
+    }
 
     let fields = [
         ("yyyy", normalized.yyyy.as_str()),
@@ -225,6 +446,10 @@ fn normalize_date_placeholders(input: DatePlaceholders) -> Result<DatePlaceholde
         ("date", normalized.date.as_str()),
         ("timestamp", normalized.timestamp.as_str()),
         ("count", normalized.count.as_str()),
+        ("yy", normalized.yy.as_str()),
+        ("ww", normalized.ww.as_str()),
+        ("ddd", normalized.ddd.as_str()),
+        ("dddd", normalized.dddd.as_str()),
     ];
 
     for (name, token) in fields {
@@ -276,6 +501,14 @@ async fn save_setting(state: &AppState, key: &str, value: &str) -> Result<(), sq
     Ok(())
 }
 
+async fn delete_setting(state: &AppState, key: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("delete from app_setting where key = ?")
+        .bind(key)
+        .execute(&state.db)
+        .await?;
+    Ok(())
+}
+
 fn now_ts() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
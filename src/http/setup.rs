@@ -0,0 +1,207 @@
+use crate::app_state::AppState;
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::util::date_util;
+use axum::Json;
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tracing::{info, warn};
+
+/// 完成初始化后写入 app_setting，之后不再接受 `POST /setup`
+const KEY_SETUP_COMPLETED: &str = "setup_completed";
+/// 启动时自动写入的记录（全文索引的分词方式、加密的盐和校验值）和初始化标记不算用户保存过的设置
+const INTERNAL_KEY_FILTER: &str =
+    "key not like 'search.%' and key not like 'encryption.%' and key <> 'setup_completed'";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetupStatusResp {
+    /// 没有日记也没有保存过设置，前端据此展示初始化向导
+    pub fresh: bool,
+    pub journal_count: i64,
+    pub config_path: String,
+    pub config_exists: bool,
+    pub utc_offset_minutes: i32,
+    pub auth_configured: bool,
+    pub sync_enabled: bool,
+    pub sync_repo_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetupReq {
+    pub utc_offset_minutes: Option<i32>,
+    pub auth_token: Option<String>,
+    pub sync: Option<SetupSyncReq>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetupSyncReq {
+    pub repo_url: String,
+    pub branch: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetupResp {
+    pub config_path: String,
+    /// 配置在启动时读取，写入后需要重启服务生效
+    pub restart_required: bool,
+}
+
+pub async fn setup_status(State(state): State<AppState>) -> ApiResult<SetupStatusResp> {
+    let rows = count_rows(&state).await.map_err(|_| {
+        ApiResponse::<SetupStatusResp>::err(ApiCode::DbQueryFailed, "db query failed")
+    })?;
+    let cfg = &state.config;
+    Ok(ApiResponse::ok(SetupStatusResp {
+        fresh: rows.fresh(),
+        journal_count: rows.journals,
+        config_path: cfg.config_path.clone(),
+        config_exists: fs::metadata(&cfg.config_path).is_ok(),
+        utc_offset_minutes: cfg.utc_offset_minutes,
+        auth_configured: cfg.auth.enabled(),
+        sync_enabled: cfg.sync.enabled,
+        sync_repo_url: cfg.sync.repo_url.clone(),
+    }))
+}
+
+/// 一次写入首次启动需要的配置，只在全新实例上可用，已经配置了 `auth.token` 时需要 `admin` 权限；
+/// 写回 config.toml 时不保留原有注释
+pub async fn apply_setup(
+    State(state): State<AppState>,
+    Json(req): Json<SetupReq>,
+) -> ApiResult<SetupResp> {
+    let rows = count_rows(&state)
+        .await
+        .map_err(|_| ApiResponse::<SetupResp>::err(ApiCode::DbQueryFailed, "db query failed"))?;
+    if !rows.fresh() {
+        return Err(ApiResponse::<SetupResp>::err(
+            ApiCode::Conflict,
+            "instance already initialized, edit config.toml instead",
+        ));
+    }
+    if let Some(v) = req.utc_offset_minutes
+        && !(-720..=840).contains(&v)
+    {
+        return Err(ApiResponse::<SetupResp>::err(
+            ApiCode::Validation,
+            "utcOffsetMinutes must be between -720 and 840",
+        ));
+    }
+    if let Some(sync) = &req.sync
+        && sync.repo_url.trim().is_empty()
+    {
+        return Err(ApiResponse::<SetupResp>::err(
+            ApiCode::BadRequest,
+            "sync.repoUrl is required",
+        ));
+    }
+
+    let path = state.config.config_path.clone();
+    let mut doc = match fs::read_to_string(&path) {
+        Ok(v) => v.parse::<toml::Table>().map_err(|e| {
+            warn!("解析配置文件失败: {}", e);
+            ApiResponse::<SetupResp>::err(ApiCode::BadRequest, "config file is not valid toml")
+        })?,
+        Err(_) => toml::Table::new(),
+    };
+    if let Some(v) = req.utc_offset_minutes {
+        doc.insert("utc_offset_minutes".into(), toml::Value::Integer(v as i64));
+    }
+    if let Some(token) = req.auth_token.as_deref().map(str::trim) {
+        section(&mut doc, "auth").insert("token".into(), toml::Value::String(token.into()));
+    }
+    if let Some(sync) = &req.sync {
+        let table = section(&mut doc, "sync");
+        table.insert("enabled".into(), toml::Value::Boolean(true));
+        table.insert(
+            "repo_url".into(),
+            toml::Value::String(sync.repo_url.trim().into()),
+        );
+        for (key, value) in [
+            ("branch", &sync.branch),
+            ("username", &sync.username),
+            ("password", &sync.password),
+        ] {
+            if let Some(v) = value {
+                table.insert(key.into(), toml::Value::String(v.trim().into()));
+            }
+        }
+    }
+
+    let contents = toml::to_string(&doc).map_err(|_| {
+        ApiResponse::<SetupResp>::err(ApiCode::FileWriteFailed, "serialize config failed")
+    })?;
+    fs::write(&path, contents).map_err(|e| {
+        warn!("写入配置文件失败: {}", e);
+        ApiResponse::<SetupResp>::err(ApiCode::FileWriteFailed, "write config failed")
+    })?;
+    sqlx::query("insert or replace into app_setting (key, value, update_time) values (?, ?, ?)")
+        .bind(KEY_SETUP_COMPLETED)
+        .bind("true")
+        .bind(date_util::now_secs())
+        .execute(&state.db)
+        .await
+        .map_err(|_| ApiResponse::<SetupResp>::err(ApiCode::DbInsertFailed, "db insert failed"))?;
+    info!(
+        "首次启动配置已写入 {}, utc_offset={:?}, auth={}, sync={}",
+        path,
+        req.utc_offset_minutes,
+        req.auth_token.is_some(),
+        req.sync.is_some()
+    );
+
+    Ok(ApiResponse::ok(SetupResp {
+        config_path: path,
+        restart_required: true,
+    }))
+}
+
+struct RowCounts {
+    journals: i64,
+    settings: i64,
+    completed: bool,
+}
+
+impl RowCounts {
+    /// 没有日记、没有保存过设置，也没有执行过初始化
+    fn fresh(&self) -> bool {
+        self.journals == 0 && self.settings == 0 && !self.completed
+    }
+}
+
+async fn count_rows(state: &AppState) -> Result<RowCounts, sqlx::Error> {
+    let journals = sqlx::query_scalar::<_, i64>("select count(*) from journal")
+        .fetch_one(&state.db)
+        .await?;
+    let settings = sqlx::query_scalar::<_, i64>(&format!(
+        "select count(*) from app_setting where {}",
+        INTERNAL_KEY_FILTER
+    ))
+    .fetch_one(&state.db)
+    .await?;
+    let completed =
+        sqlx::query_scalar::<_, bool>("select exists(select 1 from app_setting where key = ?)")
+            .bind(KEY_SETUP_COMPLETED)
+            .fetch_one(&state.db)
+            .await?;
+    Ok(RowCounts {
+        journals,
+        settings,
+        completed,
+    })
+}
+
+fn section<'a>(doc: &'a mut toml::Table, name: &str) -> &'a mut toml::Table {
+    let value = doc
+        .entry(name)
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    if !value.is_table() {
+        *value = toml::Value::Table(toml::Table::new());
+    }
+    value.as_table_mut().expect("section is a table")
+}
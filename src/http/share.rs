@@ -0,0 +1,246 @@
+use crate::app_state::AppState;
+use crate::http::quick;
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::util::{date_util, markdown, token};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+use axum::{Form, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use tracing::{info, warn};
+
+#[derive(Debug, Deserialize)]
+pub struct ShareMonthReq {
+    /// yyyy-MM
+    pub month: String,
+    /// 为空时不需要密码
+    pub password: Option<String>,
+    /// 有效天数，不传为永久
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareResp {
+    pub token: String,
+    pub url: String,
+    pub month: String,
+    pub expire_time: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SharePasswordForm {
+    pub password: Option<String>,
+}
+
+#[derive(Debug, FromRow)]
+struct ShareRow {
+    scope: String,
+    period: String,
+    password_hash: Option<String>,
+    expire_time: Option<i64>,
+}
+
+#[derive(Debug, FromRow)]
+struct ShareJournalRow {
+    id: i64,
+    date: String,
+    content: String,
+    update_time: i64,
+}
+
+/// 生成整月只读分享链接
+pub async fn share_month(
+    State(state): State<AppState>,
+    Json(req): Json<ShareMonthReq>,
+) -> ApiResult<ShareResp> {
+    let month = req.month.trim().to_string();
+    if date_util::parse_date(&format!("{}-01", month)).is_none() || month.len() != 7 {
+        return Err(ApiResponse::<ShareResp>::err(
+            ApiCode::Validation,
+            "month must be yyyy-MM",
+        ));
+    }
+    let expire_time = match req.expires_in_days {
+        Some(days) if days <= 0 => {
+            return Err(ApiResponse::<ShareResp>::err(
+                ApiCode::Validation,
+                "expires_in_days must be positive",
+            ));
+        }
+        Some(days) => Some(date_util::now_secs() + days * 86_400),
+        None => None,
+    };
+
+    let share_token = token::random_token(24);
+    let password_hash = req
+        .password
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(|v| token::hash_secret(&share_token, v));
+    info!(
+        "创建月份分享 month={}, password={}, expire_time={:?}",
+        month,
+        password_hash.is_some(),
+        expire_time
+    );
+    sqlx::query(
+        "insert into share_link (token, scope, period, password_hash, expire_time, create_time) values (?, 'month', ?, ?, ?, ?)",
+    )
+    .bind(&share_token)
+    .bind(&month)
+    .bind(password_hash)
+    .bind(expire_time)
+    .bind(date_util::now_secs())
+    .execute(&state.db)
+    .await
+    .map_err(|_| ApiResponse::<ShareResp>::err(ApiCode::DbInsertFailed, "db insert failed"))?;
+
+    Ok(ApiResponse::ok(ShareResp {
+        url: format!("/share/{}", share_token),
+        token: share_token,
+        month,
+        expire_time,
+    }))
+}
+
+/// 撤销分享链接
+pub async fn revoke_share(
+    State(state): State<AppState>,
+    Path(share_token): Path<String>,
+) -> ApiResult<()> {
+    let result = sqlx::query("delete from share_link where token = ?")
+        .bind(&share_token)
+        .execute(&state.db)
+        .await
+        .map_err(|_| ApiResponse::<()>::err(ApiCode::DbDeleteFailed, "db delete failed"))?;
+    if result.rows_affected() == 0 {
+        return Err(ApiResponse::<()>::err(ApiCode::NotFound, "not found"));
+    }
+    Ok(ApiResponse::ok(()))
+}
+
+/// 打开分享页面，没有密码的分享直接展示
+pub async fn view_share(
+    State(state): State<AppState>,
+    Path(share_token): Path<String>,
+) -> Response {
+    render_share(&state, &share_token, None).await
+}
+
+/// 提交分享密码
+pub async fn unlock_share(
+    State(state): State<AppState>,
+    Path(share_token): Path<String>,
+    Form(form): Form<SharePasswordForm>,
+) -> Response {
+    render_share(&state, &share_token, form.password.as_deref()).await
+}
+
+async fn render_share(state: &AppState, share_token: &str, password: Option<&str>) -> Response {
+    let share = match sqlx::query_as::<_, ShareRow>(
+        "select scope, period, password_hash, expire_time from share_link where token = ?",
+    )
+    .bind(share_token)
+    .fetch_optional(&state.db)
+    .await
+    {
+        Ok(Some(v)) => v,
+        Ok(None) => return page(StatusCode::NOT_FOUND, "DayLog", "<p>分享不存在或已撤销</p>"),
+        Err(e) => {
+            warn!("读取分享失败: {}", e);
+            return page(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "DayLog",
+                "<p>读取失败</p>",
+            );
+        }
+    };
+    if share.expire_time.is_some_and(|v| v < date_util::now_secs()) {
+        return page(StatusCode::GONE, "DayLog", "<p>分享已过期</p>");
+    }
+    if let Some(hash) = share.password_hash.as_deref() {
+        let ok = password
+            .map(|v| token::hash_secret(share_token, v.trim()))
+            .is_some_and(|v| quick::token_eq(&v, hash));
+        if !ok {
+            let hint = if password.is_some() {
+                "<p class=\"error\">密码错误</p>"
+            } else {
+                ""
+            };
+            let body = format!(
+                "{}<form method=\"post\"><input type=\"password\" name=\"password\" placeholder=\"密码\" autofocus> <button type=\"submit\">查看</button></form>",
+                hint
+            );
+            return page(StatusCode::UNAUTHORIZED, "DayLog", &body);
+        }
+    }
+    if share.scope != "month" {
+        return page(StatusCode::NOT_FOUND, "DayLog", "<p>不支持的分享类型</p>");
+    }
+
+    let mut rows = match sqlx::query_as::<_, ShareJournalRow>(
+        "select id, date, content, update_time from journal where date like ? order by date asc, id asc",
+    )
+    .bind(format!("{}-%", share.period))
+    .fetch_all(&state.db)
+    .await
+    {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("读取分享日记失败: {}", e);
+            return page(StatusCode::INTERNAL_SERVER_ERROR, "DayLog", "<p>读取失败</p>");
+        }
+    };
+
+    for row in rows.iter_mut() {
+        row.content = state.cipher.open(std::mem::take(&mut row.content));
+    }
+
+    let title = format!("DayLog {}", share.period);
+    let mut body = format!("<h1>{}</h1>\n", markdown::escape_html(&title));
+    if rows.is_empty() {
+        body.push_str("<p>这个月还没有日记</p>\n");
+    }
+    for row in rows {
+        let html = state
+            .render_cache
+            .get_or_render(row.id, row.update_time, &row.content);
+        body.push_str(&format!(
+            "<article><h2>{}</h2>\n{}</article>\n",
+            markdown::escape_html(&row.date),
+            html
+        ));
+    }
+    page(StatusCode::OK, &title, &body)
+}
+
+fn page(status: StatusCode, title: &str, body: &str) -> Response {
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="zh">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="robots" content="noindex">
+<title>{}</title>
+<style>
+body {{ max-width: 760px; margin: 2em auto; padding: 0 1em; font-family: sans-serif; line-height: 1.7; color: #222; }}
+article {{ border-top: 1px solid #ddd; padding-top: 1em; margin-top: 1.5em; }}
+img {{ max-width: 100%; }}
+.error {{ color: #c00; }}
+</style>
+</head>
+<body>
+{}
+</body>
+</html>
+"#,
+        markdown::escape_html(title),
+        body
+    );
+    (status, Html(html)).into_response()
+}
@@ -0,0 +1,267 @@
+use crate::app_state::AppState;
+use crate::http::journal;
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::util::{date_util, markdown, words};
+use axum::extract::{Query, State};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use tracing::info;
+
+#[derive(Debug, Deserialize)]
+pub struct WordsQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WordsResp {
+    pub from: String,
+    pub to: String,
+    /// 参与统计的日记篇数
+    pub entries: usize,
+    pub tokens: Vec<TokenCount>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenCount {
+    pub token: String,
+    pub count: u64,
+}
+
+/// 日期范围内的高频词，`from`/`to` 为空时不限制
+pub async fn word_stats(
+    State(state): State<AppState>,
+    Query(query): Query<WordsQuery>,
+) -> ApiResult<WordsResp> {
+    let from = query.from.unwrap_or_default().trim().to_string();
+    let to = query.to.unwrap_or_default().trim().to_string();
+    if [&from, &to]
+        .iter()
+        .any(|v| !v.is_empty() && date_util::parse_date(v).is_none())
+    {
+        return Err(ApiResponse::<WordsResp>::err(
+            ApiCode::Validation,
+            "from/to must be yyyy-MM-dd",
+        ));
+    }
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    info!("统计词频 from={}, to={}, limit={}", from, to, limit);
+
+    let contents = sqlx::query_scalar::<_, String>(
+        "select content from journal where (? = '' or date >= ?) and (? = '' or date <= ?)",
+    )
+    .bind(&from)
+    .bind(&from)
+    .bind(&to)
+    .bind(&to)
+    .fetch_all(&state.read_db)
+    .await
+    .map_err(|_| ApiResponse::<WordsResp>::err(ApiCode::DbListFailed, "db query failed"))?
+    .into_iter()
+    .map(|v| state.cipher.open(v))
+    .collect::<Vec<_>>();
+
+    let entries = contents.len();
+    let top = state
+        .blocking
+        .run("word stats", move || {
+            let texts = contents
+                .iter()
+                .map(|v| markdown::plain_text(v))
+                .collect::<Vec<_>>();
+            words::top_tokens(texts.iter().map(String::as_str), limit)
+        })
+        .await
+        .map_err(|_| {
+            ApiResponse::<WordsResp>::err(ApiCode::BadRequest, "word stats task failed")
+        })?;
+
+    Ok(ApiResponse::ok(WordsResp {
+        from,
+        to,
+        entries,
+        tokens: top
+            .into_iter()
+            .map(|(token, count)| TokenCount { token, count })
+            .collect(),
+    }))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RhythmResp {
+    /// 没有记录写入偏移的旧数据按这个偏移计算
+    pub utc_offset_minutes: i32,
+    pub created: Rhythm,
+    /// 每篇日记只保留最后一次修改时间
+    pub updated: Rhythm,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rhythm {
+    /// 下标 0 = 周一 ... 6 = 周日
+    pub by_weekday: [i64; 7],
+    /// 本地时间 0..23 点
+    pub by_hour: [i64; 24],
+    /// `grid[weekday][hour]`
+    pub grid: [[i64; 24]; 7],
+}
+
+/// 按写入时的本地时间统计创建/修改发生在周几、几点
+pub async fn rhythm_stats(State(state): State<AppState>) -> ApiResult<RhythmResp> {
+    let offset = state.config.utc_offset_minutes;
+    info!("统计写日记时间分布");
+    let created = load_rhythm(&state, "create_time", "create_utc_offset", offset)
+        .await
+        .map_err(|_| ApiResponse::<RhythmResp>::err(ApiCode::DbQueryFailed, "db query failed"))?;
+    let updated = load_rhythm(&state, "update_time", "update_utc_offset", offset)
+        .await
+        .map_err(|_| ApiResponse::<RhythmResp>::err(ApiCode::DbQueryFailed, "db query failed"))?;
+    Ok(ApiResponse::ok(RhythmResp {
+        utc_offset_minutes: offset,
+        created,
+        updated,
+    }))
+}
+
+async fn load_rhythm(
+    state: &AppState,
+    time_column: &str,
+    offset_column: &str,
+    default_offset: i32,
+) -> Result<Rhythm, sqlx::Error> {
+    let local = format!(
+        "{} + coalesce({}, ?) * 60, 'unixepoch'",
+        time_column, offset_column
+    );
+    let rows = sqlx::query_as::<_, (i64, i64, i64)>(&format!(
+        "select cast(strftime('%w', {local}) as integer) as weekday, cast(strftime('%H', {local}) as integer) as hour, count(*) from journal group by weekday, hour",
+        local = local
+    ))
+    .bind(default_offset)
+    .bind(default_offset)
+    .fetch_all(&state.read_db)
+    .await?;
+
+    let mut rhythm = Rhythm::default();
+    for (weekday, hour, count) in rows {
+        // strftime 的 %w 以周日为 0
+        let weekday = ((weekday + 6) % 7) as usize;
+        let hour = hour.clamp(0, 23) as usize;
+        rhythm.by_weekday[weekday] += count;
+        rhythm.by_hour[hour] += count;
+        rhythm.grid[weekday][hour] += count;
+    }
+    Ok(rhythm)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OverviewQuery {
+    /// 热力图的年份，默认今年
+    pub year: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverviewResp {
+    pub total_entries: i64,
+    /// 去掉空白后的字数
+    pub total_words: i64,
+    pub by_month: Vec<MonthCount>,
+    /// 截止到今天或昨天的连续天数，今天还没写不算中断
+    pub current_streak: i64,
+    pub longest_streak: i64,
+    /// 最长连续记录的起止日期，有多段一样长时取最近的
+    pub longest_streak_from: Option<String>,
+    pub longest_streak_to: Option<String>,
+    pub year: i64,
+    /// `year` 每一天的字数，下标 0 为 1 月 1 日，没写为 0
+    pub heatmap: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct MonthCount {
+    /// yyyy-MM
+    pub month: String,
+    pub entries: i64,
+    pub words: i64,
+}
+
+/// 按月篇数、连续天数、总字数和某一年的每日热力图，全部在数据库中聚合
+pub async fn journal_overview(
+    State(state): State<AppState>,
+    Query(query): Query<OverviewQuery>,
+) -> ApiResult<OverviewResp> {
+    let today = state.config.today();
+    let year = query
+        .year
+        .unwrap_or_else(|| today[..4].parse().unwrap_or(1970));
+    if !(1..=9999).contains(&year) {
+        return Err(ApiResponse::<OverviewResp>::err(
+            ApiCode::Validation,
+            "year must be between 1 and 9999",
+        ));
+    }
+    info!("统计日记概览 year={}", year);
+    let db_err = |_| ApiResponse::<OverviewResp>::err(ApiCode::DbQueryFailed, "db query failed");
+
+    let by_month = sqlx::query_as::<_, MonthCount>(
+        "select substr(date, 1, 7) as month, count(*) as entries, coalesce(sum(word_count), 0) as words from journal group by month order by month",
+    )
+    .fetch_all(&state.read_db)
+    .await
+    .map_err(db_err)?;
+    let total_entries = by_month.iter().map(|v| v.entries).sum();
+    let total_words = by_month.iter().map(|v| v.words).sum();
+
+    // 日期减去序号相同的是同一段连续记录
+    let streaks = sqlx::query_as::<_, (String, String, i64)>(
+        "select min(date), max(date), count(*) from (select date, julianday(date) - row_number() over (order by date) as grp from journal) group by grp order by max(date)",
+    )
+    .fetch_all(&state.read_db)
+    .await
+    .map_err(db_err)?;
+    let today_days = date_util::parse_date(&today).unwrap_or_default();
+    let current_streak = streaks
+        .last()
+        .filter(|(_, to, _)| {
+            date_util::parse_date(to).is_some_and(|v| v == today_days || v == today_days - 1)
+        })
+        .map(|v| v.2)
+        .unwrap_or(0);
+    let longest = streaks.iter().max_by_key(|v| v.2);
+
+    let start = date_util::days_from_civil(year, 1, 1);
+    let len = date_util::days_from_civil(year + 1, 1, 1) - start;
+    let mut heatmap = vec![0i64; len as usize];
+    let days = journal::date_word_counts(
+        &state,
+        &format!("{:04}-01-01", year),
+        &format!("{:04}-01-01", year + 1),
+    )
+    .await
+    .map_err(db_err)?;
+    for (date, words) in days {
+        if let Some(idx) = date_util::parse_date(&date).map(|v| v - start)
+            && (0..len).contains(&idx)
+        {
+            heatmap[idx as usize] += words;
+        }
+    }
+
+    Ok(ApiResponse::ok(OverviewResp {
+        total_entries,
+        total_words,
+        by_month,
+        current_streak,
+        longest_streak: longest.map(|v| v.2).unwrap_or(0),
+        longest_streak_from: longest.map(|v| v.0.clone()),
+        longest_streak_to: longest.map(|v| v.1.clone()),
+        year,
+        heatmap,
+    }))
+}
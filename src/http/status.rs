@@ -0,0 +1,148 @@
+use crate::app_state::AppState;
+use crate::db::migrate;
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::util::blocking::BlockingStats;
+use crate::util::date_util;
+use axum::extract::State;
+use git2::{Repository, Sort};
+use serde::Serialize;
+use sqlx::FromRow;
+use std::path::{Path, PathBuf};
+
+/// 重任务额度的使用情况，`queued` 持续大于 0 时说明 `blocking_workers` 不够用
+pub async fn blocking_stats(State(state): State<AppState>) -> ApiResult<BlockingStats> {
+    Ok(ApiResponse::ok(state.blocking.stats()))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminStatsResp {
+    /// 数据库文件加上 wal 的字节数
+    pub db_size: u64,
+    /// 已执行到的数据库迁移版本
+    pub schema_version: i64,
+    pub tables: Vec<TableCount>,
+    pub uploads_by_month: Vec<UploadMonth>,
+    pub sync: SyncCadence,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableCount {
+    pub name: String,
+    pub rows: i64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadMonth {
+    /// yyyy-MM，按 utc
+    pub month: String,
+    pub files: i64,
+    pub bytes: i64,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncCadence {
+    pub enabled: bool,
+    /// 本地同步仓库当前分支最新提交的时间
+    pub last_commit_time: Option<i64>,
+    pub commits_last_30_days: usize,
+    /// 最近 30 天相邻两次提交的平均间隔
+    pub avg_interval_secs: Option<i64>,
+}
+
+/// 数据库大小、各表行数、每月上传量和同步频率，小实例不需要另外部署监控
+pub async fn admin_stats(State(state): State<AppState>) -> ApiResult<AdminStatsResp> {
+    let db_err = |_| ApiResponse::<AdminStatsResp>::err(ApiCode::DbQueryFailed, "db query failed");
+    let names = sqlx::query_scalar::<_, String>(
+        "select name from sqlite_master where type = 'table' and name not like 'sqlite_%' order by name",
+    )
+    .fetch_all(&state.read_db)
+    .await
+    .map_err(db_err)?;
+    let mut tables = Vec::with_capacity(names.len());
+    for name in names {
+        // 表名来自 sqlite_master，不是用户输入
+        let rows = sqlx::query_scalar::<_, i64>(&format!(
+            "select count(*) from \"{}\"",
+            name.replace('"', "\"\"")
+        ))
+        .fetch_one(&state.read_db)
+        .await
+        .map_err(db_err)?;
+        tables.push(TableCount { name, rows });
+    }
+
+    let uploads_by_month = sqlx::query_as::<_, UploadMonth>(
+        "select strftime('%Y-%m', create_time, 'unixepoch') as month, count(*) as files, coalesce(sum(size), 0) as bytes from file_blob group by month order by month",
+    )
+    .fetch_all(&state.read_db)
+    .await
+    .map_err(db_err)?;
+
+    let db_path = state.config.db_path.clone();
+    let db_size = [String::new(), "-wal".to_string()]
+        .iter()
+        .filter_map(|suffix| std::fs::metadata(format!("{}{}", db_path, suffix)).ok())
+        .map(|v| v.len())
+        .sum();
+
+    let sync = if state.config.sync.enabled {
+        let repo_path = PathBuf::from(state.config.get_sync_repo_path());
+        state
+            .blocking
+            .run("sync cadence", move || sync_cadence(&repo_path))
+            .await
+            .unwrap_or_default()
+    } else {
+        SyncCadence::default()
+    };
+
+    let schema_version = migrate::current_version(&state.read_db)
+        .await
+        .map_err(db_err)?;
+    Ok(ApiResponse::ok(AdminStatsResp {
+        db_size,
+        schema_version,
+        tables,
+        uploads_by_month,
+        sync,
+    }))
+}
+
+fn sync_cadence(repo_path: &Path) -> SyncCadence {
+    let mut cadence = SyncCadence {
+        enabled: true,
+        ..SyncCadence::default()
+    };
+    let Ok(repo) = Repository::open(repo_path) else {
+        return cadence;
+    };
+    let Ok(mut walk) = repo.revwalk() else {
+        return cadence;
+    };
+    if walk.push_head().is_err() || walk.set_sorting(Sort::TIME).is_err() {
+        return cadence;
+    }
+    let since = date_util::now_secs() - 30 * 86_400;
+    let mut times = Vec::new();
+    for oid in walk.flatten() {
+        let Ok(commit) = repo.find_commit(oid) else {
+            continue;
+        };
+        let time = commit.time().seconds();
+        cadence.last_commit_time.get_or_insert(time);
+        if time < since {
+            break;
+        }
+        times.push(time);
+    }
+    cadence.commits_last_30_days = times.len();
+    if times.len() > 1 {
+        let span = times[0] - times[times.len() - 1];
+        cadence.avg_interval_secs = Some(span / (times.len() as i64 - 1));
+    }
+    cadence
+}
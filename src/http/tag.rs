@@ -0,0 +1,250 @@
+use crate::app_state::AppState;
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::util::date_util;
+use axum::Json;
+use axum::extract::{Path, State};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+const MAX_TAG_LEN: usize = 32;
+
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    pub color: Option<String>,
+    /// 带有该标签的日记篇数
+    pub journal_count: i64,
+    pub create_time: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTagReq {
+    pub name: String,
+    /// 前端展示用，例如 `#e67e22`
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttachTagsReq {
+    /// 标签名，不存在的标签会自动创建
+    pub names: Vec<String>,
+}
+
+const TAG_COLUMNS: &str = "t.id, t.name, t.color, t.create_time, (select count(1) from journal_tag jt where jt.tag_id = t.id) as journal_count";
+
+pub async fn list_tags(State(state): State<AppState>) -> ApiResult<Vec<Tag>> {
+    let tags = sqlx::query_as::<_, Tag>(&format!(
+        "select {} from tag t order by t.name collate nocase asc",
+        TAG_COLUMNS
+    ))
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| ApiResponse::<Vec<Tag>>::err(ApiCode::DbListFailed, "db query failed"))?;
+    Ok(ApiResponse::ok(tags))
+}
+
+pub async fn create_tag(
+    State(state): State<AppState>,
+    Json(req): Json<CreateTagReq>,
+) -> ApiResult<Tag> {
+    let name = normalize_name(&req.name)
+        .map_err(|msg| ApiResponse::<Tag>::err(ApiCode::Validation, msg))?;
+    let color = req
+        .color
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string);
+    let exists = sqlx::query_scalar::<_, i64>("select count(1) from tag where name = ?")
+        .bind(&name)
+        .fetch_one(&state.db)
+        .await
+        .map_err(|_| ApiResponse::<Tag>::err(ApiCode::DbQueryFailed, "db query failed"))?;
+    if exists > 0 {
+        return Err(ApiResponse::<Tag>::err(
+            ApiCode::Conflict,
+            "tag already exists",
+        ));
+    }
+    let id = sqlx::query("insert into tag (name, color, create_time) values (?, ?, ?)")
+        .bind(&name)
+        .bind(color)
+        .bind(date_util::now_secs())
+        .execute(&state.db)
+        .await
+        .map_err(|_| ApiResponse::<Tag>::err(ApiCode::DbInsertFailed, "db insert failed"))?
+        .last_insert_rowid();
+    info!("创建标签 id={}, name={}", id, name);
+    let tag = load_tag(&state, id)
+        .await
+        .map_err(|_| ApiResponse::<Tag>::err(ApiCode::DbGetFailed, "db query failed"))?;
+    Ok(ApiResponse::ok(tag))
+}
+
+/// 删除标签，同时从所有日记上移除
+pub async fn delete_tag(State(state): State<AppState>, Path(id): Path<i64>) -> ApiResult<()> {
+    let deleted = async {
+        let mut tx = state.db.begin().await?;
+        sqlx::query("delete from journal_tag where tag_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        let result = sqlx::query("delete from tag where id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok::<u64, sqlx::Error>(result.rows_affected())
+    }
+    .await
+    .map_err(|e| {
+        warn!("删除标签失败: {}", e);
+        ApiResponse::<()>::err(ApiCode::DbDeleteFailed, "db delete failed")
+    })?;
+    if deleted == 0 {
+        return Err(ApiResponse::<()>::err(ApiCode::NotFound, "not found"));
+    }
+    info!("删除标签 id={}", id);
+    Ok(ApiResponse::ok(()))
+}
+
+pub async fn list_journal_tags(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> ApiResult<Vec<Tag>> {
+    let tags = journal_tags(&state, id)
+        .await
+        .map_err(|_| ApiResponse::<Vec<Tag>>::err(ApiCode::DbListFailed, "db query failed"))?;
+    Ok(ApiResponse::ok(tags))
+}
+
+/// 给日记加上标签，已经有的标签忽略，返回日记当前的全部标签
+pub async fn attach_tags(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(req): Json<AttachTagsReq>,
+) -> ApiResult<Vec<Tag>> {
+    let mut names = Vec::new();
+    for raw in &req.names {
+        let name = normalize_name(raw)
+            .map_err(|msg| ApiResponse::<Vec<Tag>>::err(ApiCode::Validation, msg))?;
+        if !names.iter().any(|v: &String| v.eq_ignore_ascii_case(&name)) {
+            names.push(name);
+        }
+    }
+    if names.is_empty() {
+        return Err(ApiResponse::<Vec<Tag>>::err(
+            ApiCode::Validation,
+            "names must not be empty",
+        ));
+    }
+    let exists = sqlx::query_scalar::<_, i64>("select count(1) from journal where id = ?")
+        .bind(id)
+        .fetch_one(&state.db)
+        .await
+        .map_err(|_| ApiResponse::<Vec<Tag>>::err(ApiCode::DbQueryFailed, "db query failed"))?;
+    if exists == 0 {
+        return Err(ApiResponse::<Vec<Tag>>::err(ApiCode::NotFound, "not found"));
+    }
+
+    let ts = date_util::now_secs();
+    let attached = async {
+        let mut tx = state.db.begin().await?;
+        for name in &names {
+            sqlx::query("insert or ignore into tag (name, color, create_time) values (?, null, ?)")
+                .bind(name)
+                .bind(ts)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(
+                "insert or ignore into journal_tag (journal_id, tag_id, create_time) select ?, id, ? from tag where name = ?",
+            )
+            .bind(id)
+            .bind(ts)
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await
+    }
+    .await;
+    attached.map_err(|e| {
+        warn!("添加标签失败: {}", e);
+        ApiResponse::<Vec<Tag>>::err(ApiCode::DbInsertFailed, "db insert failed")
+    })?;
+    info!("日记 {} 添加标签 {:?}", id, names);
+
+    let tags = journal_tags(&state, id)
+        .await
+        .map_err(|_| ApiResponse::<Vec<Tag>>::err(ApiCode::DbListFailed, "db query failed"))?;
+    Ok(ApiResponse::ok(tags))
+}
+
+pub async fn detach_tag(
+    State(state): State<AppState>,
+    Path((id, tag_id)): Path<(i64, i64)>,
+) -> ApiResult<()> {
+    let result = sqlx::query("delete from journal_tag where journal_id = ? and tag_id = ?")
+        .bind(id)
+        .bind(tag_id)
+        .execute(&state.db)
+        .await
+        .map_err(|_| ApiResponse::<()>::err(ApiCode::DbDeleteFailed, "db delete failed"))?;
+    if result.rows_affected() == 0 {
+        return Err(ApiResponse::<()>::err(ApiCode::NotFound, "not found"));
+    }
+    Ok(ApiResponse::ok(()))
+}
+
+async fn load_tag(state: &AppState, id: i64) -> Result<Tag, sqlx::Error> {
+    sqlx::query_as::<_, Tag>(&format!("select {} from tag t where t.id = ?", TAG_COLUMNS))
+        .bind(id)
+        .fetch_one(&state.db)
+        .await
+}
+
+async fn journal_tags(state: &AppState, journal_id: i64) -> Result<Vec<Tag>, sqlx::Error> {
+    sqlx::query_as::<_, Tag>(&format!(
+        "select {} from tag t join journal_tag j on j.tag_id = t.id where j.journal_id = ? order by t.name collate nocase asc",
+        TAG_COLUMNS
+    ))
+    .bind(journal_id)
+    .fetch_all(&state.db)
+    .await
+}
+
+/// 标签名去掉首尾空白和开头的 `#`，不能包含逗号和换行
+/// 精选日记的 id：`metadata.pinned` 为 true，或带有 `highlight_tag` 标签
+pub async fn highlight_ids(state: &AppState) -> Result<HashSet<i64>, sqlx::Error> {
+    let ids = sqlx::query_scalar::<_, i64>(
+        r#"
+        select id from journal
+        where json_valid(metadata) and json_extract(metadata, '$.pinned') = 1
+        union
+        select jt.journal_id from journal_tag jt join tag t on t.id = jt.tag_id
+        where t.name = ?
+        "#,
+    )
+    .bind(state.config.highlight_tag.trim())
+    .fetch_all(&state.db)
+    .await?;
+    Ok(ids.into_iter().collect())
+}
+
+fn normalize_name(raw: &str) -> Result<String, &'static str> {
+    let name = raw.trim().trim_start_matches('#').trim();
+    if name.is_empty() {
+        return Err("tag name must not be empty");
+    }
+    if name.chars().count() > MAX_TAG_LEN {
+        return Err("tag name must be at most 32 characters");
+    }
+    if name.contains([',', '\n', '\r']) {
+        return Err("tag name must not contain commas or line breaks");
+    }
+    Ok(name.to_string())
+}
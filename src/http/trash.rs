@@ -0,0 +1,44 @@
+use crate::app_state::AppState;
+use crate::http::resp::{ApiCode, ApiResponse, ApiResult};
+use crate::trash::{self, PurgeReport, TrashEntry};
+use axum::extract::{Path, State};
+use serde::Serialize;
+use tracing::{info, warn};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashRestoreResp {
+    pub journal_id: i64,
+}
+
+pub async fn list_trash(State(state): State<AppState>) -> ApiResult<Vec<TrashEntry>> {
+    let items = trash::list(&state).await.map_err(|_| {
+        ApiResponse::<Vec<TrashEntry>>::err(ApiCode::DbListFailed, "db query failed")
+    })?;
+    Ok(ApiResponse::ok(items))
+}
+
+pub async fn restore_trash(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> ApiResult<TrashRestoreResp> {
+    info!("从回收站恢复 id={}", id);
+    let journal_id = trash::restore(&state, id).await.map_err(|msg| {
+        warn!("恢复回收站日记失败: {}", msg);
+        ApiResponse::<TrashRestoreResp>::err(ApiCode::BadRequest, &msg)
+    })?;
+    Ok(ApiResponse::ok(TrashRestoreResp { journal_id }))
+}
+
+/// 立即清理超过保留天数的回收站日记
+pub async fn purge_trash(State(state): State<AppState>) -> ApiResult<PurgeReport> {
+    info!(
+        "手动清理回收站 trash_days={}",
+        state.config.retention.trash_days
+    );
+    let report = trash::purge(&state).await.map_err(|msg| {
+        warn!("清理回收站失败: {}", msg);
+        ApiResponse::<PurgeReport>::err(ApiCode::DbDeleteFailed, &msg)
+    })?;
+    Ok(ApiResponse::ok(report))
+}
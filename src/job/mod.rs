@@ -0,0 +1,421 @@
+use crate::app_state::AppState;
+use crate::util::cron::Schedule;
+use crate::util::date_util;
+use serde::Serialize;
+use sqlx::FromRow;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{LazyLock, RwLock};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+/// 没有到期任务时最长睡眠时间，配置的时区偏移变化等情况也能及时重新计算
+const MAX_IDLE_SECS: i64 = 60;
+/// 执行完的一次性任务保留这么久
+const ONE_SHOT_KEEP_SECS: i64 = 7 * 86_400;
+/// 失败重试的间隔从 1 分钟起按次数翻倍，最长 1 小时
+const RETRY_BASE_SECS: i64 = 60;
+const RETRY_MAX_SECS: i64 = 3600;
+
+pub const STATUS_IDLE: &str = "idle";
+pub const STATUS_RUNNING: &str = "running";
+pub const STATUS_OK: &str = "ok";
+pub const STATUS_FAILED: &str = "failed";
+/// 配置中已经关闭的定时任务，保留历史记录
+pub const STATUS_DISABLED: &str = "disabled";
+
+pub type JobFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+/// 第二个参数是一次性任务入队时的 payload，定时任务为 None
+pub type JobHandler = fn(AppState, Option<String>) -> JobFuture;
+
+static HANDLERS: LazyLock<RwLock<HashMap<&'static str, JobHandler>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+static WAKE: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+tokio::task_local! {
+    static CURRENT_JOB: i64;
+}
+
+/// 一个定时任务，`name` 唯一，`schedule` 可以被 `jobs.schedules` 覆盖
+pub struct JobDef {
+    pub name: String,
+    pub kind: &'static str,
+    pub schedule: String,
+    pub handler: JobHandler,
+    pub max_retries: u32,
+    /// 停机期间错过的执行是否在启动后补一次，提醒这类过时就没意义的任务为 false
+    pub catch_up: bool,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct JobRecord {
+    pub id: i64,
+    pub name: String,
+    pub kind: String,
+    /// 一次性任务为 null
+    pub schedule: Option<String>,
+    pub status: String,
+    pub next_run_time: Option<i64>,
+    pub last_run_time: Option<i64>,
+    pub last_finish_time: Option<i64>,
+    pub last_error: Option<String>,
+    /// 当前连续失败次数
+    pub attempts: i64,
+    pub max_retries: i64,
+    pub run_count: i64,
+    pub create_time: i64,
+}
+
+#[derive(Debug, FromRow)]
+struct DueJob {
+    id: i64,
+    name: String,
+    kind: String,
+    schedule: Option<String>,
+    payload: Option<String>,
+    attempts: i64,
+    max_retries: i64,
+}
+
+/// 一次性任务的类型，需要在 `spawn` 之前注册
+pub fn register(kind: &'static str, handler: JobHandler) {
+    HANDLERS.write().unwrap().insert(kind, handler);
+}
+
+/// 正在执行的任务 id，只在任务 handler 内有值
+pub fn current_id() -> Option<i64> {
+    CURRENT_JOB.try_with(|v| *v).ok()
+}
+
+/// 同步定时任务定义到 `job` 表并启动调度
+pub async fn spawn(state: AppState, defs: Vec<JobDef>) {
+    if let Err(e) = prepare(&state, defs).await {
+        warn!("job scheduler init failed: {}", e);
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            let wait = match run_due(&state).await {
+                Ok(wait) => wait,
+                Err(e) => {
+                    warn!("job scheduler query failed: {}", e);
+                    MAX_IDLE_SECS
+                }
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(wait.max(1) as u64)) => {}
+                _ = WAKE.notified() => {}
+            }
+        }
+    });
+}
+
+async fn prepare(state: &AppState, defs: Vec<JobDef>) -> Result<(), String> {
+    let now = date_util::now_secs();
+    let offset = state.config.utc_offset_minutes;
+    // 上次没跑完就退出的任务
+    sqlx::query(
+        "update job set status = ?, last_error = 'interrupted by restart', next_run_time = case when schedule is null then null else next_run_time end, update_time = ? where status = ?",
+    )
+    .bind(STATUS_FAILED)
+    .bind(now)
+    .bind(STATUS_RUNNING)
+    .execute(&state.db)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut names = Vec::with_capacity(defs.len());
+    for mut def in defs {
+        if let Some(v) = state.config.jobs.schedules.get(&def.name) {
+            def.schedule = v.trim().to_string();
+        }
+        let schedule = match Schedule::parse(&def.schedule) {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("job {} skipped: {}", def.name, e);
+                continue;
+            }
+        };
+        register(def.kind, def.handler);
+        let existing = sqlx::query_as::<_, (i64, Option<String>, Option<i64>)>(
+            "select id, schedule, next_run_time from job where name = ? and schedule is not null",
+        )
+        .bind(&def.name)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|e| e.to_string())?;
+        let fresh = schedule.next_after(now, offset);
+        let next = match &existing {
+            Some((_, Some(old), Some(next))) if *old == def.schedule => {
+                if *next < now && !def.catch_up {
+                    fresh
+                } else {
+                    Some(*next)
+                }
+            }
+            _ => fresh,
+        };
+        match existing {
+            Some((id, _, _)) => {
+                sqlx::query(
+                    "update job set kind = ?, schedule = ?, status = case when status = ? then ? else status end, next_run_time = ?, max_retries = ?, update_time = ? where id = ?",
+                )
+                .bind(def.kind)
+                .bind(&def.schedule)
+                .bind(STATUS_DISABLED)
+                .bind(STATUS_IDLE)
+                .bind(next)
+                .bind(def.max_retries as i64)
+                .bind(now)
+                .bind(id)
+                .execute(&state.db)
+                .await
+                .map_err(|e| e.to_string())?;
+            }
+            None => {
+                sqlx::query(
+                    "insert into job (name, kind, schedule, status, next_run_time, max_retries, create_time, update_time) values (?, ?, ?, ?, ?, ?, ?, ?)",
+                )
+                .bind(&def.name)
+                .bind(def.kind)
+                .bind(&def.schedule)
+                .bind(STATUS_IDLE)
+                .bind(next)
+                .bind(def.max_retries as i64)
+                .bind(now)
+                .bind(now)
+                .execute(&state.db)
+                .await
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        info!(
+            "job {} scheduled: {}, next={:?}",
+            def.name, def.schedule, next
+        );
+        names.push(def.name);
+    }
+
+    // 配置中关掉的定时任务
+    let rows =
+        sqlx::query_as::<_, (i64, String)>("select id, name from job where schedule is not null")
+            .fetch_all(&state.db)
+            .await
+            .map_err(|e| e.to_string())?;
+    for (id, name) in rows {
+        if names.contains(&name) {
+            continue;
+        }
+        sqlx::query(
+            "update job set status = ?, next_run_time = null, update_time = ? where id = ?",
+        )
+        .bind(STATUS_DISABLED)
+        .bind(now)
+        .bind(id)
+        .execute(&state.db)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// 启动到期的任务，返回距离下一个任务的秒数
+async fn run_due(state: &AppState) -> Result<i64, sqlx::Error> {
+    let now = date_util::now_secs();
+    sqlx::query(
+        "delete from job where schedule is null and next_run_time is null and status != ? and update_time < ?",
+    )
+    .bind(STATUS_RUNNING)
+    .bind(now - ONE_SHOT_KEEP_SECS)
+    .execute(&state.db)
+    .await?;
+    let due = sqlx::query_as::<_, DueJob>(
+        "select id, name, kind, schedule, payload, attempts, max_retries from job where next_run_time <= ? and status not in (?, ?) order by next_run_time asc",
+    )
+    .bind(now)
+    .bind(STATUS_RUNNING)
+    .bind(STATUS_DISABLED)
+    .fetch_all(&state.db)
+    .await?;
+    for job in due {
+        let claimed = sqlx::query(
+            "update job set status = ?, last_run_time = ?, run_count = run_count + 1, update_time = ? where id = ? and status != ?",
+        )
+        .bind(STATUS_RUNNING)
+        .bind(now)
+        .bind(now)
+        .bind(job.id)
+        .bind(STATUS_RUNNING)
+        .execute(&state.db)
+        .await?
+        .rows_affected();
+        if claimed == 1 {
+            tokio::spawn(execute(state.clone(), job));
+        }
+    }
+    let next = sqlx::query_scalar::<_, Option<i64>>(
+        "select min(next_run_time) from job where status not in (?, ?)",
+    )
+    .bind(STATUS_RUNNING)
+    .bind(STATUS_DISABLED)
+    .fetch_one(&state.db)
+    .await?;
+    Ok(next.map_or(MAX_IDLE_SECS, |v| (v - now).clamp(1, MAX_IDLE_SECS)))
+}
+
+async fn execute(state: AppState, job: DueJob) {
+    let handler = HANDLERS.read().unwrap().get(job.kind.as_str()).copied();
+    let result = match handler {
+        // 任务 panic 时也要记录结果
+        Some(handler) => {
+            tokio::spawn(CURRENT_JOB.scope(job.id, handler(state.clone(), job.payload.clone())))
+                .await
+                .unwrap_or_else(|e| Err(format!("job panicked: {}", e)))
+        }
+        None => Err(format!("unknown job kind: {}", job.kind)),
+    };
+    let now = date_util::now_secs();
+    let scheduled_next = job
+        .schedule
+        .as_deref()
+        .and_then(|v| Schedule::parse(v).ok())
+        .and_then(|v| v.next_after(now, state.config.utc_offset_minutes));
+    let (status, error, attempts, next) = match result {
+        Ok(()) => (STATUS_OK, None, 0, scheduled_next),
+        Err(e) => {
+            warn!("job {} failed: {}", job.name, e);
+            let attempts = job.attempts + 1;
+            if attempts <= job.max_retries {
+                let delay = (RETRY_BASE_SECS << (attempts - 1).min(10)).min(RETRY_MAX_SECS);
+                (STATUS_FAILED, Some(e), attempts, Some(now + delay))
+            } else {
+                (STATUS_FAILED, Some(e), 0, scheduled_next)
+            }
+        }
+    };
+    if let Err(e) = sqlx::query(
+        "update job set status = ?, last_error = ?, attempts = ?, next_run_time = ?, last_finish_time = ?, update_time = ? where id = ?",
+    )
+    .bind(status)
+    .bind(error)
+    .bind(attempts)
+    .bind(next)
+    .bind(now)
+    .bind(now)
+    .bind(job.id)
+    .execute(&state.db)
+    .await
+    {
+        warn!("job {} save result failed: {}", job.name, e);
+    }
+    WAKE.notify_one();
+}
+
+/// 一次性任务入队，立即执行
+pub async fn enqueue(
+    state: &AppState,
+    kind: &'static str,
+    payload: Option<String>,
+    max_retries: u32,
+) -> Result<i64, sqlx::Error> {
+    let now = date_util::now_secs();
+    let id = sqlx::query(
+        "insert into job (name, kind, payload, status, next_run_time, max_retries, create_time, update_time) values (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(kind)
+    .bind(kind)
+    .bind(payload)
+    .bind(STATUS_IDLE)
+    .bind(now)
+    .bind(max_retries as i64)
+    .bind(now)
+    .bind(now)
+    .execute(&state.db)
+    .await?
+    .last_insert_rowid();
+    WAKE.notify_one();
+    Ok(id)
+}
+
+/// 同类一次性任务还在排队时返回它的 id，不重复入队
+pub async fn enqueue_unless_pending(
+    state: &AppState,
+    kind: &'static str,
+    payload: Option<String>,
+    max_retries: u32,
+) -> Result<i64, sqlx::Error> {
+    let pending = sqlx::query_scalar::<_, i64>(
+        "select id from job where kind = ? and schedule is null and status = ? limit 1",
+    )
+    .bind(kind)
+    .bind(STATUS_IDLE)
+    .fetch_optional(&state.db)
+    .await?;
+    match pending {
+        Some(id) => Ok(id),
+        None => enqueue(state, kind, payload, max_retries).await,
+    }
+}
+
+/// 定时任务和最近的一次性任务
+pub async fn list(state: &AppState) -> Result<Vec<JobRecord>, sqlx::Error> {
+    sqlx::query_as::<_, JobRecord>(
+        r#"
+        select id, name, kind, schedule, status, next_run_time, last_run_time, last_finish_time,
+            last_error, attempts, max_retries, run_count, create_time
+        from job
+        where schedule is not null or id in (select id from job where schedule is null order by id desc limit 50)
+        order by schedule is null, name, id desc
+        "#,
+    )
+    .fetch_all(&state.db)
+    .await
+}
+
+pub async fn get(state: &AppState, id: i64) -> Result<Option<JobRecord>, sqlx::Error> {
+    sqlx::query_as::<_, JobRecord>(
+        r#"
+        select id, name, kind, schedule, status, next_run_time, last_run_time, last_finish_time,
+            last_error, attempts, max_retries, run_count, create_time
+        from job where id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await
+}
+
+/// 立即执行一次，之后定时任务按计划继续；一次性任务只能重跑失败的
+pub async fn trigger(state: &AppState, id: i64) -> Result<JobRecord, String> {
+    let job = get(state, id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "job not found".to_string())?;
+    if job.status == STATUS_RUNNING {
+        return Err(format!("job {} is already running", job.name));
+    }
+    if job.schedule.is_none() && job.status != STATUS_FAILED {
+        return Err(format!("job {} can only be rerun after failure", job.id));
+    }
+    if job.status == STATUS_DISABLED {
+        return Err(format!("job {} is disabled in config", job.name));
+    }
+    let now = date_util::now_secs();
+    sqlx::query("update job set next_run_time = ?, attempts = 0, update_time = ? where id = ?")
+        .bind(now)
+        .bind(now)
+        .bind(id)
+        .execute(&state.db)
+        .await
+        .map_err(|e| e.to_string())?;
+    WAKE.notify_one();
+    info!("job {} triggered", job.name);
+    Ok(JobRecord {
+        next_run_time: Some(now),
+        attempts: 0,
+        ..job
+    })
+}
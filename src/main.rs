@@ -1,7 +1,18 @@
 mod app_state;
+mod archive;
+mod backup;
+mod bot;
 mod config;
 mod db;
+mod digest;
+mod error;
 mod http;
+mod job;
+mod notify;
+mod reminder;
+mod search;
+mod sync;
+mod trash;
 mod util;
 
 use std::sync::Arc;
@@ -36,12 +47,76 @@ async fn main() {
         }
     };
 
+    let read_pool = match db::init_read(&app_config, &pool).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            error!("初始化只读连接池失败: {}", e);
+            return;
+        }
+    };
+
+    let cipher = match util::crypto::load(&app_config.encryption, &pool).await {
+        Ok(v) => v,
+        Err(e) => {
+            error!("初始化正文加密失败: {}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = search::prepare(&app_config.search, &pool, cipher.enabled()).await {
+        error!("初始化全文索引失败: {}", e);
+        return;
+    }
+
+    let http = match util::http_client::build(&app_config.http) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("初始化 http 客户端失败: {}", e);
+            return;
+        }
+    };
+    let outbound = match util::outbound::OutboundClient::new(&app_config.http, &app_config.outbound)
+    {
+        Ok(v) => v,
+        Err(e) => {
+            error!("初始化外部地址访问客户端（[outbound]）失败: {}", e);
+            return;
+        }
+    };
+
+    let blocking_workers = app_config.blocking_workers;
+    let render_cache = util::render_cache::RenderCache::new(
+        app_config.render_cache_size,
+        util::sanitize::Sanitizer::new(&app_config.render),
+    );
     let state = app_state::AppState {
         db: pool,
+        read_db: read_pool,
         config: Arc::new(app_config),
+        render_cache: Arc::new(render_cache),
+        blocking: Arc::new(util::blocking::BlockingPool::new(blocking_workers)),
+        http,
+        outbound,
+        cipher: Arc::new(cipher),
     };
 
-    if let Err(e) = http::server::run(state).await {
+    bot::telegram::spawn(state.clone());
+    bot::matrix::spawn(state.clone());
+
+    let mut jobs = reminder::jobs(&state);
+    jobs.extend(
+        [
+            notify::daily_check_job(&state),
+            digest::job(&state),
+            archive::job(&state),
+            backup::job(&state),
+            trash::job(&state),
+        ]
+        .into_iter()
+        .flatten(),
+    );
+
+    if let Err(e) = http::server::run(state, jobs).await {
         error!("服务启动失败: {}", e);
     }
 }
@@ -0,0 +1,184 @@
+use crate::app_state::AppState;
+use crate::job::{JobDef, JobFuture};
+use crate::util::date_util;
+use crate::util::outbound;
+use serde_json::json;
+use tracing::{info, warn};
+
+#[derive(Debug, Clone)]
+pub enum NotifyEvent {
+    SyncFailed { reason: String },
+    MissedJournal { date: String },
+    Reminder { date: String },
+    WeeklySummary { title: String, body: String },
+    TrashPurged { journals: usize, files: usize },
+}
+
+impl NotifyEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NotifyEvent::SyncFailed { .. } => "sync_failed",
+            NotifyEvent::MissedJournal { .. } => "missed_journal",
+            NotifyEvent::Reminder { .. } => "reminder",
+            NotifyEvent::WeeklySummary { .. } => "weekly_summary",
+            NotifyEvent::TrashPurged { .. } => "trash_purged",
+        }
+    }
+
+    fn title(&self) -> String {
+        match self {
+            NotifyEvent::SyncFailed { .. } => "DayLog sync failed".to_string(),
+            NotifyEvent::MissedJournal { date } => format!("DayLog: no journal for {}", date),
+            NotifyEvent::Reminder { .. } => "DayLog reminder".to_string(),
+            NotifyEvent::WeeklySummary { title, .. } => title.clone(),
+            NotifyEvent::TrashPurged { .. } => "DayLog trash purged".to_string(),
+        }
+    }
+
+    fn body(&self) -> String {
+        match self {
+            NotifyEvent::SyncFailed { reason } => reason.clone(),
+            NotifyEvent::MissedJournal { date } => {
+                format!("还没有写 {} 的日记", date)
+            }
+            NotifyEvent::Reminder { date } => format!("今天 ({}) 还没有写日记", date),
+            NotifyEvent::WeeklySummary { body, .. } => body.clone(),
+            NotifyEvent::TrashPurged { journals, files } => {
+                format!(
+                    "回收站中 {} 篇日记已彻底删除，清理附件 {} 个",
+                    journals, files
+                )
+            }
+        }
+    }
+}
+
+/// 后台发送通知，失败只记录日志，不影响调用方
+pub fn spawn_send(state: &AppState, event: NotifyEvent) {
+    let cfg = &state.config.notify;
+    if !cfg.enabled || !cfg.events.iter().any(|v| v == event.name()) {
+        return;
+    }
+    let state = state.clone();
+    tokio::spawn(async move {
+        if let Err(e) = send(&state, &event).await {
+            warn!("notify {} failed: {}", event.name(), e);
+        }
+    });
+}
+
+/// 每天零点检查昨天是否漏写
+pub fn daily_check_job(state: &AppState) -> Option<JobDef> {
+    if !state.config.notify.enabled {
+        return None;
+    }
+    Some(JobDef {
+        name: "daily_check".to_string(),
+        kind: "daily_check",
+        schedule: "0 0 * * *".to_string(),
+        handler: run_daily_check_job,
+        max_retries: 0,
+        catch_up: true,
+    })
+}
+
+fn run_daily_check_job(state: AppState, _: Option<String>) -> JobFuture {
+    Box::pin(async move {
+        let days = date_util::local_days(date_util::now_secs(), state.config.utc_offset_minutes);
+        run_day_change_checks(&state, days).await;
+        Ok(())
+    })
+}
+
+async fn run_day_change_checks(state: &AppState, today_days: i64) {
+    let yesterday = date_util::date_from_days(today_days - 1);
+    match sqlx::query_scalar::<_, i64>("select count(1) from journal where date = ?")
+        .bind(&yesterday)
+        .fetch_one(&state.db)
+        .await
+    {
+        Ok(0) => spawn_send(state, NotifyEvent::MissedJournal { date: yesterday }),
+        Ok(_) => {}
+        Err(e) => warn!("notify missed journal check failed: {}", e),
+    }
+}
+
+pub async fn send(state: &AppState, event: &NotifyEvent) -> Result<(), String> {
+    let config = state.config.as_ref();
+    let cfg = &config.notify;
+    let client = &state.outbound;
+    let title = event.title();
+    let body = event.body();
+
+    let provider = cfg.provider.trim().to_ascii_lowercase();
+    let req = match provider.as_str() {
+        "slack" => client
+            .post(webhook_url(cfg)?)?
+            .json(&json!({ "text": format!("*{}*\n{}", title, body) })),
+        "discord" => client
+            .post(webhook_url(cfg)?)?
+            .json(&json!({ "content": format!("**{}**\n{}", title, body) })),
+        "ntfy" => {
+            let topic = cfg.ntfy_topic.trim();
+            if topic.is_empty() {
+                return Err("notify.ntfy_topic is required for ntfy".to_string());
+            }
+            let url = format!("{}/{}", cfg.ntfy_server.trim().trim_end_matches('/'), topic);
+            client.post(&url)?.header("Title", title).body(body)
+        }
+        "telegram" => {
+            let token = config.telegram.token.trim();
+            let chat_id = if cfg.telegram_chat_id != 0 {
+                cfg.telegram_chat_id
+            } else {
+                config
+                    .telegram
+                    .allowed_chat_ids
+                    .first()
+                    .copied()
+                    .unwrap_or(0)
+            };
+            if token.is_empty() || chat_id == 0 {
+                return Err(
+                    "telegram.token and notify.telegram_chat_id are required for telegram"
+                        .to_string(),
+                );
+            }
+            client
+                .post(&format!(
+                    "https://api.telegram.org/bot{}/sendMessage",
+                    token
+                ))?
+                .json(&json!({ "chat_id": chat_id, "text": format!("{}\n{}", title, body) }))
+        }
+        "webhook" => client.post(webhook_url(cfg)?)?.json(&json!({
+            "event": event.name(),
+            "title": title,
+            "message": body,
+        })),
+        _ => {
+            return Err(
+                "notify.provider must be one of: slack, discord, ntfy, telegram, webhook"
+                    .to_string(),
+            );
+        }
+    };
+
+    req.send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(outbound::error_message)?;
+    info!("notify sent: event={}, provider={}", event.name(), provider);
+    Ok(())
+}
+
+fn webhook_url(cfg: &crate::config::app_config::NotifyConfig) -> Result<&str, String> {
+    let url = cfg.webhook_url.trim();
+    if url.is_empty() {
+        return Err(format!(
+            "notify.webhook_url is required for {}",
+            cfg.provider.trim()
+        ));
+    }
+    Ok(url)
+}
@@ -0,0 +1,66 @@
+use crate::app_state::AppState;
+use crate::job::{JobDef, JobFuture};
+use crate::notify::{self, NotifyEvent};
+use crate::util::date_util;
+use tracing::{info, warn};
+
+/// 每个提醒时间点一个任务，当天没有日记时通过 notify 推送；停机期间错过的时间点不补发
+pub fn jobs(state: &AppState) -> Vec<JobDef> {
+    let cfg = &state.config.reminder;
+    if !cfg.enabled {
+        return Vec::new();
+    }
+    let mut jobs = Vec::new();
+    for raw in &cfg.times {
+        let Some(minute) = parse_time(raw) else {
+            warn!("reminder time ignored, expect HH:mm: {}", raw);
+            continue;
+        };
+        let (h, m) = (minute / 60, minute % 60);
+        jobs.push(JobDef {
+            name: format!("reminder_{:02}{:02}", h, m),
+            kind: "reminder",
+            schedule: format!("{} {} * * *", m, h),
+            handler: run_job,
+            max_retries: 0,
+            catch_up: false,
+        });
+    }
+    if jobs.is_empty() {
+        warn!("reminder skipped: no valid reminder.times");
+        return jobs;
+    }
+    if !state.config.notify.enabled {
+        warn!("reminder enabled but notify.enabled=false, reminders will not be delivered");
+    }
+    info!("reminder jobs enabled, times={:?}", cfg.times);
+    jobs
+}
+
+fn run_job(state: AppState, _: Option<String>) -> JobFuture {
+    Box::pin(async move {
+        let days = date_util::local_days(date_util::now_secs(), state.config.utc_offset_minutes);
+        let date = date_util::date_from_days(days);
+        let count = sqlx::query_scalar::<_, i64>("select count(1) from journal where date = ?")
+            .bind(&date)
+            .fetch_one(&state.db)
+            .await
+            .map_err(|e| format!("reminder query failed: {}", e))?;
+        if count == 0 {
+            info!("reminder fired: no journal for {}", date);
+            notify::spawn_send(&state, NotifyEvent::Reminder { date });
+        }
+        Ok(())
+    })
+}
+
+/// `HH:mm` 转为当天分钟数
+fn parse_time(raw: &str) -> Option<i64> {
+    let (h, m) = raw.trim().split_once(':')?;
+    let h = h.parse::<i64>().ok()?;
+    let m = m.parse::<i64>().ok()?;
+    if !(0..24).contains(&h) || !(0..60).contains(&m) {
+        return None;
+    }
+    Some(h * 60 + m)
+}
@@ -0,0 +1,329 @@
+use crate::app_state::AppState;
+use crate::config::app_config::SearchConfig;
+use crate::http::journal::JournalMetadata;
+use crate::job::{self, JobFuture};
+use crate::util::{date_util, markdown, words};
+use serde::Serialize;
+use sqlx::{FromRow, Pool, Sqlite};
+use std::sync::LazyLock;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// 建索引时使用的分词方式，和配置不同时启动时重建
+const TOKENIZER_KEY: &str = "search.tokenizer";
+const REFRESH_JOB: &str = "search_refresh";
+const REFRESH_BATCH: i64 = 200;
+/// 摘要在命中位置之前保留的字数和总字数
+const SNIPPET_BEFORE: usize = 20;
+const SNIPPET_CHARS: usize = 80;
+
+/// 同一时间只有一个请求在更新索引，其他请求等它完成后直接查询
+static REFRESH_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tokenizer {
+    /// sqlite 默认分词，按空格和标点切分，连续的中文会被当成一个词
+    Unicode61,
+    /// 按三个字符切分，可以搜任意子串，少于三个字的词搜不到
+    Trigram,
+    /// 写入索引前把中日韩文字逐字隔开，查询时按短语匹配相邻的字
+    Cjk,
+}
+
+impl Tokenizer {
+    pub fn parse(raw: &str) -> Option<Tokenizer> {
+        match raw.trim() {
+            "unicode61" => Some(Tokenizer::Unicode61),
+            "trigram" => Some(Tokenizer::Trigram),
+            "cjk" => Some(Tokenizer::Cjk),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Tokenizer::Unicode61 => "unicode61",
+            Tokenizer::Trigram => "trigram",
+            Tokenizer::Cjk => "cjk",
+        }
+    }
+
+    fn fts_option(self) -> &'static str {
+        match self {
+            Tokenizer::Unicode61 | Tokenizer::Cjk => "unicode61 remove_diacritics 2",
+            Tokenizer::Trigram => "trigram",
+        }
+    }
+
+    /// 索引和查询都经过这里，两边切分方式一致
+    fn segment(self, text: &str) -> String {
+        if self != Tokenizer::Cjk {
+            return text.to_string();
+        }
+        let mut out = String::with_capacity(text.len() * 2);
+        for c in text.chars() {
+            if words::is_cjk(c) {
+                out.push(' ');
+                out.push(c);
+                out.push(' ');
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReindexReport {
+    pub tokenizer: &'static str,
+    pub indexed: usize,
+}
+
+#[derive(Debug, Serialize, FromRow, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub id: i64,
+    pub date: String,
+    /// `metadata.title`
+    #[sqlx(skip)]
+    pub title: Option<String>,
+    /// 正文中第一处命中附近的纯文本
+    #[sqlx(skip)]
+    pub snippet: String,
+    #[serde(skip)]
+    content: String,
+    #[serde(skip)]
+    metadata: Option<String>,
+}
+
+/// 启动时准备索引表，记录改动的 `search_dirty` 和触发器由数据库迁移创建；分词配置变了或索引表不存在时重建，
+/// 实际写入索引由 `queue_refresh` 排队的任务在后台完成
+pub async fn prepare(cfg: &SearchConfig, db: &Pool<Sqlite>, encrypted: bool) -> Result<(), String> {
+    let tokenizer = Tokenizer::parse(&cfg.tokenizer).ok_or_else(|| {
+        format!(
+            "search.tokenizer must be unicode61, trigram or cjk, got {}",
+            cfg.tokenizer
+        )
+    })?;
+    let indexed = sqlx::query_scalar::<_, String>("select value from app_setting where key = ?")
+        .bind(TOKENIZER_KEY)
+        .fetch_optional(db)
+        .await
+        .map_err(|e| e.to_string())?;
+    let exists = sqlx::query_scalar::<_, bool>(
+        "select exists(select 1 from sqlite_master where name = 'journal_fts')",
+    )
+    .fetch_one(db)
+    .await
+    .map_err(|e| e.to_string())?;
+    if !exists || indexed.as_deref() != Some(tokenizer.as_str()) {
+        info!(
+            "分词方式变更，重建全文索引: {:?} -> {}",
+            indexed,
+            tokenizer.as_str()
+        );
+        rebuild(db, tokenizer).await.map_err(|e| e.to_string())?;
+    }
+    if encrypted {
+        // 索引里是明文，加密后不再保留
+        sqlx::query("delete from journal_fts")
+            .execute(db)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// 按 `tokenizer` 重新建表，所有日记标记为待索引
+async fn rebuild(db: &Pool<Sqlite>, tokenizer: Tokenizer) -> Result<(), sqlx::Error> {
+    let mut tx = db.begin().await?;
+    sqlx::query("drop table if exists journal_fts")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(&format!(
+        "create virtual table journal_fts using fts5(body, tokenize = '{}')",
+        tokenizer.fts_option()
+    ))
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("insert or ignore into search_dirty (journal_id) select id from journal")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "insert into app_setting (key, value, update_time) values (?, ?, ?) on conflict(key) do update set value = excluded.value, update_time = excluded.update_time",
+    )
+    .bind(TOKENIZER_KEY)
+    .bind(tokenizer.as_str())
+    .bind(date_util::now_secs())
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await
+}
+
+fn tokenizer(state: &AppState) -> Tokenizer {
+    // 启动时已经校验过
+    Tokenizer::parse(&state.config.search.tokenizer).unwrap_or(Tokenizer::Unicode61)
+}
+
+/// 需要在 `job::spawn` 之前调用
+pub fn register_jobs() {
+    job::register(REFRESH_JOB, run_refresh_job);
+}
+
+/// 有待索引的日记时排队更新索引，启动时调用
+pub async fn queue_refresh(state: &AppState) {
+    if state.cipher.enabled() {
+        return;
+    }
+    let dirty = sqlx::query_scalar::<_, bool>("select exists(select 1 from search_dirty)")
+        .fetch_one(&state.db)
+        .await;
+    match dirty {
+        Ok(true) => {
+            if let Err(e) = job::enqueue_unless_pending(state, REFRESH_JOB, None, 1).await {
+                warn!("全文索引更新任务排队失败: {}", e);
+            }
+        }
+        Ok(false) => {}
+        Err(e) => warn!("检查待索引日记失败: {}", e),
+    }
+}
+
+fn run_refresh_job(state: AppState, _: Option<String>) -> JobFuture {
+    Box::pin(async move {
+        let indexed = refresh(&state).await.map_err(|e| e.to_string())?;
+        info!("全文索引已更新 journals={}", indexed);
+        Ok(())
+    })
+}
+
+/// 把 `search_dirty` 中的日记写入索引，已删除的从索引移除，返回处理的篇数
+pub async fn refresh(state: &AppState) -> Result<usize, sqlx::Error> {
+    let _guard = REFRESH_LOCK.lock().await;
+    let tokenizer = tokenizer(state);
+    let mut done = 0;
+    loop {
+        let rows = sqlx::query_as::<_, (i64, Option<String>, Option<String>)>(
+            "select d.journal_id, j.content, j.metadata from search_dirty d left join journal j on j.id = d.journal_id order by d.journal_id limit ?",
+        )
+        .bind(REFRESH_BATCH)
+        .fetch_all(&state.db)
+        .await?;
+        if rows.is_empty() {
+            return Ok(done);
+        }
+        let mut tx = state.db.begin().await?;
+        for (id, content, metadata) in rows {
+            sqlx::query("delete from journal_fts where rowid = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            if let Some(content) = content {
+                let body = index_body(&content, metadata.as_deref());
+                sqlx::query("insert into journal_fts (rowid, body) values (?, ?)")
+                    .bind(id)
+                    .bind(tokenizer.segment(&body))
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            sqlx::query("delete from search_dirty where journal_id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            done += 1;
+        }
+        tx.commit().await?;
+    }
+}
+
+/// 标题和去掉 markdown 语法后的正文
+fn index_body(content: &str, metadata: Option<&str>) -> String {
+    let text = markdown::plain_text(content);
+    match JournalMetadata::parse(metadata).title {
+        Some(title) => format!("{}\n{}", title, text),
+        None => text,
+    }
+}
+
+/// 按当前分词方式重建整个索引，返回写入的篇数
+pub async fn reindex(state: &AppState) -> Result<ReindexReport, sqlx::Error> {
+    let tokenizer = tokenizer(state);
+    rebuild(&state.db, tokenizer).await?;
+    let indexed = refresh(state).await?;
+    Ok(ReindexReport {
+        tokenizer: tokenizer.as_str(),
+        indexed,
+    })
+}
+
+/// 查询前先把还没索引的改动写进去，结果按相关度排序；
+/// 空格分开的每个词都要命中，每个词按短语匹配，不支持 fts5 的查询语法
+pub async fn search(
+    state: &AppState,
+    query: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<SearchHit>, sqlx::Error> {
+    refresh(state).await?;
+    let tokenizer = tokenizer(state);
+    let terms = query.split_whitespace().collect::<Vec<_>>();
+    let expr = terms
+        .iter()
+        .map(|v| format!("\"{}\"", tokenizer.segment(v).replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    let mut hits = sqlx::query_as::<_, SearchHit>(
+        r#"
+        select j.id, j.date, j.content, j.metadata
+        from journal_fts f join journal j on j.id = f.rowid
+        where journal_fts match ?
+        order by f.rank, j.date desc
+        limit ? offset ?
+        "#,
+    )
+    .bind(expr)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&state.read_db)
+    .await?;
+    for hit in hits.iter_mut() {
+        hit.title = JournalMetadata::parse(hit.metadata.as_deref()).title;
+        hit.snippet = snippet(&markdown::plain_text(&hit.content), &terms);
+    }
+    Ok(hits)
+}
+
+/// 第一处命中的词前后的文字，找不到时（大小写、变音符号不同）取开头
+fn snippet(text: &str, terms: &[&str]) -> String {
+    let chars = text.chars().collect::<Vec<_>>();
+    let lower = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect::<Vec<_>>();
+    let pos = terms
+        .iter()
+        .filter_map(|term| {
+            let term = term.to_lowercase().chars().collect::<Vec<_>>();
+            if term.is_empty() {
+                return None;
+            }
+            lower.windows(term.len()).position(|w| w == term.as_slice())
+        })
+        .min()
+        .unwrap_or(0);
+    let start = pos
+        .saturating_sub(SNIPPET_BEFORE)
+        .min(chars.len().saturating_sub(SNIPPET_CHARS));
+    let end = (start + SNIPPET_CHARS).min(chars.len());
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    out.push_str(chars[start..end].iter().collect::<String>().trim());
+    if end < chars.len() {
+        out.push('…');
+    }
+    out
+}
@@ -0,0 +1,78 @@
+use crate::config::app_config::SyncConfig;
+use crate::error::DayLogError;
+use crate::sync::mirror::{self, FileRemote, RemoteFile};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `sync.backend = "dir"`：同步到 `repo_url` 这个本地目录，例如网盘客户端的同步目录或挂载的 NAS
+pub struct Dir {
+    root: PathBuf,
+}
+
+impl Dir {
+    pub fn new(cfg: &SyncConfig) -> Result<Self, DayLogError> {
+        let root = PathBuf::from(cfg.repo_url.trim().trim_start_matches("file://"));
+        if !root.is_absolute() {
+            return Err(DayLogError::Validation(
+                "sync.repo_url must be an absolute directory path for the dir backend".to_string(),
+            ));
+        }
+        if root == Path::new(&cfg.repo_local_path) {
+            return Err(DayLogError::Validation(
+                "sync.repo_url must differ from sync.repo_local_path".to_string(),
+            ));
+        }
+        Ok(Self { root })
+    }
+}
+
+impl FileRemote for Dir {
+    fn list(&self) -> Result<Vec<RemoteFile>, DayLogError> {
+        // 挂载点掉线时目录不存在，不能当成远端被清空
+        if !self.root.is_dir() {
+            return Err(DayLogError::Remote(format!(
+                "sync dir not found: {}",
+                self.root.display()
+            )));
+        }
+        let files =
+            mirror::list_files(&self.root).map_err(|e| DayLogError::Remote(e.to_string()))?;
+        Ok(files
+            .into_iter()
+            .filter_map(|rel| {
+                let meta = fs::metadata(self.root.join(&rel)).ok()?;
+                Some(RemoteFile {
+                    path: rel.to_string_lossy().replace('\\', "/"),
+                    size: meta.len(),
+                    modified: mirror::modified_secs(&meta),
+                })
+            })
+            .collect())
+    }
+
+    fn get(&self, path: &str) -> Result<Vec<u8>, DayLogError> {
+        let full_path = self.root.join(path);
+        fs::read(&full_path)
+            .map_err(|e| DayLogError::Remote(format!("read {} failed: {}", full_path.display(), e)))
+    }
+
+    fn put(&self, path: &str, bytes: &[u8]) -> Result<(), DayLogError> {
+        let full_path = self.root.join(path);
+        if let Some(parent) = full_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| DayLogError::Remote(e.to_string()))?;
+        }
+        fs::write(&full_path, bytes).map_err(|e| {
+            DayLogError::Remote(format!("write {} failed: {}", full_path.display(), e))
+        })
+    }
+
+    fn delete(&self, path: &str) -> Result<(), DayLogError> {
+        let full_path = self.root.join(path);
+        match fs::remove_file(&full_path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(DayLogError::Remote(
+                format!("remove {} failed: {}", full_path.display(), e),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,567 @@
+use crate::config::app_config::SyncConfig;
+use crate::error::DayLogError;
+use crate::sync::{self, PushInput, PushOutput, SyncBackend};
+use git2::{
+    BranchType, Cred, FetchOptions, Index, IndexTime, ObjectType, Oid, PushOptions,
+    RemoteCallbacks, Repository, Signature, build::CheckoutBuilder, build::RepoBuilder,
+};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// `sync.backend = "git"`：工作目录是 `repo_url` 的克隆，每次同步一个提交
+pub struct GitBackend {
+    cfg: SyncConfig,
+}
+
+impl GitBackend {
+    pub fn new(cfg: &SyncConfig) -> Result<Self, DayLogError> {
+        let auth_mode = resolve_auth_mode(cfg)?;
+        validate_auth_config(cfg, auth_mode)?;
+        Ok(Self { cfg: cfg.clone() })
+    }
+}
+
+impl SyncBackend for GitBackend {
+    fn pull(&self, workdir: &Path) -> Result<(), DayLogError> {
+        prepare_repo_for_import(&self.cfg, workdir)
+    }
+
+    fn modified_times(
+        &self,
+        workdir: &Path,
+        paths: &[String],
+    ) -> Result<HashMap<String, i64>, DayLogError> {
+        let repo = Repository::open(workdir)?;
+        last_commit_times(&repo, paths)
+    }
+
+    fn push(&self, workdir: &Path, input: &PushInput) -> Result<PushOutput, DayLogError> {
+        execute_sync(&self.cfg, workdir, input)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMode {
+    Password,
+    Ssh,
+}
+
+/// 每个路径最后一次被改动的提交时间，从 HEAD 沿第一父提交往回找，全部找到即停止
+fn last_commit_times(
+    repo: &Repository,
+    paths: &[String],
+) -> Result<HashMap<String, i64>, DayLogError> {
+    let mut remaining = paths.iter().map(String::as_str).collect::<HashSet<_>>();
+    let mut times = HashMap::new();
+    let mut commit = repo.head().and_then(|h| h.peel_to_commit()).ok();
+    while let Some(current) = commit {
+        if remaining.is_empty() {
+            break;
+        }
+        let tree = current.tree()?;
+        let parent = current.parent(0).ok();
+        let parent_tree = match &parent {
+            Some(p) => Some(p.tree()?),
+            None => None,
+        };
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+        for delta in diff.deltas() {
+            let Some(path) = delta.new_file().path() else {
+                continue;
+            };
+            let path = path.to_string_lossy().replace('\\', "/");
+            if remaining.remove(path.as_str()) {
+                times.insert(path, current.time().seconds());
+            }
+        }
+        commit = parent;
+    }
+    Ok(times)
+}
+
+fn prepare_repo_for_import(cfg: &SyncConfig, repo_path: &Path) -> Result<(), DayLogError> {
+    if let Some(parent) = repo_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let repo = open_or_clone(cfg, repo_path)?;
+    fetch_branch(&repo, cfg)?;
+    let _guard = sync::repo_write_guard();
+    fast_forward(&repo, cfg)
+}
+
+/// 网络请求期间不持有写锁：已有仓库直接打开，没有时克隆到旁边的临时目录，持锁改名到 `repo_path`，
+/// 读取方不会看到克隆到一半的工作区
+fn open_or_clone(cfg: &SyncConfig, repo_path: &Path) -> Result<Repository, DayLogError> {
+    if repo_path.join(".git").exists() {
+        info!("opening existing repo {}", repo_path.display());
+        return Ok(Repository::open(repo_path)?);
+    }
+    let mut tmp = repo_path.as_os_str().to_owned();
+    tmp.push(".cloning");
+    let tmp = PathBuf::from(tmp);
+    if tmp.exists() {
+        fs::remove_dir_all(&tmp).map_err(|e| DayLogError::io(tmp.display(), e))?;
+    }
+    info!("cloning repo {} -> {}", cfg.repo_url, repo_path.display());
+    drop(clone_repo(cfg, &tmp)?);
+    {
+        let _guard = sync::repo_write_guard();
+        // 克隆要求目标目录不存在或为空，空目录先删掉再改名
+        if repo_path.is_dir() {
+            fs::remove_dir(repo_path).map_err(|e| DayLogError::io(repo_path.display(), e))?;
+        }
+        fs::rename(&tmp, repo_path).map_err(|e| DayLogError::io(repo_path.display(), e))?;
+    }
+    Ok(Repository::open(repo_path)?)
+}
+
+fn execute_sync(
+    cfg: &SyncConfig,
+    repo_path: &Path,
+    input: &PushInput,
+) -> Result<PushOutput, DayLogError> {
+    info!(
+        "execute sync: repo_path={}, branch={}, output_files={}",
+        repo_path.display(),
+        cfg.branch,
+        input.output_files.len()
+    );
+    if let Some(parent) = repo_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let repo = open_or_clone(cfg, repo_path)?;
+    info!("execute sync: fetch branch");
+    fetch_branch(&repo, cfg)?;
+
+    // 只在检出、写文件和提交期间持有，克隆、拉取和推送的网络请求期间不阻塞读取
+    let guard = sync::repo_write_guard();
+    info!("execute sync: fast-forward branch");
+    fast_forward(&repo, cfg)?;
+
+    let sparse = sparse_paths(cfg)?;
+    if let Some(f) = input
+        .output_files
+        .iter()
+        .find(|f| !in_sparse_paths(&sparse, &f.rel_path))
+    {
+        return Err(DayLogError::Validation(format!(
+            "output file {} is outside sync.sparse_paths",
+            f.rel_path.display()
+        )));
+    }
+    // 稀疏检出时工作区和 index 都不完整，改为在内存中从 HEAD 的树构建 index，其他目录原样保留
+    let mut index = if sparse.is_empty() {
+        repo.index()?
+    } else {
+        let mut index = Index::new()?;
+        if let Ok(tree) = repo.head().and_then(|h| h.peel_to_tree()) {
+            index.read_tree(&tree)?;
+        }
+        index
+    };
+
+    // 内容的 blob id 与 index 中一致时说明文件没变，跳过写入和暂存
+    let mut changed = 0usize;
+    for f in &input.output_files {
+        let full_output_path = repo_path.join(&f.rel_path);
+        let blob_id = Oid::hash_object(ObjectType::Blob, f.content.as_bytes())?;
+        let unchanged = index
+            .get_path(f.rel_path.as_path(), 0)
+            .is_some_and(|entry| entry.id == blob_id)
+            && full_output_path.is_file();
+        if unchanged {
+            continue;
+        }
+        if let Some(parent) = full_output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        info!(
+            "execute sync: writing output file {}",
+            full_output_path.display()
+        );
+        fs::write(&full_output_path, f.content.as_bytes())?;
+        if sparse.is_empty() {
+            index.add_path(f.rel_path.as_path())?;
+        } else {
+            let id = repo.blob(f.content.as_bytes())?;
+            index.add(&blob_entry(&f.rel_path, id))?;
+        }
+        changed += 1;
+    }
+    for rel_path in input
+        .stale_paths
+        .iter()
+        .filter(|p| in_sparse_paths(&sparse, p))
+    {
+        let full_path = repo_path.join(rel_path);
+        let tracked = index.get_path(rel_path.as_path(), 0).is_some();
+        if !tracked && !full_path.exists() {
+            continue;
+        }
+        info!("execute sync: removing stale file {}", full_path.display());
+        if full_path.is_file() {
+            fs::remove_file(&full_path)?;
+        }
+        if tracked {
+            index.remove_path(rel_path.as_path())?;
+        }
+        changed += 1;
+    }
+    info!(
+        "execute sync: {} of {} output files changed",
+        changed,
+        input.output_files.len()
+    );
+    if changed > 0 && sparse.is_empty() {
+        index.write()?;
+    }
+
+    let tree_id = index.write_tree_to(&repo)?;
+    let tree = repo.find_tree(tree_id)?;
+
+    let mut parents = Vec::new();
+    if let Ok(head) = repo.head() {
+        let commit = head.peel_to_commit()?;
+        if commit.tree_id() == tree_id {
+            info!("execute sync: no file changes detected, skip commit/push");
+            return Ok(PushOutput {
+                pushed: false,
+                commit_id: "".to_string(),
+                squashed: false,
+            });
+        }
+        parents.push(commit);
+    }
+
+    let sig = Signature::now(&cfg.author_name, &cfg.author_email)?;
+
+    // HEAD 还是上一次定时同步的提交，说明之后没有其他人推送，可以直接改写
+    if let Some(head) = parents
+        .first()
+        .filter(|c| input.squash_onto.as_deref() == Some(c.id().to_string().as_str()))
+    {
+        let old_id = head.id();
+        let commit_id = head.amend(
+            Some("HEAD"),
+            Some(&sig),
+            Some(&sig),
+            None,
+            Some(&input.commit_message),
+            Some(&tree),
+        )?;
+        info!("execute sync: amended {} -> {}", old_id, commit_id);
+        drop(guard);
+        info!("execute sync: force pushing branch {}", cfg.branch);
+        push_branch(&repo, cfg, Some(old_id))?;
+        info!("execute sync: push success");
+        return Ok(PushOutput {
+            pushed: true,
+            commit_id: commit_id.to_string(),
+            squashed: true,
+        });
+    }
+
+    let parent_refs = parents.iter().collect::<Vec<_>>();
+    let commit_id = repo.commit(
+        Some("HEAD"),
+        &sig,
+        &sig,
+        &input.commit_message,
+        &tree,
+        &parent_refs,
+    )?;
+    info!("execute sync: commit created {}", commit_id);
+    drop(guard);
+
+    info!("execute sync: pushing branch {}", cfg.branch);
+    push_branch(&repo, cfg, None)?;
+    info!("execute sync: push success");
+
+    Ok(PushOutput {
+        pushed: true,
+        commit_id: commit_id.to_string(),
+        squashed: false,
+    })
+}
+
+fn clone_repo(cfg: &SyncConfig, repo_path: &Path) -> Result<Repository, DayLogError> {
+    let auth_mode = resolve_auth_mode(cfg)?;
+    let cb = remote_callbacks(cfg, auth_mode);
+    let mut fetch = FetchOptions::new();
+    fetch.remote_callbacks(cb);
+
+    let mut builder = RepoBuilder::new();
+    builder.fetch_options(fetch);
+    builder.branch(cfg.branch.trim());
+    let sparse = sparse_paths(cfg)?;
+    if !sparse.is_empty() {
+        let mut checkout = CheckoutBuilder::new();
+        for path in &sparse {
+            checkout.path(path);
+        }
+        builder.with_checkout(checkout);
+    }
+    builder
+        .clone(cfg.repo_url.trim(), repo_path)
+        .map_err(remote_error)
+}
+
+/// 只更新 `refs/remotes/origin/<branch>`，不动工作区，不需要持有写锁
+fn fetch_branch(repo: &Repository, cfg: &SyncConfig) -> Result<(), DayLogError> {
+    let auth_mode = resolve_auth_mode(cfg)?;
+    let cb = remote_callbacks(cfg, auth_mode);
+    let mut fetch_opts = FetchOptions::new();
+    fetch_opts.remote_callbacks(cb);
+
+    let mut remote = repo.find_remote("origin")?;
+    remote
+        .fetch(&[cfg.branch.trim()], Some(&mut fetch_opts), None)
+        .map_err(remote_error)?;
+    Ok(())
+}
+
+/// 把本地分支快进到 `fetch_branch` 拉下来的提交并检出，调用方持有写锁
+fn fast_forward(repo: &Repository, cfg: &SyncConfig) -> Result<(), DayLogError> {
+    let branch_name = cfg.branch.trim();
+    let remote_branch = format!("refs/remotes/origin/{}", branch_name);
+    let local_branch = format!("refs/heads/{}", branch_name);
+
+    let oid = repo.refname_to_id(&remote_branch)?;
+    let target = repo.find_commit(oid)?;
+
+    // HEAD 已经在本地分支上且等于远端时工作区无需变动
+    let head_oid = repo
+        .head()
+        .ok()
+        .filter(|h| h.name() == Some(local_branch.as_str()))
+        .and_then(|h| h.target());
+    if head_oid == Some(target.id()) {
+        info!(
+            "checkout skipped: {} already at {}",
+            branch_name,
+            target.id()
+        );
+        return Ok(());
+    }
+
+    if repo.find_branch(branch_name, BranchType::Local).is_err() {
+        repo.branch(branch_name, &target, true)?;
+    }
+
+    let mut local_ref = repo.find_reference(&local_branch)?;
+    local_ref.set_target(target.id(), "fast-forward")?;
+
+    repo.set_head(&local_branch)?;
+    let sparse = sparse_paths(cfg)?;
+    let mut checkout = CheckoutBuilder::new();
+    checkout.force();
+
+    // 原来就在本地分支上时只检出两次提交之间变动的路径，否则整体检出
+    if let Some(old_commit) = head_oid.and_then(|oid| repo.find_commit(oid).ok()) {
+        let old_tree = old_commit.tree()?;
+        let new_tree = target.tree()?;
+        let diff = repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)?;
+        let mut paths = 0usize;
+        for delta in diff.deltas() {
+            for path in [delta.old_file().path(), delta.new_file().path()]
+                .into_iter()
+                .flatten()
+                .filter(|p| in_sparse_paths(&sparse, p))
+            {
+                checkout.path(path);
+                paths += 1;
+            }
+        }
+        if paths == 0 {
+            info!("checkout skipped: no path changes to {}", target.id());
+            return Ok(());
+        }
+        info!(
+            "checkout {} changed paths to {}",
+            diff.deltas().len(),
+            target.id()
+        );
+    } else {
+        for path in &sparse {
+            checkout.path(path);
+        }
+    }
+
+    repo.checkout_head(Some(&mut checkout))?;
+    Ok(())
+}
+
+/// `sync.sparse_paths` 中的目录，为空时检出整个仓库
+fn sparse_paths(cfg: &SyncConfig) -> Result<Vec<PathBuf>, DayLogError> {
+    cfg.sparse_paths
+        .iter()
+        .map(|v| v.trim().trim_matches('/'))
+        .filter(|v| !v.is_empty())
+        .map(|v| {
+            sync::validate_rel_path(v).map_err(|e| {
+                DayLogError::Validation(format!("invalid sync.sparse_paths '{}': {}", v, e))
+            })
+        })
+        .collect()
+}
+
+fn in_sparse_paths(sparse: &[PathBuf], path: &Path) -> bool {
+    sparse.is_empty() || sparse.iter().any(|v| path.starts_with(v))
+}
+
+/// 不经过工作区直接写入 index 的普通文件，`id` 是已经写入对象库的 blob
+fn blob_entry(rel_path: &Path, id: Oid) -> git2::IndexEntry {
+    let path = rel_path.to_string_lossy().replace('\\', "/").into_bytes();
+    git2::IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode: 0o100644,
+        uid: 0,
+        gid: 0,
+        file_size: 0,
+        id,
+        flags: path.len().min(0xfff) as u16,
+        flags_extended: 0,
+        path,
+    }
+}
+
+/// `lease` 不为空时强制推送，但远端分支必须仍指向 `lease`（相当于 `--force-with-lease`）
+fn push_branch(repo: &Repository, cfg: &SyncConfig, lease: Option<Oid>) -> Result<(), DayLogError> {
+    let auth_mode = resolve_auth_mode(cfg)?;
+    let mut cb = remote_callbacks(cfg, auth_mode);
+    if let Some(expected) = lease {
+        cb.push_negotiation(
+            move |updates| match updates.iter().find(|u| u.src() != expected) {
+                Some(u) => Err(git2::Error::from_str(&format!(
+                    "remote {} moved to {}, expected {}",
+                    u.dst_refname().unwrap_or("branch"),
+                    u.src(),
+                    expected
+                ))),
+                None => Ok(()),
+            },
+        );
+    }
+    let mut push_opts = PushOptions::new();
+    push_opts.remote_callbacks(cb);
+
+    let mut remote = repo.find_remote("origin")?;
+    let force = if lease.is_some() { "+" } else { "" };
+    let spec = format!("{0}refs/heads/{1}:refs/heads/{1}", force, cfg.branch.trim());
+    remote
+        .push(&[&spec], Some(&mut push_opts))
+        .map_err(remote_error)
+}
+
+/// clone、fetch、push 连接远端失败
+fn remote_error(e: git2::Error) -> DayLogError {
+    DayLogError::Remote(e.message().to_string())
+}
+
+fn remote_callbacks(cfg: &SyncConfig, auth_mode: AuthMode) -> RemoteCallbacks<'static> {
+    let username = cfg.username.clone();
+    let password = cfg.password.clone();
+    let ssh_username = cfg.ssh_username.clone();
+    let ssh_private_key = cfg.ssh_private_key_path.clone();
+    let ssh_public_key = cfg.ssh_public_key_path.clone();
+    let ssh_passphrase = cfg.ssh_passphrase.clone();
+    let mut cb = RemoteCallbacks::new();
+    cb.credentials(move |_url, user, _allowed| match auth_mode {
+        AuthMode::Password => Cred::userpass_plaintext(&username, &password),
+        AuthMode::Ssh => {
+            let user_name = if !ssh_username.trim().is_empty() {
+                ssh_username.as_str()
+            } else {
+                user.unwrap_or("git")
+            };
+            let public_key = if ssh_public_key.trim().is_empty() {
+                None
+            } else {
+                Some(Path::new(ssh_public_key.trim()))
+            };
+            let passphrase = if ssh_passphrase.trim().is_empty() {
+                None
+            } else {
+                Some(ssh_passphrase.as_str())
+            };
+            Cred::ssh_key(
+                user_name,
+                public_key,
+                Path::new(ssh_private_key.trim()),
+                passphrase,
+            )
+        }
+    });
+    cb
+}
+
+/// `sync.auth_method` 解析后的认证方式，配置无效时为 None
+pub fn auth_mode_name(cfg: &SyncConfig) -> Option<&'static str> {
+    match resolve_auth_mode(cfg).ok()? {
+        AuthMode::Password => Some("password"),
+        AuthMode::Ssh => Some("ssh"),
+    }
+}
+
+pub fn resolve_auth_mode(cfg: &SyncConfig) -> Result<AuthMode, DayLogError> {
+    let method = cfg.auth_method.trim().to_ascii_lowercase();
+    match method.as_str() {
+        "password" | "userpass" | "https" => Ok(AuthMode::Password),
+        "ssh" => Ok(AuthMode::Ssh),
+        "auto" | "" => {
+            if looks_like_github_repo(&cfg.repo_url) {
+                return Ok(AuthMode::Ssh);
+            }
+            if !cfg.username.trim().is_empty() && !cfg.password.trim().is_empty() {
+                return Ok(AuthMode::Password);
+            }
+            if !cfg.ssh_private_key_path.trim().is_empty() {
+                return Ok(AuthMode::Ssh);
+            }
+            Ok(AuthMode::Password)
+        }
+        _ => Err(DayLogError::Validation(
+            "sync.auth_method must be one of: auto, password, ssh".to_string(),
+        )),
+    }
+}
+
+fn validate_auth_config(cfg: &SyncConfig, mode: AuthMode) -> Result<(), DayLogError> {
+    let invalid = |msg: String| Err(DayLogError::Validation(msg));
+    match mode {
+        AuthMode::Password => {
+            if cfg.username.trim().is_empty() || cfg.password.trim().is_empty() {
+                if looks_like_github_repo(&cfg.repo_url) {
+                    return invalid(
+                        "GitHub repo should use ssh auth. set sync.auth_method='ssh' and sync.ssh_private_key_path"
+                            .to_string(),
+                    );
+                }
+                return invalid(
+                    "sync.username and sync.password are required for password auth".to_string(),
+                );
+            }
+            Ok(())
+        }
+        AuthMode::Ssh => {
+            if cfg.ssh_private_key_path.trim().is_empty() {
+                return invalid("sync.ssh_private_key_path is required for ssh auth".to_string());
+            }
+            let key_path = cfg.ssh_private_key_path.trim();
+            if !Path::new(key_path).exists() {
+                return invalid(format!("ssh private key not found: {}", key_path));
+            }
+            Ok(())
+        }
+    }
+}
+
+fn looks_like_github_repo(repo_url: &str) -> bool {
+    let lower = repo_url.trim().to_ascii_lowercase();
+    lower.contains("github.com")
+}
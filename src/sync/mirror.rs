@@ -0,0 +1,197 @@
+use crate::error::DayLogError;
+use crate::sync::{self, PushInput, PushOutput, SyncBackend};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// 远端的一个文件，`path` 是用 `/` 分隔的相对路径
+pub struct RemoteFile {
+    pub path: String,
+    pub size: u64,
+    /// 最后修改时间（秒）
+    pub modified: i64,
+}
+
+/// 按文件读写、没有提交历史的远端
+pub trait FileRemote: Send + Sync {
+    /// 远端目录下的全部文件
+    fn list(&self) -> Result<Vec<RemoteFile>, DayLogError>;
+
+    fn get(&self, path: &str) -> Result<Vec<u8>, DayLogError>;
+
+    /// 上级目录不存在时先创建
+    fn put(&self, path: &str, bytes: &[u8]) -> Result<(), DayLogError>;
+
+    /// 文件已经不存在时不报错
+    fn delete(&self, path: &str) -> Result<(), DayLogError>;
+}
+
+/// `webdav` `dir` 后端：工作目录是远端文件的镜像，修改时间和远端一致；
+/// 推送只上传内容有变化的文件，提交信息、`squash_window`、`sparse_paths` 不生效
+pub struct Mirror<R>(pub R);
+
+impl<R: FileRemote> SyncBackend for Mirror<R> {
+    fn pull(&self, workdir: &Path) -> Result<(), DayLogError> {
+        let files = self.0.list()?;
+        // 先下载到内存，网络请求期间不阻塞读取工作目录
+        let mut remote_paths = HashSet::with_capacity(files.len());
+        let mut downloads = Vec::new();
+        for f in files {
+            let rel_path = match sync::validate_rel_path(&f.path) {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!("mirror pull skip {}: {}", f.path, e);
+                    continue;
+                }
+            };
+            let local = workdir.join(&rel_path);
+            remote_paths.insert(rel_path);
+            // 大小相同且本地不比远端旧时认为没有变化
+            let unchanged = fs::metadata(&local)
+                .is_ok_and(|m| m.len() == f.size && modified_secs(&m) >= f.modified);
+            if unchanged {
+                continue;
+            }
+            let bytes = self.0.get(&f.path)?;
+            downloads.push((local, bytes, f.modified));
+        }
+
+        let _guard = sync::repo_write_guard();
+        fs::create_dir_all(workdir)?;
+        for (local, bytes, modified) in &downloads {
+            write_local(local, bytes, Some(*modified))?;
+        }
+        let mut removed = 0usize;
+        for rel_path in list_files(workdir)? {
+            if remote_paths.contains(&rel_path) {
+                continue;
+            }
+            fs::remove_file(workdir.join(&rel_path))?;
+            removed += 1;
+        }
+        info!(
+            "mirror pull: files={}, downloaded={}, removed={}",
+            remote_paths.len(),
+            downloads.len(),
+            removed
+        );
+        Ok(())
+    }
+
+    fn modified_times(
+        &self,
+        workdir: &Path,
+        paths: &[String],
+    ) -> Result<HashMap<String, i64>, DayLogError> {
+        let _guard = sync::repo_read_guard();
+        Ok(paths
+            .iter()
+            .filter_map(|p| {
+                let meta = fs::metadata(workdir.join(p)).ok()?;
+                Some((p.clone(), modified_secs(&meta)))
+            })
+            .collect())
+    }
+
+    fn push(&self, workdir: &Path, input: &PushInput) -> Result<PushOutput, DayLogError> {
+        let (changed, stale) = {
+            let _guard = sync::repo_read_guard();
+            let changed = input
+                .output_files
+                .iter()
+                .filter(|f| {
+                    fs::read(workdir.join(&f.rel_path)).map_or(true, |v| v != f.content.as_bytes())
+                })
+                .collect::<Vec<_>>();
+            let stale = input
+                .stale_paths
+                .iter()
+                .filter(|p| workdir.join(p).is_file())
+                .collect::<Vec<_>>();
+            (changed, stale)
+        };
+        info!(
+            "mirror push: {} of {} output files changed, stale={}",
+            changed.len(),
+            input.output_files.len(),
+            stale.len()
+        );
+        for f in &changed {
+            self.0.put(&slash_path(&f.rel_path), f.content.as_bytes())?;
+        }
+        for rel_path in &stale {
+            self.0.delete(&slash_path(rel_path))?;
+        }
+
+        // 上传成功后再改工作目录，中途失败时下次同步会重新上传
+        let _guard = sync::repo_write_guard();
+        for f in &changed {
+            write_local(&workdir.join(&f.rel_path), f.content.as_bytes(), None)?;
+        }
+        for rel_path in &stale {
+            let _ = fs::remove_file(workdir.join(rel_path));
+        }
+        Ok(PushOutput {
+            pushed: !changed.is_empty() || !stale.is_empty(),
+            commit_id: String::new(),
+            squashed: false,
+        })
+    }
+}
+
+/// `root` 下全部文件的相对路径，跳过 `.git`（从 git 后端切换过来时留下的）
+pub fn list_files(root: &Path) -> Result<Vec<PathBuf>, DayLogError> {
+    let mut out = Vec::new();
+    if root.is_dir() {
+        collect_files(root, root, &mut out)?;
+    }
+    Ok(out)
+}
+
+fn collect_files(root: &Path, current: &Path, out: &mut Vec<PathBuf>) -> Result<(), DayLogError> {
+    let rd = fs::read_dir(current)
+        .map_err(|e| DayLogError::io(format_args!("read dir {}", current.display()), e))?;
+    for item in rd {
+        let path = item?.path();
+        if path.is_dir() {
+            if path.file_name().and_then(|v| v.to_str()) != Some(".git") {
+                collect_files(root, &path, out)?;
+            }
+            continue;
+        }
+        if let Ok(rel) = path.strip_prefix(root) {
+            out.push(rel.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+pub fn modified_secs(meta: &fs::Metadata) -> i64 {
+    meta.modified()
+        .ok()
+        .and_then(|v| v.duration_since(UNIX_EPOCH).ok())
+        .map(|v| v.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn slash_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// `modified` 为远端的修改时间，下载的文件按它设置，下次拉取时据此判断是否变化
+fn write_local(path: &Path, bytes: &[u8], modified: Option<i64>) -> Result<(), DayLogError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, bytes)
+        .map_err(|e| DayLogError::io(format_args!("write {}", path.display()), e))?;
+    if let Some(secs) = modified {
+        File::options()
+            .write(true)
+            .open(path)
+            .and_then(|f| f.set_modified(UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)))?;
+    }
+    Ok(())
+}
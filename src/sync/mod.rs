@@ -0,0 +1,101 @@
+mod dir;
+pub mod git;
+mod mirror;
+mod webdav;
+
+use crate::config::app_config::SyncConfig;
+use crate::error::DayLogError;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+#[derive(Clone)]
+pub struct SyncOutputFile {
+    pub rel_path: PathBuf,
+    pub content: String,
+}
+
+pub struct PushInput {
+    pub output_files: Vec<SyncOutputFile>,
+    /// 日记改日期或被合并后遗留的旧文件
+    pub stale_paths: Vec<PathBuf>,
+    pub commit_message: String,
+    /// HEAD 仍是这个提交时改写它而不是新建提交
+    pub squash_onto: Option<String>,
+}
+
+pub struct PushOutput {
+    pub pushed: bool,
+    /// 没有提交历史的后端为空
+    pub commit_id: String,
+    /// 改写了上一次定时同步的提交
+    pub squashed: bool,
+}
+
+/// 同步的远端，`sync.backend` 选择实现。工作目录（`sync.repo_local_path`）是远端内容的本地副本，
+/// 导入和双向同步都从工作目录读取；方法都在 blocking 线程中调用
+pub trait SyncBackend: Send + Sync {
+    /// 把远端的改动拉到工作目录
+    fn pull(&self, workdir: &Path) -> Result<(), DayLogError>;
+
+    /// 工作目录中这些文件在远端最后一次改动的时间（秒），双向同步据此判断冲突
+    fn modified_times(
+        &self,
+        workdir: &Path,
+        paths: &[String],
+    ) -> Result<HashMap<String, i64>, DayLogError>;
+
+    /// 写入输出文件、删除旧文件并推送到远端，没有改动时不推送
+    fn push(&self, workdir: &Path, input: &PushInput) -> Result<PushOutput, DayLogError>;
+}
+
+/// 按 `sync.backend` 创建后端并检查对应的配置，需要在 tokio 运行时中调用
+pub fn backend(
+    cfg: &SyncConfig,
+    http: &reqwest::Client,
+) -> Result<Arc<dyn SyncBackend>, DayLogError> {
+    if cfg.repo_url.trim().is_empty() {
+        return Err(DayLogError::Validation(
+            "sync.repo_url is required".to_string(),
+        ));
+    }
+    match cfg.backend.trim() {
+        "git" => Ok(Arc::new(git::GitBackend::new(cfg)?)),
+        "webdav" => Ok(Arc::new(mirror::Mirror(webdav::WebDav::new(cfg, http)?))),
+        "dir" => Ok(Arc::new(mirror::Mirror(dir::Dir::new(cfg)?))),
+        other => Err(DayLogError::Validation(format!(
+            "invalid sync.backend: {} (expected git, webdav or dir)",
+            other
+        ))),
+    }
+}
+
+/// 检出、写入等改动工作区的操作持有写锁，读取仓库目录中文件的地方持有读锁，
+/// 避免读到强制检出到一半的文件；都在阻塞线程中使用
+static REPO_FILES_LOCK: RwLock<()> = RwLock::new(());
+
+/// 读取同步仓库工作区中的文件前获取，持有期间同步不会改动工作区
+pub fn repo_read_guard() -> RwLockReadGuard<'static, ()> {
+    REPO_FILES_LOCK.read().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn repo_write_guard() -> RwLockWriteGuard<'static, ()> {
+    REPO_FILES_LOCK.write().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn validate_rel_path(input: &str) -> Result<PathBuf, DayLogError> {
+    let invalid = |msg: &str| Err(DayLogError::Validation(msg.to_string()));
+    let p = Path::new(input.trim());
+    if input.trim().is_empty() {
+        return invalid("path is empty");
+    }
+    if p.is_absolute() {
+        return invalid("absolute path is not allowed");
+    }
+    for c in p.components() {
+        if matches!(c, Component::ParentDir) {
+            return invalid("parent dir is not allowed");
+        }
+    }
+    Ok(p.to_path_buf())
+}
@@ -0,0 +1,280 @@
+use crate::config::app_config::SyncConfig;
+use crate::error::DayLogError;
+use crate::sync::mirror::{FileRemote, RemoteFile};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode, Url};
+use std::time::UNIX_EPOCH;
+use tokio::runtime::Handle;
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/><d:getcontentlength/><d:getlastmodified/></d:prop></d:propfind>"#;
+
+/// `sync.backend = "webdav"`：同步到 `repo_url` 这个 WebDAV 目录，例如 Nextcloud 的
+/// `https://host/remote.php/dav/files/{user}/daylog`，用 `username` `password`（应用密码）认证
+pub struct WebDav {
+    client: Client,
+    /// 以 `/` 结尾的目录地址
+    base: Url,
+    username: String,
+    password: String,
+    /// 方法在 blocking 线程中调用，请求交回创建时所在的运行时执行
+    handle: Handle,
+}
+
+struct DavEntry {
+    path: String,
+    collection: bool,
+    size: u64,
+    modified: i64,
+}
+
+impl WebDav {
+    pub fn new(cfg: &SyncConfig, client: &Client) -> Result<Self, DayLogError> {
+        let invalid = |msg: String| DayLogError::Validation(msg);
+        let mut base = Url::parse(cfg.repo_url.trim())
+            .map_err(|e| invalid(format!("invalid sync.repo_url: {}", e)))?;
+        if !matches!(base.scheme(), "http" | "https") {
+            return Err(invalid(
+                "sync.repo_url must be an http(s) url for the webdav backend".to_string(),
+            ));
+        }
+        if !base.path().ends_with('/') {
+            let path = format!("{}/", base.path());
+            base.set_path(&path);
+        }
+        let handle = Handle::try_current().map_err(|e| DayLogError::Internal(e.to_string()))?;
+        Ok(Self {
+            client: client.clone(),
+            base,
+            username: cfg.username.trim().to_string(),
+            password: cfg.password.clone(),
+            handle,
+        })
+    }
+
+    /// `path` 为空时是目录本身，`dir` 为 true 时末尾加 `/`
+    fn url(&self, path: &str, dir: bool) -> Url {
+        let mut url = self.base.clone();
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments.pop_if_empty();
+            segments.extend(path.split('/').filter(|v| !v.is_empty()));
+            if dir {
+                segments.push("");
+            }
+        }
+        url
+    }
+
+    fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        let req = self.client.request(method, url);
+        if self.username.is_empty() {
+            req
+        } else {
+            req.basic_auth(&self.username, Some(&self.password))
+        }
+    }
+
+    fn send(&self, req: RequestBuilder) -> Result<Response, DayLogError> {
+        self.handle
+            .block_on(req.send())
+            .map_err(|e| DayLogError::Remote(format!("webdav request failed: {}", e)))
+    }
+
+    /// 目录下一层的文件和子目录，目录不存在时为空
+    fn list_dir(&self, dir: &str) -> Result<Vec<DavEntry>, DayLogError> {
+        let url = self.url(dir, true);
+        let resp = self.send(
+            self.request(method("PROPFIND"), url)
+                .header("Depth", "1")
+                .header("Content-Type", "application/xml; charset=utf-8")
+                .body(PROPFIND_BODY),
+        )?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        if resp.status() != StatusCode::MULTI_STATUS {
+            return Err(DayLogError::Remote(format!(
+                "webdav PROPFIND /{} failed: {}",
+                dir,
+                resp.status()
+            )));
+        }
+        let body = self
+            .handle
+            .block_on(resp.text())
+            .map_err(|e| DayLogError::Remote(e.to_string()))?;
+        let base_path = percent_decode(self.base.path());
+        let entries = parse_multistatus(&body)?
+            .into_iter()
+            .filter_map(|mut v| {
+                v.path = v
+                    .path
+                    .strip_prefix(&base_path)?
+                    .trim_matches('/')
+                    .to_string();
+                // 结果中包含目录自己
+                (!v.path.is_empty() && v.path != dir).then_some(v)
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    /// 逐级创建 `path` 的上级目录，已经存在的返回 405，忽略
+    fn make_parents(&self, path: &str) -> Result<(), DayLogError> {
+        let mut dir = String::new();
+        let mut parts = path.split('/').collect::<Vec<_>>();
+        parts.pop();
+        for (i, part) in std::iter::once("").chain(parts).enumerate() {
+            if i > 1 {
+                dir.push('/');
+            }
+            dir.push_str(part);
+            let resp = self.send(self.request(method("MKCOL"), self.url(&dir, true)))?;
+            let status = resp.status();
+            if !status.is_success() && status != StatusCode::METHOD_NOT_ALLOWED {
+                return Err(DayLogError::Remote(format!(
+                    "webdav MKCOL /{} failed: {}",
+                    dir, status
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FileRemote for WebDav {
+    fn list(&self) -> Result<Vec<RemoteFile>, DayLogError> {
+        let mut files = Vec::new();
+        let mut dirs = vec![String::new()];
+        while let Some(dir) = dirs.pop() {
+            for entry in self.list_dir(&dir)? {
+                if entry.collection {
+                    dirs.push(entry.path);
+                } else {
+                    files.push(RemoteFile {
+                        path: entry.path,
+                        size: entry.size,
+                        modified: entry.modified,
+                    });
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    fn get(&self, path: &str) -> Result<Vec<u8>, DayLogError> {
+        let resp = self.send(self.request(Method::GET, self.url(path, false)))?;
+        if !resp.status().is_success() {
+            return Err(DayLogError::Remote(format!(
+                "webdav GET /{} failed: {}",
+                path,
+                resp.status()
+            )));
+        }
+        self.handle
+            .block_on(resp.bytes())
+            .map(|v| v.to_vec())
+            .map_err(|e| DayLogError::Remote(e.to_string()))
+    }
+
+    fn put(&self, path: &str, bytes: &[u8]) -> Result<(), DayLogError> {
+        let put = || {
+            self.send(
+                self.request(Method::PUT, self.url(path, false))
+                    .body(bytes.to_vec()),
+            )
+        };
+        let mut resp = put()?;
+        // 上级目录不存在
+        if resp.status() == StatusCode::CONFLICT || resp.status() == StatusCode::NOT_FOUND {
+            self.make_parents(path)?;
+            resp = put()?;
+        }
+        if !resp.status().is_success() {
+            return Err(DayLogError::Remote(format!(
+                "webdav PUT /{} failed: {}",
+                path,
+                resp.status()
+            )));
+        }
+        Ok(())
+    }
+
+    fn delete(&self, path: &str) -> Result<(), DayLogError> {
+        let resp = self.send(self.request(Method::DELETE, self.url(path, false)))?;
+        if !resp.status().is_success() && resp.status() != StatusCode::NOT_FOUND {
+            return Err(DayLogError::Remote(format!(
+                "webdav DELETE /{} failed: {}",
+                path,
+                resp.status()
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn method(name: &str) -> Method {
+    Method::from_bytes(name.as_bytes()).expect("valid webdav method")
+}
+
+/// PROPFIND 的 207 响应，`path` 是解码后的绝对路径
+fn parse_multistatus(body: &str) -> Result<Vec<DavEntry>, DayLogError> {
+    let doc = roxmltree::Document::parse(body)
+        .map_err(|e| DayLogError::Remote(format!("invalid webdav response: {}", e)))?;
+    let dav = |node: &roxmltree::Node, name: &str| {
+        node.is_element()
+            && node.tag_name().name() == name
+            && node.tag_name().namespace() == Some("DAV:")
+    };
+    let mut entries = Vec::new();
+    for response in doc.descendants().filter(|n| dav(n, "response")) {
+        let text = |name: &str| {
+            response
+                .descendants()
+                .find(|n| dav(n, name))
+                .and_then(|n| n.text())
+                .map(|v| v.trim().to_string())
+        };
+        let Some(href) = text("href") else {
+            continue;
+        };
+        // href 可能是完整地址，也可能只有路径
+        let path = match Url::parse(&href) {
+            Ok(url) => url.path().to_string(),
+            Err(_) => href,
+        };
+        let modified = text("getlastmodified")
+            .and_then(|v| httpdate::parse_http_date(&v).ok())
+            .and_then(|v| v.duration_since(UNIX_EPOCH).ok())
+            .map(|v| v.as_secs() as i64)
+            .unwrap_or(0);
+        entries.push(DavEntry {
+            path: percent_decode(&path),
+            collection: response.descendants().any(|n| dav(&n, "collection")),
+            size: text("getcontentlength")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            modified,
+        });
+    }
+    Ok(entries)
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(v) = input
+                .get(i + 1..i + 3)
+                .and_then(|v| u8::from_str_radix(v, 16).ok())
+        {
+            out.push(v);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
@@ -0,0 +1,246 @@
+use crate::app_state::AppState;
+use crate::http::file;
+use crate::http::journal::{self, JournalMetadata};
+use crate::job::{JobDef, JobFuture};
+use crate::notify::{self, NotifyEvent};
+use crate::util::date_util;
+use serde::Serialize;
+use sqlx::FromRow;
+use std::collections::BTreeSet;
+use tracing::{info, warn};
+
+const SCHEDULE: &str = "@every 6h";
+
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashEntry {
+    pub id: i64,
+    pub journal_id: i64,
+    pub date: String,
+    pub delete_time: i64,
+    /// 到这个时间后被彻底删除
+    #[sqlx(skip)]
+    pub purge_time: i64,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgeReport {
+    pub journals: usize,
+    /// 不再被任何日记引用、一起删除的附件
+    pub files: usize,
+}
+
+/// 按 `retention.trash_days` 定时清理回收站
+pub fn job(state: &AppState) -> Option<JobDef> {
+    let days = state.config.retention.trash_days;
+    if days == 0 {
+        return None;
+    }
+    info!("trash purge job enabled, trash_days={}", days);
+    Some(JobDef {
+        name: "trash_purge".to_string(),
+        kind: "trash_purge",
+        schedule: SCHEDULE.to_string(),
+        handler: run_job,
+        max_retries: 1,
+        catch_up: true,
+    })
+}
+
+fn run_job(state: AppState, _: Option<String>) -> JobFuture {
+    Box::pin(async move {
+        let report = purge(&state).await?;
+        if report.journals > 0 {
+            info!(
+                "trash purged {} journals, {} files",
+                report.journals, report.files
+            );
+            notify::spawn_send(
+                &state,
+                NotifyEvent::TrashPurged {
+                    journals: report.journals,
+                    files: report.files,
+                },
+            );
+        }
+        Ok(())
+    })
+}
+
+pub async fn list(state: &AppState) -> Result<Vec<TrashEntry>, sqlx::Error> {
+    let keep = state.config.retention.trash_days as i64 * 86_400;
+    let mut items = sqlx::query_as::<_, TrashEntry>(
+        "select id, journal_id, date, delete_time from journal_trash order by delete_time desc, id desc",
+    )
+    .fetch_all(&state.db)
+    .await?;
+    for item in &mut items {
+        item.purge_time = item.delete_time + keep;
+    }
+    Ok(items)
+}
+
+/// 放回原来的 id，同一天已经有日记时不恢复；删除时的标签一起恢复，期间被删掉的标签跳过
+pub async fn restore(state: &AppState, id: i64) -> Result<i64, String> {
+    let row = sqlx::query_as::<_, (i64, String)>(
+        "select journal_id, date from journal_trash where id = ?",
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "trash entry not found".to_string())?;
+    let (journal_id, date) = row;
+    let exists = sqlx::query_scalar::<_, i64>("select count(1) from journal where date = ?")
+        .bind(&date)
+        .fetch_one(&state.db)
+        .await
+        .map_err(|e| e.to_string())?;
+    if exists > 0 {
+        return Err(format!("journal for {} already exists", date));
+    }
+
+    let mut tx = state.db.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query(
+        "insert into journal (id, content, date, create_time, update_time, metadata) select journal_id, content, date, create_time, update_time, metadata from journal_trash where id = ?",
+    )
+    .bind(id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    sqlx::query(
+        "insert or ignore into journal_tag (journal_id, tag_id, create_time) select ?, t.id, ? from journal_trash r, json_each(coalesce(r.tag_ids, '[]')) v join tag t on t.id = v.value where r.id = ?",
+    )
+    .bind(journal_id)
+    .bind(date_util::now_secs())
+    .bind(id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    sqlx::query("delete from journal_trash where id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    state.render_cache.invalidate(journal_id);
+    // 回收站里没有字数列
+    journal::queue_count_backfill(state).await;
+    Ok(journal_id)
+}
+
+/// 彻底删除超过 `retention.trash_days` 的日记，再删掉其中不再被其他日记、草稿或回收站引用的附件
+pub async fn purge(state: &AppState) -> Result<PurgeReport, String> {
+    let before = date_util::now_secs() - state.config.retention.trash_days as i64 * 86_400;
+    let rows = sqlx::query_as::<_, (String, Option<String>)>(
+        "select content, metadata from journal_trash where delete_time < ?",
+    )
+    .bind(before)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| e.to_string())?;
+    if rows.is_empty() {
+        return Ok(PurgeReport::default());
+    }
+    let mut uris = BTreeSet::new();
+    for (content, metadata) in rows {
+        uris.extend(file::file_uris(&state.cipher.open(content)));
+        uris.extend(
+            JournalMetadata::parse(metadata.as_deref())
+                .attachments
+                .unwrap_or_default(),
+        );
+    }
+
+    let journals = sqlx::query("delete from journal_trash where delete_time < ?")
+        .bind(before)
+        .execute(&state.db)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected() as usize;
+
+    // 正文加密时 sql 里查不到引用，解密后在内存里查
+    let texts = if state.cipher.enabled() {
+        Some(referencing_texts(state).await.map_err(|e| e.to_string())?)
+    } else {
+        None
+    };
+    let mut files = 0usize;
+    for uri in uris {
+        match remove_orphan_file(state, &uri, texts.as_deref()).await {
+            Ok(true) => files += 1,
+            Ok(false) => {}
+            Err(e) => warn!("remove orphan file {} failed: {}", uri, e),
+        }
+    }
+    Ok(PurgeReport { journals, files })
+}
+
+/// 日记、回收站和草稿的正文（已解密）和 metadata
+async fn referencing_texts(state: &AppState) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (String, Option<String>)>(
+        "select content, metadata from journal union all select content, metadata from journal_trash union all select content, null from journal_draft",
+    )
+    .fetch_all(&state.db)
+    .await?;
+    let mut texts = Vec::with_capacity(rows.len() * 2);
+    for (content, metadata) in rows {
+        texts.push(state.cipher.open(content));
+        texts.extend(metadata);
+    }
+    Ok(texts)
+}
+
+async fn remove_orphan_file(
+    state: &AppState,
+    uri: &str,
+    texts: Option<&[String]>,
+) -> Result<bool, sqlx::Error> {
+    let referenced = match texts {
+        Some(texts) => texts.iter().any(|v| v.contains(uri)),
+        None => sqlx::query_scalar::<_, bool>(
+        r#"
+        select exists(
+            select 1 from journal where instr(content, ?1) > 0 or instr(coalesce(metadata, ''), ?1) > 0
+        ) or exists(
+            select 1 from journal_trash where instr(content, ?1) > 0 or instr(coalesce(metadata, ''), ?1) > 0
+        ) or exists(
+            select 1 from journal_draft where instr(content, ?1) > 0
+        )
+        "#,
+    )
+    .bind(uri)
+    .fetch_one(&state.db)
+    .await?,
+    };
+    if referenced {
+        return Ok(false);
+    }
+    let Some((path, thumb_small, thumb_medium, original)) =
+        sqlx::query_as::<_, (String, Option<String>, Option<String>, Option<String>)>(
+            "select file_path, thumb_small_path, thumb_medium_path, original_path from file_blob where uri = ? limit 1",
+        )
+        .bind(uri)
+        .fetch_optional(&state.db)
+        .await?
+    else {
+        return Ok(false);
+    };
+    let path = state.config.resolve_stored_path(&path);
+    if let Err(e) = tokio::fs::remove_file(&path).await
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        warn!("remove file {} failed: {}", path.display(), e);
+        return Ok(false);
+    }
+    // 缩略图可以重新生成，删不掉也不影响清理记录；保留的压缩前原文件一起删除
+    for extra in [thumb_small, thumb_medium, original].into_iter().flatten() {
+        let _ = tokio::fs::remove_file(state.config.resolve_stored_path(&extra)).await;
+    }
+    sqlx::query("delete from file_blob where uri = ?")
+        .bind(uri)
+        .execute(&state.db)
+        .await?;
+    Ok(true)
+}
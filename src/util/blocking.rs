@@ -0,0 +1,66 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use tokio::sync::Semaphore;
+use tokio::task::{self, JoinError};
+use tracing::debug;
+
+/// git 同步、导入解析、哈希等重任务的专用额度，避免占满 tokio 共享的 blocking 线程池拖慢上传
+pub struct BlockingPool {
+    workers: usize,
+    permits: Semaphore,
+    queued: AtomicUsize,
+    running: AtomicUsize,
+    completed: AtomicU64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockingStats {
+    pub workers: usize,
+    pub running: usize,
+    /// 等待额度的任务数
+    pub queued: usize,
+    pub completed: u64,
+}
+
+impl BlockingPool {
+    pub fn new(workers: usize) -> Self {
+        let workers = workers.max(1);
+        Self {
+            workers,
+            permits: Semaphore::new(workers),
+            queued: AtomicUsize::new(0),
+            running: AtomicUsize::new(0),
+            completed: AtomicU64::new(0),
+        }
+    }
+
+    /// 拿到额度后在 blocking 线程中执行 `f`，同时运行的任务不超过 `workers` 个
+    pub async fn run<F, R>(&self, name: &str, f: F) -> Result<R, JoinError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let queued = self.queued.fetch_add(1, Ordering::Relaxed) + 1;
+        debug!("blocking task {} queued, depth={}", name, queued);
+        let permit = self.permits.acquire().await;
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+        // 信号量不会被关闭
+        let _permit = permit.expect("blocking pool semaphore closed");
+
+        self.running.fetch_add(1, Ordering::Relaxed);
+        let result = task::spawn_blocking(f).await;
+        self.running.fetch_sub(1, Ordering::Relaxed);
+        self.completed.fetch_add(1, Ordering::Relaxed);
+        result
+    }
+
+    pub fn stats(&self) -> BlockingStats {
+        BlockingStats {
+            workers: self.workers,
+            running: self.running.load(Ordering::Relaxed),
+            queued: self.queued.load(Ordering::Relaxed),
+            completed: self.completed.load(Ordering::Relaxed),
+        }
+    }
+}
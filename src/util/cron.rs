@@ -0,0 +1,167 @@
+use crate::error::DayLogError;
+use crate::util::date_util;
+
+/// 任务的执行时间：5 段 cron 表达式（分 时 日 月 周，按 `utc_offset_minutes` 的本地时间），
+/// 或 `@every 30m` 这样的固定间隔，另外支持 `@hourly` `@daily` `@weekly` `@monthly`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    Every(i64),
+    Cron(CronFields),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronFields {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    /// 0 = 周日 ... 6 = 周六
+    weekdays: u8,
+    /// 日和周都有限制时满足其一即可，和 vixie cron 一致
+    days_any: bool,
+    weekdays_any: bool,
+}
+
+/// 最多往后找这么多天，`0 0 30 2 *` 这种不会发生的表达式返回 None
+const SEARCH_DAYS: i64 = 366 * 5;
+
+impl Schedule {
+    pub fn parse(input: &str) -> Result<Schedule, DayLogError> {
+        let input = input.trim();
+        let expr = match input {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            _ => input,
+        };
+        if let Some(rest) = expr.strip_prefix("@every") {
+            return parse_duration(rest.trim())
+                .map(Schedule::Every)
+                .ok_or_else(|| DayLogError::Validation(format!("invalid interval: {}", input)));
+        }
+        let parts = expr.split_whitespace().collect::<Vec<_>>();
+        if parts.len() != 5 {
+            return Err(DayLogError::Validation(format!(
+                "cron expression needs 5 fields: {}",
+                input
+            )));
+        }
+        let field = |idx: usize, min: u32, max: u32| {
+            parse_field(parts[idx], min, max)
+                .map_err(|e| DayLogError::Validation(format!("{}: {}", input, e)))
+        };
+        // 周日可以写成 0 或 7
+        let weekdays = field(4, 0, 7)?;
+        Ok(Schedule::Cron(CronFields {
+            minutes: field(0, 0, 59)?,
+            hours: field(1, 0, 23)? as u32,
+            days: field(2, 1, 31)? as u32,
+            months: field(3, 1, 12)? as u16,
+            weekdays: ((weekdays | (weekdays >> 7)) & 0x7f) as u8,
+            days_any: parts[2] == "*",
+            weekdays_any: parts[4] == "*",
+        }))
+    }
+
+    /// `after` 之后（不含）的下一次执行时间
+    pub fn next_after(&self, after: i64, utc_offset_minutes: i32) -> Option<i64> {
+        let cron = match self {
+            Schedule::Every(secs) => return Some(after + secs),
+            Schedule::Cron(v) => v,
+        };
+        let offset = utc_offset_minutes as i64 * 60;
+        let start = (after + offset).div_euclid(60) * 60 + 60;
+        let first_day = start.div_euclid(86_400);
+        let first_minute = start.rem_euclid(86_400) / 60;
+        for day in first_day..first_day + SEARCH_DAYS {
+            if !cron.day_matches(day) {
+                continue;
+            }
+            let from = if day == first_day { first_minute } else { 0 };
+            if let Some(minute) = (from..1440).find(|m| cron.minute_matches(*m)) {
+                return Some(day * 86_400 + minute * 60 - offset);
+            }
+        }
+        None
+    }
+}
+
+impl CronFields {
+    fn day_matches(&self, days: i64) -> bool {
+        let (_, month, day) = date_util::civil_from_days(days);
+        if self.months & (1 << month) == 0 {
+            return false;
+        }
+        let weekday = (date_util::weekday_from_days(days) + 1) % 7;
+        let day_ok = self.days & (1 << day) != 0;
+        let weekday_ok = self.weekdays & (1 << weekday) != 0;
+        match (self.days_any, self.weekdays_any) {
+            (true, true) => true,
+            (true, false) => weekday_ok,
+            (false, true) => day_ok,
+            (false, false) => day_ok || weekday_ok,
+        }
+    }
+
+    fn minute_matches(&self, minute_of_day: i64) -> bool {
+        self.hours & (1 << (minute_of_day / 60)) != 0
+            && self.minutes & (1 << (minute_of_day % 60)) != 0
+    }
+}
+
+/// `*` `5` `1-5` `*/15` `10-40/10` 以及逗号分隔的组合，返回按位的取值集合
+fn parse_field(raw: &str, min: u32, max: u32) -> Result<u64, DayLogError> {
+    let mut bits = 0u64;
+    for item in raw.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((r, s)) => (
+                r,
+                s.parse::<u32>()
+                    .ok()
+                    .filter(|v| *v > 0)
+                    .ok_or_else(|| DayLogError::Validation(format!("invalid step: {}", item)))?,
+            ),
+            None => (item, 1),
+        };
+        let (from, to) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (parse_value(a, min, max)?, parse_value(b, min, max)?)
+        } else {
+            let v = parse_value(range, min, max)?;
+            // `5/10` 表示从 5 开始每 10 个
+            (v, if item.contains('/') { max } else { v })
+        };
+        if from > to {
+            return Err(DayLogError::Validation(format!("invalid range: {}", item)));
+        }
+        for v in (from..=to).step_by(step as usize) {
+            bits |= 1 << v;
+        }
+    }
+    Ok(bits)
+}
+
+fn parse_value(raw: &str, min: u32, max: u32) -> Result<u32, DayLogError> {
+    raw.parse::<u32>()
+        .ok()
+        .filter(|v| (min..=max).contains(v))
+        .ok_or_else(|| {
+            DayLogError::Validation(format!("value out of range {}-{}: {}", min, max, raw))
+        })
+}
+
+/// `90s` `30m` `6h` `1d`
+fn parse_duration(raw: &str) -> Option<i64> {
+    let unit = raw.chars().last()?;
+    let n = raw[..raw.len() - unit.len_utf8()].parse::<i64>().ok()?;
+    let secs = match unit {
+        's' => n,
+        'm' => n * 60,
+        'h' => n * 3600,
+        'd' => n * 86_400,
+        _ => return None,
+    };
+    (secs > 0).then_some(secs)
+}
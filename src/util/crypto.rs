@@ -0,0 +1,181 @@
+use crate::config::app_config::EncryptionConfig;
+use crate::error::DayLogError;
+use crate::util::date_util;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rand::RngCore;
+use sha2::Sha256;
+use sqlx::{Pool, Sqlite};
+use tracing::warn;
+
+/// 加密后的正文以它开头，没有这个前缀的按明文处理，迁移期间两种可以并存
+const PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 12;
+const PBKDF2_ROUNDS: u32 = 100_000;
+const KEY_SALT: &str = "encryption.salt";
+/// 用当前密钥加密的固定文本，启动时解不开说明口令或密钥换了
+const KEY_CHECK: &str = "encryption.check";
+const KEY_CHECK_TEXT: &str = "day-log";
+
+/// 日记正文的加解密，放在 `AppState.cipher`；未开启 `[encryption]` 时原样进出
+pub struct ContentCipher {
+    aead: Option<Aes256Gcm>,
+}
+
+impl ContentCipher {
+    pub fn enabled(&self) -> bool {
+        self.aead.is_some()
+    }
+
+    /// 写库前调用；未开启或已经是密文时原样返回
+    pub fn seal(&self, plain: &str) -> String {
+        let Some(aead) = &self.aead else {
+            return plain.to_string();
+        };
+        if is_sealed(plain) {
+            return plain.to_string();
+        }
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        // 只有明文超过 64GiB 时才会失败
+        let Ok(data) = aead.encrypt(&nonce, plain.as_bytes()) else {
+            return plain.to_string();
+        };
+        let mut buf = nonce.to_vec();
+        buf.extend_from_slice(&data);
+        format!("{}{}", PREFIX, STANDARD.encode(buf))
+    }
+
+    /// 读库后调用；明文原样返回，解不开时记日志并返回原文，不让一条坏数据拖垮整个列表
+    pub fn open(&self, stored: String) -> String {
+        if !is_sealed(&stored) {
+            return stored;
+        }
+        match self.try_open(&stored) {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("decrypt journal content failed: {}", e);
+                stored
+            }
+        }
+    }
+
+    pub fn try_open(&self, stored: &str) -> Result<String, DayLogError> {
+        let invalid = |msg: &str| DayLogError::Validation(msg.to_string());
+        let Some(data) = stored.strip_prefix(PREFIX) else {
+            return Ok(stored.to_string());
+        };
+        let aead = self
+            .aead
+            .as_ref()
+            .ok_or_else(|| invalid("content is encrypted but [encryption] is disabled"))?;
+        let buf = STANDARD
+            .decode(data.trim())
+            .map_err(|_| invalid("invalid encrypted content"))?;
+        if buf.len() < NONCE_LEN {
+            return Err(invalid("invalid encrypted content"));
+        }
+        let (nonce, data) = buf.split_at(NONCE_LEN);
+        let plain = aead
+            .decrypt(Nonce::from_slice(nonce), data)
+            .map_err(|_| invalid("wrong key or corrupted content"))?;
+        String::from_utf8(plain).map_err(|_| invalid("decrypted content is not utf-8"))
+    }
+}
+
+pub fn is_sealed(stored: &str) -> bool {
+    stored.starts_with(PREFIX)
+}
+
+/// 按配置准备密钥；口令的盐和校验值保存在 `app_setting`，换了口令或密钥时拒绝启动，
+/// 避免新旧密钥加密的内容混在一起
+pub async fn load(cfg: &EncryptionConfig, db: &Pool<Sqlite>) -> Result<ContentCipher, DayLogError> {
+    if !cfg.enabled {
+        return Ok(ContentCipher { aead: None });
+    }
+    let key = if !cfg.key_file.trim().is_empty() {
+        read_key_file(&cfg.key_file).await?
+    } else if !cfg.passphrase.is_empty() {
+        let salt = match load_setting(db, KEY_SALT).await? {
+            Some(v) => v,
+            None => {
+                let mut buf = [0u8; 16];
+                rand::thread_rng().fill_bytes(&mut buf);
+                let salt = STANDARD.encode(buf);
+                save_setting(db, KEY_SALT, &salt).await?;
+                salt
+            }
+        };
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(
+            cfg.passphrase.as_bytes(),
+            salt.as_bytes(),
+            PBKDF2_ROUNDS,
+            &mut key,
+        );
+        key
+    } else {
+        return Err(DayLogError::Validation(
+            "encryption.passphrase or encryption.key_file is required".to_string(),
+        ));
+    };
+
+    let cipher = ContentCipher {
+        aead: Some(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))),
+    };
+    match load_setting(db, KEY_CHECK).await? {
+        Some(check) => {
+            if cipher.try_open(&check).ok().as_deref() != Some(KEY_CHECK_TEXT) {
+                return Err(DayLogError::Validation(
+                    "encryption key does not match the one used for existing content".to_string(),
+                ));
+            }
+        }
+        None => save_setting(db, KEY_CHECK, &cipher.seal(KEY_CHECK_TEXT)).await?,
+    }
+    Ok(cipher)
+}
+
+async fn read_key_file(path: &str) -> Result<[u8; 32], DayLogError> {
+    let raw = tokio::fs::read(path)
+        .await
+        .map_err(|e| DayLogError::io("read encryption.key_file", e))?;
+    if let Ok(key) = <[u8; 32]>::try_from(raw.as_slice()) {
+        return Ok(key);
+    }
+    let text = String::from_utf8_lossy(&raw);
+    let text = text.trim();
+    let decoded = if text.len() == 64 && text.bytes().all(|b| b.is_ascii_hexdigit()) {
+        (0..64)
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .ok()
+    } else {
+        STANDARD.decode(text).ok()
+    };
+    decoded
+        .and_then(|v| <[u8; 32]>::try_from(v.as_slice()).ok())
+        .ok_or_else(|| {
+            DayLogError::Validation("encryption.key_file must contain a 32 byte key".to_string())
+        })
+}
+
+async fn load_setting(db: &Pool<Sqlite>, key: &str) -> Result<Option<String>, DayLogError> {
+    let value = sqlx::query_scalar::<_, String>("select value from app_setting where key = ?")
+        .bind(key)
+        .fetch_optional(db)
+        .await?;
+    Ok(value)
+}
+
+async fn save_setting(db: &Pool<Sqlite>, key: &str, value: &str) -> Result<(), DayLogError> {
+    sqlx::query("insert into app_setting (key, value, update_time) values (?, ?, ?)")
+        .bind(key)
+        .bind(value)
+        .bind(date_util::now_secs())
+        .execute(db)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// 将 unix 天数转换为 (年, 月, 日)
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let y = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = mp + if mp < 10 { 3 } else { -9 };
+    let year = y + if m <= 2 { 1 } else { 0 };
+    (year, m, d)
+}
+
+/// `civil_from_days` 的逆运算
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// 解析 yyyy-MM-dd 为 unix 天数
+pub fn parse_date(date: &str) -> Option<i64> {
+    let mut parts = date.trim().split('-');
+    let y = parts.next()?.parse::<i64>().ok()?;
+    let m = parts.next()?.parse::<i64>().ok()?;
+    let d = parts.next()?.parse::<i64>().ok()?;
+    if parts.next().is_some() || !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+        return None;
+    }
+    Some(days_from_civil(y, m, d))
+}
+
+/// 按 utc 偏移（分钟）计算时间戳所在的本地 unix 天数
+pub fn local_days(secs: i64, utc_offset_minutes: i32) -> i64 {
+    (secs + utc_offset_minutes as i64 * 60).div_euclid(86_400)
+}
+
+/// 本地时间当天已过去的分钟数
+pub fn local_minute_of_day(secs: i64, utc_offset_minutes: i32) -> i64 {
+    (secs + utc_offset_minutes as i64 * 60).rem_euclid(86_400) / 60
+}
+
+pub fn date_from_days(days: i64) -> String {
+    let (y, m, d) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// 0 = 周一 ... 6 = 周日
+pub fn weekday_from_days(days: i64) -> i64 {
+    (days + 3).rem_euclid(7)
+}
+
+/// ISO 8601 周：(周所属年份, 第几周)，每周从周一开始，含当年第一个周四的周为第 1 周
+pub fn iso_week(days: i64) -> (i64, i64) {
+    let thursday = days - weekday_from_days(days) + 3;
+    let (year, _, _) = civil_from_days(thursday);
+    let week = (thursday - days_from_civil(year, 1, 1)) / 7 + 1;
+    (year, week)
+}
+
+/// 支持的星期名称语言
+pub const WEEKDAY_LOCALES: &[&str] = &["en", "zh", "ja"];
+
+/// `weekday` 为 `weekday_from_days` 的结果，未知语言按 en 处理
+pub fn weekday_name(weekday: i64, locale: &str, long: bool) -> &'static str {
+    const EN: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+    const EN_LONG: [&str; 7] = [
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+    ];
+    const ZH: [&str; 7] = ["周一", "周二", "周三", "周四", "周五", "周六", "周日"];
+    const ZH_LONG: [&str; 7] = [
+        "星期一",
+        "星期二",
+        "星期三",
+        "星期四",
+        "星期五",
+        "星期六",
+        "星期日",
+    ];
+    const JA: [&str; 7] = ["月", "火", "水", "木", "金", "土", "日"];
+    const JA_LONG: [&str; 7] = [
+        "月曜日",
+        "火曜日",
+        "水曜日",
+        "木曜日",
+        "金曜日",
+        "土曜日",
+        "日曜日",
+    ];
+    let names = match (locale, long) {
+        ("zh", false) => &ZH,
+        ("zh", true) => &ZH_LONG,
+        ("ja", false) => &JA,
+        ("ja", true) => &JA_LONG,
+        (_, false) => &EN,
+        (_, true) => &EN_LONG,
+    };
+    names[weekday.rem_euclid(7) as usize]
+}
+
+/// 按 utc 偏移（分钟）格式化时间戳所在的日期 yyyy-MM-dd
+pub fn date_of(secs: i64, utc_offset_minutes: i32) -> String {
+    date_from_days(local_days(secs, utc_offset_minutes))
+}
+
+pub fn today(utc_offset_minutes: i32) -> String {
+    date_of(now_secs(), utc_offset_minutes)
+}
@@ -1,8 +1,10 @@
 use sha2::{Digest, Sha256};
-use std::io;
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 use tokio::fs;
 
+const HASH_BUF_SIZE: usize = 64 * 1024;
+
 /// 确保 `file_path` 的父目录存在，并确保文件存在
 pub async fn ensure_file_path(file_path: impl AsRef<Path>) -> Result<(PathBuf, bool), io::Error> {
     let file_path = file_path.as_ref().to_path_buf();
@@ -44,9 +46,49 @@ pub async fn create_file(
 }
 
 pub fn file_hash(bytes: impl AsRef<[u8]>) -> String {
-    let mut hasher = Sha256::new();
-    Digest::update(&mut hasher, &bytes);
-    let result = hasher.finalize();
-    let result = format!("{:x}", result);
-    result
+    let mut hasher = StreamHasher::new();
+    hasher.update(bytes.as_ref());
+    hasher.finish()
+}
+
+/// 分块读取文件计算 sha256，在 blocking 线程中调用
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = StreamHasher::new();
+    let mut buf = vec![0u8; HASH_BUF_SIZE];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finish())
+}
+
+/// 分块计算 sha256，上传时边收边算，不需要把整个文件放进内存
+#[derive(Default)]
+pub struct StreamHasher {
+    hasher: Sha256,
+    size: u64,
+}
+
+impl StreamHasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, chunk: &[u8]) {
+        Digest::update(&mut self.hasher, chunk);
+        self.size += chunk.len() as u64;
+    }
+
+    /// 已经处理的字节数
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn finish(self) -> String {
+        format!("{:x}", self.hasher.finalize())
+    }
 }
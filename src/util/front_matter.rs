@@ -0,0 +1,112 @@
+use crate::util::date_util;
+
+/// 解析 markdown 开头的 `---` front matter，返回 (key/value 列表, 正文)
+///
+/// 只支持单行的 `key: value`，value 可以是 json 风格的双引号字符串
+pub fn split(content: &str) -> Option<(Vec<(String, String)>, &str)> {
+    let rest = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))?;
+    let mut offset = 0usize;
+    let mut pairs = Vec::new();
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let trimmed = line.trim();
+        if trimmed == "---" {
+            let body = &rest[offset..];
+            let body = body
+                .strip_prefix("\r\n")
+                .or_else(|| body.strip_prefix('\n'))
+                .unwrap_or(body);
+            return Some((pairs, body));
+        }
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let (key, value) = trimmed.split_once(':')?;
+        pairs.push((key.trim().to_string(), unquote(value.trim())));
+    }
+    None
+}
+
+/// front matter 中 `date:` 或 `created:` 的日期，Obsidian / Jekyll 等导出的文件名里不一定有日期
+///
+/// 与 `split` 不同，这里跳过无法识别的行（例如 yaml 列表），值可以带时间，例如
+/// `2024-01-05 10:00`、`2024-01-05T10:00:00+08:00`、`2024/1/5`
+pub fn date(content: &str) -> Option<String> {
+    let rest = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))?;
+    let mut date = None;
+    let mut created = None;
+    for line in rest.lines() {
+        let trimmed = line.trim();
+        if trimmed == "---" {
+            return date.or(created);
+        }
+        // 缩进的行属于上一个 key 的嵌套值
+        if line.starts_with([' ', '\t']) {
+            continue;
+        }
+        let Some((key, value)) = trimmed.split_once(':') else {
+            continue;
+        };
+        let slot = match key.trim().to_ascii_lowercase().as_str() {
+            "date" => &mut date,
+            "created" => &mut created,
+            _ => continue,
+        };
+        if slot.is_none() {
+            *slot = normalize_date(&unquote(value.trim()));
+        }
+    }
+    None
+}
+
+fn normalize_date(value: &str) -> Option<String> {
+    let day = value.split(['T', ' ']).next()?;
+    let mut parts = day.split(['-', '/', '.']);
+    let y = parts.next()?.parse::<i64>().ok()?;
+    let m = parts.next()?.parse::<i64>().ok()?;
+    let d = parts.next()?.parse::<i64>().ok()?;
+    if parts.next().is_some() || !(1000..=9999).contains(&y) {
+        return None;
+    }
+    let date = format!("{:04}-{:02}-{:02}", y, m, d);
+    // 2 月 30 日之类的日期换算后会变成另一天
+    let days = date_util::parse_date(&date)?;
+    (date_util::date_from_days(days) == date).then_some(date)
+}
+
+/// 渲染 front matter，字符串统一用 json 转义，保证能被 `split` 读回
+pub fn render(pairs: &[(&str, String)], body: &str) -> String {
+    if pairs.is_empty() {
+        return body.to_string();
+    }
+    let mut out = String::from("---\n");
+    for (key, value) in pairs {
+        out.push_str(key);
+        out.push_str(": ");
+        out.push_str(value);
+        out.push('\n');
+    }
+    out.push_str("---\n\n");
+    out.push_str(body);
+    out
+}
+
+pub fn quote(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| format!("\"{}\"", value))
+}
+
+fn unquote(value: &str) -> String {
+    if value.starts_with('"')
+        && let Ok(v) = serde_json::from_str::<String>(value)
+    {
+        return v;
+    }
+    if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        return value[1..value.len() - 1].replace("''", "'");
+    }
+    value.to_string()
+}
@@ -0,0 +1,44 @@
+use crate::config::app_config::HttpConfig;
+use crate::error::DayLogError;
+use reqwest::{Certificate, Client, ClientBuilder, Proxy};
+use std::time::Duration;
+
+const DEFAULT_USER_AGENT: &str = concat!("day-log/", env!("CARGO_PKG_VERSION"));
+
+/// 按 `[http]` 配置好代理、超时、user-agent 和证书的 builder，
+/// `AppState.http` 和 `util::outbound` 都从这里创建
+pub fn builder(cfg: &HttpConfig) -> Result<ClientBuilder, DayLogError> {
+    let mut builder = Client::builder()
+        .timeout(Duration::from_secs(cfg.timeout_secs.max(1)))
+        .connect_timeout(Duration::from_secs(cfg.connect_timeout_secs.max(1)))
+        .user_agent(match cfg.user_agent.trim() {
+            "" => DEFAULT_USER_AGENT,
+            v => v,
+        })
+        .danger_accept_invalid_certs(cfg.accept_invalid_certs);
+    let proxy = cfg.proxy.trim();
+    if !proxy.is_empty() {
+        let proxy = Proxy::all(proxy)
+            .map_err(|e| DayLogError::Validation(format!("invalid http.proxy: {}", e)))?;
+        builder = builder.proxy(proxy);
+    }
+    let ca_file = cfg.ca_cert_file.trim();
+    if !ca_file.is_empty() {
+        let pem = std::fs::read(ca_file)
+            .map_err(|e| DayLogError::io(format_args!("read http.ca_cert_file {}", ca_file), e))?;
+        let certs = Certificate::from_pem_bundle(&pem).map_err(|e| {
+            DayLogError::Validation(format!("invalid http.ca_cert_file {}: {}", ca_file, e))
+        })?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    Ok(builder)
+}
+
+/// 访问配置里写明的服务（telegram、matrix、备份上传）用的客户端
+pub fn build(cfg: &HttpConfig) -> Result<Client, DayLogError> {
+    builder(cfg)?
+        .build()
+        .map_err(|e| DayLogError::Internal(format!("build http client failed: {}", e)))
+}
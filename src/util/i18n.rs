@@ -0,0 +1,229 @@
+use std::future::Future;
+
+/// 接口返回给人看的文字（`msg`、`GET /errors` 的说明）使用的语言；
+/// `code` 和 `error` 与语言无关，客户端应按它们判断
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    Zh,
+}
+
+tokio::task_local! {
+    static CURRENT: Locale;
+}
+
+impl Locale {
+    /// 只看主标签，`zh-CN`、`zh-Hans`、`zh-TW` 都按中文处理
+    pub fn parse(tag: &str) -> Option<Locale> {
+        let primary = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+        match primary.as_str() {
+            "en" => Some(Locale::En),
+            "zh" => Some(Locale::Zh),
+            _ => None,
+        }
+    }
+
+    /// 按 q 值从高到低取第一个支持的语言，`*` 和不支持的语言跳过
+    pub fn from_accept_language(header: &str) -> Option<Locale> {
+        let mut ranges = header
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.split(';');
+                let tag = parts.next()?.trim();
+                let q = parts
+                    .find_map(|p| p.trim().strip_prefix("q="))
+                    .map(|v| v.trim().parse::<f32>().unwrap_or(0.0))
+                    .unwrap_or(1.0);
+                (q > 0.0).then_some((tag, q))
+            })
+            .collect::<Vec<_>>();
+        // 稳定排序，q 相同时保持请求头里的顺序
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranges.into_iter().find_map(|(tag, _)| Locale::parse(tag))
+    }
+}
+
+/// 在 `locale` 下运行请求，期间 `current()` 返回它
+pub async fn scope<F: Future>(locale: Locale, f: F) -> F::Output {
+    CURRENT.scope(locale, f).await
+}
+
+/// 不在请求里（后台任务、启动时）返回英文
+pub fn current() -> Locale {
+    CURRENT.try_with(|v| *v).unwrap_or(Locale::En)
+}
+
+/// 按当前语言翻译。英文原文就是消息的 key，没有译文时原样返回；
+/// `invalid backup: <detail>` 这类带细节的消息只翻译冒号前的部分
+pub fn text(msg: &str) -> String {
+    let catalog = match current() {
+        Locale::En => return msg.to_string(),
+        Locale::Zh => zh,
+    };
+    if let Some(v) = catalog(msg) {
+        return v.to_string();
+    }
+    match msg.split_once(": ") {
+        Some((head, detail)) => match catalog(head) {
+            Some(v) => format!("{}: {}", v, detail),
+            None => msg.to_string(),
+        },
+        None => msg.to_string(),
+    }
+}
+
+/// 新增固定的错误消息时在这里补上译文
+fn zh(msg: &str) -> Option<&'static str> {
+    let v = match msg {
+        // 通用
+        "not found" => "记录不存在",
+        "invalid date" => "日期无效",
+        "date must be yyyy-MM-dd" => "日期格式应为 yyyy-MM-dd",
+        "month must be yyyy-MM" => "月份格式应为 yyyy-MM",
+        "from/to must be yyyy-MM-dd" => "from/to 格式应为 yyyy-MM-dd",
+        "year must be between 1 and 9999" => "年份应在 1 到 9999 之间",
+        "invalid json body" => "请求体不是有效的 json",
+        "invalid multipart data" => "multipart 数据无效",
+        "db query failed" => "查询数据库失败",
+        "db insert failed" => "写入数据库失败",
+        "db update failed" => "更新数据库失败",
+        "db delete failed" => "删除失败",
+        // 日记
+        "date already exists, one day only one journal" => "当天已有日记，每天只能有一篇",
+        "content, date or metadata required" => "需要 content、date 或 metadata",
+        "source not found" => "源日记不存在",
+        "target not found" => "目标日记不存在",
+        "source_id and target_id must differ" => "source_id 和 target_id 不能相同",
+        "draft not found" => "草稿不存在",
+        "no journals in this year" => "这一年没有日记",
+        "by must be updated or created" => "by 只能是 updated 或 created",
+        "threshold must be between 0 and 1" => "threshold 应在 0 到 1 之间",
+        "format must be epub or pdf" => "format 只能是 epub 或 pdf",
+        "names must not be empty" => "names 不能为空",
+        "after must be a journal id or yyyy-MM-dd" => "after 应为日记 id 或 yyyy-MM-dd",
+        "tag already exists" => "标签已存在",
+        "tag name must not be empty" => "标签名不能为空",
+        "q must not be empty" => "q 不能为空",
+        "size must be thumb or medium" => "size 只能是 thumb 或 medium",
+        "too many requests, try again later" => "请求过于频繁，请稍后再试",
+        "search is unavailable while encryption is enabled" => "开启加密后不能使用全文搜索",
+        // 文件和导入
+        "file required" => "缺少文件",
+        "file missing" => "文件不存在",
+        "file not found" => "文件不存在",
+        "zip file required" => "缺少 zip 文件",
+        "export file required" => "缺少导出文件",
+        "database file required" => "缺少数据库文件",
+        "read file failed" => "读取文件失败",
+        "read zip file failed" => "读取 zip 文件失败",
+        "read export file failed" => "读取导出文件失败",
+        "read upload bytes failed" => "读取上传内容失败",
+        "read options failed" => "读取 options 失败",
+        "read date_field failed" => "读取 date_field 失败",
+        "read fetch_media failed" => "读取 fetch_media 失败",
+        "date_field must be publish or modified" => "date_field 只能是 publish 或 modified",
+        "file exceeds upload_file_limit" => "文件超过 upload_file_limit",
+        "save file failed" => "保存文件失败",
+        "save file metadata failed" => "保存文件信息失败",
+        "hash file failed" => "计算文件哈希失败",
+        "query file hash failed" => "查询文件哈希失败",
+        "file scan failed" => "扫描文件失败",
+        "file rejected by scanner" => "文件被扫描程序拒绝",
+        "media probe failed" => "读取视频或动图信息失败",
+        "media exceeds size limit" => "视频或动图的尺寸、码率超过上限",
+        "media transcode failed" => "视频或动图转码失败",
+        "import job not found" => "导入任务不存在",
+        "parse zip task failed" => "解析 zip 失败",
+        "parse vault task failed" => "解析 vault 失败",
+        "parse export task failed" => "解析导出文件失败",
+        "not a sqlite database" => "不是 sqlite 数据库",
+        "invalid backup" => "备份无效",
+        "db snapshot failed" => "数据库快照失败",
+        // 设置和配置
+        "save settings failed" => "保存设置失败",
+        "invalid importPatterns" => "importPatterns 无效",
+        "importPatterns cannot be empty" => "importPatterns 不能为空",
+        "invalid datePlaceholders" => "datePlaceholders 无效",
+        "invalid ingestMapping" => "ingestMapping 无效",
+        "syncOutputPath cannot be empty" => "syncOutputPath 不能为空",
+        "syncCommitMessage cannot be empty" => "syncCommitMessage 不能为空",
+        "sync.repoUrl is required" => "需要 sync.repoUrl",
+        "utcOffsetMinutes must be between -720 and 840" => "utcOffsetMinutes 应在 -720 到 840 之间",
+        "retention.archive_after_years must be at least 1" => {
+            "retention.archive_after_years 至少为 1"
+        }
+        "config file is not valid toml" => "配置文件不是有效的 toml",
+        "serialize config failed" => "序列化配置失败",
+        "write config failed" => "写入配置失败",
+        "encryption disabled in config" => "配置中未开启加密",
+        "instance already initialized, edit config.toml instead" => {
+            "实例已初始化，请直接修改 config.toml"
+        }
+        // 鉴权
+        "invalid token" => "令牌无效",
+        "missing token" => "缺少令牌",
+        "insufficient scope" => "令牌权限不足",
+        "csrf token mismatch" => "csrf 令牌不匹配",
+        "password login disabled" => "未开启密码登录",
+        "invalid username or password" => "用户名或密码错误",
+        "too many failed attempts, try again later" => "失败次数过多，请稍后再试",
+        "label is required" => "需要 label",
+        "scopes must not be empty" => "scopes 不能为空",
+        "expires_in_days must be positive" => "expires_in_days 必须大于 0",
+        "expiresInDays must be positive" => "expiresInDays 必须大于 0",
+        "ingest hook disabled" => "未开启 ingest hook",
+        // 同步
+        "sync disabled in config" => "配置中未开启同步",
+        "sync.repo_url is required" => "需要 sync.repo_url",
+        "sync task join failed" => "同步任务异常退出",
+        "diagnose task failed" => "诊断任务失败",
+        "internal error" => "服务端内部错误",
+        "file operation failed" => "读写文件失败",
+        // 后台任务
+        "word stats task failed" => "字数统计失败",
+        "duplicate task failed" => "查找重复日记失败",
+        "file verify task failed" => "校验文件失败",
+        "file path repair task failed" => "修复文件路径失败",
+        // `GET /errors` 的说明
+        "Success" => "成功",
+        "Malformed request: json, multipart or query could not be parsed, or the feature is disabled" => {
+            "请求格式不对：json、multipart 或查询参数无法解析，或功能未启用"
+        }
+        "Missing or invalid token" => "没有令牌或令牌无效",
+        "Token lacks the scope this endpoint requires" => "令牌没有这个接口需要的权限",
+        "Record does not exist" => "记录不存在",
+        "Conflicts with existing data, e.g. the day already has a journal or the tag name is taken" => {
+            "和已有数据冲突，例如当天已有日记、标签重名"
+        }
+        "Upload exceeds `upload_file_limit`" => "上传内容超过 `upload_file_limit`",
+        "A field parsed but its value is invalid, `msg` names the field" => {
+            "字段能解析但取值不合法，`msg` 中是具体字段"
+        }
+        "Temporarily locked after too many failed logins, `Retry-After` is the remaining seconds" => {
+            "登录失败次数过多被暂时锁定，`Retry-After` 是剩余秒数"
+        }
+        "Count or rate limit exceeded" => "超过次数或频率限制",
+        "Unexpected server error, details are in the server log" => {
+            "服务端意外出错，详情见服务端日志"
+        }
+        "Writing to the database failed" => "写入数据库失败",
+        "Querying the database failed" => "查询数据库失败",
+        "Listing records failed" => "查询列表失败",
+        "Reading the record failed" => "读取记录失败",
+        "Updating the database failed" => "更新数据库失败",
+        "Reading the record after update failed" => "更新后读取记录失败",
+        "Deleting failed" => "删除失败",
+        "File does not exist or the request has no file" => "文件不存在或请求中缺少文件",
+        "Saving the file failed" => "保存文件失败",
+        "Uploaded file was rejected by `upload.scan_command` or the `[media]` size limits" => {
+            "上传的文件被 `upload.scan_command` 拒绝或超过 `[media]` 的尺寸上限"
+        }
+        "Reading or writing a local file or directory failed" => "读写本地文件或目录失败",
+        "Local repository operation failed during sync" => "同步时本地仓库操作失败",
+        "Sync remote failed: clone, fetch, push or upload error, or authentication failed" => {
+            "连接同步远端失败：clone、fetch、push、上传出错或认证失败"
+        }
+        _ => return None,
+    };
+    Some(v)
+}
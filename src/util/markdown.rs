@@ -0,0 +1,113 @@
+use pulldown_cmark::{CowStr, Event, Options, Parser, html};
+
+/// 链接和图片中可以执行脚本的协议，渲染时替换为 `#`
+const UNSAFE_URL_SCHEMES: &[&str] = &["javascript:", "vbscript:", "data:text/html"];
+
+/// markdown 转 html，日记中的原始 html 会被转义，输出同时是合法的 xhtml 片段
+///
+/// `rewrite` 用来改写图片地址，返回 None 时保留原地址
+pub fn to_html_with_images<F>(markdown: &str, rewrite: F) -> String
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    render(markdown, false, rewrite)
+}
+
+/// `raw_html` 为 true 时原样输出日记中的 html，调用方需要再经过 `sanitize::Sanitizer` 过滤
+pub fn to_html(markdown: &str, raw_html: bool) -> String {
+    render(markdown, raw_html, |_| None)
+}
+
+fn render<F>(markdown: &str, raw_html: bool, mut rewrite: F) -> String
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    let parser = Parser::new_ext(markdown, options).map(|event| match event {
+        Event::Html(v) | Event::InlineHtml(v) if !raw_html => Event::Text(v),
+        Event::SoftBreak => Event::HardBreak,
+        Event::Start(pulldown_cmark::Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => {
+            let dest_url = match rewrite(&dest_url) {
+                Some(v) => CowStr::from(v),
+                None => safe_url(dest_url),
+            };
+            Event::Start(pulldown_cmark::Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            })
+        }
+        Event::Start(pulldown_cmark::Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(pulldown_cmark::Tag::Link {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        other => other,
+    });
+    let mut out = String::new();
+    html::push_html(&mut out, parser);
+    out
+}
+
+fn safe_url(url: CowStr<'_>) -> CowStr<'_> {
+    let lower = url
+        .trim()
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_ascii_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if UNSAFE_URL_SCHEMES.iter().any(|v| lower.starts_with(v)) {
+        CowStr::Borrowed("#")
+    } else {
+        url
+    }
+}
+
+pub fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// 只保留 markdown 中的文字，去掉链接地址、图片和代码块
+pub fn plain_text(markdown: &str) -> String {
+    let mut out = String::with_capacity(markdown.len());
+    let mut in_code_block = false;
+    for event in Parser::new_ext(markdown, Options::ENABLE_TABLES) {
+        match event {
+            Event::Start(pulldown_cmark::Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(pulldown_cmark::TagEnd::CodeBlock) => in_code_block = false,
+            Event::Text(v) if !in_code_block => {
+                out.push_str(&v);
+                out.push(' ');
+            }
+            Event::SoftBreak | Event::HardBreak | Event::End(_) => out.push('\n'),
+            _ => {}
+        }
+    }
+    out
+}
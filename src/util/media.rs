@@ -0,0 +1,153 @@
+use crate::error::DayLogError;
+use image::ImageReader;
+use serde::Deserialize;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// 动图、视频的尺寸和总码率
+#[derive(Debug, Clone, Copy)]
+pub struct MediaInfo {
+    pub width: u32,
+    pub height: u32,
+    /// 无法得知时为 None（gif 不计码率）
+    pub kbps: Option<u32>,
+}
+
+impl MediaInfo {
+    pub fn longest_side(&self) -> u32 {
+        self.width.max(self.height)
+    }
+
+    /// 是否超出上限，上限为 0 的一项不检查
+    pub fn exceeds(&self, max_px: u32, max_kbps: u32) -> bool {
+        let px = max_px > 0 && self.longest_side() > max_px;
+        let kbps = max_kbps > 0 && self.kbps.is_some_and(|v| v > max_kbps);
+        px || kbps
+    }
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_type: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    bit_rate: Option<String>,
+    duration: Option<String>,
+}
+
+/// 读取 gif 文件头中的尺寸，在 blocking 线程中调用
+pub fn gif_info(path: &Path) -> Result<MediaInfo, DayLogError> {
+    let (width, height) = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_dimensions()?;
+    Ok(MediaInfo {
+        width,
+        height,
+        kbps: None,
+    })
+}
+
+/// 用 `command`（输出 ffprobe 的 json）读取视频的尺寸和码率；
+/// 没有记录码率时按文件大小和时长估算
+pub async fn probe(
+    command: &str,
+    path: &Path,
+    timeout: Duration,
+) -> Result<MediaInfo, DayLogError> {
+    let input = path.to_string_lossy();
+    let mut args = command
+        .split_whitespace()
+        .map(|v| v.replace("{input}", &input))
+        .collect::<Vec<_>>();
+    if !command.contains("{input}") {
+        args.push(input.to_string());
+    }
+    let stdout = run(&args, timeout).await?;
+    let output = serde_json::from_slice::<ProbeOutput>(&stdout)
+        .map_err(|e| DayLogError::Validation(format!("invalid probe output: {}", e)))?;
+    let stream = output
+        .streams
+        .iter()
+        .find(|v| v.codec_type.as_deref() == Some("video") && v.width.is_some())
+        .ok_or_else(|| DayLogError::Validation("no video stream".to_string()))?;
+    let format = output.format.as_ref();
+    let bit_rate = format
+        .and_then(|v| v.bit_rate.as_deref())
+        .and_then(|v| v.parse::<u64>().ok())
+        .or_else(|| {
+            let duration = format
+                .and_then(|v| v.duration.as_deref())
+                .and_then(|v| v.parse::<f64>().ok())
+                .filter(|v| *v > 0.0)?;
+            let size = std::fs::metadata(path).ok()?.len();
+            Some((size as f64 * 8.0 / duration) as u64)
+        });
+    Ok(MediaInfo {
+        width: stream.width.unwrap_or(0),
+        height: stream.height.unwrap_or(0),
+        kbps: bit_rate.map(|v| (v / 1000).min(u32::MAX as u64) as u32),
+    })
+}
+
+/// 执行 `command` 把 `input` 转成 `output`，`{max_px}` `{max_kbps}` 替换为目标最长边和码率
+pub async fn transcode(
+    command: &str,
+    input: &Path,
+    output: &Path,
+    max_px: u32,
+    max_kbps: u32,
+    timeout: Duration,
+) -> Result<(), DayLogError> {
+    if !command.contains("{input}") || !command.contains("{output}") {
+        return Err(DayLogError::Validation(
+            "media.transcode_command must contain {input} and {output}".to_string(),
+        ));
+    }
+    let (input, output) = (input.to_string_lossy(), output.to_string_lossy());
+    let (max_px, max_kbps) = (max_px.to_string(), max_kbps.to_string());
+    let args = command
+        .split_whitespace()
+        .map(|v| {
+            v.replace("{input}", &input)
+                .replace("{output}", &output)
+                .replace("{max_px}", &max_px)
+                .replace("{max_kbps}", &max_kbps)
+        })
+        .collect::<Vec<_>>();
+    run(&args, timeout).await.map(|_| ())
+}
+
+/// 不经过 shell 执行，成功时返回标准输出；程序处理不了这个文件时按内容不合法处理
+async fn run(args: &[String], timeout: Duration) -> Result<Vec<u8>, DayLogError> {
+    let (program, args) = args
+        .split_first()
+        .ok_or_else(|| DayLogError::Validation("empty media command".to_string()))?;
+    let run = Command::new(program).args(args).kill_on_drop(true).output();
+    match tokio::time::timeout(timeout, run).await {
+        Ok(Ok(out)) if out.status.success() => Ok(out.stdout),
+        Ok(Ok(out)) => Err(DayLogError::Validation(format!(
+            "{} exit with {}: {}",
+            program,
+            out.status,
+            String::from_utf8_lossy(&out.stderr).trim()
+        ))),
+        Ok(Err(e)) => Err(DayLogError::io(format_args!("run {}", program), e)),
+        Err(_) => Err(DayLogError::Io(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("{} timeout after {}s", program, timeout.as_secs()),
+        ))),
+    }
+}
@@ -1 +1,17 @@
+pub mod blocking;
+pub mod cron;
+pub mod crypto;
+pub mod date_util;
 pub mod file_util;
+pub mod front_matter;
+pub mod http_client;
+pub mod i18n;
+pub mod markdown;
+pub mod media;
+pub mod outbound;
+pub mod recompress;
+pub mod render_cache;
+pub mod sanitize;
+pub mod thumbnail;
+pub mod token;
+pub mod words;
@@ -0,0 +1,295 @@
+use crate::config::app_config::{HttpConfig, OutboundConfig};
+use crate::error::DayLogError;
+use crate::util::http_client;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect;
+use reqwest::{Client, RequestBuilder, Url};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+
+const MAX_REDIRECTS: usize = 5;
+
+/// 访问用户提供的地址（导入时下载图片、通知 webhook）用的客户端，放在 `AppState.outbound`：
+/// 只允许 http/https，域名解析和每次重定向都按 `[outbound]` 的规则检查，
+/// 连接只会发往检查通过的地址，解析结果在请求过程中变化也绕不过去
+#[derive(Debug, Clone)]
+pub struct OutboundClient {
+    client: Client,
+    policy: Arc<Policy>,
+}
+
+impl OutboundClient {
+    pub fn new(http: &HttpConfig, cfg: &OutboundConfig) -> Result<OutboundClient, DayLogError> {
+        let mut policy = Policy::from_config(cfg)?;
+        let mut builder = http_client::builder(http)?;
+        if http.proxy.trim().is_empty() {
+            builder = builder.no_proxy();
+        } else {
+            // 配置了代理时由代理解析域名，只解析代理本身，这里只能检查 url 里的主机名和 IP
+            policy.proxy_host = Url::parse(http.proxy.trim())
+                .ok()
+                .and_then(|v| v.host_str().map(|h| h.to_ascii_lowercase()));
+        }
+        let policy = Arc::new(policy);
+        let redirect_policy = policy.clone();
+        let client = builder
+            .dns_resolver(Arc::new(GuardedResolver {
+                policy: policy.clone(),
+            }))
+            .redirect(redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    return attempt.error("too many redirects");
+                }
+                match redirect_policy.check_url(attempt.url()) {
+                    Ok(()) => attempt.follow(),
+                    Err(e) => attempt.error(e),
+                }
+            }))
+            .build()
+            .map_err(|e| DayLogError::Internal(format!("build http client failed: {}", e)))?;
+        Ok(OutboundClient { client, policy })
+    }
+
+    pub fn get(&self, url: &str) -> Result<RequestBuilder, DayLogError> {
+        Ok(self.client.get(self.parse(url)?))
+    }
+
+    pub fn post(&self, url: &str) -> Result<RequestBuilder, DayLogError> {
+        Ok(self.client.post(self.parse(url)?))
+    }
+
+    /// 地址不合法或被规则拒绝都算取值不合法
+    fn parse(&self, url: &str) -> Result<Url, DayLogError> {
+        let url =
+            Url::parse(url).map_err(|e| DayLogError::Validation(format!("invalid url: {}", e)))?;
+        self.policy.check_url(&url)?;
+        Ok(url)
+    }
+}
+
+/// reqwest 的错误只显示最外层，被拦截的原因在 source 里；不带 url，避免 token 写进日志
+pub fn error_message(e: reqwest::Error) -> String {
+    let e = e.without_url();
+    let mut msg = e.to_string();
+    let mut source = std::error::Error::source(&e);
+    while let Some(inner) = source {
+        msg.push_str(": ");
+        msg.push_str(&inner.to_string());
+        source = inner.source();
+    }
+    msg
+}
+
+#[derive(Debug)]
+struct Policy {
+    block_private: bool,
+    allow: Vec<HostRule>,
+    deny: Vec<HostRule>,
+    /// `[http] proxy` 的主机，不受地址检查限制
+    proxy_host: Option<String>,
+}
+
+/// `example.com` 只匹配本身，`*.example.com` 匹配子域名，IP 和 `10.0.0.0/8` 匹配解析出的地址
+#[derive(Debug)]
+enum HostRule {
+    Host(String),
+    Suffix(String),
+    Net(IpAddr, u8),
+}
+
+impl HostRule {
+    fn parse(raw: &str) -> Result<HostRule, DayLogError> {
+        let raw = raw.trim().trim_end_matches('.').to_ascii_lowercase();
+        if let Some(suffix) = raw.strip_prefix("*.") {
+            return Ok(HostRule::Suffix(format!(".{}", suffix)));
+        }
+        let (addr, prefix) = match raw.split_once('/') {
+            Some((a, p)) => (a, Some(p)),
+            None => (raw.as_str(), None),
+        };
+        let ip = addr.trim_start_matches('[').trim_end_matches(']');
+        let Ok(ip) = ip.parse::<IpAddr>() else {
+            if prefix.is_some() || raw.is_empty() {
+                return Err(DayLogError::Validation(format!(
+                    "invalid outbound host rule: {}",
+                    raw
+                )));
+            }
+            return Ok(HostRule::Host(raw));
+        };
+        let max = if ip.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p.parse::<u8>().ok().filter(|v| *v <= max).ok_or_else(|| {
+                DayLogError::Validation(format!("invalid outbound host rule: {}", raw))
+            })?,
+            None => max,
+        };
+        Ok(HostRule::Net(ip, prefix))
+    }
+
+    fn matches_host(&self, host: &str) -> bool {
+        match self {
+            HostRule::Host(v) => v == host,
+            HostRule::Suffix(v) => host.ends_with(v.as_str()),
+            HostRule::Net(..) => false,
+        }
+    }
+
+    fn matches_ip(&self, ip: IpAddr) -> bool {
+        let HostRule::Net(net, prefix) = self else {
+            return false;
+        };
+        match (canonical(ip), net) {
+            (IpAddr::V4(ip), IpAddr::V4(net)) => {
+                let mask = u32::MAX.checked_shl(32 - *prefix as u32).unwrap_or(0);
+                u32::from(ip) & mask == u32::from(*net) & mask
+            }
+            (IpAddr::V6(ip), IpAddr::V6(net)) => {
+                let mask = u128::MAX.checked_shl(128 - *prefix as u32).unwrap_or(0);
+                u128::from(ip) & mask == u128::from(*net) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Policy {
+    fn from_config(cfg: &OutboundConfig) -> Result<Policy, DayLogError> {
+        let rules = |list: &[String]| {
+            list.iter()
+                .filter(|v| !v.trim().is_empty())
+                .map(|v| HostRule::parse(v))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Policy {
+            block_private: cfg.block_private,
+            allow: rules(&cfg.allow_hosts)?,
+            deny: rules(&cfg.deny_hosts)?,
+            proxy_host: None,
+        })
+    }
+
+    /// 请求前和每次重定向时检查协议和主机名，地址写成 IP 时直接检查地址
+    fn check_url(&self, url: &Url) -> Result<(), DayLogError> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(DayLogError::Validation(format!(
+                "url scheme not allowed: {}",
+                url.scheme()
+            )));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| DayLogError::Validation("url has no host".to_string()))?;
+        match host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+        {
+            Ok(ip) => self.check_ip("", ip),
+            Err(_) => self.check_host(host),
+        }
+    }
+
+    fn check_host(&self, host: &str) -> Result<(), DayLogError> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if self.deny.iter().any(|v| v.matches_host(&host)) {
+            return Err(DayLogError::Validation(format!("host is denied: {}", host)));
+        }
+        Ok(())
+    }
+
+    /// `host` 为空表示地址直接写在 url 里
+    fn check_ip(&self, host: &str, ip: IpAddr) -> Result<(), DayLogError> {
+        if self.deny.iter().any(|v| v.matches_ip(ip)) {
+            return Err(DayLogError::Validation(format!(
+                "address is denied: {}",
+                ip
+            )));
+        }
+        let allowed = self.allow.iter().any(|v| v.matches_ip(ip))
+            || (!host.is_empty() && self.allow.iter().any(|v| v.matches_host(host)));
+        if self.block_private && !allowed && !is_public(ip) {
+            return Err(DayLogError::Validation(format!(
+                "address is not public: {}",
+                ip
+            )));
+        }
+        Ok(())
+    }
+}
+
+struct GuardedResolver {
+    policy: Arc<Policy>,
+}
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = self.policy.clone();
+        Box::pin(async move {
+            let host = name.as_str().trim_end_matches('.').to_ascii_lowercase();
+            let is_proxy = policy.proxy_host.as_deref() == Some(host.as_str());
+            if !is_proxy {
+                policy.check_host(&host)?;
+            }
+            let resolved = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .collect::<Vec<SocketAddr>>();
+            if resolved.is_empty() {
+                return Err(format!("no address for host: {}", host).into());
+            }
+            if is_proxy {
+                return Ok(Box::new(resolved.into_iter()) as Addrs);
+            }
+            // 有一个地址不通过就整体拒绝，避免同时解析到公网和内网时碰运气
+            for addr in &resolved {
+                policy.check_ip(&host, addr.ip())?;
+            }
+            Ok(Box::new(resolved.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// ipv4 映射地址和 nat64 地址按内嵌的 ipv4 处理
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => match v6.segments() {
+            [0, 0, 0, 0, 0, 0xffff, ..] | [0x64, 0xff9b, 0, 0, 0, 0, ..] => {
+                let [.., a, b, c, d] = v6.octets();
+                IpAddr::V4(Ipv4Addr::new(a, b, c, d))
+            }
+            _ => ip,
+        },
+        IpAddr::V4(_) => ip,
+    }
+}
+
+/// 回环、内网、链路本地、运营商 nat、组播、保留等地址都不算公网
+fn is_public(ip: IpAddr) -> bool {
+    match canonical(ip) {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_unspecified()
+                || v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_multicast()
+                || a == 0
+                || a >= 240
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 192 && b == 0 && v4.octets()[2] == 0)
+                || (a == 198 && (18..20).contains(&b)))
+        }
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+            !(v6.is_unspecified()
+                || v6.is_loopback()
+                || v6.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first & 0xffc0) == 0xfec0
+                || (first == 0x2001 && v6.segments()[1] == 0x0db8))
+        }
+    }
+}
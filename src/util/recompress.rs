@@ -0,0 +1,44 @@
+use crate::error::DayLogError;
+use crate::util::file_util;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use std::path::Path;
+
+/// 重新编码后的文件内容和 sha256
+pub struct Recompressed {
+    pub bytes: Vec<u8>,
+    pub oid: String,
+}
+
+/// 按原格式重新编码 jpeg（`quality` 为 1-100）和 png（无损，只提高压缩级别），
+/// 不是这两种格式或结果不比原文件小时返回 None。jpeg 会先按 exif 方向摆正，
+/// 编码后不再带 exif（包括拍摄地点）。在 blocking 线程中调用
+pub fn recompress(src: &Path, quality: u8) -> Result<Option<Recompressed>, DayLogError> {
+    let reader = ImageReader::open(src)?.with_guessed_format()?;
+    let format = match reader.format() {
+        Some(v @ (ImageFormat::Jpeg | ImageFormat::Png)) => v,
+        _ => return Ok(None),
+    };
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    let mut bytes = Vec::new();
+    if format == ImageFormat::Jpeg {
+        JpegEncoder::new_with_quality(&mut bytes, quality.clamp(1, 100))
+            .encode_image(&image.to_rgb8())?;
+    } else {
+        let encoder =
+            PngEncoder::new_with_quality(&mut bytes, CompressionType::Best, FilterType::Adaptive);
+        image.write_with_encoder(encoder)?;
+    }
+    let original = std::fs::metadata(src)?.len();
+    if bytes.len() as u64 >= original {
+        return Ok(None);
+    }
+    let oid = file_util::file_hash(&bytes);
+    Ok(Some(Recompressed { bytes, oid }))
+}
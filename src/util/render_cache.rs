@@ -0,0 +1,96 @@
+use crate::util::markdown;
+use crate::util::sanitize::Sanitizer;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// 日记渲染后的 html 缓存，按 `(journal_id, update_time)` 命中，超出容量时淘汰最久未使用的
+pub struct RenderCache {
+    capacity: usize,
+    sanitizer: Sanitizer,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    /// journal_id -> 条目，每篇日记只保留最新 update_time 的渲染结果
+    entries: HashMap<i64, Entry>,
+    tick: u64,
+}
+
+struct Entry {
+    update_time: i64,
+    html: Arc<str>,
+    last_used: u64,
+}
+
+impl RenderCache {
+    /// `capacity` 为 0 时不缓存，每次都重新渲染
+    pub fn new(capacity: usize, sanitizer: Sanitizer) -> Self {
+        Self {
+            capacity,
+            sanitizer,
+            inner: Mutex::new(Inner {
+                entries: HashMap::new(),
+                tick: 0,
+            }),
+        }
+    }
+
+    /// 返回缓存的 html，未命中时渲染 `content` 并放入缓存
+    pub fn get_or_render(&self, journal_id: i64, update_time: i64, content: &str) -> Arc<str> {
+        if self.capacity == 0 {
+            return Arc::from(self.render(content));
+        }
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.tick += 1;
+            let tick = inner.tick;
+            if let Some(entry) = inner.entries.get_mut(&journal_id)
+                && entry.update_time == update_time
+            {
+                entry.last_used = tick;
+                return entry.html.clone();
+            }
+        }
+
+        // 渲染时不持有锁
+        let html: Arc<str> = Arc::from(self.render(content));
+        let mut inner = self.inner.lock().unwrap();
+        inner.tick += 1;
+        let tick = inner.tick;
+        if !inner.entries.contains_key(&journal_id) && inner.entries.len() >= self.capacity {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(id, _)| *id);
+            if let Some(id) = oldest {
+                inner.entries.remove(&id);
+            }
+        }
+        inner.entries.insert(
+            journal_id,
+            Entry {
+                update_time,
+                html: html.clone(),
+                last_used: tick,
+            },
+        );
+        html
+    }
+
+    /// 日记被修改或删除时调用
+    pub fn invalidate(&self, journal_id: i64) {
+        self.inner.lock().unwrap().entries.remove(&journal_id);
+    }
+
+    /// 批量导入后整体清空
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().entries.clear();
+    }
+
+    /// 渲染结果直接进入浏览器，经过白名单过滤后才缓存
+    fn render(&self, content: &str) -> String {
+        let html = markdown::to_html(content, self.sanitizer.raw_html());
+        self.sanitizer.clean(&html)
+    }
+}
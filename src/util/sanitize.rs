@@ -0,0 +1,113 @@
+use crate::config::app_config::RenderConfig;
+use std::collections::HashSet;
+
+/// 无论白名单怎么配置都会连同内容一起去掉的标签
+const STRIPPED_CONTENT_TAGS: &[&str] = &["script", "style"];
+/// 无论白名单怎么配置都会去掉的标签，内容作为文本保留
+const FORBIDDEN_TAGS: &[&str] = &[
+    "iframe", "frame", "frameset", "object", "embed", "applet", "form", "base", "meta", "link",
+];
+
+/// 没有配置 `render.allowed_tags` 时使用，覆盖 markdown 渲染会产生的全部标签
+const DEFAULT_TAGS: &[&str] = &[
+    "a",
+    "abbr",
+    "b",
+    "blockquote",
+    "br",
+    "code",
+    "del",
+    "details",
+    "div",
+    "em",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "input",
+    "kbd",
+    "li",
+    "mark",
+    "ol",
+    "p",
+    "pre",
+    "s",
+    "small",
+    "span",
+    "strong",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "u",
+    "ul",
+];
+
+/// 渲染后 html 的白名单过滤，去掉脚本、事件属性和不在白名单中的协议
+pub struct Sanitizer {
+    raw_html: bool,
+    tags: Vec<String>,
+    url_schemes: Vec<String>,
+}
+
+impl Sanitizer {
+    pub fn new(cfg: &RenderConfig) -> Self {
+        let tags: Vec<String> = if cfg.allowed_tags.is_empty() {
+            DEFAULT_TAGS.iter().map(|v| v.to_string()).collect()
+        } else {
+            cfg.allowed_tags
+                .iter()
+                .map(|v| v.trim().to_ascii_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect()
+        };
+        let tags = tags
+            .into_iter()
+            .filter(|v| {
+                !STRIPPED_CONTENT_TAGS.contains(&v.as_str())
+                    && !FORBIDDEN_TAGS.contains(&v.as_str())
+            })
+            .collect();
+        Self {
+            raw_html: cfg.raw_html,
+            tags,
+            url_schemes: cfg
+                .allowed_url_schemes
+                .iter()
+                .map(|v| v.trim().trim_end_matches(':').to_ascii_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect(),
+        }
+    }
+
+    /// 是否保留日记中的原始 html，交给 `clean` 过滤
+    pub fn raw_html(&self) -> bool {
+        self.raw_html
+    }
+
+    pub fn clean(&self, html: &str) -> String {
+        let mut builder = ammonia::Builder::default();
+        builder
+            .tags(self.tags.iter().map(String::as_str).collect::<HashSet<_>>())
+            .clean_content_tags(STRIPPED_CONTENT_TAGS.iter().copied().collect())
+            .url_schemes(self.url_schemes.iter().map(String::as_str).collect())
+            // 任务列表的复选框
+            .add_tag_attributes("input", ["type", "checked", "disabled"])
+            .add_tag_attributes("td", ["align", "style"])
+            .add_tag_attributes("th", ["align", "style"]);
+        builder.clean(html).to_string()
+    }
+}
@@ -0,0 +1,108 @@
+use crate::error::DayLogError;
+use image::codecs::jpeg::JpegEncoder;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+const JPEG_QUALITY: u8 = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThumbSize {
+    Small,
+    Medium,
+}
+
+impl ThumbSize {
+    /// `GET /files/picture/{name}?size=` 的取值
+    pub fn parse(raw: &str) -> Option<ThumbSize> {
+        match raw.trim() {
+            "thumb" | "small" => Some(ThumbSize::Small),
+            "medium" => Some(ThumbSize::Medium),
+            _ => None,
+        }
+    }
+
+    pub fn suffix(self) -> &'static str {
+        match self {
+            ThumbSize::Small => "small",
+            ThumbSize::Medium => "medium",
+        }
+    }
+}
+
+/// 没有生成的一档为 None：原图不比这一档大，或配置为 0
+#[derive(Debug, Default)]
+pub struct Thumbnails {
+    pub small: Option<PathBuf>,
+    pub medium: Option<PathBuf>,
+}
+
+/// 按最长边把 `src` 缩小到 `small_px` 和 `medium_px`，写到 `dir` 下以原文件名加后缀命名；
+/// 会先按 exif 方向摆正，有透明通道的存 png，其余存 jpeg。在 blocking 线程中调用
+pub fn generate(
+    src: &Path,
+    dir: &Path,
+    small_px: u32,
+    medium_px: u32,
+) -> Result<Thumbnails, DayLogError> {
+    let mut decoder = ImageReader::open(src)?
+        .with_guessed_format()?
+        .into_decoder()?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    let name = src
+        .file_name()
+        .map(|v| v.to_string_lossy().to_string())
+        .ok_or_else(|| {
+            DayLogError::Validation(format!("invalid picture path {}", src.display()))
+        })?;
+    std::fs::create_dir_all(dir)?;
+    let mut out = Thumbnails::default();
+    // 小图从中图缩，少处理一次原图
+    let mut source = &image;
+    let medium;
+    if let Some(img) = shrink(&image, medium_px) {
+        let path = dir.join(file_name(&name, ThumbSize::Medium, &img));
+        save(&img, &path)?;
+        out.medium = Some(path);
+        medium = img;
+        source = &medium;
+    }
+    if let Some(img) = shrink(source, small_px) {
+        let path = dir.join(file_name(&name, ThumbSize::Small, &img));
+        save(&img, &path)?;
+        out.small = Some(path);
+    }
+    Ok(out)
+}
+
+fn shrink(image: &DynamicImage, max_px: u32) -> Option<DynamicImage> {
+    if max_px == 0 || image.width().max(image.height()) <= max_px {
+        return None;
+    }
+    Some(image.thumbnail(max_px, max_px))
+}
+
+fn file_name(name: &str, size: ThumbSize, image: &DynamicImage) -> String {
+    let ext = if image.color().has_alpha() {
+        "png"
+    } else {
+        "jpg"
+    };
+    format!("{}.{}.{}", name, size.suffix(), ext)
+}
+
+fn save(image: &DynamicImage, path: &Path) -> Result<(), DayLogError> {
+    if image.color().has_alpha() {
+        image.save_with_format(path, ImageFormat::Png)?;
+        return Ok(());
+    }
+    let file = File::create(path)?;
+    JpegEncoder::new_with_quality(BufWriter::new(file), JPEG_QUALITY)
+        .encode_image(&image.to_rgb8())?;
+    Ok(())
+}
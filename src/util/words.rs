@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+/// 常见英文虚词
+const EN_STOPWORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "am", "an", "and", "any", "are", "as", "at", "be",
+    "because", "been", "before", "being", "but", "by", "can", "could", "did", "do", "does", "done",
+    "for", "from", "get", "got", "had", "has", "have", "he", "her", "him", "his", "how", "if",
+    "in", "into", "is", "it", "its", "just", "me", "more", "my", "no", "not", "now", "of", "on",
+    "one", "only", "or", "our", "out", "she", "so", "some", "than", "that", "the", "their", "them",
+    "then", "there", "these", "they", "this", "to", "too", "up", "us", "very", "was", "we", "were",
+    "what", "when", "which", "who", "will", "with", "would", "you", "your",
+];
+
+/// 出现在二元组中就丢弃的高频单字
+const CJK_STOP_CHARS: &str =
+    "的了是在我你他她它们和与及也就都而且还又着把被给让吗呢吧啊呀哦嗯个这那之其或从对到于以很太";
+
+/// 常见的无意义中文二元组
+const CJK_STOPWORDS: &[&str] = &[
+    "今天", "一个", "没有", "什么", "自己", "可以", "因为", "所以", "但是", "还是", "如果", "已经",
+    "现在", "时候", "一下", "一些", "感觉", "觉得", "然后", "不过", "知道", "怎么",
+];
+
+/// 统计词频，英文按单词、中日韩文字按相邻二字切分，返回按次数倒序的前 `limit` 个
+pub fn top_tokens<'a>(texts: impl Iterator<Item = &'a str>, limit: usize) -> Vec<(String, u64)> {
+    let mut counts: HashMap<String, u64> = HashMap::new();
+    for text in texts {
+        for token in tokenize(text) {
+            *counts.entry(token).or_insert(0) += 1;
+        }
+    }
+    let mut list = counts.into_iter().collect::<Vec<_>>();
+    list.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    list.truncate(limit);
+    list
+}
+
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut word = String::new();
+    let mut cjk: Vec<char> = Vec::new();
+    for c in text.chars() {
+        if is_cjk(c) {
+            flush_word(&mut word, &mut out);
+            cjk.push(c);
+        } else if c.is_alphanumeric() || c == '\'' {
+            flush_cjk(&mut cjk, &mut out);
+            word.extend(c.to_lowercase());
+        } else {
+            flush_word(&mut word, &mut out);
+            flush_cjk(&mut cjk, &mut out);
+        }
+    }
+    flush_word(&mut word, &mut out);
+    flush_cjk(&mut cjk, &mut out);
+    out
+}
+
+fn flush_word(word: &mut String, out: &mut Vec<String>) {
+    let token = word.trim_matches('\'');
+    if token.chars().count() >= 2
+        && !token.chars().all(|c| c.is_ascii_digit())
+        && !EN_STOPWORDS.contains(&token)
+    {
+        out.push(token.to_string());
+    }
+    word.clear();
+}
+
+fn flush_cjk(run: &mut Vec<char>, out: &mut Vec<String>) {
+    for pair in run.windows(2) {
+        if pair.iter().any(|c| CJK_STOP_CHARS.contains(*c)) {
+            continue;
+        }
+        let token = pair.iter().collect::<String>();
+        if !CJK_STOPWORDS.contains(&token.as_str()) {
+            out.push(token);
+        }
+    }
+    run.clear();
+}
+
+/// 中日韩文字每分钟阅读字数
+const CJK_CHARS_PER_MINUTE: i64 = 400;
+/// 其他文字每分钟阅读词数
+const WORDS_PER_MINUTE: i64 = 200;
+
+/// 字数：中日韩文字每个字算一个，其他文字连续的字母数字算一个词
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WordCount {
+    pub cjk: i64,
+    pub words: i64,
+}
+
+impl WordCount {
+    pub fn of(text: &str) -> WordCount {
+        let mut count = WordCount::default();
+        let mut in_word = false;
+        for c in text.chars() {
+            if is_cjk(c) {
+                count.cjk += 1;
+                in_word = false;
+            } else if c.is_alphanumeric() {
+                if !in_word {
+                    count.words += 1;
+                }
+                in_word = true;
+            } else {
+                // `don't` `co-op` 这类算一个词
+                in_word = in_word && (c == '\'' || c == '-');
+            }
+        }
+        count
+    }
+
+    pub fn total(self) -> i64 {
+        self.cjk + self.words
+    }
+
+    /// 向上取整，有内容时至少 1 分钟
+    pub fn reading_minutes(self) -> i64 {
+        // 通分后一起取整，避免中英混排时两边各进一位
+        let scaled = self.cjk * WORDS_PER_MINUTE + self.words * CJK_CHARS_PER_MINUTE;
+        let unit = CJK_CHARS_PER_MINUTE * WORDS_PER_MINUTE;
+        (scaled + unit - 1) / unit
+    }
+}
+
+pub fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF // 平假名、片假名
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xAC00..=0xD7AF // 韩文
+        | 0xF900..=0xFAFF
+        | 0x20000..=0x2FA1F)
+}